@@ -0,0 +1,47 @@
+//! Error types for the matchmaking layer.
+
+use arcforge_protocol::{PartyId, PlayerId};
+
+/// Errors that can occur when entering or leaving the queue.
+#[derive(Debug, thiserror::Error)]
+pub enum MatchmakingError {
+    /// The player is already waiting in the queue.
+    #[error("player {0} is already queued")]
+    AlreadyQueued(PlayerId),
+
+    /// The player isn't waiting in the queue.
+    #[error("player {0} is not queued")]
+    NotQueued(PlayerId),
+
+    /// The party has more players than a match.
+    #[error("a party of {size} doesn't fit in a match of {match_size}")]
+    PartyTooLarge { size: usize, match_size: usize },
+}
+
+/// Errors that can occur when managing parties.
+#[derive(Debug, thiserror::Error)]
+pub enum PartyError {
+    /// The player is already in a party (or in this one).
+    #[error("player {0} is already in a party")]
+    AlreadyInParty(PlayerId),
+
+    /// The player isn't in the party.
+    #[error("player {0} is not in the party")]
+    NotInParty(PlayerId),
+
+    /// Only the party's leader may do this.
+    #[error("player {0} is not the party leader")]
+    NotLeader(PlayerId),
+
+    /// The player has no open invite to this party.
+    #[error("player {0} has no invite to party {1}")]
+    NotInvited(PlayerId, PartyId),
+
+    /// The party no longer exists.
+    #[error("party {0} not found")]
+    NotFound(PartyId),
+
+    /// The party has as many members as a room has seats.
+    #[error("party {0} is full")]
+    PartyFull(PartyId),
+}
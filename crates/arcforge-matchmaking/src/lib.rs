@@ -0,0 +1,43 @@
+//! Skill-based matchmaking, player ratings, and parties for Arcforge.
+//!
+//! Players enter a queue with a rating and optional parameters (region,
+//! mode, ...). The queue pairs players with the same parameters whose
+//! ratings are close, and the range it accepts widens the longer they
+//! wait. Each match gets a fresh room whose game config is built from
+//! the match.
+//!
+//! Ratings come from finished games: a [`RatingSink`] takes each
+//! room's result, updates its players' ratings with Elo or Glicko-2,
+//! and saves them to a [`RatingStore`].
+//!
+//! Friends who want to play together form a party (see [`Parties`]):
+//! the queue matches a party as a whole, and rooms seat it all at once.
+//!
+//! # Key types
+//!
+//! - [`MatchQueue`] — the queue itself: tickets in, matches out
+//! - [`QueueConfig`] — match size and how fast the rating range widens
+//! - [`Matchmaker`] — runs a queue against a [`RoomManager`] and keeps
+//!   queued players posted
+//! - [`RatingSystem`] — Elo or Glicko-2, for 1v1, team, and
+//!   free-for-all games
+//! - [`RatingSink`] — rates every finished game
+//! - [`RatingStore`] — where ratings live ([`MemoryStore`],
+//!   [`FileStore`], or your own)
+//! - [`Parties`] — who's in which party, and who leads it
+//!
+//! [`RoomManager`]: arcforge_room::RoomManager
+
+mod error;
+mod matchmaker;
+mod party;
+mod queue;
+mod rating;
+mod store;
+
+pub use error::{MatchmakingError, PartyError};
+pub use matchmaker::Matchmaker;
+pub use party::Parties;
+pub use queue::{Match, MatchQueue, QueueConfig, Ticket, DEFAULT_RATING};
+pub use rating::{Rating, RatingSink, RatingSystem};
+pub use store::{FileStore, MemoryStore, RatingStore};
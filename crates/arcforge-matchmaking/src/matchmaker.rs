@@ -0,0 +1,318 @@
+//! Runs a [`MatchQueue`] against a [`RoomManager`].
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use arcforge_protocol::{PlayerId, RoomId, RoomMetadata, SystemMessage};
+use arcforge_room::{
+    GameLogic, JoinCredentials, PlayerSender, RoomManager, RoomOptions,
+    RoomOutbound,
+};
+
+use crate::{Match, MatchQueue, MatchmakingError, QueueConfig, Ticket};
+
+/// How many times a ticket's match may fail to be seated before the
+/// ticket is dropped from the queue.
+const MAX_SEAT_ATTEMPTS: u32 = 3;
+
+/// Builds the game config for a match's room.
+type ConfigFor<G> =
+    Arc<dyn Fn(&Match) -> <G as GameLogic>::Config + Send + Sync>;
+
+/// Puts queued players into fresh rooms as matches form.
+///
+/// Queued players hand over the same sender they'd join a room with.
+/// The matchmaker posts their place in line on it as
+/// [`SystemMessage::QueuePosition`], announces their match with
+/// [`SystemMessage::MatchFound`], and seats them with it, so the room's
+/// traffic follows on the same channel. If the match can't be seated
+/// after all, [`SystemMessage::MatchCancelled`] takes it back; players
+/// whose matches keep failing are dropped with a
+/// [`SystemMessage::Error`].
+///
+/// Call [`place`](Self::place) every [`QueueConfig::interval`].
+pub struct Matchmaker<G: GameLogic> {
+    queue: MatchQueue,
+    senders: HashMap<PlayerId, PlayerSender<G>>,
+    /// The last place in line each queued player was told.
+    positions: HashMap<PlayerId, usize>,
+    /// How many of each ticket's matches couldn't be seated, by leader.
+    failed_seats: HashMap<PlayerId, u32>,
+    config_for: ConfigFor<G>,
+}
+
+impl<G: GameLogic> Matchmaker<G> {
+    /// Creates a matchmaker whose rooms get their game config from
+    /// `config_for`.
+    ///
+    /// # Panics
+    ///
+    /// If `config.match_size` is zero or more than the game's
+    /// [`max_players`](arcforge_room::RoomConfig::max_players).
+    pub fn new<F>(config: QueueConfig, config_for: F) -> Self
+    where
+        F: Fn(&Match) -> G::Config + Send + Sync + 'static,
+    {
+        let max_players = G::room_config().max_players;
+        assert!(
+            config.match_size <= max_players,
+            "match_size {} is more than the game's max_players {}",
+            config.match_size,
+            max_players
+        );
+        Self {
+            queue: MatchQueue::new(config),
+            senders: HashMap::new(),
+            positions: HashMap::new(),
+            failed_seats: HashMap::new(),
+            config_for: Arc::new(config_for),
+        }
+    }
+
+    /// Returns the queue's configuration.
+    pub fn config(&self) -> &QueueConfig {
+        self.queue.config()
+    }
+
+    /// Puts a player in the queue and tells them their place in line.
+    pub fn enter(
+        &mut self,
+        player_id: PlayerId,
+        rating: f64,
+        params: RoomMetadata,
+        sender: PlayerSender<G>,
+    ) -> Result<(), MatchmakingError> {
+        self.enter_party(vec![(player_id, sender)], rating, params)
+    }
+
+    /// Puts a party in the queue as one ticket, led by the first player,
+    /// and tells everyone their place in line. The party is only ever
+    /// matched whole.
+    pub fn enter_party(
+        &mut self,
+        players: Vec<(PlayerId, PlayerSender<G>)>,
+        rating: f64,
+        params: RoomMetadata,
+    ) -> Result<(), MatchmakingError> {
+        let mut ids = players.iter().map(|(p, _)| *p);
+        let Some(player_id) = ids.next() else {
+            return Ok(());
+        };
+        self.queue.enter(Ticket {
+            player_id,
+            party: ids.collect(),
+            rating,
+            params,
+            entered_at: Instant::now(),
+        })?;
+        let size = players.len();
+        self.senders.extend(players);
+        self.post_positions();
+        tracing::debug!(%player_id, size, rating, "entered the queue");
+        Ok(())
+    }
+
+    /// Takes a player out of the queue, along with the rest of their
+    /// party.
+    pub fn leave(
+        &mut self,
+        player_id: PlayerId,
+    ) -> Result<(), MatchmakingError> {
+        let ticket = self.queue.leave(player_id)?;
+        self.forget(&ticket);
+        self.post_positions();
+        tracing::debug!(%player_id, "left the queue");
+        Ok(())
+    }
+
+    /// Returns `true` if the player is waiting for a match.
+    pub fn is_queued(&self, player_id: PlayerId) -> bool {
+        self.queue.contains(player_id)
+    }
+
+    /// Returns the underlying queue.
+    pub fn queue(&self) -> &MatchQueue {
+        &self.queue
+    }
+
+    /// Forms the matches the queue allows and seats each one in a new
+    /// private room. Returns the rooms it created.
+    ///
+    /// Players who went into a room some other way, or whose sender was
+    /// dropped, lose their place first, along with their party. If a
+    /// match can't be seated, its players go back in line where they
+    /// were; after three failed tries, they're dropped with an error.
+    pub async fn place(&mut self, rooms: &mut RoomManager<G>) -> Vec<RoomId> {
+        let gone: Vec<PlayerId> = self
+            .queue
+            .tickets()
+            .iter()
+            .filter(|t| {
+                t.players().any(|p| {
+                    rooms.player_room(&p).is_some()
+                        || self.senders.get(&p).is_none_or(|s| s.is_closed())
+                })
+            })
+            .map(|t| t.player_id)
+            .collect();
+        for player_id in gone {
+            if let Ok(ticket) = self.queue.leave(player_id) {
+                self.forget(&ticket);
+            }
+        }
+
+        let mut placed = Vec::new();
+        for found in self.queue.find_matches(Instant::now()) {
+            match self.seat(&found, rooms).await {
+                Some(room_id) => placed.push(room_id),
+                None => {
+                    for ticket in found.tickets {
+                        self.requeue(ticket);
+                    }
+                }
+            }
+        }
+        self.post_positions();
+        placed
+    }
+
+    /// Creates a room for a match and seats its players.
+    async fn seat(
+        &mut self,
+        found: &Match,
+        rooms: &mut RoomManager<G>,
+    ) -> Option<RoomId> {
+        let options = RoomOptions {
+            private: true,
+            ..Default::default()
+        };
+        let room_id = rooms.create_room_with((self.config_for)(found), options);
+        let credentials = JoinCredentials {
+            invite_code: rooms.invite_code(room_id).map(str::to_string),
+            password: None,
+        };
+
+        // The room may start as soon as everyone's in, so MatchFound
+        // goes out first.
+        let players: Vec<(PlayerId, PlayerSender<G>)> = found
+            .players()
+            .into_iter()
+            .filter_map(|p| self.senders.get(&p).map(|s| (p, s.clone())))
+            .collect();
+        for (_, sender) in &players {
+            let _ = sender.send(RoomOutbound::System(
+                SystemMessage::MatchFound { room_id },
+            ));
+        }
+
+        match rooms.join_room_together(players, room_id, credentials).await {
+            Ok(()) => {
+                for ticket in &found.tickets {
+                    self.forget(ticket);
+                }
+                tracing::info!(
+                    %room_id,
+                    players = found.tickets.len(),
+                    rating = found.average_rating(),
+                    "match placed"
+                );
+                Some(room_id)
+            }
+            Err(e) => {
+                tracing::warn!(%room_id, error = %e, "failed to seat match");
+                let _ = rooms.destroy_room(room_id).await;
+                // Take back the MatchFound; their place in line follows.
+                for player_id in found.players() {
+                    self.positions.remove(&player_id);
+                    if let Some(sender) = self.senders.get(&player_id) {
+                        let _ = sender.send(RoomOutbound::System(
+                            SystemMessage::MatchCancelled { room_id },
+                        ));
+                    }
+                }
+                None
+            }
+        }
+    }
+
+    /// Puts a ticket whose match couldn't be seated back in line, or
+    /// drops it with an error once it has failed too often.
+    fn requeue(&mut self, ticket: Ticket) {
+        let attempts = self.failed_seats.entry(ticket.player_id).or_default();
+        *attempts += 1;
+        let message = if *attempts >= MAX_SEAT_ATTEMPTS {
+            tracing::warn!(
+                player_id = %ticket.player_id,
+                attempts = *attempts,
+                "giving up on seating a ticket"
+            );
+            "couldn't seat your match; please queue again".to_string()
+        } else {
+            match self.queue.enter(ticket.clone()) {
+                Ok(()) => return,
+                Err(e) => {
+                    tracing::warn!(
+                        player_id = %ticket.player_id,
+                        error = %e,
+                        "failed to requeue a ticket"
+                    );
+                    e.to_string()
+                }
+            }
+        };
+        for player_id in ticket.players() {
+            if let Some(sender) = self.senders.get(&player_id) {
+                let _ = sender.send(RoomOutbound::System(
+                    SystemMessage::Error {
+                        code: 503,
+                        message: message.clone(),
+                    },
+                ));
+            }
+        }
+        self.forget(&ticket);
+    }
+
+    /// Drops what the matchmaker keeps for a ticket's players.
+    fn forget(&mut self, ticket: &Ticket) {
+        self.failed_seats.remove(&ticket.player_id);
+        for player_id in ticket.players() {
+            self.senders.remove(&player_id);
+            self.positions.remove(&player_id);
+        }
+    }
+
+    /// Tells every queued player whose place in line changed.
+    fn post_positions(&mut self) {
+        // Places are counted per set of parameters, in one pass over
+        // the queue.
+        let mut lines: Vec<(&RoomMetadata, usize)> = Vec::new();
+        for ticket in self.queue.tickets() {
+            let position = match lines
+                .iter_mut()
+                .find(|(params, _)| **params == ticket.params)
+            {
+                Some((_, count)) => {
+                    *count += 1;
+                    *count
+                }
+                None => {
+                    lines.push((&ticket.params, 1));
+                    1
+                }
+            };
+            for player_id in ticket.players() {
+                if self.positions.get(&player_id) == Some(&position) {
+                    continue;
+                }
+                self.positions.insert(player_id, position);
+                if let Some(sender) = self.senders.get(&player_id) {
+                    let _ = sender.send(RoomOutbound::System(
+                        SystemMessage::QueuePosition { position },
+                    ));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,219 @@
+//! Parties: players who join rooms and queue together.
+//!
+//! [`Parties`] only keeps track of who's in which party; it doesn't
+//! talk to anyone. Each change returns the party as it now stands, as a
+//! [`PartyInfo`] ready to send to its members. Seating a party is the
+//! caller's job: hand its members to the room manager's `_together`
+//! methods or to [`Matchmaker::enter_party`](crate::Matchmaker::enter_party).
+
+use std::collections::{HashMap, HashSet};
+
+use arcforge_protocol::{PartyId, PartyInfo, PlayerId};
+
+use crate::PartyError;
+
+#[derive(Debug)]
+struct Party {
+    leader: PlayerId,
+    /// In the order they joined, leader included.
+    members: Vec<PlayerId>,
+}
+
+/// Every party on the server.
+#[derive(Debug)]
+pub struct Parties {
+    max_size: usize,
+    next_id: u64,
+    parties: HashMap<PartyId, Party>,
+    member_of: HashMap<PlayerId, PartyId>,
+    /// Open invites, by invited player.
+    invites: HashMap<PlayerId, HashSet<PartyId>>,
+}
+
+impl Parties {
+    /// Creates an empty registry whose parties hold at most `max_size`
+    /// players (usually the game's `max_players`).
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            max_size,
+            next_id: 1,
+            parties: HashMap::new(),
+            member_of: HashMap::new(),
+            invites: HashMap::new(),
+        }
+    }
+
+    /// Starts a party with `leader` as its only member.
+    pub fn create(
+        &mut self,
+        leader: PlayerId,
+    ) -> Result<PartyInfo, PartyError> {
+        if self.member_of.contains_key(&leader) {
+            return Err(PartyError::AlreadyInParty(leader));
+        }
+        let party_id = PartyId(self.next_id);
+        self.next_id += 1;
+        self.parties.insert(
+            party_id,
+            Party {
+                leader,
+                members: vec![leader],
+            },
+        );
+        self.member_of.insert(leader, party_id);
+        Ok(self.info(party_id).expect("just created this party"))
+    }
+
+    /// Invites `player` to `leader`'s party. Returns the party's ID.
+    pub fn invite(
+        &mut self,
+        leader: PlayerId,
+        player: PlayerId,
+    ) -> Result<PartyId, PartyError> {
+        let party_id = self.led_by(leader)?;
+        let party = &self.parties[&party_id];
+        if party.members.contains(&player) {
+            return Err(PartyError::AlreadyInParty(player));
+        }
+        if party.members.len() >= self.max_size {
+            return Err(PartyError::PartyFull(party_id));
+        }
+        self.invites.entry(player).or_default().insert(party_id);
+        Ok(party_id)
+    }
+
+    /// Accepts an invite, adding `player` to the party.
+    pub fn accept(
+        &mut self,
+        player: PlayerId,
+        party_id: PartyId,
+    ) -> Result<PartyInfo, PartyError> {
+        let invited = self
+            .invites
+            .get(&player)
+            .is_some_and(|parties| parties.contains(&party_id));
+        if !invited {
+            return Err(PartyError::NotInvited(player, party_id));
+        }
+        if self.member_of.contains_key(&player) {
+            return Err(PartyError::AlreadyInParty(player));
+        }
+        let party = self
+            .parties
+            .get_mut(&party_id)
+            .ok_or(PartyError::NotFound(party_id))?;
+        if party.members.len() >= self.max_size {
+            return Err(PartyError::PartyFull(party_id));
+        }
+
+        party.members.push(player);
+        self.member_of.insert(player, party_id);
+        self.invites.remove(&player);
+        Ok(self.info(party_id).expect("party exists"))
+    }
+
+    /// Takes `player` out of their party. Returns the party's ID and
+    /// what's left of it, or `None` if they were the last one out, in
+    /// which case its open invites go too.
+    ///
+    /// A leader who leaves hands the party to the longest-standing
+    /// member.
+    pub fn leave(
+        &mut self,
+        player: PlayerId,
+    ) -> Result<(PartyId, Option<PartyInfo>), PartyError> {
+        let party_id = self
+            .member_of
+            .remove(&player)
+            .ok_or(PartyError::NotInParty(player))?;
+        let party = self
+            .parties
+            .get_mut(&party_id)
+            .expect("members' parties exist");
+        party.members.retain(|p| *p != player);
+
+        let Some(&next) = party.members.first() else {
+            self.parties.remove(&party_id);
+            self.invites.retain(|_, parties| {
+                parties.remove(&party_id);
+                !parties.is_empty()
+            });
+            return Ok((party_id, None));
+        };
+        if party.leader == player {
+            party.leader = next;
+        }
+        Ok((party_id, self.info(party_id)))
+    }
+
+    /// Makes `player` the leader of `leader`'s party.
+    pub fn promote(
+        &mut self,
+        leader: PlayerId,
+        player: PlayerId,
+    ) -> Result<PartyInfo, PartyError> {
+        let party_id = self.led_by(leader)?;
+        let party = self.parties.get_mut(&party_id).expect("party exists");
+        if !party.members.contains(&player) {
+            return Err(PartyError::NotInParty(player));
+        }
+        party.leader = player;
+        Ok(self.info(party_id).expect("party exists"))
+    }
+
+    /// Removes `player` from `leader`'s party. Returns what's left of
+    /// it, as [`leave`](Self::leave) does.
+    pub fn kick(
+        &mut self,
+        leader: PlayerId,
+        player: PlayerId,
+    ) -> Result<(PartyId, Option<PartyInfo>), PartyError> {
+        let party_id = self.led_by(leader)?;
+        if self.member_of.get(&player) != Some(&party_id) {
+            return Err(PartyError::NotInParty(player));
+        }
+        self.leave(player)
+    }
+
+    /// Returns the party `player` is in, if any.
+    pub fn party_of(&self, player: PlayerId) -> Option<PartyInfo> {
+        self.info(*self.member_of.get(&player)?)
+    }
+
+    /// Returns a party's current members.
+    pub fn info(&self, party_id: PartyId) -> Option<PartyInfo> {
+        let party = self.parties.get(&party_id)?;
+        Some(PartyInfo {
+            party_id,
+            leader: party.leader,
+            members: party.members.clone(),
+        })
+    }
+
+    /// Drops a player's open invites, e.g. when they disconnect.
+    pub fn clear_invites(&mut self, player: PlayerId) {
+        self.invites.remove(&player);
+    }
+
+    /// Returns the number of parties.
+    pub fn len(&self) -> usize {
+        self.parties.len()
+    }
+
+    /// Returns `true` if there are no parties.
+    pub fn is_empty(&self) -> bool {
+        self.parties.is_empty()
+    }
+
+    /// Returns the party `leader` leads, or why they can't act for one.
+    fn led_by(&self, leader: PlayerId) -> Result<PartyId, PartyError> {
+        let party_id = *self
+            .member_of
+            .get(&leader)
+            .ok_or(PartyError::NotInParty(leader))?;
+        if self.parties[&party_id].leader != leader {
+            return Err(PartyError::NotLeader(leader));
+        }
+        Ok(party_id)
+    }
+}
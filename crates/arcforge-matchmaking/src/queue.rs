@@ -0,0 +1,292 @@
+//! The matchmaking queue: tickets in, matches out.
+//!
+//! Matching is greedy, longest wait first. The ticket that has waited
+//! longest picks the closest-rated tickets it can reach; a pair of
+//! tickets can reach each other when their ratings are within the
+//! smaller of the two tickets' spreads, so nobody is matched further
+//! from their rating than they've waited for. A party queues as one
+//! ticket and is only matched whole.
+
+use std::time::{Duration, Instant};
+
+use arcforge_protocol::{PlayerId, RoomMetadata};
+use serde::{Deserialize, Serialize};
+
+use crate::MatchmakingError;
+
+/// The rating players start with before they've played a rated game.
+pub const DEFAULT_RATING: f64 = 1500.0;
+
+/// How the queue forms matches.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueConfig {
+    /// Players per match.
+    pub match_size: usize,
+
+    /// How far apart two ratings may be the moment a player enters.
+    pub initial_spread: f64,
+
+    /// How much the accepted rating gap widens per second of waiting.
+    pub spread_per_sec: f64,
+
+    /// The widest the gap ever gets. `None` lets it grow until anyone
+    /// with the same parameters is a match.
+    pub max_spread: Option<f64>,
+
+    /// How often the [`Matchmaker`](crate::Matchmaker) looks for
+    /// matches.
+    pub interval: Duration,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            match_size: 2,
+            initial_spread: 100.0,
+            spread_per_sec: 10.0,
+            max_spread: None,
+            interval: Duration::from_secs(1),
+        }
+    }
+}
+
+/// A player, or a party, waiting in the queue.
+#[derive(Debug, Clone)]
+pub struct Ticket {
+    /// The player who queued: the leader, for a party.
+    pub player_id: PlayerId,
+    /// The rest of the party, if any. Empty for solo players.
+    pub party: Vec<PlayerId>,
+    /// The player's rating; for a party, its members' mean rating.
+    pub rating: f64,
+    /// Only tickets with exactly the same parameters are matched.
+    pub params: RoomMetadata,
+    pub entered_at: Instant,
+}
+
+impl Ticket {
+    /// Everyone on the ticket, the player who queued first.
+    pub fn players(&self) -> impl Iterator<Item = PlayerId> + '_ {
+        std::iter::once(self.player_id).chain(self.party.iter().copied())
+    }
+
+    /// How many seats the ticket needs.
+    pub fn size(&self) -> usize {
+        1 + self.party.len()
+    }
+}
+
+/// Tickets the queue put together, longest-waiting first.
+#[derive(Debug, Clone)]
+pub struct Match {
+    pub tickets: Vec<Ticket>,
+    /// The parameters every ticket in the match entered with.
+    pub params: RoomMetadata,
+}
+
+impl Match {
+    /// The matched players, longest-waiting ticket first, parties
+    /// together.
+    pub fn players(&self) -> Vec<PlayerId> {
+        self.tickets.iter().flat_map(Ticket::players).collect()
+    }
+
+    /// The mean rating of the matched players.
+    pub fn average_rating(&self) -> f64 {
+        let total: f64 = self
+            .tickets
+            .iter()
+            .map(|t| t.rating * t.size() as f64)
+            .sum();
+        let players: usize = self.tickets.iter().map(Ticket::size).sum();
+        total / players as f64
+    }
+}
+
+/// Players waiting for a match.
+#[derive(Debug)]
+pub struct MatchQueue {
+    config: QueueConfig,
+    /// In the order they entered, so the longest wait comes first.
+    tickets: Vec<Ticket>,
+}
+
+impl MatchQueue {
+    /// Creates an empty queue.
+    ///
+    /// # Panics
+    ///
+    /// If `config.match_size` is zero.
+    pub fn new(config: QueueConfig) -> Self {
+        assert!(config.match_size > 0, "match_size must be at least 1");
+        Self {
+            config,
+            tickets: Vec::new(),
+        }
+    }
+
+    /// Returns the queue's configuration.
+    pub fn config(&self) -> &QueueConfig {
+        &self.config
+    }
+
+    /// Adds a ticket to the back of the queue.
+    pub fn enter(&mut self, ticket: Ticket) -> Result<(), MatchmakingError> {
+        if let Some(queued) = ticket.players().find(|p| self.contains(*p)) {
+            return Err(MatchmakingError::AlreadyQueued(queued));
+        }
+        if ticket.size() > self.config.match_size {
+            return Err(MatchmakingError::PartyTooLarge {
+                size: ticket.size(),
+                match_size: self.config.match_size,
+            });
+        }
+        // Tickets put back after a failed placement keep their place.
+        let at = self
+            .tickets
+            .partition_point(|t| t.entered_at <= ticket.entered_at);
+        self.tickets.insert(at, ticket);
+        Ok(())
+    }
+
+    /// Takes the ticket a player is on out of the queue, along with the
+    /// rest of their party.
+    pub fn leave(
+        &mut self,
+        player_id: PlayerId,
+    ) -> Result<Ticket, MatchmakingError> {
+        let index = self
+            .index_of(player_id)
+            .ok_or(MatchmakingError::NotQueued(player_id))?;
+        Ok(self.tickets.remove(index))
+    }
+
+    /// Returns `true` if the player is waiting in the queue.
+    pub fn contains(&self, player_id: PlayerId) -> bool {
+        self.index_of(player_id).is_some()
+    }
+
+    /// Returns the player's place in line (1 is next) among the tickets
+    /// with the same parameters as theirs.
+    pub fn position(&self, player_id: PlayerId) -> Option<usize> {
+        let index = self.index_of(player_id)?;
+        let params = &self.tickets[index].params;
+        let ahead = self.tickets[..index]
+            .iter()
+            .filter(|t| t.params == *params)
+            .count();
+        Some(ahead + 1)
+    }
+
+    /// Returns the waiting tickets, longest wait first.
+    pub fn tickets(&self) -> &[Ticket] {
+        &self.tickets
+    }
+
+    /// Returns how many players are waiting.
+    pub fn len(&self) -> usize {
+        self.tickets.len()
+    }
+
+    /// Returns `true` if nobody is waiting.
+    pub fn is_empty(&self) -> bool {
+        self.tickets.is_empty()
+    }
+
+    fn index_of(&self, player_id: PlayerId) -> Option<usize> {
+        self.tickets
+            .iter()
+            .position(|t| t.players().any(|p| p == player_id))
+    }
+
+    /// How far from its rating `ticket` accepts a match at `now`.
+    pub fn spread(&self, ticket: &Ticket, now: Instant) -> f64 {
+        let waited = now.saturating_duration_since(ticket.entered_at);
+        let spread = self.config.initial_spread
+            + self.config.spread_per_sec * waited.as_secs_f64();
+        match self.config.max_spread {
+            Some(max) => spread.min(max),
+            None => spread,
+        }
+    }
+
+    /// Forms every match it can at `now` and takes the matched tickets
+    /// out of the queue.
+    pub fn find_matches(&mut self, now: Instant) -> Vec<Match> {
+        let mut taken = vec![false; self.tickets.len()];
+        let mut matches = Vec::new();
+        let spreads: Vec<f64> =
+            self.tickets.iter().map(|t| self.spread(t, now)).collect();
+        let reaches = |i: usize, j: usize| {
+            let gap = (self.tickets[i].rating - self.tickets[j].rating).abs();
+            gap <= spreads[i].min(spreads[j])
+        };
+
+        for anchor in 0..self.tickets.len() {
+            if taken[anchor] {
+                continue;
+            }
+            let a = &self.tickets[anchor];
+            let mut needed = self.config.match_size - a.size();
+
+            let mut candidates: Vec<(f64, usize)> = self
+                .tickets
+                .iter()
+                .enumerate()
+                .filter(|&(i, t)| {
+                    i != anchor
+                        && !taken[i]
+                        && t.params == a.params
+                        && reaches(anchor, i)
+                })
+                .map(|(i, t)| ((t.rating - a.rating).abs(), i))
+                .collect();
+            // Closest ratings first; the longer wait breaks ties.
+            candidates
+                .sort_by(|x, y| x.0.total_cmp(&y.0).then(x.1.cmp(&y.1)));
+
+            let mut members = vec![anchor];
+            for (_, i) in candidates {
+                if needed == 0 {
+                    break;
+                }
+                // Everyone in a match must be within reach of everyone
+                // else, not just of the anchor.
+                let size = self.tickets[i].size();
+                if size <= needed && members.iter().all(|&m| reaches(m, i)) {
+                    members.push(i);
+                    needed -= size;
+                }
+            }
+            if needed > 0 {
+                continue;
+            }
+            members.sort_unstable();
+            for &i in &members {
+                taken[i] = true;
+            }
+            matches.push(members);
+        }
+
+        let mut tickets: Vec<Option<Ticket>> =
+            std::mem::take(&mut self.tickets)
+                .into_iter()
+                .map(Some)
+                .collect();
+        let matches = matches
+            .into_iter()
+            .map(|members| {
+                let tickets: Vec<Ticket> = members
+                    .into_iter()
+                    .filter_map(|i| tickets[i].take())
+                    .collect();
+                Match {
+                    params: tickets[0].params.clone(),
+                    tickets,
+                }
+            })
+            .collect();
+        self.tickets = tickets.into_iter().flatten().collect();
+        matches
+    }
+}
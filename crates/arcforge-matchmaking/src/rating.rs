@@ -0,0 +1,318 @@
+//! Player ratings, updated from finished games.
+//!
+//! [`RatingSink`] is a [`ResultSink`]: hand it to the room manager (or
+//! the server builder) and every finished game's [`MatchResult`] moves
+//! its players' ratings in a [`RatingStore`](crate::RatingStore).
+//!
+//! A game is rated as a contest between sides. Players who share a
+//! [`team`](arcforge_protocol::PlayerResult::team) form one side;
+//! everyone else is a side of their own. Each side plays every other
+//! side, winning against worse placements, drawing with equal ones, and
+//! losing to better ones, so a 1v1, a 2v2, and an eight-player
+//! free-for-all all go through the same update. [`RatingSink`] leaves
+//! bots out, as if they hadn't played.
+
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use arcforge_protocol::{MatchResult, PlayerId, TeamId};
+use arcforge_room::{MatchRecord, ResultSink};
+use serde::{Deserialize, Serialize};
+
+use crate::{RatingStore, DEFAULT_RATING};
+
+/// Converts between Glicko and Glicko-2 scales.
+const GLICKO2_SCALE: f64 = 173.7178;
+
+/// Convergence tolerance for the Glicko-2 volatility iteration.
+const GLICKO2_EPSILON: f64 = 0.000_001;
+
+/// A player's skill estimate.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Rating {
+    /// The estimated skill, on the familiar 1500-centred scale.
+    pub rating: f64,
+    /// How uncertain `rating` is (Glicko's RD). Elo leaves it alone.
+    pub deviation: f64,
+    /// How erratic the player's results are (Glicko-2's σ). Elo leaves
+    /// it alone.
+    pub volatility: f64,
+    /// Rated games played.
+    pub games: u32,
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Self {
+            rating: DEFAULT_RATING,
+            deviation: 350.0,
+            volatility: 0.06,
+            games: 0,
+        }
+    }
+}
+
+/// How ratings move after a game.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum RatingSystem {
+    /// Classic Elo. `k_factor` is the most a 1v1 can move a rating;
+    /// with more sides, each pairing's share is scaled down so a whole
+    /// game moves ratings about as much as a 1v1 does.
+    Elo { k_factor: f64 },
+    /// Glicko-2, with every game treated as its own rating period.
+    /// `tau` limits how fast volatility changes (0.3 to 1.2 is
+    /// typical; lower is steadier).
+    Glicko2 { tau: f64 },
+}
+
+impl RatingSystem {
+    /// Elo with a K-factor of 32.
+    pub fn elo() -> Self {
+        Self::Elo { k_factor: 32.0 }
+    }
+
+    /// Glicko-2 with a τ of 0.5.
+    pub fn glicko2() -> Self {
+        Self::Glicko2 { tau: 0.5 }
+    }
+
+    /// Computes everyone's new rating after `result`, given their
+    /// current ones.
+    ///
+    /// Returns nothing if the game had fewer than two sides.
+    pub fn rate(
+        &self,
+        result: &MatchResult,
+        current: impl Fn(PlayerId) -> Rating,
+    ) -> Vec<(PlayerId, Rating)> {
+        let sides = sides(result, current);
+        if sides.len() < 2 {
+            return Vec::new();
+        }
+
+        let mut rated = Vec::new();
+        for (i, side) in sides.iter().enumerate() {
+            let opponents: Vec<(&Side, f64)> = sides
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(_, other)| (other, side.score_against(other)))
+                .collect();
+            for &(player_id, rating) in &side.members {
+                let mut new = match *self {
+                    Self::Elo { k_factor } => {
+                        elo(side, &opponents, rating, k_factor)
+                    }
+                    Self::Glicko2 { tau } => glicko2(rating, &opponents, tau),
+                };
+                new.games = rating.games + 1;
+                rated.push((player_id, new));
+            }
+        }
+        rated
+    }
+}
+
+/// Players rated together, with their ratings before the game.
+struct Side {
+    placement: u32,
+    members: Vec<(PlayerId, Rating)>,
+}
+
+impl Side {
+    /// 1 for a win over `other`, 0.5 for a draw, 0 for a loss.
+    fn score_against(&self, other: &Side) -> f64 {
+        match self.placement.cmp(&other.placement) {
+            std::cmp::Ordering::Less => 1.0,
+            std::cmp::Ordering::Equal => 0.5,
+            std::cmp::Ordering::Greater => 0.0,
+        }
+    }
+
+    fn mean_rating(&self) -> f64 {
+        let total: f64 = self.members.iter().map(|(_, r)| r.rating).sum();
+        total / self.members.len() as f64
+    }
+
+    /// The side as a single Glicko-2 opponent: mean μ and RMS φ.
+    fn glicko2_opponent(&self) -> (f64, f64) {
+        let n = self.members.len() as f64;
+        let mu = self
+            .members
+            .iter()
+            .map(|(_, r)| to_mu(r.rating))
+            .sum::<f64>()
+            / n;
+        let phi_sq = self
+            .members
+            .iter()
+            .map(|(_, r)| (r.deviation / GLICKO2_SCALE).powi(2))
+            .sum::<f64>()
+            / n;
+        (mu, phi_sq.sqrt())
+    }
+}
+
+/// Groups a result's players into sides, in result order.
+fn sides(
+    result: &MatchResult,
+    current: impl Fn(PlayerId) -> Rating,
+) -> Vec<Side> {
+    let mut sides: Vec<Side> = Vec::new();
+    let mut teams: HashMap<TeamId, usize> = HashMap::new();
+    for player in &result.players {
+        let member = (player.player_id, current(player.player_id));
+        let existing = player.team.and_then(|team| teams.get(&team));
+        match existing {
+            Some(&index) => {
+                let side = &mut sides[index];
+                side.placement = side.placement.min(player.placement);
+                side.members.push(member);
+            }
+            None => {
+                if let Some(team) = player.team {
+                    teams.insert(team, sides.len());
+                }
+                sides.push(Side {
+                    placement: player.placement,
+                    members: vec![member],
+                });
+            }
+        }
+    }
+    sides
+}
+
+fn elo(
+    side: &Side,
+    opponents: &[(&Side, f64)],
+    rating: Rating,
+    k_factor: f64,
+) -> Rating {
+    let own = side.mean_rating();
+    let delta: f64 = opponents
+        .iter()
+        .map(|(other, score)| {
+            let expected =
+                1.0 / (1.0 + 10f64.powf((other.mean_rating() - own) / 400.0));
+            score - expected
+        })
+        .sum();
+    Rating {
+        rating: rating.rating + k_factor * delta / opponents.len() as f64,
+        ..rating
+    }
+}
+
+fn to_mu(rating: f64) -> f64 {
+    (rating - DEFAULT_RATING) / GLICKO2_SCALE
+}
+
+/// Glickman's g(φ): discounts results against uncertain opponents.
+fn g(phi: f64) -> f64 {
+    1.0 / (1.0 + 3.0 * phi * phi / (PI * PI)).sqrt()
+}
+
+/// One Glicko-2 rating period, following Glickman's "Example of the
+/// Glicko-2 system" step by step.
+fn glicko2(rating: Rating, opponents: &[(&Side, f64)], tau: f64) -> Rating {
+    let mu = to_mu(rating.rating);
+    let phi = rating.deviation / GLICKO2_SCALE;
+    let sigma = rating.volatility;
+
+    // Steps 3 and 4: estimated variance and improvement.
+    let mut inv_v = 0.0;
+    let mut sum = 0.0;
+    for (other, score) in opponents {
+        let (mu_j, phi_j) = other.glicko2_opponent();
+        let g_j = g(phi_j);
+        let e = 1.0 / (1.0 + (-g_j * (mu - mu_j)).exp());
+        inv_v += g_j * g_j * e * (1.0 - e);
+        sum += g_j * (score - e);
+    }
+    let v = 1.0 / inv_v;
+    let delta = v * sum;
+
+    // Step 5: new volatility, by the Illinois algorithm.
+    let a = (sigma * sigma).ln();
+    let f = |x: f64| {
+        let ex = x.exp();
+        let denom = phi * phi + v + ex;
+        ex * (delta * delta - phi * phi - v - ex) / (2.0 * denom * denom)
+            - (x - a) / (tau * tau)
+    };
+    let mut big_a = a;
+    let mut big_b = if delta * delta > phi * phi + v {
+        (delta * delta - phi * phi - v).ln()
+    } else {
+        let mut k = 1.0;
+        while f(a - k * tau) < 0.0 {
+            k += 1.0;
+        }
+        a - k * tau
+    };
+    let mut f_a = f(big_a);
+    let mut f_b = f(big_b);
+    while (big_b - big_a).abs() > GLICKO2_EPSILON {
+        let big_c = big_a + (big_a - big_b) * f_a / (f_b - f_a);
+        let f_c = f(big_c);
+        if f_c * f_b <= 0.0 {
+            big_a = big_b;
+            f_a = f_b;
+        } else {
+            f_a /= 2.0;
+        }
+        big_b = big_c;
+        f_b = f_c;
+    }
+    let new_sigma = (big_a / 2.0).exp();
+
+    // Steps 6 to 8: new deviation and rating.
+    let phi_star = (phi * phi + new_sigma * new_sigma).sqrt();
+    let new_phi = 1.0 / (1.0 / (phi_star * phi_star) + 1.0 / v).sqrt();
+    let new_mu = mu + new_phi * new_phi * sum;
+
+    Rating {
+        rating: new_mu * GLICKO2_SCALE + DEFAULT_RATING,
+        deviation: new_phi * GLICKO2_SCALE,
+        volatility: new_sigma,
+        games: rating.games,
+    }
+}
+
+/// A [`ResultSink`] that rates every finished game and saves the new
+/// ratings to a store.
+pub struct RatingSink {
+    system: RatingSystem,
+    store: Arc<dyn RatingStore>,
+}
+
+impl RatingSink {
+    /// Rates games with `system`, reading and saving ratings in `store`.
+    pub fn new(system: RatingSystem, store: Arc<dyn RatingStore>) -> Self {
+        Self { system, store }
+    }
+}
+
+impl ResultSink for RatingSink {
+    fn record(&self, record: &MatchRecord) {
+        let mut result = record.result.clone();
+        result
+            .players
+            .retain(|player| !record.bots.contains(&player.player_id));
+        let rated = self.system.rate(&result, |player| {
+            self.store.get(player).unwrap_or_default()
+        });
+        if rated.is_empty() {
+            return;
+        }
+        if let Err(e) = self.store.save(&rated) {
+            tracing::warn!(
+                room_id = %record.room_id,
+                error = %e,
+                "failed to save ratings"
+            );
+        }
+    }
+}
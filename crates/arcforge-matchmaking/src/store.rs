@@ -0,0 +1,202 @@
+//! Where ratings are kept between games.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use arcforge_protocol::PlayerId;
+
+use crate::Rating;
+
+/// Keeps players' ratings.
+///
+/// Called on room tasks (through [`RatingSink`](crate::RatingSink)) and
+/// by the matchmaker, so keep it quick: a database-backed store should
+/// cache reads and write in the background.
+pub trait RatingStore: Send + Sync + 'static {
+    /// Returns a player's rating, or `None` if they've never been rated.
+    fn get(&self, player: PlayerId) -> Option<Rating>;
+
+    /// Stores the new ratings from one game, all together.
+    fn save(&self, ratings: &[(PlayerId, Rating)]) -> io::Result<()>;
+
+    /// Returns every stored rating, in no particular order.
+    fn all(&self) -> Vec<(PlayerId, Rating)>;
+
+    /// Returns the `n` highest-rated players, best first.
+    fn top(&self, n: usize) -> Vec<(PlayerId, Rating)> {
+        let mut all = self.all();
+        all.sort_by(|(a_id, a), (b_id, b)| {
+            b.rating.total_cmp(&a.rating).then(a_id.0.cmp(&b_id.0))
+        });
+        all.truncate(n);
+        all
+    }
+}
+
+/// Lets the server and your own code share one store.
+impl<S: RatingStore + ?Sized> RatingStore for Arc<S> {
+    fn get(&self, player: PlayerId) -> Option<Rating> {
+        (**self).get(player)
+    }
+
+    fn save(&self, ratings: &[(PlayerId, Rating)]) -> io::Result<()> {
+        (**self).save(ratings)
+    }
+
+    fn all(&self) -> Vec<(PlayerId, Rating)> {
+        (**self).all()
+    }
+
+    fn top(&self, n: usize) -> Vec<(PlayerId, Rating)> {
+        (**self).top(n)
+    }
+}
+
+/// A [`RatingStore`] that keeps ratings in memory only.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    ratings: Mutex<HashMap<PlayerId, Rating>>,
+}
+
+impl MemoryStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl RatingStore for MemoryStore {
+    fn get(&self, player: PlayerId) -> Option<Rating> {
+        let ratings = self.ratings.lock().unwrap_or_else(|e| e.into_inner());
+        ratings.get(&player).copied()
+    }
+
+    fn save(&self, ratings: &[(PlayerId, Rating)]) -> io::Result<()> {
+        let mut stored =
+            self.ratings.lock().unwrap_or_else(|e| e.into_inner());
+        stored.extend(ratings.iter().copied());
+        Ok(())
+    }
+
+    fn all(&self) -> Vec<(PlayerId, Rating)> {
+        let ratings = self.ratings.lock().unwrap_or_else(|e| e.into_inner());
+        ratings.iter().map(|(id, r)| (*id, *r)).collect()
+    }
+}
+
+/// A [`RatingStore`] that keeps every rating in memory and writes them
+/// all to a JSON file after each game.
+///
+/// The file is written on a thread of its own, so [`save`] returns as
+/// soon as the ratings are in memory; a burst of games is written once.
+/// Write failures are logged, not returned. Dropping the store waits for
+/// the last write to finish.
+///
+/// The file is replaced atomically, so a crash mid-write leaves the
+/// previous ratings intact. Fine for a few thousand players; beyond
+/// that, implement [`RatingStore`] over a database.
+///
+/// [`save`]: RatingStore::save
+#[derive(Debug)]
+pub struct FileStore {
+    ratings: Arc<Mutex<HashMap<PlayerId, Rating>>>,
+    /// Wakes the writer. Taken on drop to stop it.
+    dirty: Option<Sender<()>>,
+    writer: Option<JoinHandle<()>>,
+}
+
+impl FileStore {
+    /// Opens the store at `path`, loading its ratings if the file
+    /// exists.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let ratings = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e),
+        };
+        let ratings = Arc::new(Mutex::new(ratings));
+
+        let (dirty, wake) = mpsc::channel();
+        let writer = thread::Builder::new()
+            .name("arcforge-ratings".into())
+            .spawn({
+                let ratings = ratings.clone();
+                move || {
+                    while wake.recv().is_ok() {
+                        // Saves that came in meanwhile are in this write.
+                        while wake.try_recv().is_ok() {}
+                        if let Err(e) = write_ratings(&path, &ratings) {
+                            tracing::warn!(
+                                path = %path.display(),
+                                error = %e,
+                                "failed to write ratings"
+                            );
+                        }
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            ratings,
+            dirty: Some(dirty),
+            writer: Some(writer),
+        })
+    }
+}
+
+/// Replaces the file at `path` with `ratings`.
+fn write_ratings(
+    path: &Path,
+    ratings: &Mutex<HashMap<PlayerId, Rating>>,
+) -> io::Result<()> {
+    let bytes = {
+        let ratings = ratings.lock().unwrap_or_else(|e| e.into_inner());
+        serde_json::to_vec_pretty(&*ratings)?
+    };
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, bytes)?;
+    fs::rename(&tmp, path)
+}
+
+impl RatingStore for FileStore {
+    fn get(&self, player: PlayerId) -> Option<Rating> {
+        let ratings = self.ratings.lock().unwrap_or_else(|e| e.into_inner());
+        ratings.get(&player).copied()
+    }
+
+    fn save(&self, ratings: &[(PlayerId, Rating)]) -> io::Result<()> {
+        let mut stored =
+            self.ratings.lock().unwrap_or_else(|e| e.into_inner());
+        stored.extend(ratings.iter().copied());
+        drop(stored);
+
+        if let Some(dirty) = &self.dirty {
+            let _ = dirty.send(());
+        }
+        Ok(())
+    }
+
+    fn all(&self) -> Vec<(PlayerId, Rating)> {
+        let ratings = self.ratings.lock().unwrap_or_else(|e| e.into_inner());
+        ratings.iter().map(|(id, r)| (*id, *r)).collect()
+    }
+}
+
+impl Drop for FileStore {
+    fn drop(&mut self) {
+        // Closing the channel lets the writer finish what's queued and
+        // stop.
+        self.dirty = None;
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
@@ -0,0 +1,623 @@
+//! Integration tests for the matchmaking queue and matchmaker.
+
+use std::time::{Duration, Instant};
+
+use arcforge_matchmaking::{
+    Match, MatchQueue, Matchmaker, MatchmakingError, QueueConfig, Ticket,
+};
+use arcforge_protocol::{PlayerId, Recipient, RoomMetadata, SystemMessage};
+use arcforge_room::{
+    GameContext, GameLogic, PlayerSender, RoomConfig, RoomManager,
+    RoomOutbound, TeamConfig,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+// =========================================================================
+// Mock game: records the rating its room was made for.
+// =========================================================================
+
+struct DuelGame;
+
+#[derive(Clone, Debug, Default)]
+struct DuelConfig {
+    rating: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct DuelState {
+    rating: f64,
+}
+
+impl GameLogic for DuelGame {
+    type Config = DuelConfig;
+    type State = DuelState;
+    type View = DuelState;
+    type ClientMessage = ();
+    type ServerMessage = ();
+
+    fn init(
+        config: &DuelConfig,
+        _ctx: &mut GameContext<'_, ()>,
+    ) -> DuelState {
+        DuelState {
+            rating: config.rating,
+        }
+    }
+
+    fn handle_message(
+        _state: &mut DuelState,
+        _sender: PlayerId,
+        _msg: (),
+        _ctx: &mut GameContext<'_, ()>,
+    ) -> Vec<(Recipient, ())> {
+        Vec::new()
+    }
+
+    fn view_for(state: &DuelState, _player: PlayerId) -> DuelState {
+        state.clone()
+    }
+
+    fn is_finished(_state: &DuelState) -> bool {
+        false
+    }
+
+    fn room_config() -> RoomConfig {
+        RoomConfig {
+            min_players: 2,
+            max_players: 2,
+            ..RoomConfig::default()
+        }
+    }
+}
+
+// =========================================================================
+// Mock game: a duel whose single one-player team can never seat a match.
+// =========================================================================
+
+struct CrampedGame;
+
+impl GameLogic for CrampedGame {
+    type Config = DuelConfig;
+    type State = DuelState;
+    type View = DuelState;
+    type ClientMessage = ();
+    type ServerMessage = ();
+
+    fn init(
+        config: &DuelConfig,
+        _ctx: &mut GameContext<'_, ()>,
+    ) -> DuelState {
+        DuelState {
+            rating: config.rating,
+        }
+    }
+
+    fn handle_message(
+        _state: &mut DuelState,
+        _sender: PlayerId,
+        _msg: (),
+        _ctx: &mut GameContext<'_, ()>,
+    ) -> Vec<(Recipient, ())> {
+        Vec::new()
+    }
+
+    fn view_for(state: &DuelState, _player: PlayerId) -> DuelState {
+        state.clone()
+    }
+
+    fn is_finished(_state: &DuelState) -> bool {
+        false
+    }
+
+    fn room_config() -> RoomConfig {
+        RoomConfig {
+            min_players: 1,
+            max_players: 2,
+            teams: Some(TeamConfig { count: 1, size: 1 }),
+            ..RoomConfig::default()
+        }
+    }
+}
+
+fn ticket(id: u64, rating: f64, entered_at: Instant) -> Ticket {
+    Ticket {
+        player_id: PlayerId(id),
+        party: Vec::new(),
+        rating,
+        params: RoomMetadata::new(),
+        entered_at,
+    }
+}
+
+fn region(name: &str) -> RoomMetadata {
+    RoomMetadata::from([("region".into(), name.into())])
+}
+
+fn matchmaker() -> Matchmaker<DuelGame> {
+    Matchmaker::new(QueueConfig::default(), |found: &Match| DuelConfig {
+        rating: found.average_rating(),
+    })
+}
+
+type Outbound = mpsc::UnboundedReceiver<RoomOutbound<DuelGame>>;
+
+fn channel() -> (PlayerSender<DuelGame>, Outbound) {
+    mpsc::unbounded_channel()
+}
+
+/// Returns the system messages waiting on `rx`, skipping everything else.
+fn system_messages(rx: &mut Outbound) -> Vec<SystemMessage> {
+    let mut msgs = Vec::new();
+    while let Ok(outbound) = rx.try_recv() {
+        if let RoomOutbound::System(msg) = outbound {
+            msgs.push(msg);
+        }
+    }
+    msgs
+}
+
+// =========================================================================
+// MatchQueue
+// =========================================================================
+
+#[test]
+fn test_close_ratings_match_right_away() {
+    let now = Instant::now();
+    let mut queue = MatchQueue::new(QueueConfig::default());
+    queue.enter(ticket(1, 1500.0, now)).unwrap();
+    queue.enter(ticket(2, 1550.0, now)).unwrap();
+
+    let matches = queue.find_matches(now);
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].players(), vec![PlayerId(1), PlayerId(2)]);
+    assert_eq!(matches[0].average_rating(), 1525.0);
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn test_spread_widens_while_waiting() {
+    let now = Instant::now();
+    let mut queue = MatchQueue::new(QueueConfig::default());
+    queue.enter(ticket(1, 1500.0, now)).unwrap();
+    queue.enter(ticket(2, 1800.0, now)).unwrap();
+
+    // 300 apart: out of reach at first...
+    assert!(queue.find_matches(now).is_empty());
+    assert!(queue.find_matches(now + Duration::from_secs(19)).is_empty());
+    assert_eq!(queue.len(), 2);
+
+    // ...and within 100 + 10/s after 20 seconds.
+    let matches = queue.find_matches(now + Duration::from_secs(20));
+    assert_eq!(matches.len(), 1);
+}
+
+#[test]
+fn test_reach_is_the_smaller_spread() {
+    let now = Instant::now();
+    let mut queue = MatchQueue::new(QueueConfig::default());
+    // A veteran of the queue doesn't drag a newcomer far from their
+    // rating.
+    queue
+        .enter(ticket(1, 1500.0, now - Duration::from_secs(60)))
+        .unwrap();
+    queue.enter(ticket(2, 1800.0, now)).unwrap();
+    assert!(queue.find_matches(now).is_empty());
+}
+
+#[test]
+fn test_max_spread_caps_widening() {
+    let now = Instant::now();
+    let config = QueueConfig {
+        max_spread: Some(250.0),
+        ..QueueConfig::default()
+    };
+    let mut queue = MatchQueue::new(config);
+    queue.enter(ticket(1, 1500.0, now)).unwrap();
+    queue.enter(ticket(2, 1800.0, now)).unwrap();
+
+    let later = now + Duration::from_secs(3600);
+    assert_eq!(queue.spread(&queue.tickets()[0], later), 250.0);
+    assert!(queue.find_matches(later).is_empty());
+}
+
+#[test]
+fn test_only_same_params_match() {
+    let now = Instant::now();
+    let mut queue = MatchQueue::new(QueueConfig::default());
+    queue
+        .enter(Ticket {
+            params: region("eu"),
+            ..ticket(1, 1500.0, now)
+        })
+        .unwrap();
+    queue
+        .enter(Ticket {
+            params: region("na"),
+            ..ticket(2, 1500.0, now)
+        })
+        .unwrap();
+    assert!(queue.find_matches(now).is_empty());
+
+    queue
+        .enter(Ticket {
+            params: region("eu"),
+            ..ticket(3, 1500.0, now)
+        })
+        .unwrap();
+    let matches = queue.find_matches(now);
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].players(), vec![PlayerId(1), PlayerId(3)]);
+    assert_eq!(matches[0].params, region("eu"));
+    assert!(queue.contains(PlayerId(2)));
+}
+
+#[test]
+fn test_longest_wait_picks_closest_ratings() {
+    let now = Instant::now();
+    let config = QueueConfig {
+        match_size: 3,
+        initial_spread: 500.0,
+        ..QueueConfig::default()
+    };
+    let mut queue = MatchQueue::new(config);
+    queue.enter(ticket(1, 1500.0, now)).unwrap();
+    queue.enter(ticket(2, 1900.0, now)).unwrap();
+    queue.enter(ticket(3, 1450.0, now)).unwrap();
+    queue.enter(ticket(4, 1600.0, now)).unwrap();
+
+    let matches = queue.find_matches(now);
+    assert_eq!(matches.len(), 1);
+    assert_eq!(
+        matches[0].players(),
+        vec![PlayerId(1), PlayerId(3), PlayerId(4)]
+    );
+    assert_eq!(queue.len(), 1);
+    assert!(queue.contains(PlayerId(2)));
+}
+
+#[test]
+fn test_position_counts_same_params_only() {
+    let now = Instant::now();
+    let mut queue = MatchQueue::new(QueueConfig::default());
+    queue.enter(ticket(1, 1500.0, now)).unwrap();
+    queue
+        .enter(Ticket {
+            params: region("eu"),
+            ..ticket(2, 1500.0, now)
+        })
+        .unwrap();
+    queue.enter(ticket(3, 1500.0, now)).unwrap();
+
+    assert_eq!(queue.position(PlayerId(1)), Some(1));
+    assert_eq!(queue.position(PlayerId(2)), Some(1));
+    assert_eq!(queue.position(PlayerId(3)), Some(2));
+
+    queue.leave(PlayerId(1)).unwrap();
+    assert_eq!(queue.position(PlayerId(3)), Some(1));
+    assert_eq!(queue.position(PlayerId(1)), None);
+}
+
+#[test]
+fn test_enter_twice_and_leave_unqueued_fail() {
+    let now = Instant::now();
+    let mut queue = MatchQueue::new(QueueConfig::default());
+    queue.enter(ticket(1, 1500.0, now)).unwrap();
+    assert!(matches!(
+        queue.enter(ticket(1, 1500.0, now)),
+        Err(MatchmakingError::AlreadyQueued(PlayerId(1)))
+    ));
+    assert!(matches!(
+        queue.leave(PlayerId(2)),
+        Err(MatchmakingError::NotQueued(PlayerId(2)))
+    ));
+}
+
+#[test]
+fn test_parties_are_matched_whole() {
+    let now = Instant::now();
+    let config = QueueConfig {
+        match_size: 4,
+        ..QueueConfig::default()
+    };
+    let mut queue = MatchQueue::new(config);
+    queue
+        .enter(Ticket {
+            party: vec![PlayerId(2), PlayerId(3)],
+            ..ticket(1, 1500.0, now)
+        })
+        .unwrap();
+    // The closest ticket is a pair, which doesn't fit next to three.
+    queue
+        .enter(Ticket {
+            party: vec![PlayerId(5)],
+            ..ticket(4, 1500.0, now)
+        })
+        .unwrap();
+    queue.enter(ticket(6, 1550.0, now)).unwrap();
+
+    assert_eq!(queue.position(PlayerId(3)), Some(1));
+    assert_eq!(queue.position(PlayerId(5)), Some(2));
+
+    let matches = queue.find_matches(now);
+    assert_eq!(matches.len(), 1);
+    assert_eq!(
+        matches[0].players(),
+        vec![PlayerId(1), PlayerId(2), PlayerId(3), PlayerId(6)]
+    );
+    assert_eq!(matches[0].average_rating(), 1512.5);
+    assert!(queue.contains(PlayerId(5)));
+}
+
+#[test]
+fn test_every_pair_in_a_match_is_within_reach() {
+    let now = Instant::now();
+    let config = QueueConfig {
+        match_size: 3,
+        ..QueueConfig::default()
+    };
+    let mut queue = MatchQueue::new(config);
+    queue.enter(ticket(1, 1500.0, now)).unwrap();
+    queue.enter(ticket(2, 1420.0, now)).unwrap();
+    queue.enter(ticket(3, 1580.0, now)).unwrap();
+
+    // Both are close to player 1, but 160 apart from each other.
+    assert!(queue.find_matches(now).is_empty());
+
+    queue.enter(ticket(4, 1450.0, now)).unwrap();
+    let matches = queue.find_matches(now);
+    assert_eq!(matches.len(), 1);
+    assert_eq!(
+        matches[0].players(),
+        vec![PlayerId(1), PlayerId(2), PlayerId(4)]
+    );
+    assert!(queue.contains(PlayerId(3)));
+}
+
+#[test]
+fn test_party_member_leaving_takes_the_party_out() {
+    let now = Instant::now();
+    let mut queue = MatchQueue::new(QueueConfig::default());
+    queue
+        .enter(Ticket {
+            party: vec![PlayerId(2)],
+            ..ticket(1, 1500.0, now)
+        })
+        .unwrap();
+
+    // A member can't queue on their own while their party is queued.
+    assert!(matches!(
+        queue.enter(ticket(2, 1500.0, now)),
+        Err(MatchmakingError::AlreadyQueued(PlayerId(2)))
+    ));
+
+    let left = queue.leave(PlayerId(2)).unwrap();
+    assert_eq!(left.player_id, PlayerId(1));
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn test_party_larger_than_a_match_is_refused() {
+    let now = Instant::now();
+    let mut queue = MatchQueue::new(QueueConfig::default());
+    let result = queue.enter(Ticket {
+        party: vec![PlayerId(2), PlayerId(3)],
+        ..ticket(1, 1500.0, now)
+    });
+    assert!(matches!(
+        result,
+        Err(MatchmakingError::PartyTooLarge {
+            size: 3,
+            match_size: 2
+        })
+    ));
+}
+
+// =========================================================================
+// Matchmaker
+// =========================================================================
+
+#[tokio::test]
+async fn test_match_is_seated_in_a_fresh_room() {
+    let mut rooms = RoomManager::<DuelGame>::new();
+    let mut mm = matchmaker();
+    let (tx1, mut rx1) = channel();
+    let (tx2, mut rx2) = channel();
+    mm.enter(PlayerId(1), 1400.0, RoomMetadata::new(), tx1).unwrap();
+    mm.enter(PlayerId(2), 1460.0, RoomMetadata::new(), tx2).unwrap();
+
+    let placed = mm.place(&mut rooms).await;
+    assert_eq!(placed.len(), 1);
+    let room_id = placed[0];
+    assert!(!mm.is_queued(PlayerId(1)));
+    assert_eq!(rooms.player_room(&PlayerId(1)), Some(room_id));
+    assert_eq!(rooms.player_room(&PlayerId(2)), Some(room_id));
+
+    let info = rooms.get_room_info(room_id).await.unwrap();
+    assert!(info.private);
+    assert_eq!(info.player_count, 2);
+
+    assert_eq!(
+        system_messages(&mut rx2),
+        vec![
+            SystemMessage::QueuePosition { position: 2 },
+            SystemMessage::MatchFound { room_id },
+        ]
+    );
+
+    // The room's traffic follows on the queue's channel, and the game
+    // was configured from the match.
+    let mut state = None;
+    while let Ok(outbound) = rx1.try_recv() {
+        if let RoomOutbound::State(view) = outbound {
+            state = Some(view);
+        }
+    }
+    assert_eq!(state.expect("game started").rating, 1430.0);
+}
+
+#[tokio::test]
+async fn test_party_is_seated_together() {
+    let mut rooms = RoomManager::<DuelGame>::new();
+    let mut mm = matchmaker();
+    let (tx1, mut rx1) = channel();
+    let (tx2, mut rx2) = channel();
+    mm.enter_party(
+        vec![(PlayerId(1), tx1), (PlayerId(2), tx2)],
+        1500.0,
+        RoomMetadata::new(),
+    )
+    .unwrap();
+
+    // Both members hear their place in line.
+    assert_eq!(
+        system_messages(&mut rx2),
+        vec![SystemMessage::QueuePosition { position: 1 }]
+    );
+
+    let placed = mm.place(&mut rooms).await;
+    assert_eq!(placed.len(), 1);
+    assert_eq!(rooms.player_room(&PlayerId(1)), Some(placed[0]));
+    assert_eq!(rooms.player_room(&PlayerId(2)), Some(placed[0]));
+    assert!(
+        system_messages(&mut rx1)
+            .contains(&SystemMessage::MatchFound { room_id: placed[0] })
+    );
+}
+
+#[tokio::test]
+async fn test_far_apart_players_keep_waiting() {
+    let mut rooms = RoomManager::<DuelGame>::new();
+    let mut mm = matchmaker();
+    let (tx1, _rx1) = channel();
+    let (tx2, _rx2) = channel();
+    mm.enter(PlayerId(1), 1000.0, RoomMetadata::new(), tx1).unwrap();
+    mm.enter(PlayerId(2), 2000.0, RoomMetadata::new(), tx2).unwrap();
+
+    assert!(mm.place(&mut rooms).await.is_empty());
+    assert_eq!(rooms.room_count(), 0);
+    assert!(mm.is_queued(PlayerId(1)));
+    assert!(mm.is_queued(PlayerId(2)));
+}
+
+#[tokio::test]
+async fn test_positions_are_posted_when_they_change() {
+    let mut mm = matchmaker();
+    let (tx1, _rx1) = channel();
+    let (tx2, mut rx2) = channel();
+    mm.enter(PlayerId(1), 1000.0, RoomMetadata::new(), tx1).unwrap();
+    mm.enter(PlayerId(2), 2000.0, RoomMetadata::new(), tx2).unwrap();
+    assert_eq!(
+        system_messages(&mut rx2),
+        vec![SystemMessage::QueuePosition { position: 2 }]
+    );
+
+    mm.leave(PlayerId(1)).unwrap();
+    assert_eq!(
+        system_messages(&mut rx2),
+        vec![SystemMessage::QueuePosition { position: 1 }]
+    );
+
+    // Nothing changed, nothing sent.
+    let mut rooms = RoomManager::<DuelGame>::new();
+    mm.place(&mut rooms).await;
+    assert!(system_messages(&mut rx2).is_empty());
+}
+
+#[tokio::test]
+async fn test_players_who_left_are_dropped_from_the_queue() {
+    let mut rooms = RoomManager::<DuelGame>::new();
+    let mut mm = matchmaker();
+
+    // Player 1 joined a room on their own; player 2 hung up.
+    let (tx1, _rx1) = channel();
+    let (tx2, rx2) = channel();
+    let (tx3, _rx3) = channel();
+    mm.enter(PlayerId(1), 1500.0, RoomMetadata::new(), tx1.clone())
+        .unwrap();
+    mm.enter(PlayerId(2), 1500.0, RoomMetadata::new(), tx2).unwrap();
+    mm.enter(PlayerId(3), 1500.0, RoomMetadata::new(), tx3).unwrap();
+    let other = rooms.create_room(DuelConfig::default());
+    rooms.join_room(PlayerId(1), other, tx1).await.unwrap();
+    drop(rx2);
+
+    assert!(mm.place(&mut rooms).await.is_empty());
+    assert!(!mm.is_queued(PlayerId(1)));
+    assert!(!mm.is_queued(PlayerId(2)));
+    assert!(mm.is_queued(PlayerId(3)));
+}
+
+#[tokio::test]
+async fn test_match_that_never_seats_is_dropped() {
+    let mut rooms = RoomManager::<CrampedGame>::new();
+    let mut mm = Matchmaker::<CrampedGame>::new(
+        QueueConfig::default(),
+        |_: &Match| DuelConfig::default(),
+    );
+    let (tx1, mut rx1) = mpsc::unbounded_channel();
+    let (tx2, _rx2) = mpsc::unbounded_channel();
+    mm.enter(PlayerId(1), 1500.0, RoomMetadata::new(), tx1).unwrap();
+    mm.enter(PlayerId(2), 1500.0, RoomMetadata::new(), tx2).unwrap();
+
+    // The first failures put them back in line.
+    for _ in 0..2 {
+        assert!(mm.place(&mut rooms).await.is_empty());
+        assert!(mm.is_queued(PlayerId(1)));
+        assert!(mm.is_queued(PlayerId(2)));
+    }
+    assert_eq!(rooms.room_count(), 0);
+
+    // Then they're told to give up instead of matching forever.
+    assert!(mm.place(&mut rooms).await.is_empty());
+    assert!(!mm.is_queued(PlayerId(1)));
+    assert!(!mm.is_queued(PlayerId(2)));
+    assert!(mm.place(&mut rooms).await.is_empty());
+
+    let mut msgs = Vec::new();
+    while let Ok(RoomOutbound::System(msg)) = rx1.try_recv() {
+        msgs.push(msg);
+    }
+    let found = msgs
+        .iter()
+        .filter(|m| matches!(m, SystemMessage::MatchFound { .. }))
+        .count();
+    let cancelled = msgs
+        .iter()
+        .filter(|m| matches!(m, SystemMessage::MatchCancelled { .. }))
+        .count();
+    assert_eq!((found, cancelled), (3, 3));
+    assert!(matches!(
+        msgs.last(),
+        Some(SystemMessage::Error { code: 503, .. })
+    ));
+    // The matchmaker let go of the player's channel.
+    assert!(rx1.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn test_enter_twice_fails() {
+    let mut mm = matchmaker();
+    let (tx, _rx) = channel();
+    mm.enter(PlayerId(1), 1500.0, RoomMetadata::new(), tx.clone())
+        .unwrap();
+    assert!(matches!(
+        mm.enter(PlayerId(1), 1500.0, RoomMetadata::new(), tx),
+        Err(MatchmakingError::AlreadyQueued(PlayerId(1)))
+    ));
+    assert!(matches!(
+        mm.leave(PlayerId(2)),
+        Err(MatchmakingError::NotQueued(PlayerId(2)))
+    ));
+}
+
+#[test]
+#[should_panic(expected = "max_players")]
+fn test_match_size_over_max_players_panics() {
+    let config = QueueConfig {
+        match_size: 3,
+        ..QueueConfig::default()
+    };
+    let _ = Matchmaker::<DuelGame>::new(config, |_: &Match| {
+        DuelConfig::default()
+    });
+}
@@ -0,0 +1,143 @@
+//! Tests for the party registry.
+
+use arcforge_matchmaking::{Parties, PartyError};
+use arcforge_protocol::{PartyId, PlayerId};
+
+fn pid(n: u64) -> PlayerId {
+    PlayerId(n)
+}
+
+/// Creates a party led by player 1 with players 2..=`size` in it.
+fn party_of(parties: &mut Parties, size: u64) -> PartyId {
+    let party_id = parties.create(pid(1)).unwrap().party_id;
+    for i in 2..=size {
+        parties.invite(pid(1), pid(i)).unwrap();
+        parties.accept(pid(i), party_id).unwrap();
+    }
+    party_id
+}
+
+#[test]
+fn test_invite_and_accept() {
+    let mut parties = Parties::new(4);
+    let party = parties.create(pid(1)).unwrap();
+    assert_eq!(party.leader, pid(1));
+    assert_eq!(party.members, vec![pid(1)]);
+
+    // No invite, no entry.
+    assert!(matches!(
+        parties.accept(pid(2), party.party_id),
+        Err(PartyError::NotInvited(..))
+    ));
+
+    assert_eq!(parties.invite(pid(1), pid(2)).unwrap(), party.party_id);
+    let party = parties.accept(pid(2), party.party_id).unwrap();
+    assert_eq!(party.members, vec![pid(1), pid(2)]);
+    assert_eq!(parties.party_of(pid(2)), Some(party));
+
+    // Invites are used up.
+    assert!(parties.accept(pid(2), PartyId(1)).is_err());
+}
+
+#[test]
+fn test_only_the_leader_invites_promotes_and_kicks() {
+    let mut parties = Parties::new(4);
+    party_of(&mut parties, 2);
+
+    assert!(matches!(
+        parties.invite(pid(2), pid(3)),
+        Err(PartyError::NotLeader(_))
+    ));
+    assert!(matches!(
+        parties.kick(pid(2), pid(1)),
+        Err(PartyError::NotLeader(_))
+    ));
+    assert!(matches!(
+        parties.promote(pid(3), pid(3)),
+        Err(PartyError::NotInParty(_))
+    ));
+
+    let party = parties.promote(pid(1), pid(2)).unwrap();
+    assert_eq!(party.leader, pid(2));
+    assert!(parties.invite(pid(1), pid(3)).is_err());
+    assert!(parties.invite(pid(2), pid(3)).is_ok());
+}
+
+#[test]
+fn test_one_party_at_a_time() {
+    let mut parties = Parties::new(4);
+    party_of(&mut parties, 2);
+    let other = parties.create(pid(3)).unwrap().party_id;
+
+    assert!(matches!(
+        parties.create(pid(2)),
+        Err(PartyError::AlreadyInParty(_))
+    ));
+    parties.invite(pid(3), pid(2)).unwrap();
+    assert!(matches!(
+        parties.accept(pid(2), other),
+        Err(PartyError::AlreadyInParty(_))
+    ));
+}
+
+#[test]
+fn test_parties_are_no_bigger_than_a_room() {
+    let mut parties = Parties::new(2);
+    let party_id = parties.create(pid(1)).unwrap().party_id;
+    parties.invite(pid(1), pid(2)).unwrap();
+    parties.invite(pid(1), pid(3)).unwrap();
+    parties.accept(pid(2), party_id).unwrap();
+
+    assert!(matches!(
+        parties.accept(pid(3), party_id),
+        Err(PartyError::PartyFull(_))
+    ));
+    assert!(matches!(
+        parties.invite(pid(1), pid(4)),
+        Err(PartyError::PartyFull(_))
+    ));
+}
+
+#[test]
+fn test_leader_leaving_hands_over_the_party() {
+    let mut parties = Parties::new(4);
+    let party_id = party_of(&mut parties, 3);
+
+    let (left, rest) = parties.leave(pid(1)).unwrap();
+    assert_eq!(left, party_id);
+    let rest = rest.unwrap();
+    assert_eq!(rest.leader, pid(2));
+    assert_eq!(rest.members, vec![pid(2), pid(3)]);
+    assert_eq!(parties.party_of(pid(1)), None);
+
+    let (_, rest) = parties.kick(pid(2), pid(3)).unwrap();
+    assert_eq!(rest.unwrap().members, vec![pid(2)]);
+
+    // The last one out disbands it.
+    let (_, rest) = parties.leave(pid(2)).unwrap();
+    assert!(rest.is_none());
+    assert!(parties.is_empty());
+    assert!(matches!(
+        parties.leave(pid(2)),
+        Err(PartyError::NotInParty(_))
+    ));
+}
+
+#[test]
+fn test_invite_to_a_disbanded_party() {
+    let mut parties = Parties::new(4);
+    let party_id = parties.create(pid(1)).unwrap().party_id;
+    parties.invite(pid(1), pid(2)).unwrap();
+    parties.invite(pid(1), pid(3)).unwrap();
+    parties.leave(pid(1)).unwrap();
+
+    // The invites went with the party.
+    assert!(matches!(
+        parties.accept(pid(2), party_id),
+        Err(PartyError::NotInvited(_, _))
+    ));
+    assert!(matches!(
+        parties.accept(pid(3), party_id),
+        Err(PartyError::NotInvited(_, _))
+    ));
+}
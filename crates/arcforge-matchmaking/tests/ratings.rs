@@ -0,0 +1,317 @@
+//! Tests for rating systems and rating stores.
+
+use std::sync::Arc;
+
+use arcforge_matchmaking::{
+    FileStore, MemoryStore, Rating, RatingSink, RatingStore, RatingSystem,
+};
+use arcforge_protocol::{EndReason, MatchResult, PlayerId, RoomId, TeamId};
+use arcforge_room::{MatchRecord, ResultSink};
+
+fn rating(value: f64) -> Rating {
+    Rating {
+        rating: value,
+        ..Rating::default()
+    }
+}
+
+fn glicko(value: f64, deviation: f64) -> Rating {
+    Rating {
+        rating: value,
+        deviation,
+        ..Rating::default()
+    }
+}
+
+/// Rates `result` with everyone's starting rating taken from `start`.
+fn rate(
+    system: RatingSystem,
+    result: &MatchResult,
+    start: &[(u64, Rating)],
+) -> Vec<(u64, Rating)> {
+    system
+        .rate(result, |player| {
+            start
+                .iter()
+                .find(|(id, _)| *id == player.0)
+                .map(|(_, r)| *r)
+                .unwrap_or_default()
+        })
+        .into_iter()
+        .map(|(id, r)| (id.0, r))
+        .collect()
+}
+
+fn find(rated: &[(u64, Rating)], id: u64) -> Rating {
+    rated.iter().find(|(p, _)| *p == id).unwrap().1
+}
+
+fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+    assert!(
+        (actual - expected).abs() <= tolerance,
+        "expected {expected}, got {actual}"
+    );
+}
+
+fn record(result: MatchResult) -> MatchRecord {
+    MatchRecord {
+        room_id: RoomId(1),
+        seed: 0,
+        reason: EndReason::Completed,
+        ended_at_ms: 0,
+        duration_ms: 0,
+        result,
+        bots: Vec::new(),
+    }
+}
+
+// =========================================================================
+// Elo
+// =========================================================================
+
+#[test]
+fn test_elo_1v1_even_match() {
+    let result = MatchResult::from_scores([(PlayerId(1), 1), (PlayerId(2), 0)]);
+    let rated = rate(RatingSystem::elo(), &result, &[]);
+
+    assert_close(find(&rated, 1).rating, 1516.0, 1e-9);
+    assert_close(find(&rated, 2).rating, 1484.0, 1e-9);
+    assert_eq!(find(&rated, 1).games, 1);
+    // Elo doesn't touch deviation.
+    assert_eq!(find(&rated, 1).deviation, Rating::default().deviation);
+}
+
+#[test]
+fn test_elo_upset_moves_ratings_further() {
+    let result = MatchResult::from_scores([(PlayerId(1), 1), (PlayerId(2), 0)]);
+    let start = [(1, rating(1400.0)), (2, rating(1800.0))];
+    let rated = rate(RatingSystem::elo(), &result, &start);
+
+    // Expected score for the underdog is 1 / (1 + 10) ≈ 0.0909.
+    let gain = 32.0 * (1.0 - 1.0 / 11.0);
+    assert_close(find(&rated, 1).rating, 1400.0 + gain, 1e-9);
+    assert_close(find(&rated, 2).rating, 1800.0 - gain, 1e-9);
+}
+
+#[test]
+fn test_elo_draw_pulls_ratings_together() {
+    let result = MatchResult::from_scores([(PlayerId(1), 0), (PlayerId(2), 0)]);
+
+    let even = rate(RatingSystem::elo(), &result, &[]);
+    assert_close(find(&even, 1).rating, 1500.0, 1e-9);
+
+    let start = [(1, rating(1400.0)), (2, rating(1600.0))];
+    let uneven = rate(RatingSystem::elo(), &result, &start);
+    assert!(find(&uneven, 1).rating > 1400.0);
+    assert!(find(&uneven, 2).rating < 1600.0);
+}
+
+#[test]
+fn test_elo_teams_share_the_update() {
+    let mut result = MatchResult::from_scores([
+        (PlayerId(1), 1),
+        (PlayerId(2), 1),
+        (PlayerId(3), 0),
+        (PlayerId(4), 0),
+    ]);
+    for (id, team) in [(1, 0), (2, 0), (3, 1), (4, 1)] {
+        result.player_mut(PlayerId(id)).unwrap().team = Some(TeamId(team));
+    }
+    // Team 0 averages 1500, team 1 averages 1500.
+    let rated = rate(
+        RatingSystem::elo(),
+        &result,
+        &[
+            (1, rating(1600.0)),
+            (2, rating(1400.0)),
+            (3, rating(1550.0)),
+            (4, rating(1450.0)),
+        ],
+    );
+
+    assert_close(find(&rated, 1).rating, 1616.0, 1e-9);
+    assert_close(find(&rated, 2).rating, 1416.0, 1e-9);
+    assert_close(find(&rated, 3).rating, 1534.0, 1e-9);
+    assert_close(find(&rated, 4).rating, 1434.0, 1e-9);
+}
+
+#[test]
+fn test_elo_free_for_all_ranks_everyone() {
+    let result = MatchResult::from_scores([
+        (PlayerId(1), 3),
+        (PlayerId(2), 2),
+        (PlayerId(3), 1),
+    ]);
+    let rated = rate(RatingSystem::elo(), &result, &[]);
+
+    // First beat two, second split, last lost twice; each pairing is
+    // worth half a 1v1.
+    assert_close(find(&rated, 1).rating, 1516.0, 1e-9);
+    assert_close(find(&rated, 2).rating, 1500.0, 1e-9);
+    assert_close(find(&rated, 3).rating, 1484.0, 1e-9);
+}
+
+#[test]
+fn test_single_side_is_not_rated() {
+    let result = MatchResult::from_scores([(PlayerId(1), 5)]);
+    assert!(rate(RatingSystem::elo(), &result, &[]).is_empty());
+
+    let mut team =
+        MatchResult::from_scores([(PlayerId(1), 5), (PlayerId(2), 5)]);
+    for player in &mut team.players {
+        player.team = Some(TeamId(0));
+    }
+    assert!(rate(RatingSystem::glicko2(), &team, &[]).is_empty());
+}
+
+// =========================================================================
+// Glicko-2
+// =========================================================================
+
+#[test]
+fn test_glicko2_matches_glickmans_example() {
+    // Player 1 (1500, RD 200) beats a 1400/30, loses to a 1550/100 and
+    // to a 1700/300. Glickman's paper gets 1464.06, RD 151.52, and
+    // σ 0.05999.
+    let result = MatchResult::from_scores([
+        (PlayerId(4), 4),
+        (PlayerId(3), 3),
+        (PlayerId(1), 2),
+        (PlayerId(2), 1),
+    ]);
+    let rated = rate(
+        RatingSystem::glicko2(),
+        &result,
+        &[
+            (1, glicko(1500.0, 200.0)),
+            (2, glicko(1400.0, 30.0)),
+            (3, glicko(1550.0, 100.0)),
+            (4, glicko(1700.0, 300.0)),
+        ],
+    );
+
+    let player = find(&rated, 1);
+    assert_close(player.rating, 1464.06, 0.01);
+    assert_close(player.deviation, 151.52, 0.01);
+    assert_close(player.volatility, 0.05999, 0.00001);
+    assert_eq!(player.games, 1);
+}
+
+#[test]
+fn test_glicko2_uncertain_players_move_further() {
+    let result = MatchResult::from_scores([
+        (PlayerId(1), 1),
+        (PlayerId(2), 0),
+        (PlayerId(3), 1),
+        (PlayerId(4), 0),
+    ]);
+    let mut split = result.clone();
+    split.players.retain(|p| p.player_id.0 <= 2);
+    let mut other = result;
+    other.players.retain(|p| p.player_id.0 > 2);
+
+    let newcomer = rate(RatingSystem::glicko2(), &split, &[]);
+    let veteran = rate(
+        RatingSystem::glicko2(),
+        &other,
+        &[(3, glicko(1500.0, 50.0)), (4, glicko(1500.0, 50.0))],
+    );
+
+    let newcomer_gain = find(&newcomer, 1).rating - 1500.0;
+    let veteran_gain = find(&veteran, 3).rating - 1500.0;
+    assert!(newcomer_gain > veteran_gain);
+    assert!(veteran_gain > 0.0);
+    // Playing makes every rating more certain.
+    assert!(find(&newcomer, 1).deviation < 350.0);
+    assert!(find(&newcomer, 2).deviation < 350.0);
+}
+
+// =========================================================================
+// Sink and stores
+// =========================================================================
+
+#[test]
+fn test_rating_sink_updates_the_store() {
+    let store = Arc::new(MemoryStore::new());
+    let sink = RatingSink::new(RatingSystem::elo(), store.clone());
+
+    let win = MatchResult::from_scores([(PlayerId(1), 1), (PlayerId(2), 0)]);
+    sink.record(&record(win.clone()));
+    sink.record(&record(win));
+
+    let winner = store.get(PlayerId(1)).unwrap();
+    assert!(winner.rating > 1516.0);
+    assert_eq!(winner.games, 2);
+    assert!(store.get(PlayerId(3)).is_none());
+
+    let ladder: Vec<u64> = store.top(10).iter().map(|(id, _)| id.0).collect();
+    assert_eq!(ladder, vec![1, 2]);
+    assert_eq!(store.top(1).len(), 1);
+}
+
+#[test]
+fn test_rating_sink_leaves_bots_out() {
+    let store = Arc::new(MemoryStore::new());
+    let sink = RatingSink::new(RatingSystem::elo(), store.clone());
+
+    // A human beating a bot is no contest at all.
+    let result = MatchResult::from_scores([(PlayerId(1), 1), (PlayerId(2), 0)]);
+    sink.record(&MatchRecord {
+        bots: vec![PlayerId(2)],
+        ..record(result)
+    });
+    assert!(store.all().is_empty());
+
+    // With a bot on each team, the humans are rated as before.
+    let mut result = MatchResult::from_scores([
+        (PlayerId(1), 1),
+        (PlayerId(2), 1),
+        (PlayerId(3), 0),
+        (PlayerId(4), 0),
+    ]);
+    for (id, team) in [(1, 0), (2, 0), (3, 1), (4, 1)] {
+        result.player_mut(PlayerId(id)).unwrap().team = Some(TeamId(team));
+    }
+    sink.record(&MatchRecord {
+        bots: vec![PlayerId(2), PlayerId(4)],
+        ..record(result)
+    });
+    let rated: Vec<u64> = store.top(10).iter().map(|(id, _)| id.0).collect();
+    assert_eq!(rated, vec![1, 3]);
+    assert_close(store.get(PlayerId(1)).unwrap().rating, 1516.0, 1e-9);
+}
+
+#[test]
+fn test_file_store_survives_reopening() {
+    let path = std::env::temp_dir().join(format!(
+        "arcforge-ratings-{}.json",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+
+    let store = FileStore::open(&path).unwrap();
+    assert!(store.all().is_empty());
+    store
+        .save(&[(PlayerId(1), rating(1600.0)), (PlayerId(2), rating(1400.0))])
+        .unwrap();
+    store.save(&[(PlayerId(2), rating(1450.0))]).unwrap();
+    drop(store);
+
+    let reopened = FileStore::open(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(reopened.get(PlayerId(1)), Some(rating(1600.0)));
+    assert_eq!(reopened.get(PlayerId(2)), Some(rating(1450.0)));
+    assert_eq!(reopened.all().len(), 2);
+}
+
+#[test]
+fn test_file_store_rejects_a_corrupt_file() {
+    let path = std::env::temp_dir().join(format!(
+        "arcforge-ratings-corrupt-{}.json",
+        std::process::id()
+    ));
+    std::fs::write(&path, b"not json").unwrap();
+    let result = FileStore::open(&path);
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+}
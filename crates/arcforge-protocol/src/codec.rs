@@ -81,6 +81,7 @@ pub trait Codec: Send + Sync + 'static {
 ///     seq: 1,
 ///     timestamp: 5000,
 ///     channel: Channel::ReliableOrdered,
+///     ack: None,
 ///     payload: Payload::System(SystemMessage::Heartbeat { client_time: 5000 }),
 /// };
 ///
@@ -45,6 +45,7 @@ pub use codec::Codec;
 pub use codec::JsonCodec;
 pub use error::ProtocolError;
 pub use types::{
-    Channel, Envelope, Payload, PlayerId, Recipient, RoomId, RoomListEntry,
-    SystemMessage,
+    Channel, ChatScope, EndReason, Envelope, MatchResult, PartyId, PartyInfo,
+    Payload, PlayerId, PlayerResult, Recipient, RoomId, RoomListEntry,
+    RoomMetadata, SystemMessage, TeamId,
 };
@@ -16,6 +16,10 @@ use serde::{Deserialize, Serialize};
 // We also need `fmt` for implementing Display (human-readable printing).
 use std::fmt;
 
+// `BTreeMap` is a sorted map. We use it (instead of `HashMap`) for data
+// that goes on the wire so the serialized key order is stable.
+use std::collections::BTreeMap;
+
 // ---------------------------------------------------------------------------
 // Identity types
 // ---------------------------------------------------------------------------
@@ -66,6 +70,32 @@ impl fmt::Display for RoomId {
     }
 }
 
+/// A unique identifier for a party: players who join rooms and queue
+/// together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PartyId(pub u64);
+
+impl fmt::Display for PartyId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PT-{}", self.0)
+    }
+}
+
+/// Identifies a team within a room. Teams are numbered from 0.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize,
+    Deserialize,
+)]
+#[serde(transparent)]
+pub struct TeamId(pub u32);
+
+impl fmt::Display for TeamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "T-{}", self.0)
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Recipient — who should receive a message?
 // ---------------------------------------------------------------------------
@@ -91,6 +121,35 @@ pub enum Recipient {
     /// Send to everyone EXCEPT the specified player.
     /// Useful for broadcasting "Player X moved" to everyone else.
     AllExcept(PlayerId),
+
+    /// Send to every player on one team (see `RoomConfig::teams`).
+    /// Nobody gets it in a room without teams.
+    Team(TeamId),
+
+    /// Send to each of these players. Players who aren't in the room
+    /// are skipped.
+    Players(Vec<PlayerId>),
+}
+
+// ---------------------------------------------------------------------------
+// ChatScope — who hears a chat message?
+// ---------------------------------------------------------------------------
+
+/// Who a chat message is for. Chat never leaves the sender's room.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default,
+)]
+pub enum ChatScope {
+    /// Everyone in the room.
+    #[default]
+    Room,
+
+    /// The sender's team. Only in rooms with teams.
+    Team,
+
+    /// One other player in the room. Whispers aren't kept in the
+    /// room's chat history.
+    Whisper(PlayerId),
 }
 
 // ---------------------------------------------------------------------------
@@ -132,6 +191,13 @@ pub enum Channel {
 // SystemMessage — framework-level messages
 // ---------------------------------------------------------------------------
 
+/// Public, game-defined key/value metadata attached to a room.
+///
+/// Examples: `"map" => "dust"`, `"mode" => "ranked"`, `"region" => "eu"`.
+/// The framework doesn't interpret these values — it only stores them,
+/// shows them in room listings, and matches them against list filters.
+pub type RoomMetadata = BTreeMap<String, String>;
+
 /// A summary of a room returned in room listings.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct RoomListEntry {
@@ -141,6 +207,109 @@ pub struct RoomListEntry {
     pub player_count: usize,
     /// Maximum players allowed.
     pub max_players: usize,
+    /// Public metadata published by the game (map, mode, region, etc.).
+    /// Omitted by older servers, so it defaults to empty.
+    #[serde(default)]
+    pub metadata: RoomMetadata,
+    /// `true` if joining requires a password.
+    #[serde(default)]
+    pub password_protected: bool,
+    /// `true` if the game is already running and takes drop-in players.
+    #[serde(default)]
+    pub in_progress: bool,
+}
+
+/// A party's members, as sent in [`SystemMessage::PartyUpdated`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartyInfo {
+    /// The party's server-assigned ID.
+    pub party_id: PartyId,
+    /// The member who joins rooms and queues for the whole party.
+    pub leader: PlayerId,
+    /// Everyone in the party, leader included, in the order they joined.
+    pub members: Vec<PlayerId>,
+}
+
+/// Why a game ended, as reported in [`SystemMessage::GameEnded`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub enum EndReason {
+    /// The game reached its own end (someone won, the board filled up).
+    Completed,
+    /// The room's `max_duration` ran out first.
+    TimedOut,
+}
+
+/// One player's outcome in a [`MatchResult`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerResult {
+    /// The player this result is for.
+    pub player_id: PlayerId,
+    /// 1 for the winner. Tied players share a placement, and the next
+    /// placement skips accordingly (1, 2, 2, 4).
+    pub placement: u32,
+    /// The game's final score for the player. Its meaning is up to the
+    /// game; placements are what ratings go by.
+    pub score: i64,
+    /// The player's team in team games. Teammates are rated as one
+    /// side; players without a team each play for themselves. Rooms
+    /// with teams fill this in when the game leaves it unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub team: Option<TeamId>,
+    /// Game-defined counters (`"kills"`, `"turns"`, ...). Integers keep
+    /// results exact; scale fractional stats if you need them.
+    #[serde(default)]
+    pub stats: BTreeMap<String, i64>,
+}
+
+/// The authoritative outcome of a game, as declared by the server.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct MatchResult {
+    /// Every player's result, best placement first.
+    pub players: Vec<PlayerResult>,
+}
+
+impl MatchResult {
+    /// Ranks players by score, highest first, with ties sharing a
+    /// placement. Stats start out empty.
+    pub fn from_scores(
+        scores: impl IntoIterator<Item = (PlayerId, i64)>,
+    ) -> Self {
+        let mut scores: Vec<_> = scores.into_iter().collect();
+        scores.sort_by_key(|(player, score)| {
+            (std::cmp::Reverse(*score), player.0)
+        });
+
+        let mut players: Vec<PlayerResult> =
+            Vec::with_capacity(scores.len());
+        for (index, (player_id, score)) in scores.into_iter().enumerate() {
+            let placement = match players.last() {
+                Some(prev) if prev.score == score => prev.placement,
+                _ => index as u32 + 1,
+            };
+            players.push(PlayerResult {
+                player_id,
+                placement,
+                score,
+                team: None,
+                stats: BTreeMap::new(),
+            });
+        }
+        Self { players }
+    }
+
+    /// Returns `player`'s result, if they're in this match.
+    pub fn player(&self, player: PlayerId) -> Option<&PlayerResult> {
+        self.players.iter().find(|r| r.player_id == player)
+    }
+
+    /// Returns `player`'s result for editing (e.g. to add stats).
+    pub fn player_mut(
+        &mut self,
+        player: PlayerId,
+    ) -> Option<&mut PlayerResult> {
+        self.players.iter_mut().find(|r| r.player_id == player)
+    }
 }
 
 /// Messages used by the framework itself (not game-specific).
@@ -197,10 +366,45 @@ pub enum SystemMessage {
         server_time: u64,
     },
 
+    /// Server → Client: the server's own heartbeat, sent every few
+    /// seconds while the player is in a room. The client answers with a
+    /// `ServerHeartbeatAck` straight away.
+    ServerHeartbeat { server_time: u64 },
+
+    /// Client → Server: echoes a `ServerHeartbeat`'s `server_time`. The
+    /// server takes the time since it sent it as the player's RTT, for
+    /// lag compensation.
+    ServerHeartbeatAck { server_time: u64 },
+
     // -- Room management --
 
     /// Client → Server: "Put me in this specific room."
-    JoinRoom { room_id: RoomId },
+    /// `password` is only needed for password-protected rooms.
+    /// Private rooms can't be joined by ID — use `JoinByCode`.
+    JoinRoom {
+        room_id: RoomId,
+        #[serde(default)]
+        password: Option<String>,
+    },
+
+    /// Client → Server: "Put me in the room with this invite code."
+    /// Works for both public and private rooms.
+    JoinByCode {
+        code: String,
+        #[serde(default)]
+        password: Option<String>,
+    },
+
+    /// Client → Server: "Create a new room and put me in it."
+    /// A `private` room is hidden from `ListRooms` and can only be
+    /// joined with its invite code. `password`, if set, is required
+    /// from everyone else who joins.
+    CreateRoom {
+        #[serde(default)]
+        private: bool,
+        #[serde(default)]
+        password: Option<String>,
+    },
 
     /// Client → Server: "Find me a room or create a new one."
     /// `name` is the game/room type. `options` is opaque config data
@@ -214,7 +418,13 @@ pub enum SystemMessage {
     LeaveRoom,
 
     /// Client → Server: "Show me available rooms."
-    ListRooms,
+    /// `filter` narrows the list to rooms whose metadata contains every
+    /// given key with exactly the given value. An empty (or missing)
+    /// filter lists all joinable rooms.
+    ListRooms {
+        #[serde(default)]
+        filter: RoomMetadata,
+    },
 
     /// Server → Client: "Here are the available rooms."
     RoomList {
@@ -227,17 +437,208 @@ pub enum SystemMessage {
     /// knows how to interpret these bytes.
     RoomState { data: Vec<u8> },
 
+    /// Server → Client: "Here's your full view of the game, snapshot
+    /// number `seq`." Sent periodically by rooms with continuous state
+    /// sync. Acknowledge with `StateAck` to start receiving deltas.
+    StateKeyframe { seq: u64, data: Vec<u8> },
+
+    /// Server → Client: "Apply this patch to snapshot `base_seq` to get
+    /// snapshot `seq`." `data` is a JSON merge patch, encoded by the
+    /// codec. `base_seq` is always a snapshot you acknowledged.
+    StateDelta {
+        seq: u64,
+        base_seq: u64,
+        data: Vec<u8>,
+    },
+
+    /// Client → Server: "I've applied snapshot `seq`."
+    StateAck { seq: u64 },
+
     /// Server → Client: "You've joined a room."
     RoomJoined {
         room_id: RoomId,
         session_id: String,
     },
 
+    /// Server → Client: "Your room was created and you're in it."
+    /// `invite_code` is a short code other players can use with
+    /// `JoinByCode`.
+    RoomCreated {
+        room_id: RoomId,
+        invite_code: String,
+    },
+
+    /// Server → Client: "Your room is gone and you're no longer in it."
+    /// Sent when a room disposes of itself (`reason` is `"finished"`,
+    /// `"empty"`, or `"idle"`) or the server shuts it down
+    /// (`"destroyed"`).
+    RoomClosed { room_id: RoomId, reason: String },
+
+    // -- Host controls --
+    // Only rooms created with `CreateRoom` have a host. Everyone else
+    // gets a 403 error for these.
+
+    /// Client → Server (host only): "Remove this player from my room."
+    KickPlayer { player_id: PlayerId },
+
+    /// Client → Server (host only): "Stop letting new players in."
+    LockRoom,
+
+    /// Client → Server (host only): "Let new players in again."
+    UnlockRoom,
+
+    /// Client → Server (host only): "Pause the game." Ticks and game
+    /// timers stop until the host resumes.
+    PauseGame,
+
+    /// Client → Server (host only): "Resume the paused game."
+    ResumeGame,
+
+    /// Client → Server (host only): "Start the game now."
+    /// Hosted rooms never start on their own; the host decides once
+    /// enough players have joined.
+    StartGame,
+
+    /// Server → Client: "This player is now the room's host."
+    /// Sent when you join a hosted room, and to everyone when the
+    /// host leaves and the role passes to the longest-present player.
+    HostChanged { host: PlayerId },
+
+    /// Server → Client: "The host removed you from the room."
+    Kicked { room_id: RoomId },
+
+    // -- Pre-game --
+    // Only used by rooms whose game configures a pre-game phase.
+
+    /// Client → Server: "I'm ready to play" (or, with `false`, "wait").
+    Ready { ready: bool },
+
+    /// Server → Client: "This player's readiness changed."
+    ReadyChanged { player_id: PlayerId, ready: bool },
+
+    /// Server → Client: "The game starts in `remaining_ms` milliseconds
+    /// unless everyone readies up first." Repeated as the clock runs down.
+    Countdown { remaining_ms: u64 },
+
+    /// Server → Client: "The countdown stopped because a player left."
+    /// The room is waiting for players again, and nobody is ready.
+    CountdownCancelled,
+
+    /// Server → Client: "The game is over." Follows the final state.
+    /// `result` is empty for games that don't report one.
+    GameEnded {
+        reason: EndReason,
+        #[serde(default)]
+        result: MatchResult,
+    },
+
+    /// Client → Server: "I'd play again" (or, with `false`, "I
+    /// wouldn't"). Only allowed once the game has ended. When every
+    /// connected player still in the room wants a rematch, the game
+    /// restarts in the same room with the same players.
+    Rematch { rematch: bool },
+
+    /// Server → Client: "This player's rematch vote changed."
+    RematchChanged { player_id: PlayerId, rematch: bool },
+
+    // -- Matchmaking --
+    // Only on servers with matchmaking turned on. Everyone else gets a
+    // 501 error for these.
+
+    /// Client → Server: "Find me a match."
+    /// Only players with exactly the same `params` (e.g. region or
+    /// mode) are matched with each other. Not allowed while in a room.
+    EnterQueue {
+        #[serde(default)]
+        params: RoomMetadata,
+    },
+
+    /// Client → Server: "Stop looking for a match."
+    LeaveQueue,
+
+    /// Server → Client: "You're number `position` in line." Sent when
+    /// you enter the queue and whenever your place in it changes.
+    QueuePosition { position: usize },
+
+    /// Server → Client: "You've been matched and are joining this room."
+    /// The room's own messages follow as soon as you're seated.
+    MatchFound { room_id: RoomId },
+
+    /// Server → Client: "That match fell through after all." You're
+    /// back in line, and your `QueuePosition` follows.
+    MatchCancelled { room_id: RoomId },
+
+    // -- Parties --
+    // While you're in a party, only its leader joins rooms, creates
+    // them, or queues, and always for the whole party: everyone gets a
+    // seat or nobody does. Other members get a 403 for these.
+
+    /// Client → Server: "Start a party with me as its leader."
+    CreateParty,
+
+    /// Client → Server (leader only): "Invite this player to my party."
+    InviteToParty { player_id: PlayerId },
+
+    /// Server → Client: "`from` invited you to their party."
+    PartyInvite { party_id: PartyId, from: PlayerId },
+
+    /// Client → Server: "I accept the invite to this party."
+    AcceptPartyInvite { party_id: PartyId },
+
+    /// Client → Server: "I'm leaving my party."
+    /// If the leader leaves, the longest-standing member takes over.
+    LeaveParty,
+
+    /// Client → Server (leader only): "Make this member the leader."
+    PromotePartyLeader { player_id: PlayerId },
+
+    /// Client → Server (leader only): "Remove this member."
+    KickFromParty { player_id: PlayerId },
+
+    /// Server → Client: "Here's your party now." Sent to every member
+    /// whenever someone joins, leaves, or the leader changes.
+    PartyUpdated { party: PartyInfo },
+
+    /// Server → Client: "You're no longer in this party" (you left or
+    /// were kicked).
+    PartyLeft { party_id: PartyId },
+
+    /// Client → Server: "Say this to my party."
+    PartyChat { text: String },
+
+    /// Server → Client: "`from` said this to your party."
+    PartyChatMessage { from: PlayerId, text: String },
+
+    // -- Chat --
+    // Works in any room, whatever state its game is in.
+
+    /// Client → Server: "Say this to my room" (or my team, or one
+    /// player). `scope` defaults to the whole room.
+    ChatSend {
+        text: String,
+        #[serde(default)]
+        scope: ChatScope,
+    },
+
+    /// Server → Client: "`from` said this." The sender gets their own
+    /// message back, as everyone else saw it. Players who join (or
+    /// reconnect) get the room's recent chat first.
+    ChatMessage {
+        from: PlayerId,
+        text: String,
+        scope: ChatScope,
+    },
+
+    /// Client → Server: "Hide this player's chat from me" (or, with
+    /// `false`, "show it again"). Lasts while you're in the room.
+    MuteChat { player_id: PlayerId, muted: bool },
+
     // -- Errors --
 
     /// Server → Client: "Something went wrong."
     /// `code` follows HTTP-style conventions (400 = bad request,
-    /// 401 = unauthorized, 404 = not found, etc.).
+    /// 401 = unauthorized, 403 = wrong room password or not the host,
+    /// 404 = not found, 423 = room locked, etc.).
     Error { code: u16, message: String },
 }
 
@@ -252,6 +653,8 @@ pub enum SystemMessage {
 ///   `{ "type": "System", "data": { "type": "Heartbeat", "client_time": 123 } }`
 /// For a game message:
 ///   `{ "type": "Game", "data": [104, 101, 108, 108, 111] }`
+/// For a game input aimed at a specific tick:
+///   `{ "type": "Input", "data": { "tick": 42, "data": [1, 2] } }`
 ///
 /// This two-level tagging lets the framework quickly check: "Is this a
 /// system message I handle, or game data I pass through to game logic?"
@@ -265,6 +668,10 @@ pub enum Payload {
     /// These bytes are the game's `ClientMessage` or `ServerMessage`
     /// serialized by the codec. The framework just passes them through.
     Game(Vec<u8>),
+
+    /// Game data aimed at a specific server tick. Rooms that buffer
+    /// input apply it on that tick; other rooms treat it like `Game`.
+    Input { tick: u64, data: Vec<u8> },
 }
 
 // ---------------------------------------------------------------------------
@@ -303,6 +710,13 @@ pub struct Envelope {
     #[serde(default)]
     pub channel: Channel,
 
+    /// On server messages: the highest client `seq` whose game input the
+    /// room has processed for this player. Clients doing prediction drop
+    /// inputs up to here and replay the rest on top of the received
+    /// state. Omitted when there's nothing to acknowledge.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ack: Option<u64>,
+
     /// The actual message content (system or game data).
     pub payload: Payload,
 }
@@ -440,16 +854,82 @@ mod tests {
         assert_eq!(msg, decoded);
     }
 
+    #[test]
+    fn test_system_message_server_heartbeat_json_format() {
+        let msg = SystemMessage::ServerHeartbeat { server_time: 7000 };
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(json["type"], "ServerHeartbeat");
+        assert_eq!(json["server_time"], 7000);
+
+        let json = r#"{ "type": "ServerHeartbeatAck", "server_time": 7000 }"#;
+        let msg: SystemMessage = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            msg,
+            SystemMessage::ServerHeartbeatAck { server_time: 7000 }
+        );
+    }
+
     #[test]
     fn test_system_message_join_room_round_trip() {
         let msg = SystemMessage::JoinRoom {
             room_id: RoomId(10),
+            password: Some("hunter2".into()),
+        };
+        let bytes = serde_json::to_vec(&msg).unwrap();
+        let decoded: SystemMessage = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(msg, decoded);
+    }
+
+    #[test]
+    fn test_system_message_join_room_password_defaults_when_missing() {
+        let json = r#"{"type": "JoinRoom", "room_id": 10}"#;
+        let msg: SystemMessage = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            msg,
+            SystemMessage::JoinRoom {
+                room_id: RoomId(10),
+                password: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_system_message_join_by_code_round_trip() {
+        let msg = SystemMessage::JoinByCode {
+            code: "K7QX2M".into(),
+            password: None,
         };
         let bytes = serde_json::to_vec(&msg).unwrap();
         let decoded: SystemMessage = serde_json::from_slice(&bytes).unwrap();
         assert_eq!(msg, decoded);
     }
 
+    #[test]
+    fn test_system_message_create_room_defaults_to_public() {
+        let json = r#"{"type": "CreateRoom"}"#;
+        let msg: SystemMessage = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            msg,
+            SystemMessage::CreateRoom {
+                private: false,
+                password: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_system_message_room_created_json_format() {
+        let msg = SystemMessage::RoomCreated {
+            room_id: RoomId(3),
+            invite_code: "K7QX2M".into(),
+        };
+        let json: serde_json::Value = serde_json::to_value(&msg).unwrap();
+
+        assert_eq!(json["type"], "RoomCreated");
+        assert_eq!(json["room_id"], 3);
+        assert_eq!(json["invite_code"], "K7QX2M");
+    }
+
     #[test]
     fn test_system_message_join_or_create_round_trip() {
         let msg = SystemMessage::JoinOrCreate {
@@ -469,6 +949,110 @@ mod tests {
         assert_eq!(msg, decoded);
     }
 
+    #[test]
+    fn test_system_message_host_controls_round_trip() {
+        let msgs = [
+            SystemMessage::KickPlayer {
+                player_id: PlayerId(3),
+            },
+            SystemMessage::LockRoom,
+            SystemMessage::UnlockRoom,
+            SystemMessage::PauseGame,
+            SystemMessage::ResumeGame,
+            SystemMessage::StartGame,
+            SystemMessage::HostChanged { host: PlayerId(1) },
+            SystemMessage::Kicked { room_id: RoomId(9) },
+        ];
+        for msg in msgs {
+            let bytes = serde_json::to_vec(&msg).unwrap();
+            let decoded: SystemMessage =
+                serde_json::from_slice(&bytes).unwrap();
+            assert_eq!(msg, decoded);
+        }
+    }
+
+    #[test]
+    fn test_system_message_pregame_round_trip() {
+        let msgs = [
+            SystemMessage::Ready { ready: true },
+            SystemMessage::ReadyChanged {
+                player_id: PlayerId(2),
+                ready: false,
+            },
+            SystemMessage::Countdown { remaining_ms: 3000 },
+            SystemMessage::CountdownCancelled,
+            SystemMessage::Rematch { rematch: true },
+            SystemMessage::RematchChanged {
+                player_id: PlayerId(2),
+                rematch: false,
+            },
+        ];
+        for msg in msgs {
+            let bytes = serde_json::to_vec(&msg).unwrap();
+            let decoded: SystemMessage =
+                serde_json::from_slice(&bytes).unwrap();
+            assert_eq!(msg, decoded);
+        }
+    }
+
+    #[test]
+    fn test_system_message_game_ended_json_format() {
+        let msg = SystemMessage::GameEnded {
+            reason: EndReason::TimedOut,
+            result: MatchResult::from_scores([(PlayerId(4), 10)]),
+        };
+        let json: serde_json::Value = serde_json::to_value(&msg).unwrap();
+        assert_eq!(json["type"], "GameEnded");
+        assert_eq!(json["reason"], "TimedOut");
+        assert_eq!(json["result"]["players"][0]["player_id"], 4);
+        assert_eq!(json["result"]["players"][0]["placement"], 1);
+        // No team unless the game sets one.
+        assert!(json["result"]["players"][0].get("team").is_none());
+        let decoded: SystemMessage = serde_json::from_value(json).unwrap();
+        assert_eq!(msg, decoded);
+
+        // Results are optional on the wire.
+        let bare: SystemMessage = serde_json::from_str(
+            r#"{"type":"GameEnded","reason":"Completed"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            bare,
+            SystemMessage::GameEnded {
+                reason: EndReason::Completed,
+                result: MatchResult::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_match_result_from_scores_shares_tied_placements() {
+        let result = MatchResult::from_scores([
+            (PlayerId(1), 5),
+            (PlayerId(2), 9),
+            (PlayerId(3), 5),
+            (PlayerId(4), 1),
+        ]);
+        let ranking: Vec<_> = result
+            .players
+            .iter()
+            .map(|r| (r.player_id.0, r.placement))
+            .collect();
+        assert_eq!(ranking, vec![(2, 1), (1, 2), (3, 2), (4, 4)]);
+        assert_eq!(result.player(PlayerId(3)).unwrap().score, 5);
+        assert!(result.player(PlayerId(9)).is_none());
+    }
+
+    #[test]
+    fn test_system_message_kick_player_json_format() {
+        let msg = SystemMessage::KickPlayer {
+            player_id: PlayerId(3),
+        };
+        let json: serde_json::Value = serde_json::to_value(&msg).unwrap();
+        assert_eq!(json["type"], "KickPlayer");
+        assert_eq!(json["player_id"], 3);
+    }
+
     #[test]
     fn test_system_message_room_state_round_trip() {
         let msg = SystemMessage::RoomState {
@@ -479,6 +1063,45 @@ mod tests {
         assert_eq!(msg, decoded);
     }
 
+    #[test]
+    fn test_system_message_state_sync_round_trip() {
+        let msgs = [
+            SystemMessage::StateKeyframe {
+                seq: 20,
+                data: vec![1, 2, 3],
+            },
+            SystemMessage::StateDelta {
+                seq: 21,
+                base_seq: 20,
+                data: vec![4],
+            },
+            SystemMessage::StateAck { seq: 21 },
+        ];
+        for msg in msgs {
+            let bytes = serde_json::to_vec(&msg).unwrap();
+            let decoded: SystemMessage =
+                serde_json::from_slice(&bytes).unwrap();
+            assert_eq!(msg, decoded);
+        }
+    }
+
+    #[test]
+    fn test_system_message_room_closed_json_format() {
+        let msg = SystemMessage::RoomClosed {
+            room_id: RoomId(4),
+            reason: "finished".into(),
+        };
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "type": "RoomClosed",
+                "room_id": 4,
+                "reason": "finished",
+            })
+        );
+    }
+
     #[test]
     fn test_system_message_room_joined_round_trip() {
         let msg = SystemMessage::RoomJoined {
@@ -541,6 +1164,23 @@ mod tests {
         assert_eq!(json["data"], serde_json::json!([1, 2, 3]));
     }
 
+    #[test]
+    fn test_payload_input_json_format() {
+        let payload = Payload::Input {
+            tick: 42,
+            data: vec![1, 2],
+        };
+        let json: serde_json::Value =
+            serde_json::to_value(&payload).unwrap();
+
+        assert_eq!(json["type"], "Input");
+        assert_eq!(json["data"]["tick"], 42);
+        assert_eq!(json["data"]["data"], serde_json::json!([1, 2]));
+
+        let back: Payload = serde_json::from_value(json).unwrap();
+        assert_eq!(back, payload);
+    }
+
     // =====================================================================
     // Envelope
     // =====================================================================
@@ -551,6 +1191,7 @@ mod tests {
             seq: 42,
             timestamp: 15000,
             channel: Channel::Unreliable,
+            ack: None,
             payload: Payload::Game(vec![1, 2, 3]),
         };
         let bytes = serde_json::to_vec(&envelope).unwrap();
@@ -572,6 +1213,25 @@ mod tests {
         assert_eq!(envelope.channel, Channel::ReliableOrdered);
     }
 
+    #[test]
+    fn test_envelope_ack_only_serialized_when_set() {
+        let mut envelope = Envelope {
+            seq: 1,
+            timestamp: 100,
+            channel: Channel::ReliableOrdered,
+            ack: None,
+            payload: Payload::Game(vec![1]),
+        };
+        let json = serde_json::to_value(&envelope).unwrap();
+        assert!(json.get("ack").is_none());
+
+        envelope.ack = Some(7);
+        let json = serde_json::to_value(&envelope).unwrap();
+        assert_eq!(json["ack"], 7);
+        let back: Envelope = serde_json::from_value(json).unwrap();
+        assert_eq!(back, envelope);
+    }
+
     // =====================================================================
     // Recipient
     // =====================================================================
@@ -600,18 +1260,174 @@ mod tests {
         assert_eq!(r, decoded);
     }
 
+    #[test]
+    fn test_recipient_team_and_players_json_format() {
+        let team = Recipient::Team(TeamId(1));
+        assert_eq!(
+            serde_json::to_value(&team).unwrap(),
+            serde_json::json!({"Team": 1})
+        );
+
+        let players = Recipient::Players(vec![PlayerId(2), PlayerId(5)]);
+        let json = serde_json::to_value(&players).unwrap();
+        assert_eq!(json, serde_json::json!({"Players": [2, 5]}));
+        let decoded: Recipient = serde_json::from_value(json).unwrap();
+        assert_eq!(players, decoded);
+    }
+
     // =====================================================================
     // Error cases — malformed input
     // =====================================================================
 
     #[test]
     fn test_system_message_list_rooms_round_trip() {
-        let msg = SystemMessage::ListRooms;
+        let msg = SystemMessage::ListRooms {
+            filter: RoomMetadata::from([("mode".into(), "ranked".into())]),
+        };
+        let bytes = serde_json::to_vec(&msg).unwrap();
+        let decoded: SystemMessage = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(msg, decoded);
+    }
+
+    #[test]
+    fn test_system_message_list_rooms_filter_defaults_when_missing() {
+        // Clients that predate filtering send a bare `ListRooms`.
+        let json = r#"{"type": "ListRooms"}"#;
+        let msg: SystemMessage = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            msg,
+            SystemMessage::ListRooms {
+                filter: RoomMetadata::new()
+            }
+        );
+    }
+
+    #[test]
+    fn test_system_message_enter_queue_round_trip() {
+        let msg = SystemMessage::EnterQueue {
+            params: RoomMetadata::from([("region".into(), "eu".into())]),
+        };
         let bytes = serde_json::to_vec(&msg).unwrap();
         let decoded: SystemMessage = serde_json::from_slice(&bytes).unwrap();
         assert_eq!(msg, decoded);
     }
 
+    #[test]
+    fn test_system_message_enter_queue_params_default_when_missing() {
+        let json = r#"{"type": "EnterQueue"}"#;
+        let msg: SystemMessage = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            msg,
+            SystemMessage::EnterQueue {
+                params: RoomMetadata::new()
+            }
+        );
+    }
+
+    #[test]
+    fn test_system_message_match_found_json_format() {
+        let msg = SystemMessage::MatchFound { room_id: RoomId(7) };
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"type": "MatchFound", "room_id": 7})
+        );
+
+        let msg = SystemMessage::MatchCancelled { room_id: RoomId(7) };
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"type": "MatchCancelled", "room_id": 7})
+        );
+    }
+
+    #[test]
+    fn test_system_message_party_updated_json_format() {
+        let msg = SystemMessage::PartyUpdated {
+            party: PartyInfo {
+                party_id: PartyId(3),
+                leader: PlayerId(1),
+                members: vec![PlayerId(1), PlayerId(2)],
+            },
+        };
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "type": "PartyUpdated",
+                "party": {"party_id": 3, "leader": 1, "members": [1, 2]},
+            })
+        );
+        let decoded: SystemMessage = serde_json::from_value(json).unwrap();
+        assert_eq!(msg, decoded);
+    }
+
+    #[test]
+    fn test_system_message_party_commands_round_trip() {
+        let msgs = [
+            SystemMessage::CreateParty,
+            SystemMessage::InviteToParty {
+                player_id: PlayerId(2),
+            },
+            SystemMessage::AcceptPartyInvite {
+                party_id: PartyId(1),
+            },
+            SystemMessage::LeaveParty,
+            SystemMessage::PartyChat {
+                text: "gl hf".into(),
+            },
+        ];
+        for msg in msgs {
+            let bytes = serde_json::to_vec(&msg).unwrap();
+            let decoded: SystemMessage =
+                serde_json::from_slice(&bytes).unwrap();
+            assert_eq!(msg, decoded);
+        }
+    }
+
+    #[test]
+    fn test_system_message_chat_json_format() {
+        let msg = SystemMessage::ChatMessage {
+            from: PlayerId(1),
+            text: "gg".into(),
+            scope: ChatScope::Whisper(PlayerId(2)),
+        };
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "type": "ChatMessage",
+                "from": 1,
+                "text": "gg",
+                "scope": {"Whisper": 2},
+            })
+        );
+        let decoded: SystemMessage = serde_json::from_value(json).unwrap();
+        assert_eq!(msg, decoded);
+    }
+
+    #[test]
+    fn test_system_message_chat_send_scope_defaults_to_room() {
+        let json = r#"{"type": "ChatSend", "text": "hi"}"#;
+        let msg: SystemMessage = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            msg,
+            SystemMessage::ChatSend {
+                text: "hi".into(),
+                scope: ChatScope::Room,
+            }
+        );
+        let json = r#"{"type": "ChatSend", "text": "hi", "scope": "Team"}"#;
+        let msg: SystemMessage = serde_json::from_str(json).unwrap();
+        assert!(matches!(
+            msg,
+            SystemMessage::ChatSend {
+                scope: ChatScope::Team,
+                ..
+            }
+        ));
+    }
+
     #[test]
     fn test_system_message_room_list_round_trip() {
         let msg = SystemMessage::RoomList {
@@ -620,11 +1436,20 @@ mod tests {
                     room_id: RoomId(1),
                     player_count: 2,
                     max_players: 4,
+                    metadata: RoomMetadata::from([(
+                        "map".into(),
+                        "dust".into(),
+                    )]),
+                    password_protected: true,
+                    in_progress: true,
                 },
                 RoomListEntry {
                     room_id: RoomId(2),
                     player_count: 0,
                     max_players: 8,
+                    metadata: RoomMetadata::new(),
+                    password_protected: false,
+                    in_progress: false,
                 },
             ],
         };
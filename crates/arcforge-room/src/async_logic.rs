@@ -0,0 +1,108 @@
+//! Async hooks for games that need to call out to other services.
+//!
+//! [`GameLogic`] methods run on the room's task and must not block.
+//! Games that need to await something before acting on a message (an
+//! inventory service, a purchase check, a storage lookup) also implement
+//! [`AsyncGameLogic`] and turn it on with
+//! [`RoomManager::with_async_hooks`](crate::RoomManager::with_async_hooks).
+//!
+//! The room drives the futures itself, so joins, ticks, timers, and the
+//! rest carry on while one is pending. Client messages still reach the
+//! game in the order they arrived: while one is being prepared, the
+//! ones behind it wait their turn. How long a hook may take, and how
+//! many messages may wait, is up to [`AsyncHookConfig`].
+//!
+//! [`AsyncHookConfig`]: crate::AsyncHookConfig
+
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::thread;
+
+use arcforge_protocol::PlayerId;
+
+use crate::GameLogic;
+
+/// Async hooks on top of [`GameLogic`].
+pub trait AsyncGameLogic: GameLogic {
+    /// Runs before a client message is validated and handled.
+    ///
+    /// `state` is the game state when the message's turn comes up; the
+    /// future can't borrow it, so clone what it needs. Resolve to `Ok`
+    /// with the message to pass on to
+    /// [`validate_message`](GameLogic::validate_message) and
+    /// [`handle_message`](GameLogic::handle_message) (the same one, or
+    /// one carrying what the future looked up), or to `Err` to drop it
+    /// the way a failed validation does.
+    ///
+    /// Only called while the game is in progress. A panic in the future,
+    /// or one that runs past [`AsyncHookConfig::timeout`], drops the
+    /// message and leaves the room running.
+    ///
+    /// [`AsyncHookConfig::timeout`]: crate::AsyncHookConfig::timeout
+    fn prepare_message(
+        state: &Self::State,
+        sender: PlayerId,
+        msg: Self::ClientMessage,
+    ) -> impl Future<Output = Result<Self::ClientMessage, String>>
+           + Send
+           + 'static;
+}
+
+/// A pending [`AsyncGameLogic::prepare_message`]. Resolves to `Err` with
+/// the panic payload if the hook panicked.
+pub(crate) type PreparedMessage<G> = Pin<
+    Box<
+        dyn Future<
+                Output = thread::Result<
+                    Result<<G as GameLogic>::ClientMessage, String>,
+                >,
+            > + Send,
+    >,
+>;
+
+/// [`AsyncGameLogic`]'s hooks as plain function pointers, so the room
+/// actor can carry them without requiring the trait of every game.
+pub(crate) struct AsyncHooks<G: GameLogic> {
+    pub(crate) prepare_message:
+        fn(&G::State, PlayerId, G::ClientMessage) -> PreparedMessage<G>,
+}
+
+impl<G: AsyncGameLogic> AsyncHooks<G> {
+    pub(crate) fn new() -> Self {
+        Self {
+            prepare_message: |state, sender, msg| {
+                Box::pin(CatchUnwind(Box::pin(G::prepare_message(
+                    state, sender, msg,
+                ))))
+            },
+        }
+    }
+}
+
+impl<G: GameLogic> Clone for AsyncHooks<G> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<G: GameLogic> Copy for AsyncHooks<G> {}
+
+/// Polls the inner future, turning a panic into an `Err` output.
+struct CatchUnwind<F>(Pin<Box<F>>);
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = thread::Result<F::Output>;
+
+    fn poll(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Self::Output> {
+        let inner = self.0.as_mut();
+        match panic::catch_unwind(AssertUnwindSafe(|| inner.poll(cx))) {
+            Ok(poll) => poll.map(Ok),
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    }
+}
@@ -0,0 +1,93 @@
+//! Bots: server-side players that stand in for missing people.
+//!
+//! A bot gets the same traffic a client would — state snapshots and
+//! game messages addressed to its seat — and answers with
+//! `ClientMessage`s, which the room handles like any player's. Each bot
+//! runs on its own task, so a slow or crashing bot never holds up the
+//! room.
+//!
+//! Bots are created by the manager's factory (see
+//! [`RoomManager::with_bots`](crate::RoomManager::with_bots)) when the
+//! room's [`BotConfig`](crate::BotConfig) calls for one.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use arcforge_protocol::PlayerId;
+use tokio::sync::mpsc;
+
+use crate::room::RoomCommand;
+use crate::{GameLogic, PlayerSender, RoomOutbound};
+
+/// Bot IDs count down from the top of the range, away from the IDs
+/// authenticators hand out.
+static NEXT_BOT_ID: AtomicU64 = AtomicU64::new(u64::MAX);
+
+/// A player driven by code instead of a client.
+pub trait Bot<G: GameLogic>: Send + 'static {
+    /// Called with every snapshot of the game state the seat receives:
+    /// at the start, on reconnect, and on every sync. Default: does
+    /// nothing.
+    fn on_state(&mut self, _view: &G::View) -> Vec<G::ClientMessage> {
+        Vec::new()
+    }
+
+    /// Called with every game message the seat receives. Default: does
+    /// nothing.
+    fn on_message(
+        &mut self,
+        _msg: &G::ServerMessage,
+    ) -> Vec<G::ClientMessage> {
+        Vec::new()
+    }
+}
+
+/// Creates the bot for a seat, given the seat's player ID.
+pub(crate) type BotFactory<G> =
+    Arc<dyn Fn(PlayerId) -> Box<dyn Bot<G>> + Send + Sync>;
+
+/// Picks a player ID for a new bot.
+pub(crate) fn next_bot_id() -> PlayerId {
+    PlayerId(NEXT_BOT_ID.fetch_sub(1, Ordering::Relaxed))
+}
+
+/// Runs `bot` on its own task, playing `player_id`'s seat.
+///
+/// Returns the sender the room delivers the seat's traffic to. The task
+/// ends when the room drops that sender or shuts down.
+pub(crate) fn spawn_bot<G: GameLogic>(
+    player_id: PlayerId,
+    mut bot: Box<dyn Bot<G>>,
+    room: mpsc::WeakSender<RoomCommand<G>>,
+) -> PlayerSender<G> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Some(outbound) = rx.recv().await {
+            let replies = match outbound {
+                RoomOutbound::State(view)
+                | RoomOutbound::Keyframe { view, .. } => bot.on_state(&view),
+                RoomOutbound::Message(msg) => bot.on_message(&msg),
+                // Bots never acknowledge snapshots, so they only ever
+                // get keyframes.
+                RoomOutbound::Delta { .. }
+                | RoomOutbound::System(_)
+                | RoomOutbound::InputAck { .. } => continue,
+            };
+            for msg in replies {
+                let Some(room) = room.upgrade() else {
+                    return;
+                };
+                let cmd = RoomCommand::Message {
+                    sender: player_id,
+                    seq: None,
+                    tick: None,
+                    msg,
+                };
+                if room.send(cmd).await.is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    tx
+}
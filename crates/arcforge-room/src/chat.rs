@@ -0,0 +1,211 @@
+//! Built-in chat: the filter hook, and each room's history and mutes.
+//!
+//! Rooms handle `ChatSend` themselves, in any state, so games don't
+//! have to carry chat in their own messages. A message goes to the
+//! sender's room, their team, or one other player (see
+//! [`ChatScope`]), skipping anyone who muted the sender. Room and team
+//! messages are kept, up to [`ChatConfig::history`], and replayed to
+//! players who join later.
+//!
+//! [`ChatConfig::history`]: crate::ChatConfig::history
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use arcforge_protocol::{ChatScope, PlayerId, SystemMessage, TeamId};
+
+/// Screens chat messages before a room sends them, e.g. for profanity.
+///
+/// Called on the room's task, so keep it quick.
+pub trait ChatFilter: Send + Sync + 'static {
+    /// Returns the text to send in place of `text` (say, with bad words
+    /// masked), or `None` to turn the message away.
+    fn filter(&self, from: PlayerId, text: &str) -> Option<String>;
+}
+
+/// A [`ChatFilter`] that masks a list of words with `*`s.
+///
+/// Only whole words match, ignoring case: with "darn" on the list,
+/// "Darn it" becomes "**** it" but "darned" gets through.
+#[derive(Debug, Clone, Default)]
+pub struct WordFilter {
+    words: HashSet<String>,
+}
+
+impl WordFilter {
+    /// Creates a filter that masks each of `words`.
+    pub fn new<I>(words: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        Self {
+            words: words
+                .into_iter()
+                .map(|word| word.as_ref().to_lowercase())
+                .collect(),
+        }
+    }
+}
+
+impl ChatFilter for WordFilter {
+    fn filter(&self, _from: PlayerId, text: &str) -> Option<String> {
+        let mut filtered = String::with_capacity(text.len());
+        let mut rest = text;
+        // Alternate between runs of word characters and everything else.
+        while let Some(first) = rest.chars().next() {
+            let in_word = first.is_alphanumeric();
+            let end = rest
+                .find(|c: char| c.is_alphanumeric() != in_word)
+                .unwrap_or(rest.len());
+            let (run, tail) = rest.split_at(end);
+            if in_word && self.words.contains(&run.to_lowercase()) {
+                filtered.push_str(&"*".repeat(run.chars().count()));
+            } else {
+                filtered.push_str(run);
+            }
+            rest = tail;
+        }
+        Some(filtered)
+    }
+}
+
+/// A message kept for players who join later.
+struct Entry {
+    from: PlayerId,
+    text: String,
+    scope: ChatScope,
+    /// The team a team message went to.
+    team: Option<TeamId>,
+}
+
+/// A room's recent chat and who has muted whom.
+pub(crate) struct ChatLog {
+    capacity: usize,
+    history: VecDeque<Entry>,
+    /// The players each player has muted.
+    mutes: HashMap<PlayerId, HashSet<PlayerId>>,
+}
+
+impl ChatLog {
+    /// Creates an empty log that keeps the last `capacity` messages.
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            history: VecDeque::new(),
+            mutes: HashMap::new(),
+        }
+    }
+
+    /// Keeps a sent message for players who join later. `team` is the
+    /// team a team message went to. Whispers aren't kept.
+    pub(crate) fn record(
+        &mut self,
+        from: PlayerId,
+        text: String,
+        scope: ChatScope,
+        team: Option<TeamId>,
+    ) {
+        if self.capacity == 0 || matches!(scope, ChatScope::Whisper(_)) {
+            return;
+        }
+        if self.history.len() == self.capacity {
+            self.history.pop_front();
+        }
+        self.history.push_back(Entry {
+            from,
+            text,
+            scope,
+            team,
+        });
+    }
+
+    /// The kept messages `player`, who's on `team`, may see, oldest
+    /// first.
+    pub(crate) fn history_for(
+        &self,
+        player: PlayerId,
+        team: Option<TeamId>,
+    ) -> Vec<SystemMessage> {
+        self.history
+            .iter()
+            .filter(|entry| match entry.scope {
+                ChatScope::Room => true,
+                _ => team.is_some() && entry.team == team,
+            })
+            .filter(|entry| !self.is_muted(player, entry.from))
+            .map(|entry| SystemMessage::ChatMessage {
+                from: entry.from,
+                text: entry.text.clone(),
+                scope: entry.scope,
+            })
+            .collect()
+    }
+
+    /// Mutes (or unmutes) `target` for `player`.
+    pub(crate) fn set_muted(
+        &mut self,
+        player: PlayerId,
+        target: PlayerId,
+        muted: bool,
+    ) {
+        if muted {
+            self.mutes.entry(player).or_default().insert(target);
+        } else if let Some(muted) = self.mutes.get_mut(&player) {
+            muted.remove(&target);
+        }
+    }
+
+    /// Returns `true` if `player` has muted `from`.
+    pub(crate) fn is_muted(&self, player: PlayerId, from: PlayerId) -> bool {
+        self.mutes
+            .get(&player)
+            .is_some_and(|muted| muted.contains(&from))
+    }
+
+    /// Forgets the mutes of a player who left.
+    pub(crate) fn remove(&mut self, player: PlayerId) {
+        self.mutes.remove(&player);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_filter_masks_whole_words_only() {
+        let filter = WordFilter::new(["darn", "heck"]);
+        assert_eq!(
+            filter.filter(PlayerId(1), "Darn it, what the HECK?!").as_deref(),
+            Some("**** it, what the ****?!")
+        );
+        assert_eq!(
+            filter.filter(PlayerId(1), "darned heckler").as_deref(),
+            Some("darned heckler")
+        );
+    }
+
+    #[test]
+    fn test_chat_log_keeps_the_latest_messages() {
+        let mut log = ChatLog::new(2);
+        for text in ["one", "two", "three"] {
+            log.record(PlayerId(1), text.into(), ChatScope::Room, None);
+        }
+        log.record(
+            PlayerId(1),
+            "psst".into(),
+            ChatScope::Whisper(PlayerId(2)),
+            None,
+        );
+
+        let texts: Vec<String> = log
+            .history_for(PlayerId(2), None)
+            .into_iter()
+            .map(|msg| match msg {
+                SystemMessage::ChatMessage { text, .. } => text,
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+        assert_eq!(texts, ["two", "three"]);
+    }
+}
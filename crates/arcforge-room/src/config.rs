@@ -1,5 +1,6 @@
 //! Room configuration and state machine.
 
+use std::fmt;
 use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
@@ -23,7 +24,9 @@ pub struct RoomConfig {
     /// Tick rate in Hz. 0 means event-driven (no tick loop).
     pub tick_rate: u32,
 
-    /// How long to wait for a disconnected player before removing them.
+    /// How long a running game holds a disconnected player's seat. If
+    /// they haven't reconnected by then, a bot takes over (see
+    /// [`BotConfig::replace_disconnected`]) or they're removed.
     pub reconnect_grace: Duration,
 
     /// Whether spectators are allowed.
@@ -31,6 +34,58 @@ pub struct RoomConfig {
 
     /// Maximum number of spectators (0 = unlimited when allowed).
     pub max_spectators: usize,
+
+    /// Whether players may join a game that's already in progress
+    /// (drop-in games). Late joiners go through
+    /// [`GameLogic::on_player_join`](crate::GameLogic::on_player_join).
+    pub allow_late_join: bool,
+
+    /// When the room disposes of itself.
+    pub disposal: DisposalPolicy,
+
+    /// Ready-check and countdown before the game starts. `None` starts
+    /// the game the moment the room fills up (or the host starts it).
+    pub pregame: Option<PregameConfig>,
+
+    /// Continuous state sync while the game runs. `None` sends a
+    /// snapshot only when the game starts (and on late join).
+    pub state_sync: Option<StateSyncConfig>,
+
+    /// Tick-aligned input processing. `None` applies client messages
+    /// the moment they arrive.
+    pub input_buffer: Option<InputBufferConfig>,
+
+    /// Server-side state history for lag compensation. `None` keeps no
+    /// history and messages always see the current state.
+    pub lag_compensation: Option<LagCompensationConfig>,
+
+    /// Longest a game may run. When it runs out, the room calls
+    /// [`GameLogic::on_timeout`](crate::GameLogic::on_timeout) and ends
+    /// the game whatever `is_finished` says. Time spent paused doesn't
+    /// count. `None` lets games run forever.
+    pub max_duration: Option<Duration>,
+
+    /// What the room does when a game callback panics.
+    pub on_panic: PanicPolicy,
+
+    /// Limits on the game's async hooks, in rooms whose manager runs
+    /// them (see [`RoomManager::with_async_hooks`]).
+    ///
+    /// [`RoomManager::with_async_hooks`]: crate::RoomManager::with_async_hooks
+    pub async_hooks: AsyncHookConfig,
+
+    /// When bots take seats. `None` never adds bots. Bots also need a
+    /// factory on the manager (see
+    /// [`RoomManager::with_bots`](crate::RoomManager::with_bots)).
+    pub bots: Option<BotConfig>,
+
+    /// Splits the players into teams. `None` leaves everyone on their
+    /// own.
+    pub teams: Option<TeamConfig>,
+
+    /// Built-in chat between the room's players. On by default; `None`
+    /// turns it off.
+    pub chat: Option<ChatConfig>,
 }
 
 impl Default for RoomConfig {
@@ -42,10 +97,386 @@ impl Default for RoomConfig {
             reconnect_grace: Duration::from_secs(30),
             allow_spectators: false,
             max_spectators: 0,
+            allow_late_join: false,
+            disposal: DisposalPolicy::default(),
+            pregame: None,
+            state_sync: None,
+            input_buffer: None,
+            lag_compensation: None,
+            max_duration: None,
+            on_panic: PanicPolicy::default(),
+            async_hooks: AsyncHookConfig::default(),
+            bots: None,
+            teams: None,
+            chat: Some(ChatConfig::default()),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// PanicPolicy
+// ---------------------------------------------------------------------------
+
+/// What to do when a [`GameLogic`](crate::GameLogic) callback panics.
+///
+/// Either way the panic is logged with the room and player it came from,
+/// and it never takes down anything outside the room.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PanicPolicy {
+    /// Tell every player the game crashed and dispose of the room.
+    #[default]
+    CloseRoom,
+    /// Put back the game state (and RNG) from before the callback, tell
+    /// the player whose message caused it (if any), and keep going, as
+    /// if the message, tick, or timer never happened. Only as safe as
+    /// the game's own invariants: anything the callback did outside the
+    /// state is not undone. A panic in `init`, or while a player joins,
+    /// leaves, or reconnects, still closes the room, since the room
+    /// can't take that back.
+    RestoreLastState,
+}
+
+// ---------------------------------------------------------------------------
+// InputBufferConfig
+// ---------------------------------------------------------------------------
+
+/// What to do with an input for a tick that has already run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum LateInputPolicy {
+    /// Discard it. Right for lockstep, where a tick's inputs are final.
+    Drop,
+    /// Apply it on the next tick instead. Right for most
+    /// server-authoritative games, where late is better than never.
+    #[default]
+    NextTick,
+}
+
+/// Settings for tick-aligned input processing.
+///
+/// Client messages are queued per player, tagged with the tick they're
+/// meant for, and handed to [`GameLogic::tick`](crate::GameLogic::tick)
+/// in batches instead of going through `handle_message`. Only takes
+/// effect in rooms with a `tick_rate` above zero.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputBufferConfig {
+    /// How far past the current tick an input may be scheduled. Inputs
+    /// further ahead are rejected, which bounds the buffer's size.
+    pub max_ticks_ahead: u64,
+
+    /// Handling of inputs that arrive after their tick ran.
+    pub late_inputs: LateInputPolicy,
+
+    /// When a player sends nothing for a tick, repeat their most recent
+    /// input (marked [`PlayerInput::repeated`](crate::PlayerInput::repeated)).
+    pub repeat_last: bool,
+}
+
+impl Default for InputBufferConfig {
+    fn default() -> Self {
+        Self {
+            max_ticks_ahead: 32,
+            late_inputs: LateInputPolicy::default(),
+            repeat_last: false,
         }
     }
 }
 
+// ---------------------------------------------------------------------------
+// LagCompensationConfig
+// ---------------------------------------------------------------------------
+
+/// Settings for lag compensation.
+///
+/// After every tick the room keeps a copy of the game state, so
+/// [`GameLogic::handle_compensated_message`](crate::GameLogic::handle_compensated_message)
+/// can look at the state as the sender saw it when they acted. Only
+/// takes effect in rooms with a `tick_rate` above zero.
+///
+/// The history holds at most `max_rewind` worth of ticks (and never more
+/// than `max_snapshots`), so memory use is bounded by that many copies
+/// of the state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LagCompensationConfig {
+    /// How far back the room will rewind, however high the sender's
+    /// RTT. Keeps players with bad connections from rewriting history.
+    pub max_rewind: Duration,
+
+    /// Hard cap on the number of stored snapshots.
+    pub max_snapshots: usize,
+
+    /// Extra rewind on top of the RTT, matching how far behind the
+    /// server the client renders (its interpolation delay).
+    pub interpolation_delay: Duration,
+}
+
+impl Default for LagCompensationConfig {
+    fn default() -> Self {
+        Self {
+            max_rewind: Duration::from_millis(250),
+            max_snapshots: 64,
+            interpolation_delay: Duration::ZERO,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// StateSyncConfig
+// ---------------------------------------------------------------------------
+
+/// Settings for continuous, delta-compressed state sync.
+///
+/// Every `interval`, each player gets either a full keyframe of their
+/// view or a delta against the last snapshot they acknowledged with
+/// `StateAck`. Players who haven't acknowledged anything get keyframes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateSyncConfig {
+    /// Time between snapshots.
+    pub interval: Duration,
+
+    /// Every Nth snapshot is a full keyframe for everyone, so clients
+    /// recover from missed deltas. 1 disables deltas entirely.
+    pub keyframe_every: u32,
+}
+
+impl Default for StateSyncConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_millis(50),
+            keyframe_every: 20,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// PregameConfig
+// ---------------------------------------------------------------------------
+
+/// Settings for the pre-game phase, spent in [`RoomState::Starting`].
+///
+/// When the room would start, it opens a countdown instead. Players send
+/// `Ready` messages meanwhile; the game starts as soon as everyone is
+/// ready or the countdown runs out. A player leaving cancels the
+/// countdown, clears everyone's readiness, and sends the room back to
+/// `WaitingForPlayers` until the next join or ready.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PregameConfig {
+    /// How long to wait for everyone to ready up.
+    pub countdown: Duration,
+
+    /// How often the remaining time is broadcast.
+    pub announce_every: Duration,
+}
+
+impl Default for PregameConfig {
+    fn default() -> Self {
+        Self {
+            countdown: Duration::from_secs(10),
+            announce_every: Duration::from_secs(1),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// BotConfig
+// ---------------------------------------------------------------------------
+
+/// When a room puts [`Bot`](crate::Bot)s in seats.
+///
+/// Bots are ordinary players to the game: they're in `ctx.players()`,
+/// count towards `min_players`, and send `ClientMessage`s. They're
+/// always ready and always vote for a rematch.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BotConfig {
+    /// Fill the empty seats up to `min_players` once someone has been
+    /// waiting in the room this long. `None` never fills seats.
+    pub fill_after: Option<Duration>,
+
+    /// Let a bot play for a disconnected player once
+    /// `reconnect_grace` runs out. The player can still reconnect and
+    /// take their seat back.
+    pub replace_disconnected: bool,
+}
+
+// ---------------------------------------------------------------------------
+// TeamConfig
+// ---------------------------------------------------------------------------
+
+/// How a room splits its players into teams.
+///
+/// Players are put on a team as they join (see
+/// [`GameContext::teams`](crate::GameContext::teams)), so a room holds
+/// at most `count * size` players whatever `max_players` says. Games
+/// can send to a whole team with `Recipient::Team`, and a finished
+/// game's results get each player's team unless the game sets it.
+///
+/// [`RoomManager::new`](crate::RoomManager::new) checks it: `size` must
+/// be at least 1, and the teams must have room for `min_players`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TeamConfig {
+    /// How many teams there are.
+    pub count: usize,
+    /// The most players one team takes.
+    pub size: usize,
+}
+
+impl TeamConfig {
+    /// Panics if a room with these teams and `min_players` could never
+    /// start.
+    pub(crate) fn assert_valid(&self, min_players: usize) {
+        assert!(self.size > 0, "team size must be at least 1");
+        assert!(
+            self.count * self.size >= min_players,
+            "{} teams of {} can't seat min_players {}",
+            self.count,
+            self.size,
+            min_players
+        );
+    }
+}
+
+// ---------------------------------------------------------------------------
+// AsyncHookConfig
+// ---------------------------------------------------------------------------
+
+/// Limits on [`AsyncGameLogic`](crate::AsyncGameLogic) hooks, so a slow
+/// service can't hold up a room's messages forever.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AsyncHookConfig {
+    /// Longest a `prepare_message` future may run. One that takes longer
+    /// is dropped, and its message rejected as if it failed validation.
+    pub timeout: Duration,
+
+    /// Most client messages that may wait behind the one being
+    /// prepared. Messages arriving while the queue is full are rejected
+    /// as if they failed validation.
+    pub max_queued: usize,
+}
+
+impl Default for AsyncHookConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(5),
+            max_queued: 64,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// ChatConfig
+// ---------------------------------------------------------------------------
+
+/// Settings for the room's built-in chat.
+///
+/// Players send `ChatSend` and get `ChatMessage`s, in any room state,
+/// without the game being involved. Messages can be screened with a
+/// [`ChatFilter`](crate::ChatFilter) on the manager.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatConfig {
+    /// How many recent messages the room replays to players who join.
+    /// 0 keeps no history.
+    pub history: usize,
+
+    /// Longest message accepted, in characters.
+    pub max_length: usize,
+}
+
+impl Default for ChatConfig {
+    fn default() -> Self {
+        Self {
+            history: 50,
+            max_length: 500,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// DisposalPolicy
+// ---------------------------------------------------------------------------
+
+/// Rules for destroying rooms nobody needs anymore.
+///
+/// Each rule is an optional delay; `None` disables it. Whichever enabled
+/// rule fires first shuts the room down and removes it from its
+/// [`RoomManager`](crate::RoomManager).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DisposalPolicy {
+    /// Destroy the room this long after the game finishes, giving
+    /// players time to see the final state. Doesn't apply while anyone
+    /// is voting for a rematch.
+    pub after_finished: Option<Duration>,
+
+    /// Destroy the room once it has had no players for this long.
+    /// Also covers rooms that were created but never joined.
+    pub when_empty: Option<Duration>,
+
+    /// Destroy the room if no player joins, leaves, or sends a message
+    /// for this long.
+    pub max_idle: Option<Duration>,
+}
+
+impl DisposalPolicy {
+    /// A policy that never disposes of rooms automatically.
+    pub fn never() -> Self {
+        Self {
+            after_finished: None,
+            when_empty: None,
+            max_idle: None,
+        }
+    }
+}
+
+impl Default for DisposalPolicy {
+    fn default() -> Self {
+        Self {
+            after_finished: Some(Duration::from_secs(30)),
+            when_empty: Some(Duration::from_secs(60)),
+            max_idle: None,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// RoomOptions
+// ---------------------------------------------------------------------------
+
+/// Per-room access settings chosen when a room is created.
+///
+/// Unlike [`RoomConfig`], which is fixed per game type, these can differ
+/// between rooms of the same game.
+#[derive(Clone, Default)]
+pub struct RoomOptions {
+    /// Hidden from room listings and `join_or_create`. Can only be
+    /// joined with the room's invite code.
+    pub private: bool,
+
+    /// If set, every player must present this password to join.
+    pub password: Option<String>,
+
+    /// The first player to join becomes the room's host. Hosted rooms
+    /// don't auto-start; the host kicks, locks, and starts the game.
+    pub hosted: bool,
+
+    /// Seed for the room's RNG (see [`GameContext::rng`]). Random if
+    /// unset. Set it to replay a recorded game or to pin down a test;
+    /// the seed a room ended up with is in [`RoomInfo::seed`].
+    ///
+    /// [`GameContext::rng`]: crate::GameContext::rng
+    /// [`RoomInfo::seed`]: crate::RoomInfo::seed
+    pub seed: Option<u64>,
+}
+
+// Written out so the password never ends up in logs.
+impl fmt::Debug for RoomOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RoomOptions")
+            .field("private", &self.private)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .field("hosted", &self.hosted)
+            .field("seed", &self.seed)
+            .finish()
+    }
+}
+
 // ---------------------------------------------------------------------------
 // RoomState
 // ---------------------------------------------------------------------------
@@ -58,14 +489,19 @@ impl Default for RoomConfig {
 /// WaitingForPlayers → Starting → InProgress → Finished → Destroying
 /// ```
 ///
+/// There are two ways back: `Starting → WaitingForPlayers`, taken when a
+/// player leaves during the pre-game countdown, and `Finished →
+/// Starting`, taken when the players agree on a rematch.
+///
 /// - **WaitingForPlayers**: Room exists, accepting joins. Not enough
 ///   players to start yet.
-/// - **Starting**: Minimum players reached. Game is initializing
-///   (loading state, sending initial data to clients).
+/// - **Starting**: Minimum players reached. Players ready up during the
+///   pre-game countdown (if configured), then the game initializes.
 /// - **InProgress**: Game is actively running. Players send game
 ///   messages, tick loop is active (if configured).
 /// - **Finished**: Game ended (someone won, draw, etc.). Players
-///   can see final state but can't send game messages.
+///   can see final state but can't send game messages. They can vote
+///   for a rematch.
 /// - **Destroying**: Room is being cleaned up. All players removed,
 ///   resources freed. After this the room is gone.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -105,6 +541,8 @@ impl RoomState {
     /// Returns `true` if transitioning to `target` is valid.
     pub fn can_transition_to(self, target: Self) -> bool {
         self.next() == Some(target)
+            || (self == Self::Starting && target == Self::WaitingForPlayers)
+            || (self == Self::Finished && target == Self::Starting)
     }
 }
 
@@ -150,6 +588,13 @@ mod tests {
             .can_transition_to(RoomState::InProgress));
         assert!(!RoomState::Finished
             .can_transition_to(RoomState::WaitingForPlayers));
+        assert!(RoomState::Finished.can_transition_to(RoomState::Starting));
+        assert!(!RoomState::Finished
+            .can_transition_to(RoomState::InProgress));
+        assert!(RoomState::Starting
+            .can_transition_to(RoomState::WaitingForPlayers));
+        assert!(!RoomState::InProgress
+            .can_transition_to(RoomState::WaitingForPlayers));
     }
 
     #[test]
@@ -183,5 +628,50 @@ mod tests {
         assert_eq!(config.max_players, 8);
         assert_eq!(config.tick_rate, 0);
         assert!(!config.allow_spectators);
+        assert!(!config.allow_late_join);
+        assert!(config.pregame.is_none());
+        assert!(config.max_duration.is_none());
+        assert_eq!(config.chat.unwrap().history, 50);
+        assert_eq!(
+            config.disposal.after_finished,
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(config.disposal.max_idle, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "team size")]
+    fn test_empty_teams_are_rejected() {
+        TeamConfig { count: 2, size: 0 }.assert_valid(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "min_players")]
+    fn test_teams_too_small_for_min_players_are_rejected() {
+        TeamConfig { count: 2, size: 2 }.assert_valid(5);
+    }
+
+    #[test]
+    fn test_teams_that_fit_min_players_pass() {
+        TeamConfig { count: 2, size: 2 }.assert_valid(4);
+    }
+
+    #[test]
+    fn test_room_options_debug_hides_the_password() {
+        let options = RoomOptions {
+            password: Some("hunter2".into()),
+            ..RoomOptions::default()
+        };
+        let debug = format!("{options:?}");
+        assert!(!debug.contains("hunter2"));
+        assert!(debug.contains("<redacted>"));
+    }
+
+    #[test]
+    fn test_disposal_policy_never() {
+        let policy = DisposalPolicy::never();
+        assert!(policy.after_finished.is_none());
+        assert!(policy.when_empty.is_none());
+        assert!(policy.max_idle.is_none());
     }
 }
@@ -0,0 +1,181 @@
+//! The `GameContext` handed to `GameLogic` hooks.
+//!
+//! `GameLogic` methods are associated functions, so everything a hook
+//! needs beyond the state and its input comes in through the context:
+//! which room it is, the current tick and game time, who's playing and
+//! on which team, the room's random number generator, and ways to send
+//! messages.
+//!
+//! Games should take all randomness from [`GameContext::rng`] and all
+//! timing from [`GameContext::elapsed`] / [`GameContext::tick`] rather
+//! than `rand::rng()` or the system clock, so that a game can be replayed
+//! from its inputs.
+
+use std::time::Duration;
+
+use arcforge_protocol::{PlayerId, Recipient, RoomId, TeamId};
+use rand_chacha::ChaCha8Rng;
+
+use crate::Team;
+
+/// The random number generator rooms hand to game logic.
+///
+/// ChaCha8 produces the same stream for the same seed on every platform
+/// and `rand` version, which keeps replays reproducible. Use it through
+/// the [`rand::Rng`](https://docs.rs/rand/latest/rand/trait.Rng.html)
+/// trait.
+pub type GameRng = ChaCha8Rng;
+
+/// Messages a hook sent through its context.
+pub(crate) struct Outbox<M> {
+    /// Messages to dispatch right away.
+    pub(crate) now: Vec<(Recipient, M)>,
+    /// Messages to dispatch once their delay has passed.
+    pub(crate) later: Vec<(Duration, Recipient, M)>,
+    /// Timers to set (`Some(delay)`) or cancel (`None`), in call order.
+    pub(crate) timers: Vec<(String, Option<Duration>)>,
+    /// `Some(true)` if the hook paused the room, `Some(false)` if it
+    /// resumed it. The last call wins.
+    pub(crate) paused: Option<bool>,
+}
+
+/// Room-level information and services for a `GameLogic` hook.
+///
+/// `M` is the game's `ServerMessage` type. Messages sent through the
+/// context go out before the ones the hook returns.
+pub struct GameContext<'a, M> {
+    room_id: RoomId,
+    tick: u64,
+    elapsed: Duration,
+    players: &'a [PlayerId],
+    teams: &'a [Team],
+    rng: &'a mut GameRng,
+    outbox: Outbox<M>,
+}
+
+impl<'a, M> GameContext<'a, M> {
+    /// Builds a context by hand. Rooms do this for you; it's public so
+    /// game logic can be unit-tested without a running room.
+    pub fn new(
+        room_id: RoomId,
+        tick: u64,
+        elapsed: Duration,
+        players: &'a [PlayerId],
+        rng: &'a mut GameRng,
+    ) -> Self {
+        Self {
+            room_id,
+            tick,
+            elapsed,
+            players,
+            teams: &[],
+            rng,
+            outbox: Outbox {
+                now: Vec::new(),
+                later: Vec::new(),
+                timers: Vec::new(),
+                paused: None,
+            },
+        }
+    }
+
+    /// Sets the room's teams (see
+    /// [`RoomConfig::teams`](crate::RoomConfig::teams)). Contexts have
+    /// none otherwise.
+    pub fn with_teams(mut self, teams: &'a [Team]) -> Self {
+        self.teams = teams;
+        self
+    }
+
+    /// The room this game runs in.
+    pub fn room_id(&self) -> RoomId {
+        self.room_id
+    }
+
+    /// The last tick the room ran. Always 0 in event-driven rooms.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Time since the game started (zero in `init`), not counting time
+    /// spent paused.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// The players in the room, sorted by ID.
+    pub fn players(&self) -> &[PlayerId] {
+        self.players
+    }
+
+    /// The room's teams, in ID order. Empty if the game has no teams.
+    pub fn teams(&self) -> &[Team] {
+        self.teams
+    }
+
+    /// The team `player` is on, if any.
+    pub fn team_of(&self, player: PlayerId) -> Option<TeamId> {
+        self.teams
+            .iter()
+            .find(|team| team.players.contains(&player))
+            .map(|team| team.id)
+    }
+
+    /// The room's random number generator, seeded from the room's seed
+    /// (see `RoomOptions::seed`).
+    pub fn rng(&mut self) -> &mut GameRng {
+        self.rng
+    }
+
+    /// Sends `msg` to `recipient`.
+    pub fn send(&mut self, recipient: Recipient, msg: M) {
+        self.outbox.now.push((recipient, msg));
+    }
+
+    /// Sends `msg` to a single player.
+    pub fn send_to(&mut self, player: PlayerId, msg: M) {
+        self.send(Recipient::Player(player), msg);
+    }
+
+    /// Sends `msg` to every player in the room.
+    pub fn broadcast(&mut self, msg: M) {
+        self.send(Recipient::All, msg);
+    }
+
+    /// Sends `msg` to `recipient` once `delay` has passed (e.g. "round
+    /// starts in 3 seconds" followed by "go!"). Players who have left by
+    /// then don't get it.
+    pub fn send_after(&mut self, delay: Duration, recipient: Recipient, msg: M) {
+        self.outbox.later.push((delay, recipient, msg));
+    }
+
+    /// Starts a timer named `id` that calls
+    /// [`GameLogic::on_timer`](crate::GameLogic::on_timer) once `after`
+    /// has passed (e.g. a turn timeout). Setting a timer that's already
+    /// running restarts it.
+    pub fn set_timer(&mut self, id: impl Into<String>, after: Duration) {
+        self.outbox.timers.push((id.into(), Some(after)));
+    }
+
+    /// Stops the timer named `id`. Does nothing if it isn't running.
+    pub fn cancel_timer(&mut self, id: impl Into<String>) {
+        self.outbox.timers.push((id.into(), None));
+    }
+
+    /// Pauses the room's clock: ticks stop, and timers and delayed sends
+    /// keep their remaining time until [`resume`](Self::resume). Client
+    /// messages are still delivered.
+    pub fn pause(&mut self) {
+        self.outbox.paused = Some(true);
+    }
+
+    /// Resumes a paused room.
+    pub fn resume(&mut self) {
+        self.outbox.paused = Some(false);
+    }
+
+    /// Ends the hook, handing over everything it sent.
+    pub(crate) fn into_outbox(self) -> Outbox<M> {
+        self.outbox
+    }
+}
@@ -26,6 +26,32 @@ pub enum RoomError {
     #[error("invalid room state for this operation: {0}")]
     InvalidState(String),
 
+    /// The password was missing or didn't match the room's password.
+    #[error("wrong password for room {0}")]
+    WrongPassword(RoomId),
+
+    /// No room has this invite code.
+    #[error("no room with invite code {0}")]
+    InvalidInviteCode(String),
+
+    /// Only the room's host may do this.
+    #[error("player {0} is not the host of room {1}")]
+    NotHost(PlayerId, RoomId),
+
+    /// The host has locked the room against new players.
+    #[error("room {0} is locked")]
+    Locked(RoomId),
+
+    /// The room turned a chat message away (empty, too long, or blocked
+    /// by the chat filter).
+    #[error("chat message rejected: {0}")]
+    ChatRejected(String),
+
+    /// The game logic panicked while handling the request, and the room
+    /// is closing.
+    #[error("the game in room {0} crashed")]
+    GameCrashed(RoomId),
+
     /// The room's command channel is full or closed.
     #[error("room {0} is unavailable")]
     Unavailable(RoomId),
@@ -0,0 +1,216 @@
+//! Server-side state history for lag compensation.
+//!
+//! A player acting on what their screen shows is acting on the past: the
+//! state they see left the server a while ago, and their action takes a
+//! while to arrive. The room keeps the last few ticks of state so games
+//! can judge an action (a hitscan shot, say) against the state the
+//! player actually saw rather than the one the server has now.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::LagCompensationConfig;
+
+/// A bounded ring buffer of game state snapshots, one per tick.
+pub(crate) struct StateHistory<S> {
+    snapshots: VecDeque<(u64, S)>,
+    capacity: usize,
+}
+
+impl<S: Clone> StateHistory<S> {
+    /// Sizes the history to cover `config.max_rewind` at `tick_rate`,
+    /// capped at `config.max_snapshots`.
+    pub(crate) fn new(config: &LagCompensationConfig, tick_rate: u32) -> Self {
+        let ticks = (config.max_rewind.as_secs_f64() * f64::from(tick_rate))
+            .ceil() as usize;
+        let capacity = (ticks + 1).min(config.max_snapshots).max(1);
+        Self {
+            snapshots: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Stores a copy of `state` as of the end of `tick`, evicting the
+    /// oldest snapshot if the history is full.
+    pub(crate) fn record(&mut self, tick: u64, state: &S) {
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back((tick, state.clone()));
+    }
+}
+
+impl<S> StateHistory<S> {
+    /// Returns the most recent snapshot taken at or before `tick`.
+    fn at_tick(&self, tick: u64) -> Option<&S> {
+        self.snapshots
+            .iter()
+            .rev()
+            .find(|(t, _)| *t <= tick)
+            .map(|(_, state)| state)
+    }
+
+    fn oldest(&self) -> Option<&S> {
+        self.snapshots.front().map(|(_, state)| state)
+    }
+}
+
+/// Past game state, as seen by the sender of a message.
+///
+/// Passed to
+/// [`GameLogic::handle_compensated_message`](crate::GameLogic::handle_compensated_message).
+/// The room rewinds by the sender's measured RTT plus the configured
+/// interpolation delay, up to `max_rewind`.
+pub struct LagCompensation<'a, S> {
+    history: &'a StateHistory<S>,
+    current_tick: u64,
+    rewind_ticks: u64,
+    rtt: Option<Duration>,
+}
+
+impl<'a, S> LagCompensation<'a, S> {
+    pub(crate) fn new(
+        history: &'a StateHistory<S>,
+        config: &LagCompensationConfig,
+        current_tick: u64,
+        tick_duration: Duration,
+        rtt: Option<Duration>,
+    ) -> Self {
+        let rewind = (rtt.unwrap_or_default() + config.interpolation_delay)
+            .min(config.max_rewind);
+        let rewind_ticks = if tick_duration.is_zero() {
+            0
+        } else {
+            (rewind.as_secs_f64() / tick_duration.as_secs_f64()).round() as u64
+        };
+        Self {
+            history,
+            current_tick,
+            rewind_ticks,
+            rtt,
+        }
+    }
+
+    /// The state as the sender saw it when they acted.
+    ///
+    /// If the rewind reaches past the stored history, this is the oldest
+    /// snapshot available.
+    pub fn sender_view(&self) -> Option<&'a S> {
+        let tick = self.current_tick.saturating_sub(self.rewind_ticks);
+        self.history.at_tick(tick).or_else(|| self.history.oldest())
+    }
+
+    /// The state as of the end of `tick`, if it's still in the history.
+    pub fn at_tick(&self, tick: u64) -> Option<&'a S> {
+        self.history.at_tick(tick)
+    }
+
+    /// The last tick the room ran.
+    pub fn current_tick(&self) -> u64 {
+        self.current_tick
+    }
+
+    /// How many ticks [`sender_view`](Self::sender_view) lies behind
+    /// the current tick.
+    pub fn rewind_ticks(&self) -> u64 {
+        self.rewind_ticks
+    }
+
+    /// The sender's last measured round-trip time, if any.
+    pub fn rtt(&self) -> Option<Duration> {
+        self.rtt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_rewind_ms: u64) -> LagCompensationConfig {
+        LagCompensationConfig {
+            max_rewind: Duration::from_millis(max_rewind_ms),
+            ..Default::default()
+        }
+    }
+
+    fn history(ticks: u64) -> StateHistory<u64> {
+        let mut history = StateHistory::new(&config(1000), 20);
+        for tick in 0..=ticks {
+            history.record(tick, &tick);
+        }
+        history
+    }
+
+    #[test]
+    fn test_capacity_covers_max_rewind() {
+        // 200ms at 20 Hz is 4 ticks, plus the current one.
+        let mut history = StateHistory::new(&config(200), 20);
+        for tick in 0..10 {
+            history.record(tick, &tick);
+        }
+        let ticks: Vec<_> = history.snapshots.iter().map(|(t, _)| *t).collect();
+        assert_eq!(ticks, vec![5, 6, 7, 8, 9]);
+
+        let capped = StateHistory::<u64>::new(
+            &LagCompensationConfig {
+                max_snapshots: 3,
+                ..config(1000)
+            },
+            60,
+        );
+        assert_eq!(capped.capacity, 3);
+    }
+
+    #[test]
+    fn test_rewinds_by_rtt() {
+        let history = history(10);
+        let tick = Duration::from_millis(50);
+        let lag = LagCompensation::new(
+            &history,
+            &config(1000),
+            10,
+            tick,
+            Some(Duration::from_millis(100)),
+        );
+        assert_eq!(lag.rewind_ticks(), 2);
+        assert_eq!(lag.sender_view(), Some(&8));
+        assert_eq!(lag.at_tick(3), Some(&3));
+    }
+
+    #[test]
+    fn test_rewind_is_capped_and_clamped_to_history() {
+        let history = history(10);
+        let tick = Duration::from_millis(50);
+        let rtt = Some(Duration::from_secs(5));
+
+        let lag = LagCompensation::new(&history, &config(150), 10, tick, rtt);
+        assert_eq!(lag.rewind_ticks(), 3);
+        assert_eq!(lag.sender_view(), Some(&7));
+
+        // Rewinding past the oldest snapshot settles for the oldest.
+        let mut short = StateHistory::new(&config(200), 20);
+        for t in 0..10 {
+            short.record(t, &t);
+        }
+        let lag = LagCompensation::new(&short, &config(1000), 9, tick, rtt);
+        assert_eq!(lag.sender_view(), Some(&5));
+    }
+
+    #[test]
+    fn test_unknown_rtt_uses_interpolation_delay_only() {
+        let history = history(10);
+        let config = LagCompensationConfig {
+            interpolation_delay: Duration::from_millis(50),
+            ..config(1000)
+        };
+        let lag = LagCompensation::new(
+            &history,
+            &config,
+            10,
+            Duration::from_millis(50),
+            None,
+        );
+        assert_eq!(lag.rewind_ticks(), 1);
+        assert_eq!(lag.sender_view(), Some(&9));
+    }
+}
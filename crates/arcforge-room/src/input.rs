@@ -0,0 +1,272 @@
+//! Tick-aligned input buffering.
+//!
+//! In buffered mode, client messages aren't applied as they arrive. Each
+//! one is tagged with the tick it's meant for, queued, and handed to
+//! [`GameLogic::tick`](crate::GameLogic::tick) as part of that tick's
+//! batch. Every player contributes at most one input per tick, and
+//! batches are ordered by player ID, so the same inputs always produce
+//! the same simulation.
+
+use std::collections::{BTreeMap, HashMap};
+
+use arcforge_protocol::PlayerId;
+
+use crate::{InputBufferConfig, LateInputPolicy};
+
+/// One player's input for one tick, as passed to `GameLogic::tick`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerInput<M> {
+    /// Who sent the input.
+    pub player: PlayerId,
+    /// The input itself.
+    pub input: M,
+    /// `true` if the player sent nothing for this tick and this is a
+    /// copy of their last input (see [`InputBufferConfig::repeat_last`](crate::InputBufferConfig::repeat_last)).
+    pub repeated: bool,
+}
+
+/// Why an input wasn't queued.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum InputRejection {
+    /// Its tick already ran and the policy is [`LateInputPolicy::Drop`].
+    Late,
+    /// It's scheduled more than `max_ticks_ahead` ticks out.
+    TooFarAhead,
+    /// The player already has an input queued for that tick.
+    Duplicate,
+}
+
+impl std::fmt::Display for InputRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Late => write!(f, "late"),
+            Self::TooFarAhead => write!(f, "too far ahead"),
+            Self::Duplicate => write!(f, "duplicate"),
+        }
+    }
+}
+
+/// Per-room queue of inputs, keyed by tick and player. Each input
+/// keeps the client `seq` it arrived with, for input acknowledgement.
+pub(crate) struct InputBuffer<M> {
+    config: InputBufferConfig,
+    queued: BTreeMap<u64, HashMap<PlayerId, (Option<u64>, M)>>,
+    last: HashMap<PlayerId, M>,
+}
+
+impl<M: Clone> InputBuffer<M> {
+    pub(crate) fn new(config: InputBufferConfig) -> Self {
+        Self {
+            config,
+            queued: BTreeMap::new(),
+            last: HashMap::new(),
+        }
+    }
+
+    /// Queues `input` for `tick`. `current_tick` is the last tick that
+    /// ran; untagged inputs (`tick == None`) go to the one after it.
+    /// `seq` is the client envelope seq the input arrived with, if any.
+    ///
+    /// Returns the tick the input was queued for.
+    pub(crate) fn push(
+        &mut self,
+        player: PlayerId,
+        seq: Option<u64>,
+        tick: Option<u64>,
+        input: M,
+        current_tick: u64,
+    ) -> Result<u64, InputRejection> {
+        let next = current_tick + 1;
+        let mut tick = tick.unwrap_or(next);
+        if tick < next {
+            match self.config.late_inputs {
+                LateInputPolicy::Drop => return Err(InputRejection::Late),
+                LateInputPolicy::NextTick => tick = next,
+            }
+        }
+        if tick > current_tick + self.config.max_ticks_ahead {
+            return Err(InputRejection::TooFarAhead);
+        }
+
+        let slot = self.queued.entry(tick).or_default();
+        if slot.contains_key(&player) {
+            return Err(InputRejection::Duplicate);
+        }
+        slot.insert(player, (seq, input));
+        Ok(tick)
+    }
+
+    /// Removes and returns the batch for `tick`, filling in repeated
+    /// inputs for `players` who sent nothing (if configured). Each input
+    /// comes with its client `seq`; repeats have none.
+    pub(crate) fn take(
+        &mut self,
+        tick: u64,
+        players: &[PlayerId],
+    ) -> Vec<(PlayerInput<M>, Option<u64>)> {
+        let mut batch = self.queued.remove(&tick).unwrap_or_default();
+
+        let mut inputs = Vec::with_capacity(players.len());
+        let mut sorted = players.to_vec();
+        sorted.sort_by_key(|p| p.0);
+        for player in sorted {
+            if let Some((seq, input)) = batch.remove(&player) {
+                self.last.insert(player, input.clone());
+                let input = PlayerInput {
+                    player,
+                    input,
+                    repeated: false,
+                };
+                inputs.push((input, seq));
+            } else if self.config.repeat_last {
+                if let Some(input) = self.last.get(&player) {
+                    let input = PlayerInput {
+                        player,
+                        input: input.clone(),
+                        repeated: true,
+                    };
+                    inputs.push((input, None));
+                }
+            }
+        }
+        inputs
+    }
+
+    /// The lowest client seq `player` has waiting in the buffer, if any.
+    /// Acks mustn't reach it until its tick runs.
+    pub(crate) fn lowest_pending(&self, player: PlayerId) -> Option<u64> {
+        self.queued
+            .values()
+            .filter_map(|slot| slot.get(&player).and_then(|(seq, _)| *seq))
+            .min()
+    }
+
+    /// Drops everything queued or remembered for a player who left.
+    pub(crate) fn remove_player(&mut self, player: PlayerId) {
+        self.last.remove(&player);
+        for slot in self.queued.values_mut() {
+            slot.remove(&player);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(config: InputBufferConfig) -> InputBuffer<&'static str> {
+        InputBuffer::new(config)
+    }
+
+    #[test]
+    fn test_batches_are_per_tick_and_sorted_by_player() {
+        let mut buf = buffer(InputBufferConfig::default());
+        buf.push(PlayerId(2), None, Some(1), "b1", 0).unwrap();
+        buf.push(PlayerId(1), None, Some(1), "a1", 0).unwrap();
+        buf.push(PlayerId(1), None, Some(2), "a2", 0).unwrap();
+
+        let batch = buf.take(1, &[PlayerId(2), PlayerId(1)]);
+        let inputs: Vec<_> =
+            batch.iter().map(|(i, _)| (i.player, i.input)).collect();
+        assert_eq!(inputs, vec![(PlayerId(1), "a1"), (PlayerId(2), "b1")]);
+
+        let batch = buf.take(2, &[PlayerId(1), PlayerId(2)]);
+        assert_eq!(batch.len(), 1);
+        assert_eq!(batch[0].0.input, "a2");
+    }
+
+    #[test]
+    fn test_untagged_input_goes_to_next_tick() {
+        let mut buf = buffer(InputBufferConfig::default());
+        assert_eq!(buf.push(PlayerId(1), None, None, "a", 7), Ok(8));
+    }
+
+    #[test]
+    fn test_duplicates_are_rejected() {
+        let mut buf = buffer(InputBufferConfig::default());
+        buf.push(PlayerId(1), None, Some(3), "first", 0).unwrap();
+        assert_eq!(
+            buf.push(PlayerId(1), None, Some(3), "second", 0),
+            Err(InputRejection::Duplicate)
+        );
+        assert_eq!(buf.take(3, &[PlayerId(1)])[0].0.input, "first");
+    }
+
+    #[test]
+    fn test_late_inputs_follow_policy() {
+        let mut next = buffer(InputBufferConfig::default());
+        assert_eq!(next.push(PlayerId(1), None, Some(3), "a", 5), Ok(6));
+
+        let mut drop = buffer(InputBufferConfig {
+            late_inputs: LateInputPolicy::Drop,
+            ..Default::default()
+        });
+        assert_eq!(
+            drop.push(PlayerId(1), None, Some(3), "a", 5),
+            Err(InputRejection::Late)
+        );
+    }
+
+    #[test]
+    fn test_inputs_too_far_ahead_are_rejected() {
+        let mut buf = buffer(InputBufferConfig {
+            max_ticks_ahead: 4,
+            ..Default::default()
+        });
+        assert!(buf.push(PlayerId(1), None, Some(4), "a", 0).is_ok());
+        assert_eq!(
+            buf.push(PlayerId(1), None, Some(5), "a", 0),
+            Err(InputRejection::TooFarAhead)
+        );
+    }
+
+    #[test]
+    fn test_repeat_last_fills_missing_inputs() {
+        let mut buf = buffer(InputBufferConfig {
+            repeat_last: true,
+            ..Default::default()
+        });
+        buf.push(PlayerId(1), None, Some(1), "left", 0).unwrap();
+        buf.take(1, &[PlayerId(1), PlayerId(2)]);
+
+        let batch = buf.take(2, &[PlayerId(1), PlayerId(2)]);
+        assert_eq!(
+            batch,
+            vec![(
+                PlayerInput {
+                    player: PlayerId(1),
+                    input: "left",
+                    repeated: true,
+                },
+                None
+            )]
+        );
+    }
+
+    #[test]
+    fn test_inputs_keep_their_client_seq() {
+        let mut buf = buffer(InputBufferConfig::default());
+        buf.push(PlayerId(1), Some(17), Some(1), "a", 0).unwrap();
+        buf.push(PlayerId(2), None, Some(1), "b", 0).unwrap();
+
+        let seqs: Vec<_> = buf
+            .take(1, &[PlayerId(1), PlayerId(2)])
+            .into_iter()
+            .map(|(i, seq)| (i.player, seq))
+            .collect();
+        assert_eq!(seqs, vec![(PlayerId(1), Some(17)), (PlayerId(2), None)]);
+    }
+
+    #[test]
+    fn test_remove_player_forgets_their_inputs() {
+        let mut buf = buffer(InputBufferConfig {
+            repeat_last: true,
+            ..Default::default()
+        });
+        buf.push(PlayerId(1), None, Some(1), "a", 0).unwrap();
+        buf.take(1, &[PlayerId(1)]);
+        buf.push(PlayerId(1), None, Some(2), "b", 1).unwrap();
+        buf.remove_player(PlayerId(1));
+        assert!(buf.take(2, &[PlayerId(1)]).is_empty());
+    }
+}
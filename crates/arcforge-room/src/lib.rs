@@ -6,21 +6,51 @@
 //! # Key types
 //!
 //! - [`GameLogic`] — the trait game developers implement
+//! - [`AsyncGameLogic`] — async hooks for games that call other services
+//! - [`Bot`] — server-side players for empty or abandoned seats
+//! - [`GameContext`] — room info and services passed to game hooks
 //! - [`RoomManager`] — creates/destroys rooms, routes players
 //! - [`RoomHandle`] — send commands to a running room actor
 //! - [`RoomState`] — lifecycle state machine
 //! - [`RoomConfig`] — room settings (player limits, tick rate, etc.)
+//! - [`Team`] — a team of players, for games with [`TeamConfig`]
+//! - [`ResultSink`] — where finished games report their results
+//! - [`ChatFilter`] — screens the built-in room chat
 
 #![allow(async_fn_in_trait)]
 
+mod async_logic;
+mod bot;
+mod chat;
 mod config;
+mod context;
 mod error;
+mod history;
+mod input;
 mod logic;
 mod manager;
+mod results;
 mod room;
+mod sync;
+mod team;
 
-pub use config::{RoomConfig, RoomState};
+pub use async_logic::AsyncGameLogic;
+pub use bot::Bot;
+pub use chat::{ChatFilter, WordFilter};
+pub use config::{
+    AsyncHookConfig, BotConfig, ChatConfig, DisposalPolicy, InputBufferConfig,
+    LagCompensationConfig, LateInputPolicy, PanicPolicy, PregameConfig,
+    RoomConfig, RoomOptions, RoomState, StateSyncConfig, TeamConfig,
+};
+pub use context::{GameContext, GameRng};
 pub use error::RoomError;
+pub use history::LagCompensation;
+pub use input::PlayerInput;
 pub use logic::GameLogic;
 pub use manager::RoomManager;
-pub use room::{RoomHandle, RoomInfo, RoomOutbound, PlayerSender};
+pub use results::{JsonLinesSink, MatchRecord, ResultSink};
+pub use room::{
+    JoinCredentials, PlayerSender, RoomHandle, RoomInfo, RoomOutbound,
+};
+pub use sync::{apply_patch, diff_values};
+pub use team::Team;
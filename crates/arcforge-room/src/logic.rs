@@ -6,22 +6,28 @@
 
 use std::time::Duration;
 
-use arcforge_protocol::{PlayerId, Recipient};
+use arcforge_protocol::{MatchResult, PlayerId, Recipient, RoomMetadata};
 use serde::{de::DeserializeOwned, Serialize};
 
-use crate::RoomConfig;
+use crate::{GameContext, LagCompensation, PlayerInput, RoomConfig};
 
 /// The core trait that game developers implement.
 ///
 /// Each associated type defines the shape of the game's data:
 /// - `Config` — game-specific settings (board size, time limit, etc.)
 /// - `State` — the full game state (board, scores, whose turn, etc.)
+/// - `View` — what one player is allowed to see of the state
 /// - `ClientMessage` — what clients can send (moves, actions)
 /// - `ServerMessage` — what the server sends back (state updates, events)
 ///
 /// The framework calls `init` to create the initial state, routes client
 /// messages through `handle_message`, and optionally calls `tick` for
 /// real-time games.
+///
+/// Hooks that change the state also get a [`GameContext`] with the room
+/// ID, tick, game time, player list, the room's RNG, and send helpers.
+/// Take randomness and time from there, not from globals, so games can
+/// be replayed.
 pub trait GameLogic: Send + Sync + 'static {
     /// Game-specific configuration (e.g., board size, time limit).
     type Config: Send + Sync + Clone + Default;
@@ -30,6 +36,12 @@ pub trait GameLogic: Send + Sync + 'static {
     /// send snapshots to clients.
     type State: Send + Sync + Clone + Serialize + DeserializeOwned;
 
+    /// What a single player gets to see of the state, as built by
+    /// [`view_for`](Self::view_for). Games without hidden information
+    /// use `type View = Self::State;`. Games with hidden information
+    /// (cards, fog of war, secret roles) use a separate type.
+    type View: Send + Sync + Clone + Serialize + DeserializeOwned;
+
     /// Messages that clients send to the server (e.g., "place marker at row 1, col 2").
     type ClientMessage: Send + Sync + Clone + Serialize + DeserializeOwned;
 
@@ -39,8 +51,11 @@ pub trait GameLogic: Send + Sync + 'static {
     /// Creates the initial game state when a room starts.
     ///
     /// Called once when the room transitions from WaitingForPlayers → Starting.
-    /// `players` contains the IDs of all players who joined.
-    fn init(config: &Self::Config, players: &[PlayerId]) -> Self::State;
+    /// `ctx.players()` contains the IDs of all players who joined.
+    fn init(
+        config: &Self::Config,
+        ctx: &mut GameContext<'_, Self::ServerMessage>,
+    ) -> Self::State;
 
     /// Processes a message from a client.
     ///
@@ -50,29 +65,97 @@ pub trait GameLogic: Send + Sync + 'static {
         state: &mut Self::State,
         sender: PlayerId,
         msg: Self::ClientMessage,
+        ctx: &mut GameContext<'_, Self::ServerMessage>,
     ) -> Vec<(Recipient, Self::ServerMessage)>;
 
+    /// Processes a message from a client, with access to past state.
+    ///
+    /// Called instead of `handle_message` in rooms with
+    /// `room_config().lag_compensation` set. Use `lag.sender_view()` to
+    /// judge timing-sensitive actions against the state the sender saw
+    /// (did the shot hit where the target *was* on their screen?), then
+    /// apply the outcome to `state`. Default: ignores the history and
+    /// calls `handle_message`.
+    fn handle_compensated_message(
+        state: &mut Self::State,
+        sender: PlayerId,
+        msg: Self::ClientMessage,
+        _lag: &LagCompensation<'_, Self::State>,
+        ctx: &mut GameContext<'_, Self::ServerMessage>,
+    ) -> Vec<(Recipient, Self::ServerMessage)> {
+        Self::handle_message(state, sender, msg, ctx)
+    }
+
     /// Returns `true` if the game is over.
     ///
     /// Called after every `handle_message` and `tick`. When this returns
     /// `true`, the room transitions to Finished.
     fn is_finished(state: &Self::State) -> bool;
 
+    /// Builds the view of `state` that `player` is allowed to see.
+    ///
+    /// Every state snapshot the framework sends to a client goes through
+    /// this (game start, late join, etc.), so the full `State` never
+    /// leaves the server. There's deliberately no default: a game
+    /// without hidden information returns `state.clone()`, and a game
+    /// with it has to decide what each player sees.
+    fn view_for(state: &Self::State, player: PlayerId) -> Self::View;
+
     /// Called every tick for real-time games.
     ///
     /// `dt` is the time since the last tick. Only called if
-    /// `room_config().tick_rate > 0`. Default: no-op.
+    /// `room_config().tick_rate > 0`. With `room_config().input_buffer`
+    /// set, `inputs` holds this tick's client messages, at most one per
+    /// player, sorted by player ID; otherwise it's always empty and
+    /// messages go through `handle_message`. Default: no-op.
     fn tick(
         _state: &mut Self::State,
         _dt: Duration,
+        _inputs: Vec<PlayerInput<Self::ClientMessage>>,
+        _ctx: &mut GameContext<'_, Self::ServerMessage>,
+    ) -> Vec<(Recipient, Self::ServerMessage)> {
+        Vec::new()
+    }
+
+    /// Called when a timer set with [`GameContext::set_timer`] fires.
+    ///
+    /// Timers don't run while the room is paused, and all of them are
+    /// dropped when the game finishes. Default: no-op.
+    fn on_timer(
+        _state: &mut Self::State,
+        _timer_id: &str,
+        _ctx: &mut GameContext<'_, Self::ServerMessage>,
+    ) -> Vec<(Recipient, Self::ServerMessage)> {
+        Vec::new()
+    }
+
+    /// Reports the outcome of a finished game: placements, scores, and
+    /// per-player stats.
+    ///
+    /// Called once when the game ends, however it ends. The result goes
+    /// to every player in the `GameEnded` message and to the server's
+    /// [`ResultSink`](crate::ResultSink). Default: an empty result.
+    fn results(_state: &Self::State) -> MatchResult {
+        MatchResult::default()
+    }
+
+    /// Called when the game reaches `room_config().max_duration`.
+    ///
+    /// Use this to declare a result (most points wins, a draw, etc.).
+    /// The game ends right after, even if `is_finished` still returns
+    /// `false`, and clients are told it timed out. Default: no-op.
+    fn on_timeout(
+        _state: &mut Self::State,
+        _ctx: &mut GameContext<'_, Self::ServerMessage>,
     ) -> Vec<(Recipient, Self::ServerMessage)> {
         Vec::new()
     }
 
     /// Validates a client message before processing.
     ///
-    /// Called before `handle_message`. If this returns `Err`, the message
-    /// is rejected and the error is sent back to the client. Default: accept all.
+    /// Called before `handle_message` (or, with an input buffer, when the
+    /// input is queued). If this returns `Err`, the message is rejected
+    /// and the error is sent back to the client. Default: accept all.
     fn validate_message(
         _state: &Self::State,
         _sender: PlayerId,
@@ -81,12 +164,38 @@ pub trait GameLogic: Send + Sync + 'static {
         Ok(())
     }
 
+    /// Called when a player joins a game that's already in progress.
+    ///
+    /// Only happens if `room_config().allow_late_join` is set. Use this
+    /// to spawn the player into the world. They receive a full state
+    /// snapshot right after this returns. Default: no-op.
+    fn on_player_join(
+        _state: &mut Self::State,
+        _player: PlayerId,
+        _ctx: &mut GameContext<'_, Self::ServerMessage>,
+    ) -> Vec<(Recipient, Self::ServerMessage)> {
+        Vec::new()
+    }
+
+    /// Called when a player leaves (or is kicked from) a running game.
+    ///
+    /// Use this to remove them from the world. Default: delegates to
+    /// `on_player_disconnect`.
+    fn on_player_leave(
+        state: &mut Self::State,
+        player: PlayerId,
+        ctx: &mut GameContext<'_, Self::ServerMessage>,
+    ) -> Vec<(Recipient, Self::ServerMessage)> {
+        Self::on_player_disconnect(state, player, ctx)
+    }
+
     /// Called when a player disconnects from the room.
     ///
     /// Use this to pause the game, skip their turn, etc. Default: no-op.
     fn on_player_disconnect(
         _state: &mut Self::State,
         _player: PlayerId,
+        _ctx: &mut GameContext<'_, Self::ServerMessage>,
     ) -> Vec<(Recipient, Self::ServerMessage)> {
         Vec::new()
     }
@@ -98,10 +207,25 @@ pub trait GameLogic: Send + Sync + 'static {
     fn on_player_reconnect(
         _state: &mut Self::State,
         _player: PlayerId,
+        _ctx: &mut GameContext<'_, Self::ServerMessage>,
     ) -> Vec<(Recipient, Self::ServerMessage)> {
         Vec::new()
     }
 
+    /// Returns the public metadata for a room (map, mode, region, host
+    /// name, skill bracket, etc.).
+    ///
+    /// `state` is `None` while the room is still waiting for players.
+    /// The room re-evaluates this after the game starts and after every
+    /// state change, so the values shown in room listings stay current.
+    /// Default: no metadata.
+    fn room_metadata(
+        _config: &Self::Config,
+        _state: Option<&Self::State>,
+    ) -> RoomMetadata {
+        RoomMetadata::new()
+    }
+
     /// Returns the room configuration for this game type.
     ///
     /// Override to customize min/max players, tick rate, etc.
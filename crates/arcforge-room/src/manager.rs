@@ -2,11 +2,21 @@
 
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
-
-use arcforge_protocol::{PlayerId, RoomId};
-
-use crate::{GameLogic, PlayerSender, RoomError, RoomHandle, RoomInfo};
-use crate::room::spawn_room;
+use std::sync::Arc;
+use std::time::Duration;
+
+use arcforge_protocol::{ChatScope, PlayerId, RoomId, RoomMetadata};
+use rand::Rng;
+use tokio::sync::mpsc;
+
+use crate::{
+    AsyncGameLogic, Bot, ChatFilter, GameLogic, JoinCredentials, PlayerSender,
+    ResultSink, RoomError, RoomHandle, RoomInfo, RoomOptions,
+};
+use crate::async_logic::AsyncHooks;
+use crate::bot::BotFactory;
+use crate::room::{spawn_room, DisposedSender, ReleasedSender, RoomServices};
+use crate::team::RatingFn;
 
 /// Counter for generating unique room IDs.
 static NEXT_ROOM_ID: AtomicU64 = AtomicU64::new(1);
@@ -14,6 +24,13 @@ static NEXT_ROOM_ID: AtomicU64 = AtomicU64::new(1);
 /// Default command channel size for room actors.
 const DEFAULT_CHANNEL_SIZE: usize = 64;
 
+/// Characters used in invite codes. Look-alikes (0/O, 1/I/L) are left
+/// out so codes are easy to read aloud and type.
+const INVITE_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKMNPQRSTUVWXYZ23456789";
+
+/// Length of generated invite codes.
+const INVITE_CODE_LEN: usize = 6;
+
 /// Manages all active rooms and tracks which player is in which room.
 ///
 /// This is the entry point for room operations from higher layers
@@ -25,33 +42,227 @@ pub struct RoomManager<G: GameLogic> {
     /// Maps each player to the room they're currently in.
     /// A player can be in at most ONE room at a time (key invariant).
     player_rooms: HashMap<PlayerId, RoomId>,
+
+    /// Index from invite codes to rooms. Kept in sync with `rooms`.
+    invite_codes: HashMap<String, RoomId>,
+
+    /// Cloned into every room actor; rooms that dispose of themselves
+    /// (see [`DisposalPolicy`](crate::DisposalPolicy)) report here.
+    disposed_tx: DisposedSender,
+    disposed_rx: mpsc::UnboundedReceiver<RoomId>,
+
+    /// Cloned into every room actor; rooms report players they removed
+    /// on their own here (disconnected players who never came back).
+    released_tx: ReleasedSender,
+    released_rx: mpsc::UnboundedReceiver<(RoomId, PlayerId)>,
+
+    /// Handed to every new room to record finished games.
+    result_sink: Option<Arc<dyn ResultSink>>,
+
+    /// Handed to every new room when the game's async hooks are on.
+    async_hooks: Option<AsyncHooks<G>>,
+
+    /// Handed to every new room to create its bots.
+    bot_factory: Option<BotFactory<G>>,
+
+    /// Handed to every new room to balance its teams.
+    ratings: Option<RatingFn>,
+
+    /// Handed to every new room to screen its chat.
+    chat_filter: Option<Arc<dyn ChatFilter>>,
 }
 
 impl<G: GameLogic> RoomManager<G> {
     /// Creates a new, empty room manager.
+    ///
+    /// # Panics
+    ///
+    /// If the game's [`TeamConfig`](crate::TeamConfig) has empty teams,
+    /// or too few seats for its `min_players`.
     pub fn new() -> Self {
+        let config = G::room_config();
+        if let Some(teams) = &config.teams {
+            teams.assert_valid(config.min_players);
+        }
+        let (disposed_tx, disposed_rx) = mpsc::unbounded_channel();
+        let (released_tx, released_rx) = mpsc::unbounded_channel();
         Self {
             rooms: HashMap::new(),
             player_rooms: HashMap::new(),
+            invite_codes: HashMap::new(),
+            disposed_tx,
+            disposed_rx,
+            released_tx,
+            released_rx,
+            result_sink: None,
+            async_hooks: None,
+            bot_factory: None,
+            ratings: None,
+            chat_filter: None,
         }
     }
 
-    /// Creates a new room and returns its ID.
+    /// Sends the result of every game that finishes in this manager's
+    /// rooms to `sink`. Applies to rooms created from now on.
+    pub fn with_result_sink(mut self, sink: Arc<dyn ResultSink>) -> Self {
+        self.result_sink = Some(sink);
+        self
+    }
+
+    /// Runs the game's [`AsyncGameLogic`] hooks in this manager's rooms.
+    /// Applies to rooms created from now on.
+    pub fn with_async_hooks(mut self) -> Self
+    where
+        G: AsyncGameLogic,
+    {
+        self.async_hooks = Some(AsyncHooks::new());
+        self
+    }
+
+    /// Creates bots with `factory`, given each bot's player ID, for the
+    /// seats the game's [`BotConfig`](crate::BotConfig) hands to bots.
+    /// Applies to rooms created from now on.
+    pub fn with_bots<F>(mut self, factory: F) -> Self
+    where
+        F: Fn(PlayerId) -> Box<dyn Bot<G>> + Send + Sync + 'static,
+    {
+        self.bot_factory = Some(Arc::new(factory));
+        self
+    }
+
+    /// Balances teams (see [`TeamConfig`](crate::TeamConfig)) by the
+    /// ratings `rating` gives each player. Without it, teams are only
+    /// kept the same size. Applies to rooms created from now on.
+    pub fn with_ratings<F>(mut self, rating: F) -> Self
+    where
+        F: Fn(PlayerId) -> f64 + Send + Sync + 'static,
+    {
+        self.ratings = Some(Arc::new(rating));
+        self
+    }
+
+    /// Runs every chat message sent in this manager's rooms through
+    /// `filter` (e.g. a [`WordFilter`](crate::WordFilter)). Applies to
+    /// rooms created from now on.
+    pub fn with_chat_filter(mut self, filter: Arc<dyn ChatFilter>) -> Self {
+        self.chat_filter = Some(filter);
+        self
+    }
+
+    /// Creates a new public room and returns its ID.
+    ///
+    /// The room's RNG gets a random seed. To pin it down, use
+    /// [`create_seeded_room`](Self::create_seeded_room), or set
+    /// [`RoomOptions::seed`] with [`create_room_with`](Self::create_room_with).
     pub fn create_room(&mut self, game_config: G::Config) -> RoomId {
+        self.create_room_with(game_config, RoomOptions::default())
+    }
+
+    /// Creates a new public room whose RNG starts from `seed`, e.g. to
+    /// replay a recorded game, and returns its ID.
+    pub fn create_seeded_room(
+        &mut self,
+        game_config: G::Config,
+        seed: u64,
+    ) -> RoomId {
+        let options = RoomOptions {
+            seed: Some(seed),
+            ..RoomOptions::default()
+        };
+        self.create_room_with(game_config, options)
+    }
+
+    /// Creates a new room with the given access options and returns its ID.
+    ///
+    /// Every room gets a unique invite code, available through
+    /// [`invite_code`](Self::invite_code).
+    pub fn create_room_with(
+        &mut self,
+        game_config: G::Config,
+        options: RoomOptions,
+    ) -> RoomId {
+        self.collect_disposed();
         let room_id =
             RoomId(NEXT_ROOM_ID.fetch_add(1, Ordering::Relaxed));
         let config = G::room_config();
+        let invite_code = self.unique_invite_code();
+        let private = options.private;
         let handle = spawn_room::<G>(
             room_id,
             config,
+            options,
+            invite_code.clone(),
             game_config,
             DEFAULT_CHANNEL_SIZE,
+            RoomServices {
+                disposed: self.disposed_tx.clone(),
+                released: self.released_tx.clone(),
+                results: self.result_sink.clone(),
+                hooks: self.async_hooks,
+                bots: self.bot_factory.clone(),
+                ratings: self.ratings.clone(),
+                chat_filter: self.chat_filter.clone(),
+            },
         );
         self.rooms.insert(room_id, handle);
-        tracing::info!(%room_id, "room created");
+        self.invite_codes.insert(invite_code, room_id);
+        tracing::info!(%room_id, private, "room created");
         room_id
     }
 
+    /// Creates a room and joins its creator as the room's host,
+    /// atomically with respect to other manager operations.
+    ///
+    /// `options.hosted` is forced on. If the creator can't be seated, the
+    /// new room is destroyed again.
+    pub async fn create_and_join(
+        &mut self,
+        player_id: PlayerId,
+        game_config: G::Config,
+        options: RoomOptions,
+        sender: PlayerSender<G>,
+    ) -> Result<RoomId, RoomError> {
+        self.create_and_join_together(
+            vec![(player_id, sender)],
+            game_config,
+            options,
+        )
+        .await
+    }
+
+    /// Like [`create_and_join`](Self::create_and_join), for a group: the
+    /// first player becomes the host and everyone is seated at once. If
+    /// anyone can't be seated, nobody is and the room is destroyed again.
+    pub async fn create_and_join_together(
+        &mut self,
+        players: Vec<(PlayerId, PlayerSender<G>)>,
+        game_config: G::Config,
+        mut options: RoomOptions,
+    ) -> Result<RoomId, RoomError> {
+        options.hosted = true;
+        self.check_not_in_room(&players)?;
+
+        let password = options.password.clone();
+        let room_id = self.create_room_with(game_config, options);
+        let credentials = JoinCredentials {
+            invite_code: self.invite_code(room_id).map(str::to_string),
+            password,
+        };
+
+        if let Err(e) =
+            self.join_room_together(players, room_id, credentials).await
+        {
+            let _ = self.destroy_room(room_id).await;
+            return Err(e);
+        }
+        Ok(room_id)
+    }
+
+    /// Returns the invite code of a room, if the room exists.
+    pub fn invite_code(&self, room_id: RoomId) -> Option<&str> {
+        self.rooms.get(&room_id).map(RoomHandle::invite_code)
+    }
+
     /// Adds a player to a room.
     ///
     /// Enforces the "one room at a time" invariant.
@@ -61,6 +272,28 @@ impl<G: GameLogic> RoomManager<G> {
         room_id: RoomId,
         sender: PlayerSender<G>,
     ) -> Result<(), RoomError> {
+        self.join_room_with(
+            player_id,
+            room_id,
+            sender,
+            JoinCredentials::default(),
+        )
+        .await
+    }
+
+    /// Adds a player to a room, presenting an invite code and/or password.
+    ///
+    /// Fails with [`RoomError::WrongPassword`] if the room's password
+    /// doesn't match, and with [`RoomError::NotFound`] for a private room
+    /// without its invite code.
+    pub async fn join_room_with(
+        &mut self,
+        player_id: PlayerId,
+        room_id: RoomId,
+        sender: PlayerSender<G>,
+        credentials: JoinCredentials,
+    ) -> Result<(), RoomError> {
+        self.collect_disposed();
         if let Some(current) = self.player_rooms.get(&player_id) {
             if *current == room_id {
                 return Err(RoomError::AlreadyInRoom(player_id, room_id));
@@ -76,16 +309,75 @@ impl<G: GameLogic> RoomManager<G> {
             .get(&room_id)
             .ok_or(RoomError::NotFound(room_id))?;
 
-        handle.join(player_id, sender).await?;
+        handle.join_with(player_id, sender, credentials).await?;
         self.player_rooms.insert(player_id, room_id);
         Ok(())
     }
 
+    /// Adds several players to a room at once: all of them, or none.
+    ///
+    /// Fails without seating anyone if any of them is already in a room
+    /// or the room doesn't have space for the whole group.
+    pub async fn join_room_together(
+        &mut self,
+        players: Vec<(PlayerId, PlayerSender<G>)>,
+        room_id: RoomId,
+        credentials: JoinCredentials,
+    ) -> Result<(), RoomError> {
+        self.collect_disposed();
+        self.check_not_in_room(&players)?;
+
+        let handle = self
+            .rooms
+            .get(&room_id)
+            .ok_or(RoomError::NotFound(room_id))?;
+
+        let ids: Vec<_> = players.iter().map(|(p, _)| *p).collect();
+        handle.join_group(players, credentials).await?;
+        for player_id in ids {
+            self.player_rooms.insert(player_id, room_id);
+        }
+        Ok(())
+    }
+
+    /// Adds a player to the room with the given invite code.
+    ///
+    /// Codes are case-insensitive. Returns the ID of the joined room.
+    pub async fn join_by_code(
+        &mut self,
+        player_id: PlayerId,
+        code: &str,
+        password: Option<String>,
+        sender: PlayerSender<G>,
+    ) -> Result<RoomId, RoomError> {
+        self.collect_disposed();
+        let (room_id, credentials) = self.resolve_code(code, password)?;
+        self.join_room_with(player_id, room_id, sender, credentials)
+            .await?;
+        Ok(room_id)
+    }
+
+    /// Like [`join_by_code`](Self::join_by_code), for a group: everyone
+    /// is seated at once, or nobody is.
+    pub async fn join_by_code_together(
+        &mut self,
+        players: Vec<(PlayerId, PlayerSender<G>)>,
+        code: &str,
+        password: Option<String>,
+    ) -> Result<RoomId, RoomError> {
+        self.collect_disposed();
+        let (room_id, credentials) = self.resolve_code(code, password)?;
+        self.join_room_together(players, room_id, credentials)
+            .await?;
+        Ok(room_id)
+    }
+
     /// Removes a player from their current room.
     pub async fn leave_room(
         &mut self,
         player_id: PlayerId,
     ) -> Result<(), RoomError> {
+        self.collect_disposed();
         let room_id = self
             .player_rooms
             .get(&player_id)
@@ -103,6 +395,129 @@ impl<G: GameLogic> RoomManager<G> {
         Ok(())
     }
 
+    /// Tells a player's room that they lost their connection.
+    ///
+    /// A running game holds their seat for `RoomConfig::reconnect_grace`,
+    /// and the player stays in the room until then (see
+    /// [`reconnect_player`](Self::reconnect_player)). Otherwise they
+    /// leave, as with [`leave_room`](Self::leave_room).
+    pub async fn disconnect_player(
+        &mut self,
+        player_id: PlayerId,
+    ) -> Result<(), RoomError> {
+        self.collect_disposed();
+        let room_id = self.current_room(player_id)?;
+        let held = match self.handle(room_id)?.disconnect(player_id).await {
+            Ok(held) => held,
+            Err(RoomError::NotInRoom(..)) => false,
+            Err(e) => return Err(e),
+        };
+        if !held {
+            self.player_rooms.remove(&player_id);
+        }
+        Ok(())
+    }
+
+    /// Puts a disconnected player back in the seat their room is holding
+    /// for them (or that a bot is playing). Returns the room's ID.
+    pub async fn reconnect_player(
+        &mut self,
+        player_id: PlayerId,
+        sender: PlayerSender<G>,
+    ) -> Result<RoomId, RoomError> {
+        self.collect_disposed();
+        let room_id = self.current_room(player_id)?;
+        let result = self.handle(room_id)?.reconnect(player_id, sender).await;
+        if let Err(RoomError::NotInRoom(..)) = result {
+            self.player_rooms.remove(&player_id);
+        }
+        result.map(|()| room_id)
+    }
+
+    /// Removes `target` from the room `host` is hosting.
+    pub async fn kick_player(
+        &mut self,
+        host: PlayerId,
+        target: PlayerId,
+    ) -> Result<(), RoomError> {
+        let room_id = self.current_room(host)?;
+        self.handle(room_id)?.kick(host, target).await?;
+        self.player_rooms.remove(&target);
+        Ok(())
+    }
+
+    /// Locks or unlocks the room `host` is hosting. Locked rooms turn
+    /// away new players and are left out of listings.
+    pub async fn set_room_locked(
+        &self,
+        host: PlayerId,
+        locked: bool,
+    ) -> Result<(), RoomError> {
+        let room_id = self.current_room(host)?;
+        self.handle(room_id)?.set_locked(host, locked).await
+    }
+
+    /// Pauses or resumes the game in the room `host` is hosting. Paused
+    /// games don't tick, and their timers stand still.
+    pub async fn set_room_paused(
+        &self,
+        host: PlayerId,
+        paused: bool,
+    ) -> Result<(), RoomError> {
+        let room_id = self.current_room(host)?;
+        self.handle(room_id)?.set_paused(host, paused).await
+    }
+
+    /// Starts the game in the room `host` is hosting.
+    pub async fn start_game(&self, host: PlayerId) -> Result<(), RoomError> {
+        let room_id = self.current_room(host)?;
+        self.handle(room_id)?.start(host).await
+    }
+
+    /// Records whether a player wants a rematch in their finished room.
+    /// The room id stays the same when the rematch starts.
+    pub async fn set_rematch(
+        &self,
+        player_id: PlayerId,
+        rematch: bool,
+    ) -> Result<(), RoomError> {
+        let room_id = self.current_room(player_id)?;
+        self.handle(room_id)?.set_rematch(player_id, rematch).await
+    }
+
+    /// Marks a player ready or not ready in their room's pre-game phase.
+    pub async fn set_ready(
+        &self,
+        player_id: PlayerId,
+        ready: bool,
+    ) -> Result<(), RoomError> {
+        let room_id = self.current_room(player_id)?;
+        self.handle(room_id)?.set_ready(player_id, ready).await
+    }
+
+    /// Sends a chat message from a player to `scope` in their room.
+    pub async fn send_chat(
+        &self,
+        player_id: PlayerId,
+        text: String,
+        scope: ChatScope,
+    ) -> Result<(), RoomError> {
+        let room_id = self.current_room(player_id)?;
+        self.handle(room_id)?.chat(player_id, text, scope).await
+    }
+
+    /// Hides (or shows again) `target`'s chat from a player, for as
+    /// long as the player stays in their room.
+    pub async fn mute_chat(
+        &self,
+        player_id: PlayerId,
+        target: PlayerId,
+        muted: bool,
+    ) -> Result<(), RoomError> {
+        let room_id = self.current_room(player_id)?;
+        self.handle(room_id)?.mute_chat(player_id, target, muted).await
+    }
+
     /// Routes a game message from a player to their current room.
     pub async fn route_message(
         &self,
@@ -125,6 +540,41 @@ impl<G: GameLogic> RoomManager<G> {
         handle.send_message(player_id, msg).await
     }
 
+    /// Routes a game message from a client connection to the player's
+    /// current room. See [`RoomHandle::send_input`].
+    pub async fn route_input(
+        &self,
+        player_id: PlayerId,
+        seq: u64,
+        tick: Option<u64>,
+        msg: G::ClientMessage,
+    ) -> Result<(), RoomError> {
+        let room_id = self.current_room(player_id)?;
+        self.handle(room_id)?
+            .send_input(player_id, seq, tick, msg)
+            .await
+    }
+
+    /// Forwards a state sync acknowledgement to the player's room.
+    pub async fn ack_state(
+        &self,
+        player_id: PlayerId,
+        seq: u64,
+    ) -> Result<(), RoomError> {
+        let room_id = self.current_room(player_id)?;
+        self.handle(room_id)?.ack_state(player_id, seq).await
+    }
+
+    /// Forwards a player's measured round-trip time to their room.
+    pub async fn report_rtt(
+        &self,
+        player_id: PlayerId,
+        rtt: Duration,
+    ) -> Result<(), RoomError> {
+        let room_id = self.current_room(player_id)?;
+        self.handle(room_id)?.report_rtt(player_id, rtt).await
+    }
+
     /// Returns info about a specific room.
     pub async fn get_room_info(
         &self,
@@ -151,25 +601,71 @@ impl<G: GameLogic> RoomManager<G> {
 
         // Remove all players that were in this room.
         self.player_rooms.retain(|_, rid| *rid != room_id);
+        self.invite_codes.retain(|_, rid| *rid != room_id);
 
         tracing::info!(%room_id, "room destroyed");
         Ok(())
     }
 
+    /// Forgets rooms that have disposed of themselves, returning their IDs.
+    ///
+    /// Rooms shut down on their own according to their
+    /// [`DisposalPolicy`](crate::DisposalPolicy). Every mutating manager
+    /// method calls this first, so a busy manager stays clean without
+    /// help; servers should also call it periodically so idle managers
+    /// release their handles too. Also forgets players whose rooms let
+    /// them go when their reconnect grace ran out.
+    pub fn collect_disposed(&mut self) -> Vec<RoomId> {
+        while let Ok((room_id, player_id)) = self.released_rx.try_recv() {
+            if self.player_rooms.get(&player_id) == Some(&room_id) {
+                self.player_rooms.remove(&player_id);
+            }
+        }
+
+        let mut disposed = Vec::new();
+        while let Ok(room_id) = self.disposed_rx.try_recv() {
+            if self.rooms.remove(&room_id).is_none() {
+                continue;
+            }
+            self.player_rooms.retain(|_, rid| *rid != room_id);
+            self.invite_codes.retain(|_, rid| *rid != room_id);
+            tracing::info!(%room_id, "disposed room removed");
+            disposed.push(room_id);
+        }
+        disposed
+    }
+
     /// Returns the room ID a player is currently in, if any.
     pub fn player_room(&self, player_id: &PlayerId) -> Option<RoomId> {
         self.player_rooms.get(player_id).copied()
     }
 
-    /// Lists all rooms that are currently joinable.
+    /// Lists every joinable public room: [`list_rooms_matching`] with
+    /// an empty filter.
     ///
-    /// Queries each room actor for its current info. Rooms that fail
-    /// to respond (e.g., shutting down) are silently skipped.
+    /// [`list_rooms_matching`]: Self::list_rooms_matching
     pub async fn list_rooms(&self) -> Vec<RoomInfo> {
+        self.list_rooms_matching(&RoomMetadata::new()).await
+    }
+
+    /// Lists joinable public rooms whose metadata matches `filter`.
+    ///
+    /// Private and locked rooms are never listed. See
+    /// [`RoomInfo::matches`] for the matching rules. Queries each room
+    /// actor for its current info; rooms that fail to respond (e.g.,
+    /// shutting down) are silently skipped.
+    pub async fn list_rooms_matching(
+        &self,
+        filter: &RoomMetadata,
+    ) -> Vec<RoomInfo> {
         let mut infos = Vec::with_capacity(self.rooms.len());
         for handle in self.rooms.values() {
             if let Ok(info) = handle.get_info().await {
-                if info.state.is_joinable() {
+                if info.accepts_joins
+                    && !info.private
+                    && !info.locked
+                    && info.matches(filter)
+                {
                     infos.push(info);
                 }
             }
@@ -189,30 +685,47 @@ impl<G: GameLogic> RoomManager<G> {
     ///
     /// This is the simple matchmaking for MVP: scan existing rooms for
     /// one that's still accepting players, join it. If none found, create
-    /// a new room with the default game config and join that.
+    /// a new room with the default game config and join that. Private,
+    /// password-protected, and locked rooms are never picked.
     pub async fn join_or_create(
         &mut self,
         player_id: PlayerId,
         game_config: G::Config,
         sender: PlayerSender<G>,
     ) -> Result<RoomId, RoomError> {
-        // Check if player is already in a room.
-        if let Some(existing) = self.player_rooms.get(&player_id) {
-            return Err(RoomError::InvalidState(format!(
-                "player {} is already in room {}",
-                player_id, existing
-            )));
-        }
+        self.join_or_create_together(vec![(player_id, sender)], game_config)
+            .await
+    }
 
-        // Try to find a joinable room.  If join() fails due to a race
+    /// Like [`join_or_create`](Self::join_or_create), for a group: only
+    /// rooms with a seat for everyone are picked, and everyone is seated
+    /// at once.
+    pub async fn join_or_create_together(
+        &mut self,
+        players: Vec<(PlayerId, PlayerSender<G>)>,
+        game_config: G::Config,
+    ) -> Result<RoomId, RoomError> {
+        self.collect_disposed();
+        // Check if anyone is already in a room.
+        self.check_not_in_room(&players)?;
+
+        // Try to find a joinable room.  If the join fails due to a race
         // (room filled between get_info and join), keep searching.
         for handle in self.rooms.values() {
             if let Ok(info) = handle.get_info().await {
-                if info.state.is_joinable()
-                    && info.player_count < info.max_players
+                if info.accepts_joins
+                    && !info.private
+                    && !info.password_protected
+                    && !info.locked
+                    && info.player_count + players.len() <= info.max_players
                 {
-                    if let Ok(()) = handle.join(player_id, sender.clone()).await {
-                        self.player_rooms.insert(player_id, info.room_id);
+                    let group = players.clone();
+                    let credentials = JoinCredentials::default();
+                    if let Ok(()) = handle.join_group(group, credentials).await
+                    {
+                        for (player_id, _) in &players {
+                            self.player_rooms.insert(*player_id, info.room_id);
+                        }
                         return Ok(info.room_id);
                     }
                 }
@@ -225,8 +738,16 @@ impl<G: GameLogic> RoomManager<G> {
             .rooms
             .get(&room_id)
             .expect("just created this room");
-        handle.join(player_id, sender).await?;
-        self.player_rooms.insert(player_id, room_id);
+        let ids: Vec<_> = players.iter().map(|(p, _)| *p).collect();
+        if let Err(e) =
+            handle.join_group(players, JoinCredentials::default()).await
+        {
+            let _ = self.destroy_room(room_id).await;
+            return Err(e);
+        }
+        for player_id in ids {
+            self.player_rooms.insert(player_id, room_id);
+        }
         Ok(room_id)
     }
 
@@ -239,6 +760,70 @@ impl<G: GameLogic> RoomManager<G> {
     pub fn room_ids(&self) -> Vec<RoomId> {
         self.rooms.keys().copied().collect()
     }
+
+    /// Returns the room a player is in, or an error if they're in none.
+    fn current_room(&self, player_id: PlayerId) -> Result<RoomId, RoomError> {
+        self.player_rooms.get(&player_id).copied().ok_or_else(|| {
+            RoomError::InvalidState(format!(
+                "player {} is not in any room",
+                player_id
+            ))
+        })
+    }
+
+    /// Looks up the room an invite code belongs to. Codes are
+    /// case-insensitive.
+    fn resolve_code(
+        &self,
+        code: &str,
+        password: Option<String>,
+    ) -> Result<(RoomId, JoinCredentials), RoomError> {
+        let code = code.trim().to_ascii_uppercase();
+        let room_id = self
+            .invite_codes
+            .get(&code)
+            .copied()
+            .ok_or_else(|| RoomError::InvalidInviteCode(code.clone()))?;
+        let credentials = JoinCredentials {
+            invite_code: Some(code),
+            password,
+        };
+        Ok((room_id, credentials))
+    }
+
+    /// Fails if `players` is empty or any of them is already in a room.
+    fn check_not_in_room(
+        &self,
+        players: &[(PlayerId, PlayerSender<G>)],
+    ) -> Result<(), RoomError> {
+        if players.is_empty() {
+            return Err(RoomError::InvalidState("no players to join".into()));
+        }
+        for (player_id, _) in players {
+            if let Some(existing) = self.player_rooms.get(player_id) {
+                return Err(RoomError::InvalidState(format!(
+                    "player {} is already in room {}",
+                    player_id, existing
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the handle of an active room.
+    fn handle(&self, room_id: RoomId) -> Result<&RoomHandle<G>, RoomError> {
+        self.rooms.get(&room_id).ok_or(RoomError::NotFound(room_id))
+    }
+
+    /// Generates an invite code that no active room is using.
+    fn unique_invite_code(&self) -> String {
+        loop {
+            let code = generate_invite_code();
+            if !self.invite_codes.contains_key(&code) {
+                return code;
+            }
+        }
+    }
 }
 
 impl<G: GameLogic> Default for RoomManager<G> {
@@ -246,3 +831,18 @@ impl<G: GameLogic> Default for RoomManager<G> {
         Self::new()
     }
 }
+
+/// Generates a random invite code such as `"K7QX2M"`.
+///
+/// 31 symbols over 6 positions gives ~887 million codes — plenty for
+/// the number of concurrently open rooms on one server, and short
+/// enough to share by voice chat.
+fn generate_invite_code() -> String {
+    let mut rng = rand::rng();
+    (0..INVITE_CODE_LEN)
+        .map(|_| {
+            let i = rng.random_range(0..INVITE_CODE_ALPHABET.len());
+            INVITE_CODE_ALPHABET[i] as char
+        })
+        .collect()
+}
@@ -0,0 +1,156 @@
+//! Where finished games report their results.
+//!
+//! When a game ends, the room asks
+//! [`GameLogic::results`](crate::GameLogic::results) for the outcome,
+//! sends it to every player in a `GameEnded` message, and hands a
+//! [`MatchRecord`] to the manager's [`ResultSink`], if it has one.
+//! Leaderboards, ratings, and progression should be fed from the sink:
+//! it's the server's word, not a client's.
+
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+
+use arcforge_protocol::{EndReason, MatchResult, PlayerId, RoomId};
+use serde::{Deserialize, Serialize};
+
+/// A finished game's result, with the room-level facts around it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchRecord {
+    /// The room the game was played in.
+    pub room_id: RoomId,
+    /// The room's RNG seed, for replaying the game.
+    pub seed: u64,
+    /// Why the game ended.
+    pub reason: EndReason,
+    /// When the game ended, in milliseconds since the Unix epoch.
+    pub ended_at_ms: u64,
+    /// How long the game ran, in milliseconds, not counting time spent
+    /// paused.
+    pub duration_ms: u64,
+    /// The outcome, as players were sent it in `GameEnded`.
+    pub result: MatchResult,
+    /// Players in `result` who were bots. Seats a bot only stood in for
+    /// while a player was away aren't listed.
+    #[serde(default)]
+    pub bots: Vec<PlayerId>,
+}
+
+/// Receives the result of every game that finishes.
+///
+/// Called on the room's task, so keep it quick: hand the record to a
+/// channel or a thread of your own rather than touching the disk or
+/// the network.
+pub trait ResultSink: Send + Sync + 'static {
+    /// Takes the record of a game that just finished.
+    fn record(&self, record: &MatchRecord);
+}
+
+/// A [`ResultSink`] that appends one JSON object per line to a file.
+///
+/// Lines are written on a thread of its own, so recording never waits
+/// on the disk. Write failures are logged. Dropping the sink waits for
+/// the lines already recorded to be written.
+#[derive(Debug)]
+pub struct JsonLinesSink {
+    /// Lines for the writer. Taken on drop to stop it.
+    lines: Option<Sender<Vec<u8>>>,
+    writer: Option<JoinHandle<()>>,
+}
+
+impl JsonLinesSink {
+    /// Opens `path` for appending, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file =
+            OpenOptions::new().create(true).append(true).open(path)?;
+        let (lines, queued) = mpsc::channel::<Vec<u8>>();
+        let writer = thread::Builder::new()
+            .name("arcforge-results".into())
+            .spawn(move || {
+                for line in queued {
+                    if let Err(e) = file.write_all(&line) {
+                        tracing::warn!(
+                            error = %e,
+                            "failed to write match result"
+                        );
+                    }
+                }
+            })?;
+        Ok(Self {
+            lines: Some(lines),
+            writer: Some(writer),
+        })
+    }
+}
+
+impl ResultSink for JsonLinesSink {
+    fn record(&self, record: &MatchRecord) {
+        let mut line = match serde_json::to_vec(record) {
+            Ok(line) => line,
+            Err(e) => {
+                tracing::warn!(
+                    room_id = %record.room_id,
+                    error = %e,
+                    "failed to encode match result"
+                );
+                return;
+            }
+        };
+        line.push(b'\n');
+        if let Some(lines) = &self.lines {
+            let _ = lines.send(line);
+        }
+    }
+}
+
+impl Drop for JsonLinesSink {
+    fn drop(&mut self) {
+        // Closing the channel lets the writer finish what's queued and
+        // stop.
+        self.lines = None;
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(room: u64) -> MatchRecord {
+        MatchRecord {
+            room_id: RoomId(room),
+            seed: 7,
+            reason: EndReason::Completed,
+            ended_at_ms: 1_700_000_000_000,
+            duration_ms: 90_000,
+            result: MatchResult::from_scores([(PlayerId(1), 3)]),
+            bots: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_json_lines_sink_appends_one_record_per_line() {
+        let path = std::env::temp_dir()
+            .join(format!("arcforge-results-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let sink = JsonLinesSink::open(&path).unwrap();
+        sink.record(&record(1));
+        sink.record(&record(2));
+        drop(sink);
+        // Reopening appends rather than truncating.
+        JsonLinesSink::open(&path).unwrap().record(&record(3));
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let records: Vec<MatchRecord> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records, vec![record(1), record(2), record(3)]);
+    }
+}
@@ -4,20 +4,57 @@
 //! through an mpsc channel. This is the "actor model" — no shared
 //! mutable state, just message passing.
 
-use std::collections::HashSet;
+use std::any::Any;
+use std::collections::{HashSet, VecDeque};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use arcforge_protocol::{PlayerId, Recipient, RoomId};
+use arcforge_protocol::{
+    ChatScope, EndReason, PlayerId, Recipient, RoomId, RoomMetadata,
+    SystemMessage,
+};
+use arcforge_tick::{TickInfo, TickScheduler};
+use rand::{Rng, SeedableRng};
 use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
 
-use crate::{GameLogic, RoomConfig, RoomError, RoomState};
+use crate::async_logic::{AsyncHooks, PreparedMessage};
+use crate::bot::{self, BotFactory};
+use crate::chat::{ChatFilter, ChatLog};
+use crate::context::Outbox;
+use crate::history::{LagCompensation, StateHistory};
+use crate::input::InputBuffer;
+use crate::results::{MatchRecord, ResultSink};
+use crate::sync::{SyncTracker, SyncUpdate};
+use crate::team::{RatingFn, Teams};
+use crate::{
+    GameContext, GameLogic, GameRng, PanicPolicy, RoomConfig, RoomError,
+    RoomOptions, RoomState,
+};
 
 /// An outbound message from the room actor to a player's connection handler.
 #[derive(Debug)]
 pub enum RoomOutbound<G: GameLogic> {
-    /// Full game state snapshot (sent on join).
-    State(G::State),
+    /// Snapshot of the game state, as seen by the receiving player
+    /// (see [`GameLogic::view_for`]).
+    State(G::View),
     /// A game message from the game logic.
     Message(G::ServerMessage),
+    /// A framework message generated by the room (host changes, kicks).
+    System(SystemMessage),
+    /// A full view sent by continuous state sync.
+    Keyframe { seq: u64, view: G::View },
+    /// A merge patch against snapshot `base_seq`, which the player
+    /// acknowledged (see [`apply_patch`](crate::apply_patch)).
+    Delta {
+        seq: u64,
+        base_seq: u64,
+        patch: serde_json::Value,
+    },
+    /// The room has now processed this player's input up to client
+    /// envelope `seq`. Applies to every outbound message that follows.
+    InputAck { seq: u64 },
 }
 
 impl<G: GameLogic> Clone for RoomOutbound<G> {
@@ -25,6 +62,21 @@ impl<G: GameLogic> Clone for RoomOutbound<G> {
         match self {
             Self::State(s) => Self::State(s.clone()),
             Self::Message(m) => Self::Message(m.clone()),
+            Self::System(m) => Self::System(m.clone()),
+            Self::Keyframe { seq, view } => Self::Keyframe {
+                seq: *seq,
+                view: view.clone(),
+            },
+            Self::Delta {
+                seq,
+                base_seq,
+                patch,
+            } => Self::Delta {
+                seq: *seq,
+                base_seq: *base_seq,
+                patch: patch.clone(),
+            },
+            Self::InputAck { seq } => Self::InputAck { seq: *seq },
         }
     }
 }
@@ -32,16 +84,96 @@ impl<G: GameLogic> Clone for RoomOutbound<G> {
 /// Channel sender for delivering outbound messages to a player.
 pub type PlayerSender<G> = mpsc::UnboundedSender<RoomOutbound<G>>;
 
+/// Channel on which room actors report that they disposed of themselves.
+pub(crate) type DisposedSender = mpsc::UnboundedSender<RoomId>;
+
+/// Channel on which room actors report players they removed on their
+/// own, without being asked to.
+pub(crate) type ReleasedSender = mpsc::UnboundedSender<(RoomId, PlayerId)>;
+
+/// What a room actor is wired up to, besides its players.
+pub(crate) struct RoomServices<G: GameLogic> {
+    /// Notifies the manager when the room disposes of itself.
+    pub(crate) disposed: DisposedSender,
+    /// Notifies the manager when the room removes a player on its own.
+    pub(crate) released: ReleasedSender,
+    /// Receives the result of every game the room finishes.
+    pub(crate) results: Option<Arc<dyn ResultSink>>,
+    /// The game's async hooks, if it has them turned on.
+    pub(crate) hooks: Option<AsyncHooks<G>>,
+    /// Creates bots for the seats `RoomConfig::bots` hands to them.
+    pub(crate) bots: Option<BotFactory<G>>,
+    /// Players' ratings, for balancing `RoomConfig::teams`.
+    pub(crate) ratings: Option<RatingFn>,
+    /// Screens chat messages before they're sent.
+    pub(crate) chat_filter: Option<Arc<dyn ChatFilter>>,
+}
+
+/// A client message waiting for its turn at
+/// [`AsyncGameLogic::prepare_message`](crate::AsyncGameLogic::prepare_message).
+struct QueuedMessage<G: GameLogic> {
+    sender: PlayerId,
+    seq: Option<u64>,
+    tick: Option<u64>,
+    msg: G::ClientMessage,
+}
+
+/// The client message being prepared, and its pending future.
+struct Preparing<G: GameLogic> {
+    sender: PlayerId,
+    seq: Option<u64>,
+    tick: Option<u64>,
+    future: PreparedMessage<G>,
+    /// When the future runs out of time (see `AsyncHookConfig::timeout`).
+    deadline: Instant,
+}
+
+/// What the room can undo when a game callback panics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rollback {
+    /// Nothing: the callback only reads the state.
+    ReadOnly,
+    /// The game state, snapshotted before the callback runs.
+    Snapshot,
+    /// Not enough: the callback goes with a change the room can't take
+    /// back, like a player joining or the game starting. A panic always
+    /// closes the room.
+    Never,
+}
+
+/// Credentials a player presents when joining a room.
+///
+/// Public rooms without a password accept the default (empty)
+/// credentials. Private rooms require the matching `invite_code`;
+/// password-protected rooms require the matching `password`.
+#[derive(Clone, Default)]
+pub struct JoinCredentials {
+    /// The room's invite code, if the player joined through one.
+    pub invite_code: Option<String>,
+    /// The room password, if the player supplied one.
+    pub password: Option<String>,
+}
+
+// Written out so the password never ends up in logs.
+impl std::fmt::Debug for JoinCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JoinCredentials")
+            .field("invite_code", &self.invite_code)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
 /// Commands sent to a room actor through its channel.
 ///
 /// Each variant represents an operation the outside world can request.
 /// The `oneshot::Sender` in some variants is a "reply channel" — the
 /// caller sends a command and waits for the response on that channel.
 pub(crate) enum RoomCommand<G: GameLogic> {
-    /// Add a player to the room.
+    /// Add players to the room: all of them, or none.
     Join {
-        player_id: PlayerId,
-        sender: PlayerSender<G>,
+        players: Vec<(PlayerId, PlayerSender<G>)>,
+        credentials: JoinCredentials,
         reply: oneshot::Sender<Result<(), RoomError>>,
     },
 
@@ -51,12 +183,93 @@ pub(crate) enum RoomCommand<G: GameLogic> {
         reply: oneshot::Sender<Result<(), RoomError>>,
     },
 
-    /// Deliver a game message from a player.
+    /// A player lost their connection. Replies `true` if the room holds
+    /// their seat for them to reconnect, `false` if they left.
+    Disconnect {
+        player_id: PlayerId,
+        reply: oneshot::Sender<Result<bool, RoomError>>,
+    },
+
+    /// A player whose seat is held (or played by a bot) is back.
+    Reconnect {
+        player_id: PlayerId,
+        sender: PlayerSender<G>,
+        reply: oneshot::Sender<Result<(), RoomError>>,
+    },
+
+    /// Deliver a game message from a player. `seq` is the client
+    /// envelope seq it arrived in and `tick` the tick the client meant
+    /// it for, when known.
     Message {
         sender: PlayerId,
+        seq: Option<u64>,
+        tick: Option<u64>,
         msg: G::ClientMessage,
     },
 
+    /// A player acknowledged a state sync snapshot.
+    StateAck { player_id: PlayerId, seq: u64 },
+
+    /// A player reported their round-trip time.
+    ReportRtt { player_id: PlayerId, rtt: Duration },
+
+    /// Remove a player on the host's behalf.
+    Kick {
+        by: PlayerId,
+        target: PlayerId,
+        reply: oneshot::Sender<Result<(), RoomError>>,
+    },
+
+    /// Lock or unlock the room on the host's behalf.
+    SetLocked {
+        by: PlayerId,
+        locked: bool,
+        reply: oneshot::Sender<Result<(), RoomError>>,
+    },
+
+    /// Pause or resume the game on the host's behalf.
+    SetPaused {
+        by: PlayerId,
+        paused: bool,
+        reply: oneshot::Sender<Result<(), RoomError>>,
+    },
+
+    /// Start the game on the host's behalf.
+    Start {
+        by: PlayerId,
+        reply: oneshot::Sender<Result<(), RoomError>>,
+    },
+
+    /// Record a player's rematch vote in a finished room.
+    SetRematch {
+        player_id: PlayerId,
+        rematch: bool,
+        reply: oneshot::Sender<Result<(), RoomError>>,
+    },
+
+    /// Mark a player ready (or not) during the pre-game phase.
+    SetReady {
+        player_id: PlayerId,
+        ready: bool,
+        reply: oneshot::Sender<Result<(), RoomError>>,
+    },
+
+    /// Send a chat message from a player.
+    Chat {
+        player_id: PlayerId,
+        text: String,
+        scope: ChatScope,
+        reply: oneshot::Sender<Result<(), RoomError>>,
+    },
+
+    /// Mute or unmute another player's chat for a player.
+    MuteChat {
+        player_id: PlayerId,
+        target: PlayerId,
+        muted: bool,
+        reply: oneshot::Sender<Result<(), RoomError>>,
+    },
+
     /// Request the current room state.
     GetState {
         reply: oneshot::Sender<RoomInfo>,
@@ -66,6 +279,32 @@ pub(crate) enum RoomCommand<G: GameLogic> {
     Shutdown,
 }
 
+impl<G: GameLogic> RoomCommand<G> {
+    /// The player the command came from, if any.
+    fn player(&self) -> Option<PlayerId> {
+        match self {
+            RoomCommand::Join { players, .. } => {
+                players.first().map(|(player_id, _)| *player_id)
+            }
+            RoomCommand::Leave { player_id, .. }
+            | RoomCommand::Disconnect { player_id, .. }
+            | RoomCommand::Reconnect { player_id, .. }
+            | RoomCommand::StateAck { player_id, .. }
+            | RoomCommand::ReportRtt { player_id, .. }
+            | RoomCommand::SetRematch { player_id, .. }
+            | RoomCommand::SetReady { player_id, .. }
+            | RoomCommand::Chat { player_id, .. }
+            | RoomCommand::MuteChat { player_id, .. } => Some(*player_id),
+            RoomCommand::Message { sender, .. } => Some(*sender),
+            RoomCommand::Kick { by, .. }
+            | RoomCommand::SetLocked { by, .. }
+            | RoomCommand::SetPaused { by, .. }
+            | RoomCommand::Start { by, .. } => Some(*by),
+            RoomCommand::GetState { .. } | RoomCommand::Shutdown => None,
+        }
+    }
+}
+
 /// A snapshot of room metadata (not the game state itself).
 #[derive(Debug, Clone)]
 pub struct RoomInfo {
@@ -75,8 +314,39 @@ pub struct RoomInfo {
     pub state: RoomState,
     /// Number of players currently in the room.
     pub player_count: usize,
+    /// How many of them are bots, or played by one.
+    pub bot_count: usize,
     /// Maximum players allowed.
     pub max_players: usize,
+    /// `true` if the room takes new players right now: it's waiting for
+    /// players, or it's a drop-in game in progress.
+    pub accepts_joins: bool,
+    /// Public metadata published by [`GameLogic::room_metadata`].
+    pub metadata: RoomMetadata,
+    /// Hidden from listings; joinable only by invite code.
+    pub private: bool,
+    /// `true` if joining requires a password.
+    pub password_protected: bool,
+    /// The room's host, if it was created with one.
+    pub host: Option<PlayerId>,
+    /// `true` if the host has locked the room against new players.
+    pub locked: bool,
+    /// `true` if the game is paused.
+    pub paused: bool,
+    /// The seed of the room's RNG. A room created with the same seed
+    /// and fed the same inputs plays out the same way.
+    pub seed: u64,
+}
+
+impl RoomInfo {
+    /// Returns `true` if the room's metadata contains every key in
+    /// `filter` with exactly the same value. An empty filter matches
+    /// every room.
+    pub fn matches(&self, filter: &RoomMetadata) -> bool {
+        filter
+            .iter()
+            .all(|(key, value)| self.metadata.get(key) == Some(value))
+    }
 }
 
 /// Handle to a running room actor. Used to send commands to it.
@@ -85,6 +355,7 @@ pub struct RoomInfo {
 /// The `RoomManager` holds one of these per room.
 pub struct RoomHandle<G: GameLogic> {
     room_id: RoomId,
+    invite_code: Arc<str>,
     sender: mpsc::Sender<RoomCommand<G>>,
 }
 
@@ -92,6 +363,7 @@ impl<G: GameLogic> Clone for RoomHandle<G> {
     fn clone(&self) -> Self {
         Self {
             room_id: self.room_id,
+            invite_code: Arc::clone(&self.invite_code),
             sender: self.sender.clone(),
         }
     }
@@ -103,24 +375,44 @@ impl<G: GameLogic> RoomHandle<G> {
         self.room_id
     }
 
-    /// Sends a join request to the room.
+    /// Returns the room's short invite code.
+    pub fn invite_code(&self) -> &str {
+        &self.invite_code
+    }
+
+    /// Sends a join request to the room without credentials.
     pub async fn join(
         &self,
         player_id: PlayerId,
         sender: PlayerSender<G>,
     ) -> Result<(), RoomError> {
-        let (reply_tx, reply_rx) = oneshot::channel();
-        self.sender
-            .send(RoomCommand::Join {
-                player_id,
-                sender,
-                reply: reply_tx,
-            })
-            .await
-            .map_err(|_| RoomError::Unavailable(self.room_id))?;
-        reply_rx
+        self.join_with(player_id, sender, JoinCredentials::default())
             .await
-            .map_err(|_| RoomError::Unavailable(self.room_id))?
+    }
+
+    /// Sends a join request with an invite code and/or password.
+    pub async fn join_with(
+        &self,
+        player_id: PlayerId,
+        sender: PlayerSender<G>,
+        credentials: JoinCredentials,
+    ) -> Result<(), RoomError> {
+        self.join_group(vec![(player_id, sender)], credentials).await
+    }
+
+    /// Sends a join request for several players at once. The room seats
+    /// all of them or, if any one can't join, none.
+    pub async fn join_group(
+        &self,
+        players: Vec<(PlayerId, PlayerSender<G>)>,
+        credentials: JoinCredentials,
+    ) -> Result<(), RoomError> {
+        self.request(|reply| RoomCommand::Join {
+            players,
+            credentials,
+            reply,
+        })
+        .await
     }
 
     /// Sends a leave request to the room.
@@ -148,7 +440,201 @@ impl<G: GameLogic> RoomHandle<G> {
         msg: G::ClientMessage,
     ) -> Result<(), RoomError> {
         self.sender
-            .send(RoomCommand::Message { sender, msg })
+            .send(RoomCommand::Message {
+                sender,
+                seq: None,
+                tick: None,
+                msg,
+            })
+            .await
+            .map_err(|_| RoomError::Unavailable(self.room_id))
+    }
+
+    /// Sends a game message from a client connection (fire-and-forget).
+    ///
+    /// `seq` is the envelope seq it arrived in. Once the room has
+    /// processed it, the player gets [`RoomOutbound::InputAck`]. `tick`
+    /// is the tick the client meant it for: rooms with an input buffer
+    /// queue it for that tick, other rooms ignore it.
+    pub async fn send_input(
+        &self,
+        sender: PlayerId,
+        seq: u64,
+        tick: Option<u64>,
+        msg: G::ClientMessage,
+    ) -> Result<(), RoomError> {
+        self.sender
+            .send(RoomCommand::Message {
+                sender,
+                seq: Some(seq),
+                tick,
+                msg,
+            })
+            .await
+            .map_err(|_| RoomError::Unavailable(self.room_id))
+    }
+
+    /// Tells the room a player lost their connection. Returns `true` if
+    /// the room holds their seat (the game is running), `false` if the
+    /// player left.
+    pub async fn disconnect(
+        &self,
+        player_id: PlayerId,
+    ) -> Result<bool, RoomError> {
+        self.request(|reply| RoomCommand::Disconnect { player_id, reply })
+            .await
+    }
+
+    /// Gives a disconnected player their seat back, with `sender` as
+    /// their new channel. They get a state snapshot right away.
+    pub async fn reconnect(
+        &self,
+        player_id: PlayerId,
+        sender: PlayerSender<G>,
+    ) -> Result<(), RoomError> {
+        self.request(|reply| RoomCommand::Reconnect {
+            player_id,
+            sender,
+            reply,
+        })
+        .await
+    }
+
+    /// Asks the room to remove `target`. Fails unless `by` is the host.
+    pub async fn kick(
+        &self,
+        by: PlayerId,
+        target: PlayerId,
+    ) -> Result<(), RoomError> {
+        self.request(|reply| RoomCommand::Kick { by, target, reply })
+            .await
+    }
+
+    /// Locks or unlocks the room. Fails unless `by` is the host.
+    pub async fn set_locked(
+        &self,
+        by: PlayerId,
+        locked: bool,
+    ) -> Result<(), RoomError> {
+        self.request(|reply| RoomCommand::SetLocked { by, locked, reply })
+            .await
+    }
+
+    /// Pauses or resumes the game. Fails unless `by` is the host and the
+    /// game is in progress.
+    pub async fn set_paused(
+        &self,
+        by: PlayerId,
+        paused: bool,
+    ) -> Result<(), RoomError> {
+        self.request(|reply| RoomCommand::SetPaused { by, paused, reply })
+            .await
+    }
+
+    /// Starts the game. Fails unless `by` is the host and enough
+    /// players have joined.
+    pub async fn start(&self, by: PlayerId) -> Result<(), RoomError> {
+        self.request(|reply| RoomCommand::Start { by, reply }).await
+    }
+
+    /// Records whether a player wants a rematch of the finished game.
+    pub async fn set_rematch(
+        &self,
+        player_id: PlayerId,
+        rematch: bool,
+    ) -> Result<(), RoomError> {
+        self.request(|reply| RoomCommand::SetRematch {
+            player_id,
+            rematch,
+            reply,
+        })
+        .await
+    }
+
+    /// Marks a player ready or not ready for the game to start.
+    pub async fn set_ready(
+        &self,
+        player_id: PlayerId,
+        ready: bool,
+    ) -> Result<(), RoomError> {
+        self.request(|reply| RoomCommand::SetReady {
+            player_id,
+            ready,
+            reply,
+        })
+        .await
+    }
+
+    /// Sends a chat message from a player to `scope`.
+    pub async fn chat(
+        &self,
+        player_id: PlayerId,
+        text: String,
+        scope: ChatScope,
+    ) -> Result<(), RoomError> {
+        self.request(|reply| RoomCommand::Chat {
+            player_id,
+            text,
+            scope,
+            reply,
+        })
+        .await
+    }
+
+    /// Hides (or shows again) `target`'s chat from a player.
+    pub async fn mute_chat(
+        &self,
+        player_id: PlayerId,
+        target: PlayerId,
+        muted: bool,
+    ) -> Result<(), RoomError> {
+        self.request(|reply| RoomCommand::MuteChat {
+            player_id,
+            target,
+            muted,
+            reply,
+        })
+        .await
+    }
+
+    /// Sends a command that carries a reply channel and awaits the reply.
+    async fn request<T>(
+        &self,
+        command: impl FnOnce(
+            oneshot::Sender<Result<T, RoomError>>,
+        ) -> RoomCommand<G>,
+    ) -> Result<T, RoomError> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.sender
+            .send(command(reply_tx))
+            .await
+            .map_err(|_| RoomError::Unavailable(self.room_id))?;
+        reply_rx
+            .await
+            .map_err(|_| RoomError::Unavailable(self.room_id))?
+    }
+
+    /// Acknowledges state sync snapshot `seq` (fire-and-forget).
+    pub async fn ack_state(
+        &self,
+        player_id: PlayerId,
+        seq: u64,
+    ) -> Result<(), RoomError> {
+        self.sender
+            .send(RoomCommand::StateAck { player_id, seq })
+            .await
+            .map_err(|_| RoomError::Unavailable(self.room_id))
+    }
+
+    /// Records a player's round-trip time, used for lag compensation
+    /// (fire-and-forget).
+    pub async fn report_rtt(
+        &self,
+        player_id: PlayerId,
+        rtt: Duration,
+    ) -> Result<(), RoomError> {
+        self.sender
+            .send(RoomCommand::ReportRtt { player_id, rtt })
             .await
             .map_err(|_| RoomError::Unavailable(self.room_id))
     }
@@ -179,187 +665,1832 @@ struct RoomActor<G: GameLogic> {
     room_id: RoomId,
     state: RoomState,
     config: RoomConfig,
-    players: HashSet<PlayerId>,
+    options: RoomOptions,
+    invite_code: Arc<str>,
+    /// Players in join order. The oldest member inherits the host role.
+    players: Vec<PlayerId>,
+    /// Who's on which team, if the game has teams.
+    teams: Teams,
+    /// Recent chat and players' mutes.
+    chat: ChatLog,
+    chat_filter: Option<Arc<dyn ChatFilter>>,
+    host: Option<PlayerId>,
+    locked: bool,
+    /// Players who have readied up for the pre-game countdown.
+    ready: HashSet<PlayerId>,
+    /// Players who have voted for a rematch of the finished game.
+    rematch: HashSet<PlayerId>,
+    /// When the running pre-game countdown ends.
+    countdown_ends: Option<Instant>,
+    /// When the remaining countdown time is next announced.
+    next_announcement: Option<Instant>,
+    /// Per-player snapshot baselines for continuous state sync.
+    sync: SyncTracker,
+    /// When the next state sync snapshot is due.
+    next_sync: Option<Instant>,
+    /// Drives `G::tick` while the game runs, if the game has a tick rate.
+    scheduler: Option<TickScheduler>,
+    /// Inputs waiting for their tick, if the game buffers input.
+    inputs: Option<InputBuffer<G::ClientMessage>>,
+    /// Highest client seq processed per player, acked or not.
+    input_seen: std::collections::HashMap<PlayerId, u64>,
+    /// Highest client seq acked per player.
+    input_acks: std::collections::HashMap<PlayerId, u64>,
+    /// Recent per-tick state, if the game uses lag compensation.
+    history: Option<StateHistory<G::State>>,
+    /// Last round-trip time each player reported.
+    rtts: std::collections::HashMap<PlayerId, Duration>,
+    /// The seed `rng` started from.
+    seed: u64,
+    /// The game's random number generator (see `GameContext::rng`).
+    rng: GameRng,
+    /// When the game started, for `GameContext::elapsed`. Resuming moves
+    /// it forward by the time spent paused.
+    started_at: Option<Instant>,
+    /// Messages sent with `GameContext::send_after`, with when they're due.
+    delayed: Vec<(Instant, Recipient, G::ServerMessage)>,
+    /// Running game timers by ID, with when they fire.
+    timers: std::collections::HashMap<String, Instant>,
+    /// When the game was paused, if it is.
+    paused_at: Option<Instant>,
+    /// When the game hits `RoomConfig::max_duration`.
+    match_deadline: Option<Instant>,
     /// Per-player outbound channels.
     senders: std::collections::HashMap<PlayerId, PlayerSender<G>>,
     game_state: Option<G::State>,
     game_config: G::Config,
+    /// Cached result of `G::room_metadata`, refreshed on state changes.
+    metadata: RoomMetadata,
     receiver: mpsc::Receiver<RoomCommand<G>>,
+    /// Notifies the manager when the room disposes of itself.
+    disposed: DisposedSender,
+    /// Receives the result of every game the room finishes.
+    results: Option<Arc<dyn ResultSink>>,
+    /// The game's async hooks. Client messages go through these first.
+    hooks: Option<AsyncHooks<G>>,
+    /// The room's own command channel, for bots to talk back on. Weak,
+    /// so the room still stops when its last handle goes away.
+    commands: mpsc::WeakSender<RoomCommand<G>>,
+    /// Notifies the manager when the room removes a player on its own.
+    released: ReleasedSender,
+    bot_factory: Option<BotFactory<G>>,
+    /// Seats filled by bots.
+    bots: HashSet<PlayerId>,
+    /// Disconnected players' seats that bots are playing meanwhile.
+    stand_ins: HashSet<PlayerId>,
+    /// Seats held for disconnected players, with when they left.
+    disconnected: std::collections::HashMap<PlayerId, Instant>,
+    /// When the room last went from empty to having a player in it.
+    occupied_since: Option<Instant>,
+    /// Client messages waiting behind the one being prepared.
+    queued: VecDeque<QueuedMessage<G>>,
+    preparing: Option<Preparing<G>>,
+    /// Last join, leave, or game message. Drives `DisposalPolicy::max_idle`.
+    last_activity: Instant,
+    /// When the last player left (or the room was created empty).
+    empty_since: Option<Instant>,
+    /// When the game reached `Finished`, or the last rematch vote was
+    /// withdrawn since.
+    finished_at: Option<Instant>,
 }
 
 impl<G: GameLogic> RoomActor<G> {
     /// Runs the actor loop, processing commands until shutdown.
     async fn run(mut self) {
-        tracing::info!(room_id = %self.room_id, "room actor started");
+        tracing::info!(
+            room_id = %self.room_id,
+            seed = self.seed,
+            "room actor started"
+        );
 
-        while let Some(cmd) = self.receiver.recv().await {
-            match cmd {
-                RoomCommand::Join {
-                    player_id,
-                    sender,
-                    reply,
-                } => {
-                    let result = self.handle_join(player_id, sender);
-                    let _ = reply.send(result);
+        loop {
+            let wake_at = [
+                self.disposal_deadline().map(|(at, _)| at),
+                self.next_announcement,
+                self.next_sync,
+                self.next_game_timer(),
+                self.bot_fill_deadline(),
+                self.next_grace_expiry(),
+            ]
+            .into_iter()
+            .flatten()
+            .min();
+            let timer = async {
+                match wake_at {
+                    Some(at) => tokio::time::sleep_until(at).await,
+                    None => std::future::pending().await,
+                }
+            };
+
+            let tick = async {
+                match self.scheduler.as_mut() {
+                    Some(scheduler) => scheduler.wait_for_tick().await,
+                    None => std::future::pending().await,
+                }
+            };
+
+            let prepared = async {
+                match self.preparing.as_mut() {
+                    Some(preparing) => {
+                        let future = preparing.future.as_mut();
+                        tokio::time::timeout_at(preparing.deadline, future)
+                            .await
+                            .ok()
+                    }
+                    None => std::future::pending().await,
                 }
-                RoomCommand::Leave { player_id, reply } => {
-                    let result = self.handle_leave(player_id);
-                    let _ = reply.send(result);
+            };
+
+            tokio::select! {
+                cmd = self.receiver.recv() => {
+                    let Some(cmd) = cmd else { break };
+                    let player = cmd.player();
+                    let keep_running = self
+                        .guard(player, |room| room.handle_command(cmd));
+                    if keep_running == Some(false) {
+                        break;
+                    }
                 }
-                RoomCommand::Message { sender, msg } => {
-                    self.handle_message(sender, msg);
+                () = timer => {
+                    let keep_running =
+                        self.guard(None, |room| room.handle_timers());
+                    if keep_running == Some(false) {
+                        break;
+                    }
                 }
-                RoomCommand::GetState { reply } => {
-                    let _ = reply.send(self.info());
+                info = tick => {
+                    self.guard(None, |room| room.run_tick(info));
                 }
-                RoomCommand::Shutdown => {
-                    tracing::info!(room_id = %self.room_id, "room shutting down");
-                    self.state = RoomState::Destroying;
-                    break;
+                outcome = prepared => {
+                    let player = self.preparing.as_ref().map(|p| p.sender);
+                    self.guard(player, |room| room.finish_preparing(outcome));
                 }
             }
+
+            if self.state == RoomState::Destroying {
+                break;
+            }
         }
 
         tracing::info!(room_id = %self.room_id, "room actor stopped");
     }
 
-    fn handle_join(
+    /// Runs `f`, closing the room if anything in it panics.
+    ///
+    /// Game callbacks catch their own panics (see
+    /// [`call_game`](Self::call_game)); this is the backstop for
+    /// anything else, like a panicking `view_for`. `player` is whoever
+    /// triggered the work, for the log.
+    fn guard<R>(
         &mut self,
-        player_id: PlayerId,
-        sender: PlayerSender<G>,
-    ) -> Result<(), RoomError> {
-        if !self.state.is_joinable() {
-            return Err(RoomError::InvalidState(format!(
-                "cannot join room in state {}",
-                self.state
-            )));
-        }
-        if self.players.contains(&player_id) {
-            return Err(RoomError::AlreadyInRoom(
-                player_id,
-                self.room_id,
-            ));
-        }
-        if self.players.len() >= self.config.max_players {
-            return Err(RoomError::RoomFull(self.room_id));
-        }
-
-        self.players.insert(player_id);
-        self.senders.insert(player_id, sender);
-        tracing::info!(
-            room_id = %self.room_id,
-            %player_id,
-            players = self.players.len(),
-            "player joined"
-        );
-
-        // Auto-start when minimum players reached.
-        if self.players.len() >= self.config.min_players {
-            self.transition_to_starting();
+        player: Option<PlayerId>,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> Option<R> {
+        match panic::catch_unwind(AssertUnwindSafe(|| f(self))) {
+            Ok(result) => Some(result),
+            Err(payload) => {
+                self.recover_from_panic(
+                    player,
+                    &*payload,
+                    Rollback::Never,
+                    None,
+                );
+                None
+            }
         }
-
-        // NOTE: State snapshot on join is handled by transition_to_starting
-        // (broadcasts to all players). For late-join/reconnection into an
-        // already-running game (Phase 2), add a snapshot send here.
-
-        Ok(())
     }
 
-    fn handle_leave(
+    /// Runs a game callback, catching a panic from it.
+    ///
+    /// Returns `None` if it panicked, after applying the room's
+    /// [`PanicPolicy`], or if the room is already closing. The state is
+    /// only snapshotted for callbacks that may change it. `player` is
+    /// whoever triggered the callback, for the log and, when the room
+    /// carries on, the error reply.
+    fn call_game<R>(
         &mut self,
-        player_id: PlayerId,
-    ) -> Result<(), RoomError> {
-        if !self.players.remove(&player_id) {
-            return Err(RoomError::NotInRoom(player_id, self.room_id));
+        player: Option<PlayerId>,
+        rollback: Rollback,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> Option<R> {
+        if self.state == RoomState::Destroying {
+            return None;
         }
-        self.senders.remove(&player_id);
+        let snapshot = match (self.config.on_panic, rollback) {
+            (PanicPolicy::RestoreLastState, Rollback::Snapshot) => self
+                .game_state
+                .clone()
+                .map(|state| (state, self.rng.clone())),
+            _ => None,
+        };
+        match panic::catch_unwind(AssertUnwindSafe(|| f(self))) {
+            Ok(result) => Some(result),
+            Err(payload) => {
+                self.recover_from_panic(player, &*payload, rollback, snapshot);
+                None
+            }
+        }
+    }
 
-        tracing::info!(
+    /// Logs a game logic panic, then either carries on (restoring
+    /// `snapshot`, if there is one) or closes the room.
+    fn recover_from_panic(
+        &mut self,
+        player: Option<PlayerId>,
+        payload: &(dyn Any + Send),
+        rollback: Rollback,
+        snapshot: Option<(G::State, GameRng)>,
+    ) {
+        let message = panic_message(payload);
+        tracing::error!(
             room_id = %self.room_id,
-            %player_id,
-            players = self.players.len(),
-            "player left"
+            player_id = ?player,
+            state = %self.state,
+            panic = message,
+            "game logic panicked"
         );
 
-        // Notify game logic if game is active.
-        if self.state.is_active() {
-            if let Some(game_state) = &mut self.game_state {
-                let msgs =
-                    G::on_player_disconnect(game_state, player_id);
-                let finished = G::is_finished(game_state);
-                self.dispatch(msgs);
-                if finished {
-                    self.state = RoomState::Finished;
-                    tracing::info!(room_id = %self.room_id, "game finished");
-                }
+        let carry_on = match (self.config.on_panic, rollback) {
+            (PanicPolicy::RestoreLastState, Rollback::ReadOnly) => true,
+            (PanicPolicy::RestoreLastState, Rollback::Snapshot) => {
+                snapshot.is_some()
+            }
+            _ => false,
+        };
+        if carry_on {
+            if let Some((state, rng)) = snapshot {
+                self.game_state = Some(state);
+                self.rng = rng;
+                tracing::warn!(
+                    room_id = %self.room_id,
+                    "restored last game state"
+                );
             }
+            if let Some(player_id) = player {
+                self.send_to(
+                    player_id,
+                    RoomOutbound::System(SystemMessage::Error {
+                        code: 500,
+                        message: "the game couldn't handle that".to_string(),
+                    }),
+                );
+            }
+            return;
         }
 
-        Ok(())
+        self.broadcast_system(SystemMessage::Error {
+            code: 500,
+            message: "the game crashed and the room was closed".to_string(),
+        });
+        self.state = RoomState::Destroying;
+        let _ = self.disposed.send(self.room_id);
     }
 
-    fn handle_message(
-        &mut self,
-        sender: PlayerId,
-        msg: G::ClientMessage,
+    /// Sends the result of a command that may have run game logic. If
+    /// the game crashed along the way, the room is closing, and the
+    /// caller hears that instead.
+    fn reply<T>(
+        &self,
+        reply: oneshot::Sender<Result<T, RoomError>>,
+        result: Result<T, RoomError>,
     ) {
-        if !self.players.contains(&sender) {
-            tracing::warn!(
-                room_id = %self.room_id,
-                %sender,
-                "message from non-member, ignoring"
-            );
-            return;
+        let result = match result {
+            Ok(_) if self.state == RoomState::Destroying => {
+                Err(RoomError::GameCrashed(self.room_id))
+            }
+            result => result,
+        };
+        let _ = reply.send(result);
+    }
+
+    /// Processes one command. Returns `false` when the actor should stop.
+    fn handle_command(&mut self, cmd: RoomCommand<G>) -> bool {
+        match cmd {
+            RoomCommand::Join {
+                players,
+                credentials,
+                reply,
+            } => {
+                self.last_activity = Instant::now();
+                let result = self.handle_join(players, credentials);
+                self.reply(reply, result);
+            }
+            RoomCommand::Leave { player_id, reply } => {
+                self.last_activity = Instant::now();
+                let result = self.handle_leave(player_id);
+                self.reply(reply, result);
+            }
+            RoomCommand::Disconnect { player_id, reply } => {
+                self.last_activity = Instant::now();
+                let result = self.handle_disconnect(player_id);
+                self.reply(reply, result);
+            }
+            RoomCommand::Reconnect {
+                player_id,
+                sender,
+                reply,
+            } => {
+                self.last_activity = Instant::now();
+                let result = self.handle_reconnect(player_id, sender);
+                self.reply(reply, result);
+            }
+            RoomCommand::Message {
+                sender,
+                seq,
+                tick,
+                msg,
+            } => {
+                self.last_activity = Instant::now();
+                let max_queued = self.config.async_hooks.max_queued;
+                if self.hooks.is_some() && self.queued.len() >= max_queued {
+                    tracing::debug!(
+                        room_id = %self.room_id,
+                        %sender,
+                        "message queue full, rejecting message"
+                    );
+                    self.reject(sender, seq, 503, "too many messages waiting");
+                } else if self.hooks.is_some() {
+                    self.queued.push_back(QueuedMessage {
+                        sender,
+                        seq,
+                        tick,
+                        msg,
+                    });
+                    self.prepare_next();
+                } else {
+                    self.handle_message(sender, seq, tick, msg);
+                }
+            }
+            RoomCommand::StateAck { player_id, seq } => {
+                self.sync.ack(player_id, seq);
+            }
+            RoomCommand::ReportRtt { player_id, rtt } => {
+                if self.players.contains(&player_id) {
+                    self.rtts.insert(player_id, rtt);
+                }
+            }
+            RoomCommand::Kick { by, target, reply } => {
+                self.last_activity = Instant::now();
+                let result = self.handle_kick(by, target);
+                self.reply(reply, result);
+            }
+            RoomCommand::SetLocked { by, locked, reply } => {
+                self.last_activity = Instant::now();
+                let result = self.require_host(by).map(|()| {
+                    self.locked = locked;
+                    tracing::info!(room_id = %self.room_id, locked, "room lock changed");
+                });
+                let _ = reply.send(result);
+            }
+            RoomCommand::SetPaused { by, paused, reply } => {
+                self.last_activity = Instant::now();
+                let result = self.require_host(by).and_then(|()| {
+                    if self.state != RoomState::InProgress {
+                        return Err(RoomError::InvalidState(format!(
+                            "cannot pause game in state {}",
+                            self.state
+                        )));
+                    }
+                    self.set_paused(paused);
+                    Ok(())
+                });
+                let _ = reply.send(result);
+            }
+            RoomCommand::Start { by, reply } => {
+                self.last_activity = Instant::now();
+                let result = self.handle_start(by);
+                self.reply(reply, result);
+            }
+            RoomCommand::SetReady {
+                player_id,
+                ready,
+                reply,
+            } => {
+                self.last_activity = Instant::now();
+                let result = self.handle_ready(player_id, ready);
+                self.reply(reply, result);
+            }
+            RoomCommand::SetRematch {
+                player_id,
+                rematch,
+                reply,
+            } => {
+                self.last_activity = Instant::now();
+                let result = self.handle_rematch(player_id, rematch);
+                self.reply(reply, result);
+            }
+            RoomCommand::Chat {
+                player_id,
+                text,
+                scope,
+                reply,
+            } => {
+                self.last_activity = Instant::now();
+                let _ = reply.send(self.handle_chat(player_id, text, scope));
+            }
+            RoomCommand::MuteChat {
+                player_id,
+                target,
+                muted,
+                reply,
+            } => {
+                let result = if !self.players.contains(&player_id) {
+                    Err(RoomError::NotInRoom(player_id, self.room_id))
+                } else if !self.players.contains(&target) {
+                    Err(RoomError::NotInRoom(target, self.room_id))
+                } else {
+                    self.chat.set_muted(player_id, target, muted);
+                    Ok(())
+                };
+                let _ = reply.send(result);
+            }
+            RoomCommand::GetState { reply } => {
+                let _ = reply.send(self.info());
+            }
+            RoomCommand::Shutdown => {
+                tracing::info!(room_id = %self.room_id, "room shutting down");
+                self.close("destroyed");
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Runs whatever timers have come due. Returns `false` when the room
+    /// disposed of itself and the actor should stop.
+    fn handle_timers(&mut self) -> bool {
+        let now = Instant::now();
+
+        if self.next_announcement.is_some_and(|at| at <= now) {
+            self.advance_countdown(now);
+        }
+
+        if self.paused_at.is_none()
+            && self.delayed.iter().any(|(at, ..)| *at <= now)
+        {
+            let (mut due, pending): (Vec<_>, Vec<_>) =
+                std::mem::take(&mut self.delayed)
+                    .into_iter()
+                    .partition(|(at, ..)| *at <= now);
+            self.delayed = pending;
+            due.sort_by_key(|(at, ..)| *at);
+            self.dispatch(due.into_iter().map(|(_, to, msg)| (to, msg)).collect());
+        }
+
+        self.fire_game_timers(now);
+
+        if self.bot_fill_deadline().is_some_and(|at| at <= now) {
+            self.fill_with_bots();
+        }
+        if self.next_grace_expiry().is_some_and(|at| at <= now) {
+            self.expire_disconnected(now);
+        }
+
+        if self.paused_at.is_none()
+            && self.match_deadline.is_some_and(|at| at <= now)
+        {
+            self.time_out();
+        }
+
+        if self.next_sync.is_some_and(|at| at <= now) {
+            self.sync_views();
+            self.next_sync = self
+                .config
+                .state_sync
+                .as_ref()
+                .map(|sync| now + sync.interval);
+        }
+
+        if let Some((at, reason)) = self.disposal_deadline() {
+            if at <= now {
+                tracing::info!(
+                    room_id = %self.room_id,
+                    reason,
+                    "room disposed"
+                );
+                self.close(reason);
+                let _ = self.disposed.send(self.room_id);
+                return false;
+            }
+        }
+        true
+    }
+
+    /// When the next game timer, delayed send, or the match deadline is
+    /// due. Nothing is due while the game is paused.
+    fn next_game_timer(&self) -> Option<Instant> {
+        if self.paused_at.is_some() {
+            return None;
+        }
+        let timers = self.timers.values().copied();
+        let delayed = self.delayed.iter().map(|(at, ..)| *at);
+        timers.chain(delayed).chain(self.match_deadline).min()
+    }
+
+    /// Calls `GameLogic::on_timer` for every game timer due by `now`,
+    /// earliest first. Stops early if a timer pauses or ends the game.
+    fn fire_game_timers(&mut self, now: Instant) {
+        while self.paused_at.is_none() && self.state == RoomState::InProgress
+        {
+            let Some(id) = self
+                .timers
+                .iter()
+                .filter(|(_, at)| **at <= now)
+                .min_by(|(a_id, a), (b_id, b)| a.cmp(b).then(a_id.cmp(b_id)))
+                .map(|(id, _)| id.clone())
+            else {
+                return;
+            };
+            self.timers.remove(&id);
+
+            let outcome = self.call_game(None, Rollback::Snapshot, |room| {
+                let (tick, elapsed, players) = room.context_parts();
+                let mut ctx = GameContext::new(
+                    room.room_id, tick, elapsed, &players, &mut room.rng,
+                )
+                .with_teams(room.teams.all());
+                let game_state = room.game_state.as_mut()?;
+                let msgs = G::on_timer(game_state, &id, &mut ctx);
+                let finished = G::is_finished(game_state);
+                Some((ctx.into_outbox(), msgs, finished))
+            });
+            let Some((outbox, msgs, finished)) = outcome.flatten() else {
+                continue;
+            };
+
+            self.deliver(outbox, msgs);
+            self.refresh_metadata();
+            if finished {
+                self.finish(EndReason::Completed);
+            }
+        }
+    }
+
+    /// Ends a game that ran past `RoomConfig::max_duration`, giving the
+    /// game logic a last word first.
+    fn time_out(&mut self) {
+        self.match_deadline = None;
+        if self.state != RoomState::InProgress {
+            return;
+        }
+        tracing::info!(room_id = %self.room_id, "game timed out");
+
+        let outcome = self.call_game(None, Rollback::Snapshot, |room| {
+            let (tick, elapsed, players) = room.context_parts();
+            let mut ctx = GameContext::new(
+                room.room_id, tick, elapsed, &players, &mut room.rng,
+            )
+            .with_teams(room.teams.all());
+            let game_state = room.game_state.as_mut()?;
+            let msgs = G::on_timeout(game_state, &mut ctx);
+            Some((ctx.into_outbox(), msgs))
+        });
+        if let Some((outbox, msgs)) = outcome.flatten() {
+            self.deliver(outbox, msgs);
+            self.refresh_metadata();
+        }
+        if self.state != RoomState::Destroying {
+            self.finish(EndReason::TimedOut);
+        }
+    }
+
+    /// Pauses or resumes the game's clock: ticks, game timers, delayed
+    /// sends, and `GameContext::elapsed`. Resuming pushes every deadline
+    /// back by the time spent paused.
+    fn set_paused(&mut self, paused: bool) {
+        match (paused, self.paused_at) {
+            (true, None) => {
+                self.paused_at = Some(Instant::now());
+                if let Some(scheduler) = &mut self.scheduler {
+                    scheduler.pause();
+                }
+            }
+            (false, Some(since)) => {
+                self.paused_at = None;
+                let paused_for = since.elapsed();
+                for at in self.timers.values_mut() {
+                    *at += paused_for;
+                }
+                for (at, ..) in &mut self.delayed {
+                    *at += paused_for;
+                }
+                if let Some(at) = &mut self.match_deadline {
+                    *at += paused_for;
+                }
+                if let Some(at) = &mut self.started_at {
+                    *at += paused_for;
+                }
+                if let Some(scheduler) = &mut self.scheduler {
+                    scheduler.resume();
+                }
+            }
+            _ => return,
+        }
+        tracing::info!(room_id = %self.room_id, paused, "room pause changed");
+    }
+
+    /// Tells everyone still in the room it's going away, then marks it
+    /// for the actor to stop.
+    fn close(&mut self, reason: &str) {
+        self.broadcast_system(SystemMessage::RoomClosed {
+            room_id: self.room_id,
+            reason: reason.to_string(),
+        });
+        self.state = RoomState::Destroying;
+    }
+
+    /// Returns the earliest point at which the disposal policy says this
+    /// room should go away, along with the rule that fires.
+    fn disposal_deadline(&self) -> Option<(Instant, &'static str)> {
+        let policy = &self.config.disposal;
+        // Players voting for a rematch still want the room.
+        let finished_at = self.finished_at.filter(|_| self.rematch.is_empty());
+        [
+            finished_at
+                .zip(policy.after_finished)
+                .map(|(at, ttl)| (at + ttl, "finished")),
+            self.empty_since
+                .zip(policy.when_empty)
+                .map(|(at, ttl)| (at + ttl, "empty")),
+            policy
+                .max_idle
+                .map(|ttl| (self.last_activity + ttl, "idle")),
+        ]
+        .into_iter()
+        .flatten()
+        .min_by_key(|(at, _)| *at)
+    }
+
+    fn handle_join(
+        &mut self,
+        players: Vec<(PlayerId, PlayerSender<G>)>,
+        credentials: JoinCredentials,
+    ) -> Result<(), RoomError> {
+        // Private rooms pretend not to exist unless the invite code
+        // matches, so room IDs can't be probed.
+        if self.options.private
+            && credentials.invite_code.as_deref() != Some(&*self.invite_code)
+        {
+            return Err(RoomError::NotFound(self.room_id));
+        }
+        if let Some(password) = &self.options.password {
+            if !password_matches(credentials.password.as_deref(), password) {
+                return Err(RoomError::WrongPassword(self.room_id));
+            }
+        }
+        if !self.accepts_joins() {
+            return Err(RoomError::InvalidState(format!(
+                "cannot join room in state {}",
+                self.state
+            )));
+        }
+        if self.locked {
+            return Err(RoomError::Locked(self.room_id));
+        }
+        for (index, (player_id, _)) in players.iter().enumerate() {
+            if self.players.contains(player_id)
+                || players[..index].iter().any(|(p, _)| p == player_id)
+            {
+                return Err(RoomError::AlreadyInRoom(
+                    *player_id,
+                    self.room_id,
+                ));
+            }
+        }
+        if self.players.len() + players.len() > self.config.max_players {
+            return Err(RoomError::RoomFull(self.room_id));
+        }
+        let ids: Vec<PlayerId> = players.iter().map(|(p, _)| *p).collect();
+        let Some(plan) = self.teams.plan(&ids) else {
+            return Err(RoomError::RoomFull(self.room_id));
+        };
+
+        for (index, (player_id, sender)) in players.into_iter().enumerate() {
+            self.players.push(player_id);
+            self.senders.insert(player_id, sender);
+            if let Some(&team) = plan.get(index) {
+                self.teams.place(player_id, team);
+            }
+            if self.empty_since.take().is_some() {
+                self.occupied_since = Some(Instant::now());
+            }
+            tracing::info!(
+                room_id = %self.room_id,
+                %player_id,
+                players = self.players.len(),
+                "player joined"
+            );
+
+            if self.options.hosted {
+                let host = *self.host.get_or_insert(player_id);
+                self.send_to(
+                    player_id,
+                    RoomOutbound::System(SystemMessage::HostChanged { host }),
+                );
+            }
+            self.replay_chat(player_id);
+            if self.state == RoomState::InProgress {
+                self.late_join(player_id);
+            }
+        }
+
+        // Auto-start when minimum players reached. The state snapshot
+        // is broadcast by transition_to_starting.
+        if self.state.is_joinable()
+            && !self.options.hosted
+            && self.players.len() >= self.config.min_players
+        {
+            self.begin_start();
+        }
+
+        Ok(())
+    }
+
+    /// Drops a player into the running game and sends them a snapshot.
+    fn late_join(&mut self, player_id: PlayerId) {
+        let player = Some(player_id);
+        let outcome = self.call_game(player, Rollback::Never, |room| {
+            let (tick, elapsed, players) = room.context_parts();
+            let mut ctx = GameContext::new(
+                room.room_id, tick, elapsed, &players, &mut room.rng,
+            )
+            .with_teams(room.teams.all());
+            let game_state = room.game_state.as_mut()?;
+            let msgs = G::on_player_join(game_state, player_id, &mut ctx);
+            let finished = G::is_finished(game_state);
+            let view = G::view_for(game_state, player_id);
+            Some((ctx.into_outbox(), msgs, finished, view))
+        });
+        let Some((outbox, msgs, finished, view)) = outcome.flatten() else {
+            return;
+        };
+        let snapshot = RoomOutbound::State(view);
+
+        self.send_to(player_id, snapshot);
+        self.deliver(outbox, msgs);
+        self.refresh_metadata();
+        if finished {
+            self.finish(EndReason::Completed);
+        }
+    }
+
+    /// Returns `true` if the room takes new players in its current state.
+    fn accepts_joins(&self) -> bool {
+        self.state.is_joinable()
+            || (self.config.allow_late_join
+                && self.state == RoomState::InProgress)
+    }
+
+    fn handle_kick(
+        &mut self,
+        by: PlayerId,
+        target: PlayerId,
+    ) -> Result<(), RoomError> {
+        self.require_host(by)?;
+        if by == target {
+            return Err(RoomError::InvalidState(
+                "the host cannot kick themselves".into(),
+            ));
+        }
+        if !self.players.contains(&target) {
+            return Err(RoomError::NotInRoom(target, self.room_id));
+        }
+
+        tracing::info!(room_id = %self.room_id, %target, "player kicked");
+        self.send_to(
+            target,
+            RoomOutbound::System(SystemMessage::Kicked {
+                room_id: self.room_id,
+            }),
+        );
+        self.handle_leave(target)
+    }
+
+    fn handle_start(&mut self, by: PlayerId) -> Result<(), RoomError> {
+        self.require_host(by)?;
+        if !self.state.is_joinable() {
+            return Err(RoomError::InvalidState(format!(
+                "cannot start game in state {}",
+                self.state
+            )));
+        }
+        if self.players.len() < self.config.min_players {
+            return Err(RoomError::InvalidState(format!(
+                "need at least {} players to start, have {}",
+                self.config.min_players,
+                self.players.len()
+            )));
+        }
+        self.begin_start();
+        Ok(())
+    }
+
+    fn handle_ready(
+        &mut self,
+        player_id: PlayerId,
+        ready: bool,
+    ) -> Result<(), RoomError> {
+        if self.config.pregame.is_none() {
+            return Err(RoomError::InvalidState(
+                "this room has no ready check".into(),
+            ));
+        }
+        if !matches!(
+            self.state,
+            RoomState::WaitingForPlayers | RoomState::Starting
+        ) {
+            return Err(RoomError::InvalidState(format!(
+                "cannot ready up in state {}",
+                self.state
+            )));
+        }
+        if !self.players.contains(&player_id) {
+            return Err(RoomError::NotInRoom(player_id, self.room_id));
+        }
+
+        let changed = if ready {
+            self.ready.insert(player_id)
+        } else {
+            self.ready.remove(&player_id)
+        };
+        if changed {
+            self.broadcast_system(SystemMessage::ReadyChanged {
+                player_id,
+                ready,
+            });
+        }
+
+        if self.state == RoomState::Starting && self.all_ready() {
+            self.end_countdown();
+        } else if ready
+            && self.state == RoomState::WaitingForPlayers
+            && !self.options.hosted
+            && self.players.len() >= self.config.min_players
+        {
+            // Enough players are still here after a cancelled countdown.
+            self.begin_start();
+        }
+        Ok(())
+    }
+
+    fn handle_chat(
+        &mut self,
+        player_id: PlayerId,
+        text: String,
+        scope: ChatScope,
+    ) -> Result<(), RoomError> {
+        let Some(config) = &self.config.chat else {
+            return Err(RoomError::InvalidState(
+                "this room has no chat".into(),
+            ));
+        };
+        if !self.players.contains(&player_id) {
+            return Err(RoomError::NotInRoom(player_id, self.room_id));
+        }
+        if text.trim().is_empty() {
+            return Err(RoomError::ChatRejected("message is empty".into()));
+        }
+        if text.chars().count() > config.max_length {
+            return Err(RoomError::ChatRejected(format!(
+                "message is longer than {} characters",
+                config.max_length
+            )));
+        }
+
+        let team = self.teams.team_of(player_id);
+        let recipients: Vec<PlayerId> = match scope {
+            ChatScope::Room => self.players.clone(),
+            ChatScope::Team => match team {
+                Some(team) => self.teams.members(team).to_vec(),
+                None => {
+                    return Err(RoomError::InvalidState(
+                        "this room has no teams".into(),
+                    ));
+                }
+            },
+            ChatScope::Whisper(to) => {
+                if !self.players.contains(&to) {
+                    return Err(RoomError::NotInRoom(to, self.room_id));
+                }
+                if to == player_id {
+                    vec![player_id]
+                } else {
+                    vec![player_id, to]
+                }
+            }
+        };
+        let text = match &self.chat_filter {
+            Some(filter) => filter.filter(player_id, &text).ok_or_else(|| {
+                RoomError::ChatRejected("blocked by the chat filter".into())
+            })?,
+            None => text,
+        };
+
+        let msg = SystemMessage::ChatMessage {
+            from: player_id,
+            text: text.clone(),
+            scope,
+        };
+        for recipient in recipients {
+            if !self.chat.is_muted(recipient, player_id) {
+                self.send_to(recipient, RoomOutbound::System(msg.clone()));
+            }
+        }
+        self.chat.record(player_id, text, scope, team);
+        Ok(())
+    }
+
+    /// Sends a player who just joined (or came back) the room's recent
+    /// chat.
+    fn replay_chat(&self, player_id: PlayerId) {
+        let team = self.teams.team_of(player_id);
+        for msg in self.chat.history_for(player_id, team) {
+            self.send_to(player_id, RoomOutbound::System(msg));
+        }
+    }
+
+    fn require_host(&self, player_id: PlayerId) -> Result<(), RoomError> {
+        if self.host == Some(player_id) {
+            Ok(())
+        } else {
+            Err(RoomError::NotHost(player_id, self.room_id))
+        }
+    }
+
+    fn handle_rematch(
+        &mut self,
+        player_id: PlayerId,
+        rematch: bool,
+    ) -> Result<(), RoomError> {
+        if self.state != RoomState::Finished {
+            return Err(RoomError::InvalidState(format!(
+                "cannot vote for a rematch in state {}",
+                self.state
+            )));
+        }
+        if !self.players.contains(&player_id) {
+            return Err(RoomError::NotInRoom(player_id, self.room_id));
+        }
+
+        let changed = if rematch {
+            self.rematch.insert(player_id)
+        } else {
+            self.rematch.remove(&player_id)
+        };
+        if changed {
+            self.broadcast_system(SystemMessage::RematchChanged {
+                player_id,
+                rematch,
+            });
+            // The room's been kept around for the vote; give the rest
+            // their full time to see the final state again.
+            if self.rematch.is_empty() {
+                self.finished_at = Some(Instant::now());
+            }
+        }
+        self.try_rematch();
+        Ok(())
+    }
+
+    /// Restarts a finished game once every player still in the room has
+    /// voted for a rematch. Held seats have no say: their players can
+    /// come back to the new game.
+    fn try_rematch(&mut self) {
+        if self.state != RoomState::Finished
+            || self.rematch.is_empty()
+            || self.players.len() < self.config.min_players
+            || !self.players.iter().all(|p| {
+                self.rematch.contains(p)
+                    || self.is_bot_seat(*p)
+                    || self.disconnected.contains_key(p)
+            })
+        {
+            return;
+        }
+        debug_assert!(self.state.can_transition_to(RoomState::Starting));
+        tracing::info!(room_id = %self.room_id, "rematch agreed");
+        self.rematch.clear();
+        self.finished_at = None;
+        self.delayed.clear();
+        self.transition_to_starting();
+    }
+
+    fn handle_leave(
+        &mut self,
+        player_id: PlayerId,
+    ) -> Result<(), RoomError> {
+        let Some(index) = self.players.iter().position(|p| *p == player_id)
+        else {
+            return Err(RoomError::NotInRoom(player_id, self.room_id));
+        };
+        self.players.remove(index);
+        self.teams.remove(player_id);
+        self.senders.remove(&player_id);
+        self.bots.remove(&player_id);
+        self.stand_ins.remove(&player_id);
+        self.disconnected.remove(&player_id);
+        self.ready.remove(&player_id);
+        self.rematch.remove(&player_id);
+        self.chat.remove(player_id);
+        self.sync.remove(player_id);
+        self.input_seen.remove(&player_id);
+        self.input_acks.remove(&player_id);
+        self.rtts.remove(&player_id);
+        if let Some(inputs) = &mut self.inputs {
+            inputs.remove_player(player_id);
+        }
+
+        // Hand the host role to the longest-present person.
+        if self.host == Some(player_id) {
+            self.host = self
+                .players
+                .iter()
+                .find(|p| !self.bots.contains(p))
+                .copied();
+            if let Some(host) = self.host {
+                tracing::info!(room_id = %self.room_id, %host, "host changed");
+                self.broadcast_system(SystemMessage::HostChanged { host });
+            }
+        }
+
+        if self.countdown_ends.is_some() {
+            self.cancel_countdown();
+        }
+        // Bots alone don't keep a room alive.
+        if self.empty_since.is_none()
+            && self.players.iter().all(|p| self.is_bot_seat(*p))
+        {
+            self.empty_since = Some(Instant::now());
+            self.occupied_since = None;
+        }
+
+        tracing::info!(
+            room_id = %self.room_id,
+            %player_id,
+            players = self.players.len(),
+            "player left"
+        );
+
+        // Notify game logic if game is active.
+        if self.state.is_active() {
+            let player = Some(player_id);
+            let outcome = self.call_game(player, Rollback::Never, |room| {
+                let (tick, elapsed, players) = room.context_parts();
+                let mut ctx = GameContext::new(
+                    room.room_id, tick, elapsed, &players, &mut room.rng,
+                )
+                .with_teams(room.teams.all());
+                let game_state = room.game_state.as_mut()?;
+                let msgs =
+                    G::on_player_leave(game_state, player_id, &mut ctx);
+                let finished = G::is_finished(game_state);
+                Some((ctx.into_outbox(), msgs, finished))
+            });
+            if let Some((outbox, msgs, finished)) = outcome.flatten() {
+                self.deliver(outbox, msgs);
+                self.refresh_metadata();
+                if finished {
+                    self.finish(EndReason::Completed);
+                }
+            }
         }
 
-        let game_state = match &mut self.game_state {
-            Some(s) => s,
-            None => return,
+        // The leaver may have been the last holdout.
+        self.try_rematch();
+        Ok(())
+    }
+
+    /// Holds a disconnected player's seat while the game runs. Outside a
+    /// running game there's nothing to hold, and they just leave.
+    fn handle_disconnect(
+        &mut self,
+        player_id: PlayerId,
+    ) -> Result<bool, RoomError> {
+        if !self.players.contains(&player_id) {
+            return Err(RoomError::NotInRoom(player_id, self.room_id));
+        }
+        if self.state != RoomState::InProgress {
+            return self.handle_leave(player_id).map(|()| false);
+        }
+
+        self.senders.remove(&player_id);
+        self.sync.remove(player_id);
+        self.input_seen.remove(&player_id);
+        self.input_acks.remove(&player_id);
+        self.disconnected.insert(player_id, Instant::now());
+        tracing::info!(
+            room_id = %self.room_id,
+            %player_id,
+            "player disconnected, holding seat"
+        );
+
+        let player = Some(player_id);
+        let outcome = self.call_game(player, Rollback::Never, |room| {
+            let (tick, elapsed, players) = room.context_parts();
+            let mut ctx = GameContext::new(
+                room.room_id, tick, elapsed, &players, &mut room.rng,
+            )
+            .with_teams(room.teams.all());
+            let game_state = room.game_state.as_mut()?;
+            let msgs =
+                G::on_player_disconnect(game_state, player_id, &mut ctx);
+            let finished = G::is_finished(game_state);
+            Some((ctx.into_outbox(), msgs, finished))
+        });
+        if let Some((outbox, msgs, finished)) = outcome.flatten() {
+            self.deliver(outbox, msgs);
+            self.refresh_metadata();
+            if finished {
+                self.finish(EndReason::Completed);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Hands a held seat back to its player, taking it off any bot
+    /// that was standing in.
+    fn handle_reconnect(
+        &mut self,
+        player_id: PlayerId,
+        sender: PlayerSender<G>,
+    ) -> Result<(), RoomError> {
+        let held = self.disconnected.remove(&player_id).is_some();
+        if !held && !self.stand_ins.remove(&player_id) {
+            return Err(RoomError::NotInRoom(player_id, self.room_id));
+        }
+        // Replacing the sender also stops the stand-in bot.
+        self.senders.insert(player_id, sender);
+        tracing::info!(
+            room_id = %self.room_id,
+            %player_id,
+            "player reconnected"
+        );
+
+        if let Some(host) = self.host {
+            self.send_to(
+                player_id,
+                RoomOutbound::System(SystemMessage::HostChanged { host }),
+            );
+        }
+        self.replay_chat(player_id);
+        let player = Some(player_id);
+        let outcome = self.call_game(player, Rollback::Never, |room| {
+            let (tick, elapsed, players) = room.context_parts();
+            let mut ctx = GameContext::new(
+                room.room_id, tick, elapsed, &players, &mut room.rng,
+            )
+            .with_teams(room.teams.all());
+            let game_state = room.game_state.as_mut()?;
+            let msgs =
+                G::on_player_reconnect(game_state, player_id, &mut ctx);
+            let finished = G::is_finished(game_state);
+            let view = G::view_for(game_state, player_id);
+            Some((ctx.into_outbox(), msgs, finished, view))
+        });
+        let Some((outbox, msgs, finished, view)) = outcome.flatten() else {
+            return Ok(());
         };
+        let snapshot = RoomOutbound::State(view);
 
-        if let Err(reason) = G::validate_message(game_state, sender, &msg)
+        self.send_to(player_id, snapshot);
+        self.deliver(outbox, msgs);
+        self.refresh_metadata();
+        if finished {
+            self.finish(EndReason::Completed);
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if a bot plays `player_id`'s seat.
+    fn is_bot_seat(&self, player_id: PlayerId) -> bool {
+        self.bots.contains(&player_id) || self.stand_ins.contains(&player_id)
+    }
+
+    /// When the room fills its empty seats with bots, if it's going to.
+    fn bot_fill_deadline(&self) -> Option<Instant> {
+        if self.state != RoomState::WaitingForPlayers
+            || self.bot_factory.is_none()
+            || self.players.len() >= self.config.min_players
         {
+            return None;
+        }
+        let fill_after = self.config.bots.as_ref()?.fill_after?;
+        Some(self.occupied_since? + fill_after)
+    }
+
+    /// Seats bots until the room has `min_players`, then starts the game
+    /// as a join would.
+    fn fill_with_bots(&mut self) {
+        let Some(factory) = self.bot_factory.clone() else {
+            return;
+        };
+        while self.players.len() < self.config.min_players {
+            let player_id = bot::next_bot_id();
+            let Some(plan) = self.teams.plan(&[player_id]) else {
+                break;
+            };
+            if let Some(&team) = plan.first() {
+                self.teams.place(player_id, team);
+            }
+            let sender = bot::spawn_bot(
+                player_id,
+                factory(player_id),
+                self.commands.clone(),
+            );
+            self.players.push(player_id);
+            self.senders.insert(player_id, sender);
+            self.bots.insert(player_id);
+            tracing::info!(room_id = %self.room_id, %player_id, "bot joined");
+            if self.config.pregame.is_some() {
+                self.broadcast_system(SystemMessage::ReadyChanged {
+                    player_id,
+                    ready: true,
+                });
+            }
+        }
+        if !self.options.hosted {
+            self.begin_start();
+        }
+    }
+
+    /// When the first held seat's reconnect grace runs out.
+    fn next_grace_expiry(&self) -> Option<Instant> {
+        let grace = self.config.reconnect_grace;
+        self.disconnected.values().min().map(|since| *since + grace)
+    }
+
+    /// Gives up on disconnected players whose grace has run out: a bot
+    /// takes over their seat if the room allows it, otherwise they're
+    /// removed.
+    fn expire_disconnected(&mut self, now: Instant) {
+        let grace = self.config.reconnect_grace;
+        let expired: Vec<_> = self
+            .players
+            .iter()
+            .filter(|p| {
+                self.disconnected
+                    .get(p)
+                    .is_some_and(|since| *since + grace <= now)
+            })
+            .copied()
+            .collect();
+
+        let replace = self
+            .config
+            .bots
+            .as_ref()
+            .is_some_and(|bots| bots.replace_disconnected)
+            && self.state == RoomState::InProgress;
+        for player_id in expired {
+            self.disconnected.remove(&player_id);
+            match self.bot_factory.clone().filter(|_| replace) {
+                Some(factory) => {
+                    let sender = bot::spawn_bot(
+                        player_id,
+                        factory(player_id),
+                        self.commands.clone(),
+                    );
+                    self.senders.insert(player_id, sender);
+                    self.stand_ins.insert(player_id);
+                    tracing::info!(
+                        room_id = %self.room_id,
+                        %player_id,
+                        "bot took over seat"
+                    );
+                    if let Some(game_state) = &self.game_state {
+                        let view = G::view_for(game_state, player_id);
+                        self.send_to(player_id, RoomOutbound::State(view));
+                    }
+                }
+                None => {
+                    tracing::info!(
+                        room_id = %self.room_id,
+                        %player_id,
+                        "reconnect grace ran out"
+                    );
+                    let _ = self.handle_leave(player_id);
+                    let _ = self.released.send((self.room_id, player_id));
+                }
+            }
+        }
+    }
+
+    /// Starts preparing the next queued message, if none is underway.
+    /// Messages the game isn't running to take go straight to
+    /// `handle_message`, which deals with them as usual.
+    fn prepare_next(&mut self) {
+        while self.preparing.is_none() {
+            let Some(queued) = self.queued.pop_front() else {
+                return;
+            };
+            match (&self.hooks, &self.game_state) {
+                (Some(hooks), Some(state))
+                    if self.state == RoomState::InProgress
+                        && self.players.contains(&queued.sender) =>
+                {
+                    let future = (hooks.prepare_message)(
+                        state,
+                        queued.sender,
+                        queued.msg,
+                    );
+                    self.preparing = Some(Preparing {
+                        sender: queued.sender,
+                        seq: queued.seq,
+                        tick: queued.tick,
+                        future,
+                        deadline: Instant::now()
+                            + self.config.async_hooks.timeout,
+                    });
+                }
+                _ => self.handle_message(
+                    queued.sender,
+                    queued.seq,
+                    queued.tick,
+                    queued.msg,
+                ),
+            }
+        }
+    }
+
+    /// Hands a prepared message on to the game (or drops it), then moves
+    /// on to the next one. `outcome` is `None` if the hook timed out.
+    fn finish_preparing(
+        &mut self,
+        outcome: Option<std::thread::Result<Result<G::ClientMessage, String>>>,
+    ) {
+        let Some(Preparing {
+            sender, seq, tick, ..
+        }) = self.preparing.take()
+        else {
+            return;
+        };
+        match outcome {
+            Some(Ok(Ok(msg))) => self.handle_message(sender, seq, tick, msg),
+            Some(Ok(Err(reason))) => {
+                tracing::debug!(
+                    room_id = %self.room_id,
+                    %sender,
+                    %reason,
+                    "message rejected while preparing"
+                );
+                self.reject(sender, seq, 400, &reason);
+            }
+            Some(Err(payload)) => {
+                tracing::error!(
+                    room_id = %self.room_id,
+                    %sender,
+                    panic = panic_message(&*payload),
+                    "prepare_message panicked"
+                );
+                self.reject(sender, seq, 500, "the game couldn't handle that");
+            }
+            None => {
+                tracing::debug!(
+                    room_id = %self.room_id,
+                    %sender,
+                    "prepare_message timed out"
+                );
+                self.reject(sender, seq, 504, "that took too long to handle");
+            }
+        }
+        self.prepare_next();
+    }
+
+    fn handle_message(
+        &mut self,
+        sender: PlayerId,
+        seq: Option<u64>,
+        tick: Option<u64>,
+        msg: G::ClientMessage,
+    ) {
+        if !self.players.contains(&sender) {
+            tracing::warn!(
+                room_id = %self.room_id,
+                %sender,
+                "message from non-member, ignoring"
+            );
+            return;
+        }
+
+        let player = Some(sender);
+        let validation = self.call_game(player, Rollback::ReadOnly, |room| {
+            let game_state = room.game_state.as_ref()?;
+            Some(G::validate_message(game_state, sender, &msg))
+        });
+        let Some(validation) = validation.flatten() else {
+            self.ack_input(sender, seq);
+            return;
+        };
+        if let Err(reason) = validation {
             tracing::debug!(
                 room_id = %self.room_id,
                 %sender,
                 %reason,
                 "message validation failed"
             );
+            self.reject(sender, seq, 400, &reason);
             return;
         }
 
-        let msgs = G::handle_message(game_state, sender, msg);
-        let finished = G::is_finished(game_state);
+        // Buffered games apply input on the tick it's meant for, and
+        // acknowledge it then. Inputs the buffer turns away are done
+        // with now, though the ack waits for anything still queued.
+        if let (Some(inputs), Some(scheduler)) =
+            (&mut self.inputs, &self.scheduler)
+        {
+            if let Err(reason) =
+                inputs.push(sender, seq, tick, msg, scheduler.tick_count())
+            {
+                tracing::debug!(
+                    room_id = %self.room_id,
+                    %sender,
+                    ?tick,
+                    %reason,
+                    "input rejected"
+                );
+                self.ack_input(sender, seq);
+            }
+            return;
+        }
 
-        // Dispatch after releasing the mutable borrow on game_state.
-        self.dispatch(msgs);
+        let outcome = self.call_game(player, Rollback::Snapshot, |room| {
+            let (current_tick, elapsed, players) = room.context_parts();
+            let mut ctx = GameContext::new(
+                room.room_id, current_tick, elapsed, &players, &mut room.rng,
+            )
+            .with_teams(room.teams.all());
+            let game_state = room.game_state.as_mut()?;
+            let msgs = match (&room.history, &room.config.lag_compensation) {
+                (Some(history), Some(config)) => {
+                    let lag = LagCompensation::new(
+                        history,
+                        config,
+                        current_tick,
+                        Duration::from_secs(1) / room.config.tick_rate,
+                        room.rtts.get(&sender).copied(),
+                    );
+                    G::handle_compensated_message(
+                        game_state, sender, msg, &lag, &mut ctx,
+                    )
+                }
+                _ => G::handle_message(game_state, sender, msg, &mut ctx),
+            };
+            let finished = G::is_finished(game_state);
+            Some((ctx.into_outbox(), msgs, finished))
+        });
+        // A message the game choked on is done with all the same.
+        self.ack_input(sender, seq);
+        let Some((outbox, msgs, finished)) = outcome.flatten() else {
+            return;
+        };
+        self.deliver(outbox, msgs);
+        self.refresh_metadata();
+
+        if finished {
+            self.finish(EndReason::Completed);
+        }
+    }
+
+    /// Runs one tick of the game with the inputs queued for it.
+    fn run_tick(&mut self, info: TickInfo) {
+        let batch = match &mut self.inputs {
+            Some(inputs) => inputs.take(info.tick, &self.players),
+            None => Vec::new(),
+        };
+        let mut inputs = Vec::with_capacity(batch.len());
+        for (input, seq) in batch {
+            self.ack_input(input.player, seq);
+            inputs.push(input);
+        }
+
+        let outcome = self.call_game(None, Rollback::Snapshot, |room| {
+            let (_, elapsed, players) = room.context_parts();
+            let mut ctx = GameContext::new(
+                room.room_id, info.tick, elapsed, &players, &mut room.rng,
+            )
+            .with_teams(room.teams.all());
+            let game_state = room.game_state.as_mut()?;
+            let msgs = G::tick(game_state, info.dt, inputs, &mut ctx);
+            let finished = G::is_finished(game_state);
+            Some((ctx.into_outbox(), msgs, finished))
+        });
+        let Some((outbox, msgs, finished)) = outcome.flatten() else {
+            return;
+        };
+        if let (Some(history), Some(game_state)) =
+            (&mut self.history, &self.game_state)
+        {
+            history.record(info.tick, game_state);
+        }
+        if let Some(scheduler) = &mut self.scheduler {
+            scheduler.record_tick_end();
+        }
+
+        self.deliver(outbox, msgs);
+        self.refresh_metadata();
 
         if finished {
-            self.state = RoomState::Finished;
-            tracing::info!(room_id = %self.room_id, "game finished");
+            self.finish(EndReason::Completed);
         }
     }
 
+    /// Values for a `GameContext`: the last tick, time since the game
+    /// started, and the players sorted by ID.
+    fn context_parts(&self) -> (u64, Duration, Vec<PlayerId>) {
+        let tick = self
+            .scheduler
+            .as_ref()
+            .map_or(0, TickScheduler::tick_count);
+        // While paused, the clock stands still at the moment of pausing.
+        let now = self.paused_at.unwrap_or_else(Instant::now);
+        let elapsed = self
+            .started_at
+            .map_or(Duration::ZERO, |at| now.saturating_duration_since(at));
+        let mut players = self.players.clone();
+        players.sort_by_key(|p| p.0);
+        (tick, elapsed, players)
+    }
+
+    /// Sends what a hook produced: messages sent through its context,
+    /// then the ones it returned. Delayed sends and timers are queued,
+    /// then any pause or resume takes effect.
+    fn deliver(
+        &mut self,
+        outbox: Outbox<G::ServerMessage>,
+        msgs: Vec<(Recipient, G::ServerMessage)>,
+    ) {
+        self.dispatch(outbox.now);
+        self.dispatch(msgs);
+        // While paused, the clock stands still at the moment of pausing.
+        let now = self.paused_at.unwrap_or_else(Instant::now);
+        self.delayed.extend(
+            outbox
+                .later
+                .into_iter()
+                .map(|(delay, to, msg)| (now + delay, to, msg)),
+        );
+        for (id, after) in outbox.timers {
+            match after {
+                Some(after) => {
+                    self.timers.insert(id, now + after);
+                }
+                None => {
+                    self.timers.remove(&id);
+                }
+            }
+        }
+        if let Some(paused) = outbox.paused {
+            self.set_paused(paused);
+        }
+    }
+
+    /// Records that `player`'s input `seq` has been processed and tells
+    /// them, if that moves their ack forward.
+    ///
+    /// The ack covers every seq up to it, so it stops short of the
+    /// lowest seq still waiting in the input buffer: a rejected seq 6
+    /// mustn't ack a seq 5 queued for a later tick. Once that tick runs,
+    /// the ack catches up to everything seen.
+    fn ack_input(&mut self, player: PlayerId, seq: Option<u64>) {
+        let Some(seq) = seq else {
+            return;
+        };
+        let seen = self.input_seen.entry(player).or_insert(seq);
+        *seen = (*seen).max(seq);
+        let mut ack = *seen;
+        if let Some(pending) = self
+            .inputs
+            .as_ref()
+            .and_then(|inputs| inputs.lowest_pending(player))
+        {
+            let Some(below) = pending.checked_sub(1) else {
+                return;
+            };
+            ack = ack.min(below);
+        }
+        if self.input_acks.get(&player).is_some_and(|acked| *acked >= ack) {
+            return;
+        }
+        self.input_acks.insert(player, ack);
+        self.send_to(player, RoomOutbound::InputAck { seq: ack });
+    }
+
+    /// Ends the game and tells every player why.
+    fn finish(&mut self, reason: EndReason) {
+        self.state = RoomState::Finished;
+        self.finished_at = Some(Instant::now());
+        self.scheduler = None;
+        self.inputs = None;
+        self.history = None;
+        self.timers.clear();
+        self.delayed.clear();
+        self.match_deadline = None;
+        self.set_paused(false);
+        tracing::info!(room_id = %self.room_id, ?reason, "game finished");
+
+        // One last sync so clients see the final state.
+        if self.next_sync.take().is_some() {
+            self.sync_views();
+        }
+
+        let mut result = self
+            .game_state
+            .as_ref()
+            .map(G::results)
+            .unwrap_or_default();
+        for player in &mut result.players {
+            if player.team.is_none() {
+                player.team = self.teams.team_of(player.player_id);
+            }
+        }
+        if let Some(sink) = &self.results {
+            let mut bots: Vec<PlayerId> = self.bots.iter().copied().collect();
+            bots.sort_by_key(|p| p.0);
+            sink.record(&MatchRecord {
+                room_id: self.room_id,
+                seed: self.seed,
+                reason,
+                ended_at_ms: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |since| since.as_millis() as u64),
+                duration_ms: self
+                    .started_at
+                    .map_or(0, |at| at.elapsed().as_millis() as u64),
+                result: result.clone(),
+                bots,
+            });
+        }
+        self.broadcast_system(SystemMessage::GameEnded { reason, result });
+    }
+
+    /// Sends every player a keyframe or delta of their current view.
+    fn sync_views(&mut self) {
+        let (Some(game_state), Some(config)) =
+            (&self.game_state, &self.config.state_sync)
+        else {
+            return;
+        };
+        let seq = self.sync.next_seq();
+        let keyframe = seq % u64::from(config.keyframe_every.max(1)) == 0;
+
+        for pid in &self.players {
+            let view = G::view_for(game_state, *pid);
+            let value = match serde_json::to_value(&view) {
+                Ok(value) => value,
+                Err(e) => {
+                    tracing::warn!(
+                        room_id = %self.room_id,
+                        error = %e,
+                        "failed to serialize view for sync"
+                    );
+                    continue;
+                }
+            };
+            let outbound =
+                match self.sync.update(*pid, seq, value, keyframe) {
+                    Some(SyncUpdate::Keyframe) => {
+                        RoomOutbound::Keyframe { seq, view }
+                    }
+                    Some(SyncUpdate::Delta { base_seq, patch }) => {
+                        RoomOutbound::Delta {
+                            seq,
+                            base_seq,
+                            patch,
+                        }
+                    }
+                    None => continue,
+                };
+            if let Some(sender) = self.senders.get(pid) {
+                let _ = sender.send(outbound);
+            }
+        }
+    }
+
+    /// Starts the game, or opens the pre-game countdown if the game
+    /// configures one.
+    fn begin_start(&mut self) {
+        let Some(pregame) = &self.config.pregame else {
+            self.transition_to_starting();
+            return;
+        };
+        let now = Instant::now();
+        self.state = RoomState::Starting;
+        self.countdown_ends = Some(now + pregame.countdown);
+        tracing::info!(
+            room_id = %self.room_id,
+            countdown_ms = pregame.countdown.as_millis() as u64,
+            "countdown started"
+        );
+
+        if self.all_ready() {
+            self.end_countdown();
+        } else {
+            self.advance_countdown(now);
+        }
+    }
+
+    /// Announces the remaining countdown, or starts the game once it
+    /// has run out.
+    fn advance_countdown(&mut self, now: Instant) {
+        let (Some(ends), Some(pregame)) =
+            (self.countdown_ends, &self.config.pregame)
+        else {
+            return;
+        };
+        if ends <= now {
+            self.end_countdown();
+            return;
+        }
+        let remaining = ends - now;
+        self.next_announcement = Some((now + pregame.announce_every).min(ends));
+        self.broadcast_system(SystemMessage::Countdown {
+            remaining_ms: remaining.as_millis() as u64,
+        });
+    }
+
+    /// Stops the countdown and sends the room back to waiting for
+    /// players, with nobody ready.
+    fn cancel_countdown(&mut self) {
+        self.countdown_ends = None;
+        self.next_announcement = None;
+        self.ready.clear();
+        self.state = RoomState::WaitingForPlayers;
+        tracing::info!(room_id = %self.room_id, "countdown cancelled");
+        self.broadcast_system(SystemMessage::CountdownCancelled);
+    }
+
+    fn end_countdown(&mut self) {
+        self.countdown_ends = None;
+        self.next_announcement = None;
+        self.transition_to_starting();
+    }
+
+    /// Returns `true` if every player in the room has readied up. Bots
+    /// are always ready.
+    fn all_ready(&self) -> bool {
+        self.players
+            .iter()
+            .all(|p| self.ready.contains(p) || self.bots.contains(p))
+    }
+
     fn transition_to_starting(&mut self) {
         self.state = RoomState::Starting;
-        let mut players: Vec<PlayerId> = self.players.iter().copied().collect();
-        players.sort_by_key(|p| p.0);
-        self.game_state =
-            Some(G::init(&self.game_config, &players));
+        self.ready.clear();
+        self.started_at = Some(Instant::now());
+        self.match_deadline =
+            self.config.max_duration.map(|limit| Instant::now() + limit);
+        let init = self.call_game(None, Rollback::Never, |room| {
+            let (_, _, players) = room.context_parts();
+            let mut ctx = GameContext::new(
+                room.room_id,
+                0,
+                Duration::ZERO,
+                &players,
+                &mut room.rng,
+            )
+            .with_teams(room.teams.all());
+            let game_state = G::init(&room.game_config, &mut ctx);
+            (game_state, ctx.into_outbox())
+        });
+        let Some((game_state, outbox)) = init else {
+            return;
+        };
+        self.game_state = Some(game_state);
+        self.refresh_metadata();
         self.state = RoomState::InProgress;
+        self.next_sync = self
+            .config
+            .state_sync
+            .as_ref()
+            .map(|sync| Instant::now() + sync.interval);
+        if self.config.tick_rate > 0 {
+            self.scheduler =
+                Some(TickScheduler::with_rate(self.config.tick_rate));
+            self.inputs =
+                self.config.input_buffer.clone().map(InputBuffer::new);
+            self.history = self.config.lag_compensation.as_ref().map(|lag| {
+                StateHistory::new(lag, self.config.tick_rate)
+            });
+            if let (Some(history), Some(game_state)) =
+                (&mut self.history, &self.game_state)
+            {
+                history.record(0, game_state);
+            }
+        }
         tracing::info!(
             room_id = %self.room_id,
-            players = players.len(),
+            players = self.players.len(),
             "game started"
         );
 
-        // Broadcast initial state to all players.
+        // Send each player their view of the initial state.
         if let Some(game_state) = &self.game_state {
-            let msg = RoomOutbound::State(game_state.clone());
             for pid in &self.players {
-                self.send_to(*pid, msg.clone());
+                let view = G::view_for(game_state, *pid);
+                self.send_to(*pid, RoomOutbound::State(view));
             }
         }
+        self.deliver(outbox, Vec::new());
     }
 
     /// Dispatches outbound messages to the correct recipients.
@@ -382,28 +2513,108 @@ impl<G: GameLogic> RoomActor<G> {
                         }
                     }
                 }
+                Recipient::Team(team) => {
+                    for pid in self.teams.members(team) {
+                        self.send_to(*pid, outbound.clone());
+                    }
+                }
+                Recipient::Players(pids) => {
+                    for pid in pids {
+                        self.send_to(pid, outbound.clone());
+                    }
+                }
             }
         }
     }
 
+    /// Sends a framework message to every player in the room.
+    fn broadcast_system(&self, msg: SystemMessage) {
+        let outbound = RoomOutbound::System(msg);
+        for pid in &self.players {
+            self.send_to(*pid, outbound.clone());
+        }
+    }
+
     /// Sends an outbound message to a single player. Silently drops
     /// if the receiver is gone (player disconnected).
+    /// Tells `sender` their message was dropped. It still counts as
+    /// processed: the client shouldn't replay it.
+    fn reject(
+        &mut self,
+        sender: PlayerId,
+        seq: Option<u64>,
+        code: u16,
+        message: &str,
+    ) {
+        self.ack_input(sender, seq);
+        self.send_to(
+            sender,
+            RoomOutbound::System(SystemMessage::Error {
+                code,
+                message: message.to_string(),
+            }),
+        );
+    }
+
     fn send_to(&self, player_id: PlayerId, msg: RoomOutbound<G>) {
         if let Some(sender) = self.senders.get(&player_id) {
             let _ = sender.send(msg);
         }
     }
 
+    /// Re-evaluates the game's public metadata.
+    fn refresh_metadata(&mut self) {
+        self.metadata =
+            G::room_metadata(&self.game_config, self.game_state.as_ref());
+    }
+
     fn info(&self) -> RoomInfo {
         RoomInfo {
             room_id: self.room_id,
             state: self.state,
             player_count: self.players.len(),
+            bot_count: self.bots.len() + self.stand_ins.len(),
             max_players: self.config.max_players,
+            accepts_joins: self.accepts_joins(),
+            metadata: self.metadata.clone(),
+            private: self.options.private,
+            password_protected: self.options.password.is_some(),
+            host: self.host,
+            locked: self.locked,
+            paused: self.paused_at.is_some(),
+            seed: self.seed,
         }
     }
 }
 
+/// Compares a presented password with the room's in constant time, so
+/// how long a wrong guess takes to reject says nothing about how close
+/// it was. Only the length can show.
+fn password_matches(given: Option<&str>, expected: &str) -> bool {
+    let Some(given) = given else {
+        return false;
+    };
+    if given.len() != expected.len() {
+        return false;
+    }
+    let diff = given
+        .bytes()
+        .zip(expected.bytes())
+        .fold(0u8, |diff, (a, b)| diff | (a ^ b));
+    std::hint::black_box(diff) == 0
+}
+
+/// The message a panic was raised with, when it's a string.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "non-string panic payload"
+    }
+}
+
 /// Spawns a new room actor task and returns a handle to communicate with it.
 ///
 /// `channel_size` controls backpressure — if the channel fills up,
@@ -411,26 +2622,77 @@ impl<G: GameLogic> RoomActor<G> {
 pub(crate) fn spawn_room<G: GameLogic>(
     room_id: RoomId,
     config: RoomConfig,
+    options: RoomOptions,
+    invite_code: String,
     game_config: G::Config,
     channel_size: usize,
+    services: RoomServices<G>,
 ) -> RoomHandle<G> {
     let (tx, rx) = mpsc::channel(channel_size);
+    let metadata = G::room_metadata(&game_config, None);
+    let invite_code: Arc<str> = invite_code.into();
+    let seed = options.seed.unwrap_or_else(|| rand::rng().random());
+    let teams = Teams::new(config.teams.as_ref(), services.ratings);
+    let chat = ChatLog::new(config.chat.as_ref().map_or(0, |c| c.history));
 
     let actor = RoomActor::<G> {
         room_id,
         state: RoomState::WaitingForPlayers,
         config,
-        players: HashSet::new(),
+        options,
+        invite_code: Arc::clone(&invite_code),
+        players: Vec::new(),
+        teams,
+        chat,
+        chat_filter: services.chat_filter,
+        host: None,
+        locked: false,
+        ready: HashSet::new(),
+        rematch: HashSet::new(),
+        countdown_ends: None,
+        next_announcement: None,
+        sync: SyncTracker::default(),
+        next_sync: None,
+        scheduler: None,
+        inputs: None,
+        input_seen: std::collections::HashMap::new(),
+        input_acks: std::collections::HashMap::new(),
+        history: None,
+        rtts: std::collections::HashMap::new(),
+        seed,
+        rng: GameRng::seed_from_u64(seed),
+        started_at: None,
+        delayed: Vec::new(),
+        timers: std::collections::HashMap::new(),
+        paused_at: None,
+        match_deadline: None,
         senders: std::collections::HashMap::new(),
         game_state: None,
         game_config,
+        metadata,
         receiver: rx,
+        disposed: services.disposed,
+        results: services.results,
+        hooks: services.hooks,
+        commands: tx.downgrade(),
+        released: services.released,
+        bot_factory: services.bots,
+        bots: HashSet::new(),
+        stand_ins: HashSet::new(),
+        disconnected: std::collections::HashMap::new(),
+        occupied_since: None,
+        queued: VecDeque::new(),
+        preparing: None,
+        last_activity: Instant::now(),
+        empty_since: Some(Instant::now()),
+        finished_at: None,
     };
 
     tokio::spawn(actor.run());
 
     RoomHandle {
         room_id,
+        invite_code,
         sender: tx,
     }
 }
@@ -0,0 +1,260 @@
+//! Delta-compressed state synchronization.
+//!
+//! Instead of resending the whole view every sync, the room diffs the
+//! player's current view against the last snapshot that player
+//! acknowledged and sends only the difference. Diffs are structural:
+//! both views are serialized to JSON values and compared as
+//! [JSON Merge Patches](https://www.rfc-editor.org/rfc/rfc7386), so games
+//! don't need to implement anything.
+//!
+//! Merge patches can't tell "set to `null`" from "remove", so a field
+//! that becomes `null` is removed. Serde reads a missing `Option` field as
+//! `None`, so this round-trips for ordinary derived types.
+
+use std::collections::{HashMap, VecDeque};
+
+use arcforge_protocol::PlayerId;
+use serde_json::{Map, Value};
+
+/// How many unacknowledged snapshots to remember per player. Acks for
+/// anything older are ignored and the player keeps getting keyframes.
+const MAX_PENDING_SNAPSHOTS: usize = 64;
+
+/// Computes a merge patch that turns `old` into `new`.
+///
+/// Returns `None` if the two values are equal.
+pub fn diff_values(old: &Value, new: &Value) -> Option<Value> {
+    if old == new {
+        return None;
+    }
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            let mut patch = Map::new();
+            for (key, old_value) in old {
+                match new.get(key) {
+                    Some(new_value) => {
+                        if let Some(p) = diff_values(old_value, new_value) {
+                            patch.insert(key.clone(), p);
+                        }
+                    }
+                    None => {
+                        patch.insert(key.clone(), Value::Null);
+                    }
+                }
+            }
+            for (key, new_value) in new {
+                if !old.contains_key(key) {
+                    patch.insert(key.clone(), strip_nulls(new_value));
+                }
+            }
+            Some(Value::Object(patch))
+        }
+        // Arrays, scalars, and type changes are replaced wholesale.
+        _ => Some(strip_nulls(new)),
+    }
+}
+
+/// Applies a merge patch produced by [`diff_values`] to `target`.
+pub fn apply_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let Value::Object(target) = target else {
+        unreachable!("target was just made an object");
+    };
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            apply_patch(
+                target.entry(key.clone()).or_insert(Value::Null),
+                value,
+            );
+        }
+    }
+}
+
+/// Removes `null` object members, which a merge patch would otherwise
+/// read as deletions.
+fn strip_nulls(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k.clone(), strip_nulls(v)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// What the room sends one player on a sync tick.
+pub(crate) enum SyncUpdate {
+    /// The full view.
+    Keyframe,
+    /// A patch against the snapshot `base_seq`.
+    Delta { base_seq: u64, patch: Value },
+}
+
+/// Per-player bookkeeping: the acknowledged baseline plus snapshots
+/// sent since then.
+#[derive(Default)]
+struct ClientSync {
+    acked: Option<(u64, Value)>,
+    pending: VecDeque<(u64, Value)>,
+}
+
+/// Tracks sync state for every player in a room.
+#[derive(Default)]
+pub(crate) struct SyncTracker {
+    /// Sequence number of the most recent snapshot.
+    seq: u64,
+    clients: HashMap<PlayerId, ClientSync>,
+}
+
+impl SyncTracker {
+    /// Starts a new snapshot round and returns its sequence number.
+    pub(crate) fn next_seq(&mut self) -> u64 {
+        self.seq += 1;
+        self.seq
+    }
+
+    /// Decides what to send `player` for snapshot `seq` and remembers
+    /// `view` until the player acknowledges it.
+    ///
+    /// Returns `None` if nothing changed since the acknowledged baseline.
+    pub(crate) fn update(
+        &mut self,
+        player: PlayerId,
+        seq: u64,
+        view: Value,
+        keyframe: bool,
+    ) -> Option<SyncUpdate> {
+        let client = self.clients.entry(player).or_default();
+        let update = match (&client.acked, keyframe) {
+            (Some((base_seq, base)), false) => {
+                let patch = diff_values(base, &view)?;
+                SyncUpdate::Delta {
+                    base_seq: *base_seq,
+                    patch,
+                }
+            }
+            _ => SyncUpdate::Keyframe,
+        };
+
+        client.pending.push_back((seq, view));
+        if client.pending.len() > MAX_PENDING_SNAPSHOTS {
+            client.pending.pop_front();
+        }
+        Some(update)
+    }
+
+    /// Records that `player` has applied snapshot `seq`. Later deltas
+    /// for that player are computed against it.
+    pub(crate) fn ack(&mut self, player: PlayerId, seq: u64) {
+        let Some(client) = self.clients.get_mut(&player) else {
+            return;
+        };
+        let Some(index) = client.pending.iter().position(|(s, _)| *s == seq)
+        else {
+            return;
+        };
+        client.acked = client.pending.drain(..=index).next_back();
+    }
+
+    /// Forgets a player who left the room.
+    pub(crate) fn remove(&mut self, player: PlayerId) {
+        self.clients.remove(&player);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn round_trip(old: Value, new: Value) {
+        let mut patched = old.clone();
+        if let Some(patch) = diff_values(&old, &new) {
+            apply_patch(&mut patched, &patch);
+        }
+        assert_eq!(patched, new);
+    }
+
+    #[test]
+    fn test_diff_equal_values_is_none() {
+        let v = json!({ "a": 1, "b": [1, 2] });
+        assert_eq!(diff_values(&v, &v), None);
+    }
+
+    #[test]
+    fn test_diff_only_contains_changes() {
+        let old = json!({ "hp": 10, "pos": { "x": 1, "y": 2 }, "name": "a" });
+        let new = json!({ "hp": 9, "pos": { "x": 1, "y": 3 }, "name": "a" });
+        assert_eq!(
+            diff_values(&old, &new),
+            Some(json!({ "hp": 9, "pos": { "y": 3 } }))
+        );
+    }
+
+    #[test]
+    fn test_diff_round_trips() {
+        round_trip(json!({ "a": 1, "b": 2 }), json!({ "a": 1, "c": 3 }));
+        round_trip(json!({ "list": [1, 2, 3] }), json!({ "list": [3] }));
+        round_trip(json!({ "a": { "b": 1 } }), json!({ "a": 5 }));
+        round_trip(json!(1), json!({ "a": 1 }));
+        round_trip(json!({ "a": null }), json!({}));
+    }
+
+    #[test]
+    fn test_tracker_keyframes_until_acked() {
+        let mut tracker = SyncTracker::default();
+        let p = PlayerId(1);
+
+        let seq1 = tracker.next_seq();
+        let update = tracker.update(p, seq1, json!({ "n": 1 }), false);
+        assert!(matches!(update, Some(SyncUpdate::Keyframe)));
+
+        let seq2 = tracker.next_seq();
+        let update = tracker.update(p, seq2, json!({ "n": 2 }), false);
+        assert!(matches!(update, Some(SyncUpdate::Keyframe)));
+
+        tracker.ack(p, seq1);
+        let seq3 = tracker.next_seq();
+        match tracker.update(p, seq3, json!({ "n": 3 }), false) {
+            Some(SyncUpdate::Delta { base_seq, patch }) => {
+                assert_eq!(base_seq, seq1);
+                assert_eq!(patch, json!({ "n": 3 }));
+            }
+            _ => panic!("expected a delta"),
+        }
+
+        // Unchanged since the baseline: nothing to send.
+        tracker.ack(p, seq3);
+        let seq4 = tracker.next_seq();
+        assert!(tracker.update(p, seq4, json!({ "n": 3 }), false).is_none());
+
+        // Forced keyframes ignore the baseline.
+        let update = tracker.update(p, seq4, json!({ "n": 3 }), true);
+        assert!(matches!(update, Some(SyncUpdate::Keyframe)));
+    }
+
+    #[test]
+    fn test_tracker_ignores_unknown_acks() {
+        let mut tracker = SyncTracker::default();
+        let p = PlayerId(1);
+        tracker.ack(p, 99);
+        let seq = tracker.next_seq();
+        tracker.update(p, seq, json!(1), false);
+        tracker.ack(p, 99);
+        let seq = tracker.next_seq();
+        assert!(matches!(
+            tracker.update(p, seq, json!(2), false),
+            Some(SyncUpdate::Keyframe)
+        ));
+    }
+}
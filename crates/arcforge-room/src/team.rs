@@ -0,0 +1,142 @@
+//! Team assignment for rooms with [`RoomConfig::teams`].
+//!
+//! Players are put on a team as they join and stay on it until they
+//! leave. Each joiner goes to the team with the fewest players; ties go
+//! to the team with the lowest total rating when the manager knows
+//! ratings (see [`RoomManager::with_ratings`]), and then to the lowest
+//! team ID. Players who join together (a party) share a team if one
+//! has room for all of them.
+//!
+//! [`RoomConfig::teams`]: crate::RoomConfig::teams
+//! [`RoomManager::with_ratings`]: crate::RoomManager::with_ratings
+
+use std::sync::Arc;
+
+use arcforge_protocol::{PlayerId, TeamId};
+
+use crate::config::TeamConfig;
+
+/// Looks up a player's rating for team balancing.
+pub(crate) type RatingFn = Arc<dyn Fn(PlayerId) -> f64 + Send + Sync>;
+
+/// A team and who's on it, as game hooks see it through
+/// [`GameContext::teams`](crate::GameContext::teams).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Team {
+    /// The team's ID. A room with `count` teams numbers them from 0
+    /// to `count - 1`.
+    pub id: TeamId,
+    /// The team's players, in the order they joined.
+    pub players: Vec<PlayerId>,
+}
+
+/// A room's teams. Empty in rooms without teams.
+pub(crate) struct Teams {
+    size: usize,
+    teams: Vec<Team>,
+    ratings: Option<RatingFn>,
+}
+
+impl Teams {
+    pub(crate) fn new(
+        config: Option<&TeamConfig>,
+        ratings: Option<RatingFn>,
+    ) -> Self {
+        let (count, size) = config.map_or((0, 0), |c| (c.count, c.size));
+        Self {
+            size,
+            teams: (0..count)
+                .map(|id| Team {
+                    id: TeamId(id as u32),
+                    players: Vec::new(),
+                })
+                .collect(),
+            ratings,
+        }
+    }
+
+    /// Every team, in ID order.
+    pub(crate) fn all(&self) -> &[Team] {
+        &self.teams
+    }
+
+    /// The players on `team`, or none if there's no such team.
+    pub(crate) fn members(&self, team: TeamId) -> &[PlayerId] {
+        self.teams
+            .get(team.0 as usize)
+            .map_or(&[], |t| t.players.as_slice())
+    }
+
+    /// The team `player` is on, if any.
+    pub(crate) fn team_of(&self, player: PlayerId) -> Option<TeamId> {
+        self.teams
+            .iter()
+            .find(|t| t.players.contains(&player))
+            .map(|t| t.id)
+    }
+
+    /// Works out a team for each of `group`, who are joining together,
+    /// without placing anyone. Returns `None` if they don't all fit, and
+    /// an empty plan in rooms without teams.
+    pub(crate) fn plan(&self, group: &[PlayerId]) -> Option<Vec<TeamId>> {
+        if self.teams.is_empty() {
+            return Some(Vec::new());
+        }
+        let mut sizes: Vec<usize> =
+            self.teams.iter().map(|t| t.players.len()).collect();
+        let mut totals: Vec<f64> = self
+            .teams
+            .iter()
+            .map(|t| t.players.iter().map(|p| self.rating(*p)).sum())
+            .collect();
+
+        if group.len() > 1 {
+            if let Some(team) = self.pick(&sizes, &totals, group.len()) {
+                return Some(vec![self.teams[team].id; group.len()]);
+            }
+        }
+        let mut plan = Vec::with_capacity(group.len());
+        for player in group {
+            let team = self.pick(&sizes, &totals, 1)?;
+            sizes[team] += 1;
+            totals[team] += self.rating(*player);
+            plan.push(self.teams[team].id);
+        }
+        Some(plan)
+    }
+
+    /// Puts `player` on `team`.
+    pub(crate) fn place(&mut self, player: PlayerId, team: TeamId) {
+        if let Some(team) = self.teams.get_mut(team.0 as usize) {
+            team.players.push(player);
+        }
+    }
+
+    /// Takes `player` off their team.
+    pub(crate) fn remove(&mut self, player: PlayerId) {
+        for team in &mut self.teams {
+            team.players.retain(|p| *p != player);
+        }
+    }
+
+    /// The index of the smallest team with room for `needed` more
+    /// players, lowest total rating first among equals.
+    fn pick(
+        &self,
+        sizes: &[usize],
+        totals: &[f64],
+        needed: usize,
+    ) -> Option<usize> {
+        (0..sizes.len())
+            .filter(|&i| sizes[i] + needed <= self.size)
+            .min_by(|&a, &b| {
+                sizes[a]
+                    .cmp(&sizes[b])
+                    .then(totals[a].total_cmp(&totals[b]))
+            })
+    }
+
+    fn rating(&self, player: PlayerId) -> f64 {
+        self.ratings.as_ref().map_or(0.0, |rating| rating(player))
+    }
+}
@@ -1,9 +1,21 @@
 //! Integration tests for the room system using a mock game.
 
+use std::sync::Arc;
 use std::time::Duration;
 
-use arcforge_protocol::{PlayerId, Recipient};
-use arcforge_room::{GameLogic, PlayerSender, RoomConfig, RoomManager, RoomState};
+use arcforge_protocol::{
+    ChatScope, EndReason, MatchResult, PlayerId, Recipient, RoomId,
+    RoomMetadata, SystemMessage, TeamId,
+};
+use arcforge_room::{
+    apply_patch, AsyncGameLogic, AsyncHookConfig, Bot, BotConfig, ChatFilter,
+    DisposalPolicy, GameContext, GameLogic, InputBufferConfig,
+    JoinCredentials, LagCompensation, LagCompensationConfig,
+    LateInputPolicy, MatchRecord, PanicPolicy, PlayerInput, PlayerSender,
+    PregameConfig, ResultSink, RoomConfig, RoomError, RoomManager, RoomOptions,
+    RoomOutbound, RoomState, StateSyncConfig, TeamConfig, WordFilter,
+};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 
@@ -17,6 +29,7 @@ struct CounterGame;
 #[derive(Clone, Debug, Default)]
 struct CounterConfig {
     finish_at: u32,
+    mode: Option<&'static str>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -37,10 +50,14 @@ enum CounterEvent {
 impl GameLogic for CounterGame {
     type Config = CounterConfig;
     type State = CounterState;
+    type View = CounterState;
     type ClientMessage = Increment;
     type ServerMessage = CounterEvent;
 
-    fn init(config: &CounterConfig, _players: &[PlayerId]) -> CounterState {
+    fn init(
+        config: &CounterConfig,
+        _ctx: &mut GameContext<'_, CounterEvent>,
+    ) -> CounterState {
         CounterState {
             count: 0,
             target: config.finish_at,
@@ -51,6 +68,7 @@ impl GameLogic for CounterGame {
         state: &mut CounterState,
         _sender: PlayerId,
         _msg: Increment,
+        _ctx: &mut GameContext<'_, CounterEvent>,
     ) -> Vec<(Recipient, CounterEvent)> {
         state.count += 1;
         if state.count >= state.target {
@@ -60,10 +78,28 @@ impl GameLogic for CounterGame {
         }
     }
 
+    fn view_for(state: &CounterState, _player: PlayerId) -> CounterState {
+        state.clone()
+    }
+
     fn is_finished(state: &CounterState) -> bool {
         state.count >= state.target
     }
 
+    fn room_metadata(
+        config: &CounterConfig,
+        state: Option<&CounterState>,
+    ) -> RoomMetadata {
+        let mut metadata = RoomMetadata::new();
+        if let Some(mode) = config.mode {
+            metadata.insert("mode".into(), mode.into());
+        }
+        if let Some(state) = state {
+            metadata.insert("count".into(), state.count.to_string());
+        }
+        metadata
+    }
+
     fn room_config() -> RoomConfig {
         RoomConfig {
             min_players: 2,
@@ -79,10 +115,14 @@ struct FullGame;
 impl GameLogic for FullGame {
     type Config = CounterConfig;
     type State = CounterState;
+    type View = CounterState;
     type ClientMessage = Increment;
     type ServerMessage = CounterEvent;
 
-    fn init(config: &CounterConfig, _players: &[PlayerId]) -> CounterState {
+    fn init(
+        config: &CounterConfig,
+        _ctx: &mut GameContext<'_, CounterEvent>,
+    ) -> CounterState {
         CounterState { count: 0, target: config.finish_at }
     }
 
@@ -90,11 +130,16 @@ impl GameLogic for FullGame {
         state: &mut CounterState,
         _sender: PlayerId,
         _msg: Increment,
+        _ctx: &mut GameContext<'_, CounterEvent>,
     ) -> Vec<(Recipient, CounterEvent)> {
         state.count += 1;
         vec![]
     }
 
+    fn view_for(state: &CounterState, _player: PlayerId) -> CounterState {
+        state.clone()
+    }
+
     fn is_finished(state: &CounterState) -> bool {
         state.count >= state.target
     }
@@ -108,293 +153,3573 @@ impl GameLogic for FullGame {
     }
 }
 
-// =========================================================================
-// Helper
-// =========================================================================
+/// A counter game whose rooms dispose of themselves quickly.
+struct ShortLivedGame;
 
-fn pid(id: u64) -> PlayerId {
-    PlayerId(id)
+impl GameLogic for ShortLivedGame {
+    type Config = CounterConfig;
+    type State = CounterState;
+    type View = CounterState;
+    type ClientMessage = Increment;
+    type ServerMessage = CounterEvent;
+
+    fn init(
+        config: &CounterConfig,
+        ctx: &mut GameContext<'_, CounterEvent>,
+    ) -> CounterState {
+        CounterGame::init(config, ctx)
+    }
+
+    fn handle_message(
+        state: &mut CounterState,
+        sender: PlayerId,
+        msg: Increment,
+        ctx: &mut GameContext<'_, CounterEvent>,
+    ) -> Vec<(Recipient, CounterEvent)> {
+        CounterGame::handle_message(state, sender, msg, ctx)
+    }
+
+    fn view_for(state: &CounterState, _player: PlayerId) -> CounterState {
+        state.clone()
+    }
+
+    fn is_finished(state: &CounterState) -> bool {
+        CounterGame::is_finished(state)
+    }
+
+    fn room_config() -> RoomConfig {
+        RoomConfig {
+            min_players: 2,
+            max_players: 4,
+            disposal: DisposalPolicy {
+                after_finished: Some(Duration::from_millis(50)),
+                when_empty: Some(Duration::from_millis(50)),
+                max_idle: Some(Duration::from_millis(300)),
+            },
+            ..RoomConfig::default()
+        }
+    }
 }
 
-/// Creates a dummy player sender (receiver is dropped immediately).
-fn dummy_sender<G: GameLogic>() -> PlayerSender<G> {
-    mpsc::unbounded_channel().0
+/// A counter game with a time limit.
+#[derive(Debug)]
+struct TimedGame;
+
+impl GameLogic for TimedGame {
+    type Config = CounterConfig;
+    type State = CounterState;
+    type View = CounterState;
+    type ClientMessage = Increment;
+    type ServerMessage = CounterEvent;
+
+    fn init(
+        config: &CounterConfig,
+        ctx: &mut GameContext<'_, CounterEvent>,
+    ) -> CounterState {
+        CounterGame::init(config, ctx)
+    }
+
+    fn handle_message(
+        state: &mut CounterState,
+        sender: PlayerId,
+        msg: Increment,
+        ctx: &mut GameContext<'_, CounterEvent>,
+    ) -> Vec<(Recipient, CounterEvent)> {
+        CounterGame::handle_message(state, sender, msg, ctx)
+    }
+
+    fn view_for(state: &CounterState, _player: PlayerId) -> CounterState {
+        state.clone()
+    }
+
+    fn is_finished(state: &CounterState) -> bool {
+        CounterGame::is_finished(state)
+    }
+
+    /// Player 1 gets credit for every count.
+    fn results(state: &CounterState) -> MatchResult {
+        MatchResult::from_scores([(PlayerId(1), i64::from(state.count))])
+    }
+
+    fn on_timeout(
+        state: &mut CounterState,
+        _ctx: &mut GameContext<'_, CounterEvent>,
+    ) -> Vec<(Recipient, CounterEvent)> {
+        vec![(Recipient::All, CounterEvent::Counted(state.count))]
+    }
+
+    fn room_config() -> RoomConfig {
+        RoomConfig {
+            min_players: 2,
+            max_players: 4,
+            max_duration: Some(Duration::from_millis(100)),
+            ..RoomConfig::default()
+        }
+    }
 }
 
-// =========================================================================
-// RoomManager tests
-// =========================================================================
+/// A counter game with a short ready-check countdown.
+struct PregameGame;
 
-#[tokio::test]
-async fn test_create_room_returns_unique_ids() {
-    let mut mgr = RoomManager::<CounterGame>::new();
-    let r1 = mgr.create_room(CounterConfig::default());
-    let r2 = mgr.create_room(CounterConfig::default());
-    assert_ne!(r1, r2);
-    assert_eq!(mgr.room_count(), 2);
+impl GameLogic for PregameGame {
+    type Config = CounterConfig;
+    type State = CounterState;
+    type View = CounterState;
+    type ClientMessage = Increment;
+    type ServerMessage = CounterEvent;
+
+    fn init(
+        config: &CounterConfig,
+        ctx: &mut GameContext<'_, CounterEvent>,
+    ) -> CounterState {
+        CounterGame::init(config, ctx)
+    }
+
+    fn handle_message(
+        state: &mut CounterState,
+        sender: PlayerId,
+        msg: Increment,
+        ctx: &mut GameContext<'_, CounterEvent>,
+    ) -> Vec<(Recipient, CounterEvent)> {
+        CounterGame::handle_message(state, sender, msg, ctx)
+    }
+
+    fn view_for(state: &CounterState, _player: PlayerId) -> CounterState {
+        state.clone()
+    }
+
+    fn is_finished(state: &CounterState) -> bool {
+        CounterGame::is_finished(state)
+    }
+
+    fn room_config() -> RoomConfig {
+        RoomConfig {
+            min_players: 2,
+            max_players: 4,
+            pregame: Some(PregameConfig {
+                countdown: Duration::from_millis(200),
+                announce_every: Duration::from_millis(50),
+            }),
+            ..RoomConfig::default()
+        }
+    }
 }
 
-#[tokio::test]
-async fn test_join_room_success() {
-    let mut mgr = RoomManager::<CounterGame>::new();
-    let room = mgr.create_room(CounterConfig::default());
+/// A drop-in arena: players can join and leave mid-game.
+#[derive(Debug)]
+struct ArenaGame;
 
-    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct ArenaState {
+    players: Vec<PlayerId>,
+}
 
-    assert_eq!(mgr.player_room(&pid(1)), Some(room));
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+enum ArenaEvent {
+    Spawned(PlayerId),
+    Left(PlayerId),
 }
 
-#[tokio::test]
-async fn test_join_room_not_found() {
-    let mut mgr = RoomManager::<CounterGame>::new();
-    let result = mgr.join_room(pid(1), arcforge_protocol::RoomId(999), dummy_sender()).await;
-    assert!(result.is_err());
+impl GameLogic for ArenaGame {
+    type Config = CounterConfig;
+    type State = ArenaState;
+    type View = ArenaState;
+    type ClientMessage = Increment;
+    type ServerMessage = ArenaEvent;
+
+    fn init(
+        _config: &CounterConfig,
+        ctx: &mut GameContext<'_, ArenaEvent>,
+    ) -> ArenaState {
+        ArenaState {
+            players: ctx.players().to_vec(),
+        }
+    }
+
+    fn handle_message(
+        _state: &mut ArenaState,
+        _sender: PlayerId,
+        _msg: Increment,
+        _ctx: &mut GameContext<'_, ArenaEvent>,
+    ) -> Vec<(Recipient, ArenaEvent)> {
+        Vec::new()
+    }
+
+    fn view_for(state: &ArenaState, _player: PlayerId) -> ArenaState {
+        state.clone()
+    }
+
+    fn is_finished(_state: &ArenaState) -> bool {
+        false
+    }
+
+    fn on_player_join(
+        state: &mut ArenaState,
+        player: PlayerId,
+        _ctx: &mut GameContext<'_, ArenaEvent>,
+    ) -> Vec<(Recipient, ArenaEvent)> {
+        state.players.push(player);
+        vec![(Recipient::AllExcept(player), ArenaEvent::Spawned(player))]
+    }
+
+    fn on_player_leave(
+        state: &mut ArenaState,
+        player: PlayerId,
+        _ctx: &mut GameContext<'_, ArenaEvent>,
+    ) -> Vec<(Recipient, ArenaEvent)> {
+        state.players.retain(|p| *p != player);
+        vec![(Recipient::All, ArenaEvent::Left(player))]
+    }
+
+    fn room_config() -> RoomConfig {
+        RoomConfig {
+            min_players: 2,
+            max_players: 4,
+            allow_late_join: true,
+            ..RoomConfig::default()
+        }
+    }
 }
 
-#[tokio::test]
-async fn test_join_room_one_room_at_a_time() {
-    let mut mgr = RoomManager::<CounterGame>::new();
-    let r1 = mgr.create_room(CounterConfig::default());
-    let r2 = mgr.create_room(CounterConfig::default());
+/// A hidden-information game: every player holds a secret card.
+#[derive(Debug)]
+struct SecretCardGame;
 
-    mgr.join_room(pid(1), r1, dummy_sender()).await.unwrap();
-    let result = mgr.join_room(pid(1), r2, dummy_sender()).await;
-    assert!(result.is_err(), "player should not join two rooms");
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SecretState {
+    /// Each player's card. Must never reach another player.
+    cards: Vec<(PlayerId, u32)>,
 }
 
-#[tokio::test]
-async fn test_join_room_already_in_same_room() {
-    let mut mgr = RoomManager::<CounterGame>::new();
-    let room = mgr.create_room(CounterConfig::default());
+/// What one player sees: their own card and how many others play.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct SecretView {
+    my_card: Option<u32>,
+    opponents: usize,
+}
 
-    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
-    let result = mgr.join_room(pid(1), room, dummy_sender()).await;
-    assert!(result.is_err());
+/// Deals a card that's easy to spot in serialized output.
+fn card_for(player: PlayerId) -> u32 {
+    7_000_000 + player.0 as u32
 }
 
-#[tokio::test]
-async fn test_join_room_full() {
-    let mut mgr = RoomManager::<CounterGame>::new();
-    let room = mgr.create_room(CounterConfig::default());
+impl GameLogic for SecretCardGame {
+    type Config = CounterConfig;
+    type State = SecretState;
+    type View = SecretView;
+    type ClientMessage = Increment;
+    type ServerMessage = CounterEvent;
 
-    // min_players is 2, max is 4. After 2 join, game auto-starts
-    // and no more joins are allowed (room is InProgress).
-    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
-    mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
+    fn init(
+        _config: &CounterConfig,
+        ctx: &mut GameContext<'_, CounterEvent>,
+    ) -> SecretState {
+        SecretState {
+            cards: ctx.players().iter().map(|p| (*p, card_for(*p))).collect(),
+        }
+    }
 
-    // 3rd player can't join — game already started
-    let result = mgr.join_room(pid(3), room, dummy_sender()).await;
-    assert!(result.is_err(), "should not join a running game");
+    fn handle_message(
+        _state: &mut SecretState,
+        _sender: PlayerId,
+        _msg: Increment,
+        _ctx: &mut GameContext<'_, CounterEvent>,
+    ) -> Vec<(Recipient, CounterEvent)> {
+        Vec::new()
+    }
+
+    fn is_finished(_state: &SecretState) -> bool {
+        false
+    }
+
+    fn on_player_join(
+        state: &mut SecretState,
+        player: PlayerId,
+        _ctx: &mut GameContext<'_, CounterEvent>,
+    ) -> Vec<(Recipient, CounterEvent)> {
+        state.cards.push((player, card_for(player)));
+        Vec::new()
+    }
+
+    fn view_for(state: &SecretState, player: PlayerId) -> SecretView {
+        SecretView {
+            my_card: state
+                .cards
+                .iter()
+                .find(|(p, _)| *p == player)
+                .map(|(_, card)| *card),
+            opponents: state.cards.len() - 1,
+        }
+    }
+
+    fn room_config() -> RoomConfig {
+        RoomConfig {
+            min_players: 2,
+            max_players: 4,
+            allow_late_join: true,
+            ..RoomConfig::default()
+        }
+    }
 }
 
-#[tokio::test]
-async fn test_join_room_at_max_capacity() {
-    // FullGame has min_players=4, max_players=4.
-    // Fill all 4 slots, then try a 5th.
-    let mut mgr = RoomManager::<FullGame>::new();
-    let room = mgr.create_room(CounterConfig::default());
+/// A counter game with fast continuous state sync.
+struct SyncGame;
 
-    for i in 1..=4 {
-        mgr.join_room(pid(i), room, dummy_sender()).await.unwrap();
+impl GameLogic for SyncGame {
+    type Config = CounterConfig;
+    type State = CounterState;
+    type View = CounterState;
+    type ClientMessage = Increment;
+    type ServerMessage = CounterEvent;
+
+    fn init(
+        config: &CounterConfig,
+        ctx: &mut GameContext<'_, CounterEvent>,
+    ) -> CounterState {
+        CounterGame::init(config, ctx)
+    }
+
+    fn handle_message(
+        state: &mut CounterState,
+        sender: PlayerId,
+        msg: Increment,
+        ctx: &mut GameContext<'_, CounterEvent>,
+    ) -> Vec<(Recipient, CounterEvent)> {
+        CounterGame::handle_message(state, sender, msg, ctx)
+    }
+
+    fn view_for(state: &CounterState, _player: PlayerId) -> CounterState {
+        state.clone()
+    }
+
+    fn is_finished(state: &CounterState) -> bool {
+        CounterGame::is_finished(state)
+    }
+
+    fn room_config() -> RoomConfig {
+        RoomConfig {
+            min_players: 2,
+            max_players: 4,
+            state_sync: Some(StateSyncConfig {
+                interval: Duration::from_millis(20),
+                keyframe_every: 1000,
+            }),
+            ..RoomConfig::default()
+        }
     }
-    // Room is now full AND game started
-    let result = mgr.join_room(pid(5), room, dummy_sender()).await;
-    assert!(result.is_err(), "room should reject 5th player");
 }
 
-#[tokio::test]
-async fn test_leave_room_success() {
-    let mut mgr = RoomManager::<CounterGame>::new();
-    let room = mgr.create_room(CounterConfig::default());
-    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+// =========================================================================
+// Mock game: a ticking game that reports every input batch it receives.
+// =========================================================================
 
-    mgr.leave_room(pid(1)).await.unwrap();
+#[derive(Debug)]
+struct TickGame;
 
-    assert_eq!(mgr.player_room(&pid(1)), None);
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct TickState {
+    ticks: u64,
 }
 
-#[tokio::test]
-async fn test_leave_room_not_in_any_room() {
-    let mut mgr = RoomManager::<CounterGame>::new();
-    let result = mgr.leave_room(pid(1)).await;
-    assert!(result.is_err());
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+enum TickEvent {
+    /// The inputs for one tick, as `(player, value, repeated)`.
+    Batch { tick: u64, inputs: Vec<(u64, u32, bool)> },
+    /// `handle_message` ran, which buffered games should never do.
+    Immediate,
 }
 
-#[tokio::test]
-async fn test_get_room_info() {
-    let mut mgr = RoomManager::<CounterGame>::new();
-    let room = mgr.create_room(CounterConfig::default());
-    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+impl GameLogic for TickGame {
+    type Config = ();
+    type State = TickState;
+    type View = TickState;
+    type ClientMessage = u32;
+    type ServerMessage = TickEvent;
+
+    fn init(
+        _config: &(),
+        _ctx: &mut GameContext<'_, TickEvent>,
+    ) -> TickState {
+        TickState::default()
+    }
+
+    fn handle_message(
+        _state: &mut TickState,
+        _sender: PlayerId,
+        _msg: u32,
+        _ctx: &mut GameContext<'_, TickEvent>,
+    ) -> Vec<(Recipient, TickEvent)> {
+        vec![(Recipient::All, TickEvent::Immediate)]
+    }
+
+    fn validate_message(
+        _state: &TickState,
+        _sender: PlayerId,
+        msg: &u32,
+    ) -> Result<(), String> {
+        if *msg == u32::MAX {
+            return Err("out of range".into());
+        }
+        Ok(())
+    }
+
+    fn tick(
+        state: &mut TickState,
+        _dt: Duration,
+        inputs: Vec<PlayerInput<u32>>,
+        _ctx: &mut GameContext<'_, TickEvent>,
+    ) -> Vec<(Recipient, TickEvent)> {
+        state.ticks += 1;
+        if inputs.is_empty() {
+            return Vec::new();
+        }
+        let inputs = inputs
+            .into_iter()
+            .map(|i| (i.player.0, i.input, i.repeated))
+            .collect();
+        vec![(
+            Recipient::All,
+            TickEvent::Batch {
+                tick: state.ticks,
+                inputs,
+            },
+        )]
+    }
+
+    fn view_for(state: &TickState, _player: PlayerId) -> TickState {
+        state.clone()
+    }
+
+    fn is_finished(_state: &TickState) -> bool {
+        false
+    }
+
+    fn room_config() -> RoomConfig {
+        RoomConfig {
+            min_players: 2,
+            max_players: 2,
+            tick_rate: 20,
+            input_buffer: Some(InputBufferConfig::default()),
+            ..RoomConfig::default()
+        }
+    }
+}
+
+/// A lockstep variant: late inputs are dropped and missing inputs are
+/// filled with the player's previous one.
+struct LockstepGame;
+
+impl GameLogic for LockstepGame {
+    type Config = ();
+    type State = TickState;
+    type View = TickState;
+    type ClientMessage = u32;
+    type ServerMessage = TickEvent;
+
+    fn init(
+        config: &(),
+        ctx: &mut GameContext<'_, TickEvent>,
+    ) -> TickState {
+        TickGame::init(config, ctx)
+    }
+
+    fn handle_message(
+        state: &mut TickState,
+        sender: PlayerId,
+        msg: u32,
+        ctx: &mut GameContext<'_, TickEvent>,
+    ) -> Vec<(Recipient, TickEvent)> {
+        TickGame::handle_message(state, sender, msg, ctx)
+    }
+
+    fn tick(
+        state: &mut TickState,
+        dt: Duration,
+        inputs: Vec<PlayerInput<u32>>,
+        ctx: &mut GameContext<'_, TickEvent>,
+    ) -> Vec<(Recipient, TickEvent)> {
+        TickGame::tick(state, dt, inputs, ctx)
+    }
+
+    fn view_for(state: &TickState, _player: PlayerId) -> TickState {
+        state.clone()
+    }
+
+    fn is_finished(state: &TickState) -> bool {
+        TickGame::is_finished(state)
+    }
+
+    fn room_config() -> RoomConfig {
+        RoomConfig {
+            input_buffer: Some(InputBufferConfig {
+                late_inputs: LateInputPolicy::Drop,
+                repeat_last: true,
+                ..InputBufferConfig::default()
+            }),
+            ..TickGame::room_config()
+        }
+    }
+}
+
+// =========================================================================
+// Mock game: hitscan shots judged against lag-compensated state.
+// =========================================================================
+
+#[derive(Debug)]
+struct HitscanGame;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Shot {
+    /// Tick of the state the shooter saw.
+    seen: Option<u64>,
+    /// Tick the server was on when the shot arrived.
+    current: u64,
+}
+
+impl GameLogic for HitscanGame {
+    type Config = ();
+    type State = TickState;
+    type View = TickState;
+    type ClientMessage = ();
+    type ServerMessage = Shot;
+
+    fn init(
+        _config: &(),
+        _ctx: &mut GameContext<'_, Shot>,
+    ) -> TickState {
+        TickState::default()
+    }
+
+    fn handle_message(
+        _state: &mut TickState,
+        _sender: PlayerId,
+        _msg: (),
+        _ctx: &mut GameContext<'_, Shot>,
+    ) -> Vec<(Recipient, Shot)> {
+        unreachable!("lag-compensated rooms use handle_compensated_message")
+    }
+
+    fn handle_compensated_message(
+        state: &mut TickState,
+        sender: PlayerId,
+        _msg: (),
+        lag: &LagCompensation<'_, TickState>,
+        _ctx: &mut GameContext<'_, Shot>,
+    ) -> Vec<(Recipient, Shot)> {
+        assert_eq!(lag.current_tick(), state.ticks);
+        let shot = Shot {
+            seen: lag.sender_view().map(|s| s.ticks),
+            current: lag.current_tick(),
+        };
+        vec![(Recipient::Player(sender), shot)]
+    }
+
+    fn tick(
+        state: &mut TickState,
+        _dt: Duration,
+        _inputs: Vec<PlayerInput<()>>,
+        _ctx: &mut GameContext<'_, Shot>,
+    ) -> Vec<(Recipient, Shot)> {
+        state.ticks += 1;
+        Vec::new()
+    }
+
+    fn view_for(state: &TickState, _player: PlayerId) -> TickState {
+        state.clone()
+    }
+
+    fn is_finished(_state: &TickState) -> bool {
+        false
+    }
+
+    fn room_config() -> RoomConfig {
+        RoomConfig {
+            min_players: 2,
+            max_players: 2,
+            tick_rate: 20,
+            lag_compensation: Some(LagCompensationConfig {
+                max_rewind: Duration::from_millis(250),
+                ..LagCompensationConfig::default()
+            }),
+            ..RoomConfig::default()
+        }
+    }
+}
+
+// =========================================================================
+// Mock game: dice rolls sent through the game context.
+// =========================================================================
+
+#[derive(Debug)]
+struct DiceGame;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+enum DiceEvent {
+    Welcome { room: RoomId, players: Vec<PlayerId> },
+    Rolled(u32),
+    /// Returned from `handle_message` rather than sent via the context.
+    Returned,
+    Reminder,
+}
+
+impl GameLogic for DiceGame {
+    type Config = ();
+    type State = TickState;
+    type View = TickState;
+    type ClientMessage = ();
+    type ServerMessage = DiceEvent;
+
+    fn init(_config: &(), ctx: &mut GameContext<'_, DiceEvent>) -> TickState {
+        let welcome = DiceEvent::Welcome {
+            room: ctx.room_id(),
+            players: ctx.players().to_vec(),
+        };
+        for player in ctx.players().to_vec() {
+            ctx.send_to(player, welcome.clone());
+        }
+        TickState::default()
+    }
+
+    fn handle_message(
+        _state: &mut TickState,
+        sender: PlayerId,
+        _msg: (),
+        ctx: &mut GameContext<'_, DiceEvent>,
+    ) -> Vec<(Recipient, DiceEvent)> {
+        let roll = ctx.rng().random_range(1..=6);
+        ctx.broadcast(DiceEvent::Rolled(roll));
+        ctx.send_after(
+            Duration::from_millis(50),
+            Recipient::Player(sender),
+            DiceEvent::Reminder,
+        );
+        vec![(Recipient::All, DiceEvent::Returned)]
+    }
+
+    fn view_for(state: &TickState, _player: PlayerId) -> TickState {
+        state.clone()
+    }
+
+    fn is_finished(_state: &TickState) -> bool {
+        false
+    }
+
+    fn room_config() -> RoomConfig {
+        RoomConfig {
+            min_players: 2,
+            max_players: 2,
+            ..RoomConfig::default()
+        }
+    }
+}
+
+// =========================================================================
+// Mock game: named timers driven by client commands.
+// =========================================================================
+
+#[derive(Debug)]
+struct TimerGame;
+
+#[derive(Clone, Serialize, Deserialize)]
+enum TimerCmd {
+    Set(String, u64),
+    Cancel(String),
+    Pause,
+    Resume,
+    /// Replies with `GameContext::elapsed`, in milliseconds.
+    Elapsed,
+}
+
+impl GameLogic for TimerGame {
+    type Config = ();
+    type State = TickState;
+    type View = TickState;
+    type ClientMessage = TimerCmd;
+    type ServerMessage = String;
+
+    fn init(_config: &(), _ctx: &mut GameContext<'_, String>) -> TickState {
+        TickState::default()
+    }
+
+    fn handle_message(
+        _state: &mut TickState,
+        _sender: PlayerId,
+        msg: TimerCmd,
+        ctx: &mut GameContext<'_, String>,
+    ) -> Vec<(Recipient, String)> {
+        match msg {
+            TimerCmd::Set(id, ms) => {
+                ctx.set_timer(id, Duration::from_millis(ms))
+            }
+            TimerCmd::Cancel(id) => ctx.cancel_timer(id),
+            TimerCmd::Pause => ctx.pause(),
+            TimerCmd::Resume => ctx.resume(),
+            TimerCmd::Elapsed => {
+                let ms = ctx.elapsed().as_millis();
+                return vec![(Recipient::All, ms.to_string())];
+            }
+        }
+        Vec::new()
+    }
+
+    fn on_timer(
+        _state: &mut TickState,
+        timer_id: &str,
+        _ctx: &mut GameContext<'_, String>,
+    ) -> Vec<(Recipient, String)> {
+        vec![(Recipient::All, timer_id.to_string())]
+    }
+
+    fn view_for(state: &TickState, _player: PlayerId) -> TickState {
+        state.clone()
+    }
+
+    fn is_finished(_state: &TickState) -> bool {
+        false
+    }
+
+    fn room_config() -> RoomConfig {
+        RoomConfig {
+            min_players: 2,
+            max_players: 2,
+            ..RoomConfig::default()
+        }
+    }
+}
+
+// =========================================================================
+// Mock game: adds up numbers, and panics on 13.
+// =========================================================================
+
+#[derive(Debug)]
+struct PanicGame;
+
+impl GameLogic for PanicGame {
+    type Config = ();
+    type State = u32;
+    type View = u32;
+    type ClientMessage = u32;
+    type ServerMessage = u32;
+
+    fn init(_config: &(), _ctx: &mut GameContext<'_, u32>) -> u32 {
+        0
+    }
+
+    fn handle_message(
+        state: &mut u32,
+        _sender: PlayerId,
+        msg: u32,
+        _ctx: &mut GameContext<'_, u32>,
+    ) -> Vec<(Recipient, u32)> {
+        *state += msg;
+        assert_ne!(msg, 13, "unlucky number");
+        vec![(Recipient::All, *state)]
+    }
+
+    fn view_for(state: &u32, _player: PlayerId) -> u32 {
+        *state
+    }
+
+    fn is_finished(_state: &u32) -> bool {
+        false
+    }
+
+    fn room_config() -> RoomConfig {
+        RoomConfig {
+            min_players: 2,
+            max_players: 2,
+            ..RoomConfig::default()
+        }
+    }
+}
+
+/// [`PanicGame`], but the room restores its state after a panic. It
+/// also panics when a player leaves with the total at 7.
+#[derive(Debug)]
+struct ForgivingGame;
+
+impl GameLogic for ForgivingGame {
+    type Config = ();
+    type State = u32;
+    type View = u32;
+    type ClientMessage = u32;
+    type ServerMessage = u32;
+
+    fn init(config: &(), ctx: &mut GameContext<'_, u32>) -> u32 {
+        PanicGame::init(config, ctx)
+    }
+
+    fn handle_message(
+        state: &mut u32,
+        sender: PlayerId,
+        msg: u32,
+        ctx: &mut GameContext<'_, u32>,
+    ) -> Vec<(Recipient, u32)> {
+        PanicGame::handle_message(state, sender, msg, ctx)
+    }
+
+    fn on_player_leave(
+        state: &mut u32,
+        _player: PlayerId,
+        _ctx: &mut GameContext<'_, u32>,
+    ) -> Vec<(Recipient, u32)> {
+        assert_ne!(*state, 7, "unlucky leave");
+        Vec::new()
+    }
+
+    fn view_for(state: &u32, _player: PlayerId) -> u32 {
+        *state
+    }
+
+    fn is_finished(state: &u32) -> bool {
+        PanicGame::is_finished(state)
+    }
+
+    fn room_config() -> RoomConfig {
+        RoomConfig {
+            on_panic: PanicPolicy::RestoreLastState,
+            ..PanicGame::room_config()
+        }
+    }
+}
+
+// =========================================================================
+// Mock game: purchases checked against a (slow) store service.
+// =========================================================================
+
+#[derive(Debug)]
+struct ShopGame;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Buy {
+    item: String,
+    /// How long the store takes to answer.
+    delay_ms: u64,
+}
+
+fn buy(item: &str, delay_ms: u64) -> Buy {
+    Buy { item: item.to_string(), delay_ms }
+}
+
+impl GameLogic for ShopGame {
+    type Config = ();
+    type State = Vec<String>;
+    type View = Vec<String>;
+    type ClientMessage = Buy;
+    type ServerMessage = String;
+
+    fn init(_config: &(), _ctx: &mut GameContext<'_, String>) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn handle_message(
+        state: &mut Vec<String>,
+        _sender: PlayerId,
+        msg: Buy,
+        _ctx: &mut GameContext<'_, String>,
+    ) -> Vec<(Recipient, String)> {
+        state.push(msg.item.clone());
+        vec![(Recipient::All, msg.item)]
+    }
+
+    fn view_for(state: &Vec<String>, _player: PlayerId) -> Vec<String> {
+        state.clone()
+    }
+
+    fn is_finished(_state: &Vec<String>) -> bool {
+        false
+    }
+
+    fn room_config() -> RoomConfig {
+        RoomConfig {
+            min_players: 2,
+            max_players: 2,
+            async_hooks: AsyncHookConfig {
+                timeout: Duration::from_millis(200),
+                max_queued: 3,
+            },
+            ..RoomConfig::default()
+        }
+    }
+}
+
+impl AsyncGameLogic for ShopGame {
+    fn prepare_message(
+        state: &Vec<String>,
+        _sender: PlayerId,
+        msg: Buy,
+    ) -> impl Future<Output = Result<Buy, String>> + Send + 'static {
+        let already_owned = state.contains(&msg.item);
+        async move {
+            tokio::time::sleep(Duration::from_millis(msg.delay_ms)).await;
+            match msg.item.as_str() {
+                "cursed" => panic!("the store is on fire"),
+                "contraband" => Err("not for sale".to_string()),
+                _ if already_owned => Err("already owned".to_string()),
+                _ => Ok(msg),
+            }
+        }
+    }
+}
+
+// =========================================================================
+// Mock game: a shared counter that remembers who was seated at the start,
+// with bots that add one whenever they see the state. Times out after
+// 300ms.
+// =========================================================================
+
+#[derive(Debug)]
+struct SeatGame;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SeatState {
+    seated: Vec<PlayerId>,
+    count: u32,
+}
+
+impl GameLogic for SeatGame {
+    type Config = ();
+    type State = SeatState;
+    type View = SeatState;
+    type ClientMessage = Increment;
+    type ServerMessage = CounterEvent;
+
+    fn init(
+        _config: &(),
+        ctx: &mut GameContext<'_, CounterEvent>,
+    ) -> SeatState {
+        SeatState {
+            seated: ctx.players().to_vec(),
+            count: 0,
+        }
+    }
+
+    fn handle_message(
+        state: &mut SeatState,
+        _sender: PlayerId,
+        _msg: Increment,
+        _ctx: &mut GameContext<'_, CounterEvent>,
+    ) -> Vec<(Recipient, CounterEvent)> {
+        state.count += 1;
+        vec![(Recipient::All, CounterEvent::Counted(state.count))]
+    }
+
+    fn view_for(state: &SeatState, _player: PlayerId) -> SeatState {
+        state.clone()
+    }
+
+    fn is_finished(_state: &SeatState) -> bool {
+        false
+    }
+
+    fn room_config() -> RoomConfig {
+        RoomConfig {
+            min_players: 2,
+            max_players: 2,
+            reconnect_grace: Duration::from_millis(50),
+            max_duration: Some(Duration::from_millis(300)),
+            bots: Some(BotConfig {
+                fill_after: Some(Duration::from_millis(50)),
+                replace_disconnected: true,
+            }),
+            ..RoomConfig::default()
+        }
+    }
+}
+
+struct EagerBot;
+
+impl Bot<SeatGame> for EagerBot {
+    fn on_state(&mut self, _view: &SeatState) -> Vec<Increment> {
+        vec![Increment]
+    }
+}
+
+fn seat_manager() -> RoomManager<SeatGame> {
+    RoomManager::new().with_bots(|_| Box::new(EagerBot))
+}
+
+// =========================================================================
+// Mock game: two teams of two. Each message is shown to the sender's
+// team and announced to the other team; team 0 wins after three.
+// =========================================================================
+
+struct TeamGame;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct TeamState {
+    /// Each team's players, as `init` saw them.
+    teams: Vec<Vec<PlayerId>>,
+    count: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+enum TeamEvent {
+    Teammate(PlayerId),
+    Opponent,
+}
+
+impl GameLogic for TeamGame {
+    type Config = ();
+    type State = TeamState;
+    type View = TeamState;
+    type ClientMessage = Increment;
+    type ServerMessage = TeamEvent;
+
+    fn init(_config: &(), ctx: &mut GameContext<'_, TeamEvent>) -> TeamState {
+        TeamState {
+            teams: ctx.teams().iter().map(|t| t.players.clone()).collect(),
+            count: 0,
+        }
+    }
+
+    fn handle_message(
+        state: &mut TeamState,
+        sender: PlayerId,
+        _msg: Increment,
+        ctx: &mut GameContext<'_, TeamEvent>,
+    ) -> Vec<(Recipient, TeamEvent)> {
+        state.count += 1;
+        let team = ctx.team_of(sender).expect("everyone has a team");
+        let opponents = ctx
+            .teams()
+            .iter()
+            .filter(|t| t.id != team)
+            .flat_map(|t| t.players.clone())
+            .collect();
+        vec![
+            (Recipient::Team(team), TeamEvent::Teammate(sender)),
+            (Recipient::Players(opponents), TeamEvent::Opponent),
+        ]
+    }
+
+    fn view_for(state: &TeamState, _player: PlayerId) -> TeamState {
+        state.clone()
+    }
+
+    fn is_finished(state: &TeamState) -> bool {
+        state.count >= 3
+    }
+
+    fn results(state: &TeamState) -> MatchResult {
+        MatchResult::from_scores(state.teams.iter().enumerate().flat_map(
+            |(i, team)| team.iter().map(move |p| (*p, i64::from(i == 0))),
+        ))
+    }
+
+    fn room_config() -> RoomConfig {
+        RoomConfig {
+            min_players: 4,
+            max_players: 4,
+            teams: Some(TeamConfig { count: 2, size: 2 }),
+            ..RoomConfig::default()
+        }
+    }
+}
+
+/// The teams a player's first state snapshot shows.
+fn teams_seen(
+    rx: &mut mpsc::UnboundedReceiver<RoomOutbound<TeamGame>>,
+) -> Vec<Vec<u64>> {
+    while let Ok(outbound) = rx.try_recv() {
+        if let RoomOutbound::State(state) = outbound {
+            return state
+                .teams
+                .iter()
+                .map(|team| team.iter().map(|p| p.0).collect())
+                .collect();
+        }
+    }
+    panic!("no state snapshot");
+}
+
+// =========================================================================
+// Helper
+// =========================================================================
+
+fn pid(id: u64) -> PlayerId {
+    PlayerId(id)
+}
+
+/// Collects the system messages waiting in a player's channel.
+fn drain_system<G: GameLogic>(
+    rx: &mut mpsc::UnboundedReceiver<RoomOutbound<G>>,
+) -> Vec<SystemMessage> {
+    let mut msgs = Vec::new();
+    while let Ok(outbound) = rx.try_recv() {
+        if let RoomOutbound::System(msg) = outbound {
+            msgs.push(msg);
+        }
+    }
+    msgs
+}
+
+/// Creates a dummy player sender (receiver is dropped immediately).
+fn dummy_sender<G: GameLogic>() -> PlayerSender<G> {
+    mpsc::unbounded_channel().0
+}
+
+// =========================================================================
+// RoomManager tests
+// =========================================================================
+
+#[tokio::test]
+async fn test_create_room_returns_unique_ids() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let r1 = mgr.create_room(CounterConfig::default());
+    let r2 = mgr.create_room(CounterConfig::default());
+    assert_ne!(r1, r2);
+    assert_eq!(mgr.room_count(), 2);
+}
+
+#[tokio::test]
+async fn test_join_room_success() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let room = mgr.create_room(CounterConfig::default());
+
+    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+
+    assert_eq!(mgr.player_room(&pid(1)), Some(room));
+}
+
+#[tokio::test]
+async fn test_join_room_not_found() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let result = mgr.join_room(pid(1), arcforge_protocol::RoomId(999), dummy_sender()).await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_join_room_one_room_at_a_time() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let r1 = mgr.create_room(CounterConfig::default());
+    let r2 = mgr.create_room(CounterConfig::default());
+
+    mgr.join_room(pid(1), r1, dummy_sender()).await.unwrap();
+    let result = mgr.join_room(pid(1), r2, dummy_sender()).await;
+    assert!(result.is_err(), "player should not join two rooms");
+}
+
+#[tokio::test]
+async fn test_join_room_already_in_same_room() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let room = mgr.create_room(CounterConfig::default());
+
+    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+    let result = mgr.join_room(pid(1), room, dummy_sender()).await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_join_room_full() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let room = mgr.create_room(CounterConfig::default());
+
+    // min_players is 2, max is 4. After 2 join, game auto-starts
+    // and no more joins are allowed (room is InProgress).
+    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+    mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
+
+    // 3rd player can't join — game already started
+    let result = mgr.join_room(pid(3), room, dummy_sender()).await;
+    assert!(result.is_err(), "should not join a running game");
+}
+
+#[tokio::test]
+async fn test_join_room_at_max_capacity() {
+    // FullGame has min_players=4, max_players=4.
+    // Fill all 4 slots, then try a 5th.
+    let mut mgr = RoomManager::<FullGame>::new();
+    let room = mgr.create_room(CounterConfig::default());
+
+    for i in 1..=4 {
+        mgr.join_room(pid(i), room, dummy_sender()).await.unwrap();
+    }
+    // Room is now full AND game started
+    let result = mgr.join_room(pid(5), room, dummy_sender()).await;
+    assert!(result.is_err(), "room should reject 5th player");
+}
+
+#[tokio::test]
+async fn test_join_room_together_seats_the_whole_group() {
+    let mut mgr = RoomManager::<FullGame>::new();
+    let room = mgr.create_room(CounterConfig::default());
+
+    let group = (1..=4).map(|i| (pid(i), dummy_sender())).collect();
+    mgr.join_room_together(group, room, JoinCredentials::default())
+        .await
+        .unwrap();
+
+    for i in 1..=4 {
+        assert_eq!(mgr.player_room(&pid(i)), Some(room));
+    }
+    let info = mgr.get_room_info(room).await.unwrap();
+    assert_eq!(info.player_count, 4);
+    assert_eq!(info.state, RoomState::InProgress);
+}
+
+#[tokio::test]
+async fn test_join_room_together_is_all_or_nothing() {
+    let mut mgr = RoomManager::<FullGame>::new();
+    let room = mgr.create_room(CounterConfig::default());
+    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+
+    // Four more don't fit next to player 1: nobody gets in.
+    let group = (2..=5).map(|i| (pid(i), dummy_sender())).collect();
+    let result = mgr
+        .join_room_together(group, room, JoinCredentials::default())
+        .await;
+    assert!(matches!(result, Err(RoomError::RoomFull(_))));
+    assert_eq!(mgr.player_room(&pid(2)), None);
+    let info = mgr.get_room_info(room).await.unwrap();
+    assert_eq!(info.player_count, 1);
+
+    // A group with someone already in a room is refused too.
+    let group = vec![(pid(1), dummy_sender()), (pid(2), dummy_sender())];
+    let result = mgr
+        .join_room_together(group, room, JoinCredentials::default())
+        .await;
+    assert!(result.is_err());
+    assert_eq!(mgr.player_room(&pid(2)), None);
+}
+
+#[tokio::test]
+async fn test_join_or_create_together_needs_a_seat_for_everyone() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let first = mgr
+        .join_or_create(pid(1), CounterConfig::default(), dummy_sender())
+        .await
+        .unwrap();
+
+    // Three of four seats are free, so a group of four gets a new room.
+    let group = (2..=5).map(|i| (pid(i), dummy_sender())).collect();
+    let second = mgr
+        .join_or_create_together(group, CounterConfig::default())
+        .await
+        .unwrap();
+    assert_ne!(first, second);
+    for i in 2..=5 {
+        assert_eq!(mgr.player_room(&pid(i)), Some(second));
+    }
+
+    // A group of three still fits in the first room.
+    let group = (6..=8).map(|i| (pid(i), dummy_sender())).collect();
+    let room = mgr
+        .join_or_create_together(group, CounterConfig::default())
+        .await
+        .unwrap();
+    assert_eq!(room, first);
+    assert_eq!(mgr.room_count(), 2);
+}
+
+#[tokio::test]
+async fn test_private_room_seats_a_group_by_code() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let hosts = vec![(pid(1), dummy_sender()), (pid(2), dummy_sender())];
+    let options = RoomOptions {
+        private: true,
+        ..Default::default()
+    };
+    let room = mgr
+        .create_and_join_together(hosts, CounterConfig::default(), options)
+        .await
+        .unwrap();
+    let code = mgr.invite_code(room).unwrap().to_lowercase();
+
+    // Three more don't fit: nobody joins.
+    let group = (3..=5).map(|i| (pid(i), dummy_sender())).collect();
+    let result = mgr.join_by_code_together(group, &code, None).await;
+    assert!(matches!(result, Err(RoomError::RoomFull(_))));
+    assert_eq!(mgr.player_room(&pid(3)), None);
+
+    let group = vec![(pid(3), dummy_sender()), (pid(4), dummy_sender())];
+    let joined = mgr.join_by_code_together(group, &code, None).await;
+    assert_eq!(joined.unwrap(), room);
+    let info = mgr.get_room_info(room).await.unwrap();
+    assert_eq!(info.player_count, 4);
+}
+
+#[tokio::test]
+async fn test_leave_room_success() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let room = mgr.create_room(CounterConfig::default());
+    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+
+    mgr.leave_room(pid(1)).await.unwrap();
+
+    assert_eq!(mgr.player_room(&pid(1)), None);
+}
+
+#[tokio::test]
+async fn test_leave_room_not_in_any_room() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let result = mgr.leave_room(pid(1)).await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_get_room_info() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let room = mgr.create_room(CounterConfig::default());
+    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+
+    let info = mgr.get_room_info(room).await.unwrap();
+
+    assert_eq!(info.room_id, room);
+    assert_eq!(info.player_count, 1);
+    assert_eq!(info.max_players, 4);
+    assert_eq!(info.state, RoomState::WaitingForPlayers);
+}
+
+#[tokio::test]
+async fn test_auto_start_when_min_players_reached() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let room = mgr.create_room(CounterConfig::default());
+
+    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+    let info = mgr.get_room_info(room).await.unwrap();
+    assert_eq!(info.state, RoomState::WaitingForPlayers);
+
+    // min_players is 2 — joining second player should auto-start
+    mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
+    let info = mgr.get_room_info(room).await.unwrap();
+    assert_eq!(info.state, RoomState::InProgress);
+}
+
+#[tokio::test]
+async fn test_cannot_join_after_game_started() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let room = mgr.create_room(CounterConfig::default());
+    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+    mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
+    // Game is now InProgress
+
+    let result = mgr.join_room(pid(3), room, dummy_sender()).await;
+    assert!(result.is_err(), "should not join a running game");
+}
+
+#[tokio::test]
+async fn test_route_message() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let room = mgr.create_room(CounterConfig { finish_at: 100, ..Default::default() });
+    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+    mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
+
+    // Game is InProgress, send a message
+    mgr.route_message(pid(1), Increment).await.unwrap();
+
+    // Give the actor a moment to process
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let info = mgr.get_room_info(room).await.unwrap();
+    assert_eq!(info.state, RoomState::InProgress);
+}
+
+#[tokio::test]
+async fn test_route_message_not_in_room() {
+    let mgr = RoomManager::<CounterGame>::new();
+    let result = mgr.route_message(pid(1), Increment).await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_destroy_room() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let room = mgr.create_room(CounterConfig::default());
+    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+
+    mgr.destroy_room(room).await.unwrap();
+
+    assert_eq!(mgr.room_count(), 0);
+    assert_eq!(mgr.player_room(&pid(1)), None);
+}
+
+#[tokio::test]
+async fn test_destroy_room_not_found() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let result = mgr.destroy_room(arcforge_protocol::RoomId(999)).await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_room_ids() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let r1 = mgr.create_room(CounterConfig::default());
+    let r2 = mgr.create_room(CounterConfig::default());
+
+    let mut ids = mgr.room_ids();
+    ids.sort_by_key(|r| r.0);
+    assert_eq!(ids.len(), 2);
+    assert!(ids.contains(&r1));
+    assert!(ids.contains(&r2));
+}
+
+#[tokio::test]
+async fn test_game_finishes_on_target() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let room = mgr.create_room(CounterConfig { finish_at: 2, ..Default::default() });
+    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+    mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
+
+    // Send 2 increments to reach the target
+    mgr.route_message(pid(1), Increment).await.unwrap();
+    mgr.route_message(pid(1), Increment).await.unwrap();
+
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let info = mgr.get_room_info(room).await.unwrap();
+    assert_eq!(info.state, RoomState::Finished);
+}
+
+#[tokio::test]
+async fn test_finished_game_reports_end_reason() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let room = mgr.create_room(CounterConfig { finish_at: 1, ..Default::default() });
+    let (tx1, mut rx1) = mpsc::unbounded_channel();
+    mgr.join_room(pid(1), room, tx1).await.unwrap();
+    mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
+    drain_system(&mut rx1);
+
+    mgr.route_message(pid(1), Increment).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    assert_eq!(
+        drain_system(&mut rx1),
+        vec![SystemMessage::GameEnded {
+            reason: EndReason::Completed,
+            result: MatchResult::default(),
+        }]
+    );
+}
+
+/// Plays a two-player counter game to the end and returns the room with
+/// player 1's channel.
+async fn finished_room(
+    mgr: &mut RoomManager<CounterGame>,
+) -> (RoomId, mpsc::UnboundedReceiver<RoomOutbound<CounterGame>>) {
+    let room = mgr.create_room(CounterConfig { finish_at: 2, ..Default::default() });
+    let (tx1, mut rx1) = mpsc::unbounded_channel();
+    mgr.join_room(pid(1), room, tx1).await.unwrap();
+    mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
+    mgr.route_message(pid(1), Increment).await.unwrap();
+    mgr.route_message(pid(2), Increment).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert_eq!(
+        mgr.get_room_info(room).await.unwrap().state,
+        RoomState::Finished
+    );
+    while rx1.try_recv().is_ok() {}
+    (room, rx1)
+}
+
+#[tokio::test]
+async fn test_rematch_restarts_game_in_same_room() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let (room, mut rx1) = finished_room(&mut mgr).await;
+
+    mgr.set_rematch(pid(1), true).await.unwrap();
+    assert_eq!(
+        mgr.get_room_info(room).await.unwrap().state,
+        RoomState::Finished
+    );
+    mgr.set_rematch(pid(2), true).await.unwrap();
+    assert_eq!(
+        mgr.get_room_info(room).await.unwrap().state,
+        RoomState::InProgress
+    );
+    assert_eq!(mgr.player_room(&pid(1)), Some(room));
+
+    let votes: Vec<_> = std::iter::from_fn(|| rx1.try_recv().ok()).collect();
+    assert!(
+        matches!(
+            votes.as_slice(),
+            [
+                RoomOutbound::System(SystemMessage::RematchChanged {
+                    rematch: true,
+                    ..
+                }),
+                RoomOutbound::System(SystemMessage::RematchChanged {
+                    rematch: true,
+                    ..
+                }),
+                RoomOutbound::State(CounterState { count: 0, .. }),
+            ]
+        ),
+        "got {votes:?}"
+    );
+
+    // The fresh game counts from zero again.
+    mgr.route_message(pid(1), Increment).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert!(matches!(
+        rx1.try_recv(),
+        Ok(RoomOutbound::Message(CounterEvent::Counted(1)))
+    ));
+}
+
+#[tokio::test]
+async fn test_rematch_needs_every_player() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let (room, _rx1) = finished_room(&mut mgr).await;
+
+    // A withdrawn vote doesn't count.
+    mgr.set_rematch(pid(1), true).await.unwrap();
+    mgr.set_rematch(pid(1), false).await.unwrap();
+    mgr.set_rematch(pid(2), true).await.unwrap();
+    assert_eq!(
+        mgr.get_room_info(room).await.unwrap().state,
+        RoomState::Finished
+    );
+
+    // Nor can one player restart the game alone.
+    mgr.leave_room(pid(2)).await.unwrap();
+    mgr.set_rematch(pid(1), true).await.unwrap();
+    assert_eq!(
+        mgr.get_room_info(room).await.unwrap().state,
+        RoomState::Finished
+    );
+}
+
+#[tokio::test]
+async fn test_rematch_vote_leaves_out_held_seats() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let room = mgr.create_room(CounterConfig {
+        finish_at: 2,
+        ..Default::default()
+    });
+    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+    mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
+    mgr.disconnect_player(pid(2)).await.unwrap();
+    mgr.route_message(pid(1), Increment).await.unwrap();
+    mgr.route_message(pid(1), Increment).await.unwrap();
+    assert_eq!(
+        mgr.get_room_info(room).await.unwrap().state,
+        RoomState::Finished
+    );
+
+    // Player 2's seat is still held, but they can't vote from outside.
+    mgr.set_rematch(pid(1), true).await.unwrap();
+    assert_eq!(
+        mgr.get_room_info(room).await.unwrap().state,
+        RoomState::InProgress
+    );
+}
+
+#[tokio::test]
+async fn test_rematch_only_after_game_ends() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let room = mgr.create_room(CounterConfig { finish_at: 5, ..Default::default() });
+    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+    mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
+
+    let result = mgr.set_rematch(pid(1), true).await;
+    assert!(matches!(result, Err(RoomError::InvalidState(_))));
+}
+
+/// Keeps every match record it's given.
+#[derive(Default)]
+struct CollectSink(std::sync::Mutex<Vec<MatchRecord>>);
+
+impl ResultSink for CollectSink {
+    fn record(&self, record: &MatchRecord) {
+        self.0.lock().unwrap().push(record.clone());
+    }
+}
+
+#[tokio::test]
+async fn test_results_go_to_players_and_sink() {
+    let sink = Arc::new(CollectSink::default());
+    let mut mgr =
+        RoomManager::<TimedGame>::new().with_result_sink(sink.clone());
+    let room = mgr.create_room(CounterConfig { finish_at: 2, ..Default::default() });
+    let (tx1, mut rx1) = mpsc::unbounded_channel();
+    mgr.join_room(pid(1), room, tx1).await.unwrap();
+    mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
+    drain_system(&mut rx1);
+
+    mgr.route_message(pid(1), Increment).await.unwrap();
+    mgr.route_message(pid(2), Increment).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let expected = MatchResult::from_scores([(pid(1), 2)]);
+    assert_eq!(
+        drain_system(&mut rx1),
+        vec![SystemMessage::GameEnded {
+            reason: EndReason::Completed,
+            result: expected.clone(),
+        }]
+    );
+
+    let records = sink.0.lock().unwrap().clone();
+    assert_eq!(records.len(), 1);
+    let record = &records[0];
+    assert_eq!(record.room_id, room);
+    assert_eq!(record.reason, EndReason::Completed);
+    assert_eq!(record.seed, mgr.get_room_info(room).await.unwrap().seed);
+    assert_eq!(record.result, expected);
+    assert!(record.ended_at_ms > 0);
+}
+
+#[tokio::test]
+async fn test_game_ends_at_max_duration() {
+    let mut mgr = RoomManager::<TimedGame>::new();
+    let room = mgr.create_room(CounterConfig { finish_at: 100, ..Default::default() });
+    let (tx1, mut rx1) = mpsc::unbounded_channel();
+    mgr.join_room(pid(1), room, tx1).await.unwrap();
+    mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
+    mgr.route_message(pid(1), Increment).await.unwrap();
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(
+        mgr.get_room_info(room).await.unwrap().state,
+        RoomState::InProgress
+    );
+    while rx1.try_recv().is_ok() {}
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(
+        mgr.get_room_info(room).await.unwrap().state,
+        RoomState::Finished
+    );
+
+    // The game's last word comes before the end notice.
+    let rest: Vec<_> = std::iter::from_fn(|| rx1.try_recv().ok()).collect();
+    assert!(
+        matches!(
+            rest.as_slice(),
+            [
+                RoomOutbound::Message(CounterEvent::Counted(1)),
+                RoomOutbound::System(SystemMessage::GameEnded {
+                    reason: EndReason::TimedOut,
+                    ..
+                }),
+            ]
+        ),
+        "got {rest:?}"
+    );
+}
+
+#[tokio::test]
+async fn test_panicking_game_closes_room() {
+    let mut mgr = RoomManager::<PanicGame>::new();
+    let bystanders = mgr.create_room(());
+    let room = mgr.create_room(());
+    let (tx1, mut rx1) = mpsc::unbounded_channel();
+    let (tx2, mut rx2) = mpsc::unbounded_channel();
+    mgr.join_room(pid(1), room, tx1).await.unwrap();
+    mgr.join_room(pid(2), room, tx2).await.unwrap();
+    drain_system(&mut rx1);
+    drain_system(&mut rx2);
+
+    mgr.route_message(pid(1), 13).await.unwrap();
+    // Queued behind the message, so it fails once the room is gone.
+    assert!(mgr.get_room_info(room).await.is_err());
+
+    for rx in [&mut rx1, &mut rx2] {
+        assert!(matches!(
+            drain_system(rx).as_slice(),
+            [SystemMessage::Error { code: 500, .. }]
+        ));
+    }
+    assert_eq!(mgr.collect_disposed(), vec![room]);
+    assert_eq!(mgr.player_room(&pid(1)), None);
+    assert_eq!(mgr.player_room(&pid(2)), None);
+
+    // Other rooms carry on.
+    assert!(mgr.get_room_info(bystanders).await.is_ok());
+    mgr.join_room(pid(1), bystanders, dummy_sender()).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_panicking_game_can_restore_last_state() {
+    let mut mgr = RoomManager::<ForgivingGame>::new();
+    let room = mgr.create_room(());
+    let (tx1, mut rx1) = mpsc::unbounded_channel();
+    let (tx2, mut rx2) = mpsc::unbounded_channel();
+    mgr.join_room(pid(1), room, tx1).await.unwrap();
+    mgr.join_room(pid(2), room, tx2).await.unwrap();
+    drain_system(&mut rx1);
+    drain_system(&mut rx2);
+
+    mgr.route_message(pid(1), 2).await.unwrap();
+    mgr.route_message(pid(1), 13).await.unwrap();
+    mgr.route_message(pid(2), 3).await.unwrap();
+    assert_eq!(
+        mgr.get_room_info(room).await.unwrap().state,
+        RoomState::InProgress
+    );
+
+    // The 13 never happened, and only its sender hears about it.
+    let received: Vec<_> = std::iter::from_fn(|| rx1.try_recv().ok()).collect();
+    assert!(
+        matches!(
+            received.as_slice(),
+            [
+                RoomOutbound::Message(2),
+                RoomOutbound::System(SystemMessage::Error { code: 500, .. }),
+                RoomOutbound::Message(5),
+            ]
+        ),
+        "got {received:?}"
+    );
+    let received: Vec<_> = std::iter::from_fn(|| rx2.try_recv().ok()).collect();
+    assert!(
+        matches!(
+            received.as_slice(),
+            [RoomOutbound::Message(2), RoomOutbound::Message(5)]
+        ),
+        "got {received:?}"
+    );
+}
+
+/// A running `ShopGame` room; returns player 1's channel.
+async fn shop_room(
+    mgr: &mut RoomManager<ShopGame>,
+) -> (RoomId, mpsc::UnboundedReceiver<RoomOutbound<ShopGame>>) {
+    let room = mgr.create_room(());
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    mgr.join_room(pid(1), room, tx).await.unwrap();
+    mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
+    while rx.try_recv().is_ok() {}
+    (room, rx)
+}
+
+fn bought(
+    rx: &mut mpsc::UnboundedReceiver<RoomOutbound<ShopGame>>,
+) -> Vec<String> {
+    std::iter::from_fn(|| rx.try_recv().ok())
+        .filter_map(|outbound| match outbound {
+            RoomOutbound::Message(item) => Some(item),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The codes of the errors waiting on `rx`.
+fn error_codes(
+    rx: &mut mpsc::UnboundedReceiver<RoomOutbound<ShopGame>>,
+) -> Vec<u16> {
+    std::iter::from_fn(|| rx.try_recv().ok())
+        .filter_map(|outbound| match outbound {
+            RoomOutbound::System(SystemMessage::Error { code, .. }) => {
+                Some(code)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_async_hooks_keep_message_order_without_stalling_room() {
+    let mut mgr = RoomManager::<ShopGame>::new().with_async_hooks();
+    let (room, mut rx) = shop_room(&mut mgr).await;
+
+    mgr.route_message(pid(1), buy("shield", 80)).await.unwrap();
+    mgr.route_message(pid(2), buy("potion", 0)).await.unwrap();
+
+    // The room still answers while the store is thinking.
+    let asked = tokio::time::Instant::now();
+    let info = mgr.get_room_info(room).await.unwrap();
+    assert!(asked.elapsed() < Duration::from_millis(40));
+    assert_eq!(info.state, RoomState::InProgress);
+    assert!(bought(&mut rx).is_empty());
+
+    tokio::time::sleep(Duration::from_millis(120)).await;
+    assert_eq!(bought(&mut rx), ["shield", "potion"]);
+}
+
+#[tokio::test]
+async fn test_async_hooks_can_reject_messages() {
+    let mut mgr = RoomManager::<ShopGame>::new().with_async_hooks();
+    let (_room, mut rx) = shop_room(&mut mgr).await;
+
+    mgr.route_message(pid(1), buy("sword", 0)).await.unwrap();
+    mgr.route_message(pid(1), buy("contraband", 0)).await.unwrap();
+    // Checked against the state after the first sword went through.
+    mgr.route_message(pid(2), buy("sword", 0)).await.unwrap();
+    mgr.route_message(pid(2), buy("bow", 0)).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    assert_eq!(bought(&mut rx), ["sword", "bow"]);
+}
+
+#[tokio::test]
+async fn test_async_hook_rejection_is_reported() {
+    let mut mgr = RoomManager::<ShopGame>::new().with_async_hooks();
+    let (_room, mut rx) = shop_room(&mut mgr).await;
+
+    mgr.route_message(pid(1), buy("contraband", 0)).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    assert!(matches!(
+        rx.try_recv(),
+        Ok(RoomOutbound::System(SystemMessage::Error { code: 400, message }))
+            if message == "not for sale"
+    ));
+}
+
+#[tokio::test]
+async fn test_panic_while_leaving_closes_room_and_says_so() {
+    let mut mgr = RoomManager::<ForgivingGame>::new();
+    let room = mgr.create_room(());
+    let (tx1, mut rx1) = mpsc::unbounded_channel();
+    mgr.join_room(pid(1), room, tx1).await.unwrap();
+    mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
+    mgr.route_message(pid(2), 7).await.unwrap();
+    drain_system(&mut rx1);
+
+    // The leave can't be taken back, so there's no state to go back to.
+    let result = mgr.leave_room(pid(2)).await;
+    assert!(
+        matches!(result, Err(RoomError::GameCrashed(id)) if id == room),
+        "got {result:?}"
+    );
+    assert!(matches!(
+        drain_system(&mut rx1).as_slice(),
+        [SystemMessage::Error { code: 500, .. }]
+    ));
+    assert!(mgr.get_room_info(room).await.is_err());
+}
+
+#[tokio::test]
+async fn test_async_hook_panic_drops_only_that_message() {
+    let mut mgr = RoomManager::<ShopGame>::new().with_async_hooks();
+    let (room, mut rx) = shop_room(&mut mgr).await;
+
+    mgr.route_message(pid(1), buy("cursed", 0)).await.unwrap();
+    mgr.route_message(pid(1), buy("potion", 0)).await.unwrap();
+
+    let next = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await;
+    assert!(
+        matches!(
+            &next,
+            Ok(Some(RoomOutbound::System(SystemMessage::Error {
+                code: 500,
+                ..
+            })))
+        ),
+        "got {next:?}"
+    );
+    let next = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await;
+    assert!(
+        matches!(&next, Ok(Some(RoomOutbound::Message(item))) if item == "potion"),
+        "got {next:?}"
+    );
+    assert_eq!(
+        mgr.get_room_info(room).await.unwrap().state,
+        RoomState::InProgress
+    );
+}
+
+#[tokio::test]
+async fn test_slow_async_hook_times_out() {
+    let mut mgr = RoomManager::<ShopGame>::new().with_async_hooks();
+    let (_room, mut rx) = shop_room(&mut mgr).await;
+
+    mgr.route_message(pid(1), buy("castle", 10_000)).await.unwrap();
+    mgr.route_message(pid(1), buy("potion", 0)).await.unwrap();
+
+    let next = tokio::time::timeout(Duration::from_secs(1), rx.recv()).await;
+    assert!(
+        matches!(
+            &next,
+            Ok(Some(RoomOutbound::System(SystemMessage::Error {
+                code: 504,
+                ..
+            })))
+        ),
+        "got {next:?}"
+    );
+    let next = tokio::time::timeout(Duration::from_secs(1), rx.recv()).await;
+    assert!(
+        matches!(&next, Ok(Some(RoomOutbound::Message(item))) if item == "potion"),
+        "got {next:?}"
+    );
+}
+
+#[tokio::test]
+async fn test_async_hook_queue_is_capped() {
+    let mut mgr = RoomManager::<ShopGame>::new().with_async_hooks();
+    let (_room, mut rx) = shop_room(&mut mgr).await;
+
+    // One being prepared, three waiting, and the rest turned away.
+    mgr.route_message(pid(1), buy("shield", 50)).await.unwrap();
+    for item in ["a", "b", "c", "d", "e"] {
+        mgr.route_message(pid(1), buy(item, 0)).await.unwrap();
+    }
+    // The ones turned away are told so straight away.
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert_eq!(error_codes(&mut rx), [503, 503]);
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(bought(&mut rx), ["shield", "a", "b", "c"]);
+}
+
+#[tokio::test]
+async fn test_async_hooks_off_by_default() {
+    let mut mgr = RoomManager::<ShopGame>::new();
+    let (_room, mut rx) = shop_room(&mut mgr).await;
+
+    mgr.route_message(pid(1), buy("contraband", 0)).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    assert_eq!(bought(&mut rx), ["contraband"]);
+}
+
+#[tokio::test]
+async fn test_bots_fill_empty_seats_after_timeout() {
+    let mut mgr = seat_manager();
+    let room = mgr.create_room(());
+    let (tx1, mut rx1) = mpsc::unbounded_channel();
+    mgr.join_room(pid(1), room, tx1).await.unwrap();
+
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let info = mgr.get_room_info(room).await.unwrap();
+    assert_eq!(info.state, RoomState::WaitingForPlayers);
+    assert_eq!(info.bot_count, 0);
+
+    tokio::time::sleep(Duration::from_millis(80)).await;
+    let info = mgr.get_room_info(room).await.unwrap();
+    assert_eq!(info.state, RoomState::InProgress);
+    assert_eq!((info.player_count, info.bot_count), (2, 1));
+
+    // The game sees the bot as an ordinary player, and the bot plays.
+    let received: Vec<_> = std::iter::from_fn(|| rx1.try_recv().ok()).collect();
+    let Some(RoomOutbound::State(start)) = received.first() else {
+        panic!("expected a snapshot first, got {received:?}");
+    };
+    assert_eq!(start.seated.len(), 2);
+    assert_eq!(start.seated[0], pid(1));
+    assert!(matches!(
+        received.last(),
+        Some(RoomOutbound::Message(CounterEvent::Counted(1)))
+    ));
+}
+
+#[tokio::test]
+async fn test_match_record_lists_bot_seats() {
+    let sink = Arc::new(CollectSink::default());
+    let mut mgr = seat_manager().with_result_sink(sink.clone());
+    let room = mgr.create_room(());
+    let (tx1, mut rx1) = mpsc::unbounded_channel();
+    mgr.join_room(pid(1), room, tx1).await.unwrap();
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let Ok(RoomOutbound::State(start)) = rx1.try_recv() else {
+        panic!("expected the game to start with a bot");
+    };
+    let bot = start.seated[1];
+
+    tokio::time::sleep(Duration::from_millis(350)).await;
+    let records = sink.0.lock().unwrap().clone();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].reason, EndReason::TimedOut);
+    assert_eq!(records[0].bots, vec![bot]);
+}
+
+#[tokio::test]
+async fn test_no_bots_without_factory() {
+    let mut mgr = RoomManager::<SeatGame>::new();
+    let room = mgr.create_room(());
+    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let info = mgr.get_room_info(room).await.unwrap();
+    assert_eq!(info.state, RoomState::WaitingForPlayers);
+    assert_eq!(info.player_count, 1);
+}
+
+/// A running `SeatGame` between players 1 and 2; returns player 2's
+/// channel.
+async fn seated_room(
+    mgr: &mut RoomManager<SeatGame>,
+) -> (RoomId, mpsc::UnboundedReceiver<RoomOutbound<SeatGame>>) {
+    let room = mgr.create_room(());
+    let (tx2, mut rx2) = mpsc::unbounded_channel();
+    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+    mgr.join_room(pid(2), room, tx2).await.unwrap();
+    while rx2.try_recv().is_ok() {}
+    (room, rx2)
+}
+
+#[tokio::test]
+async fn test_disconnected_player_can_reconnect() {
+    let mut mgr = RoomManager::<SeatGame>::new();
+    let (room, _rx2) = seated_room(&mut mgr).await;
+
+    mgr.disconnect_player(pid(1)).await.unwrap();
+    assert_eq!(mgr.player_room(&pid(1)), Some(room));
+    assert_eq!(mgr.get_room_info(room).await.unwrap().player_count, 2);
+
+    let (tx1, mut rx1) = mpsc::unbounded_channel();
+    assert_eq!(mgr.reconnect_player(pid(1), tx1).await.unwrap(), room);
+    assert!(matches!(rx1.try_recv(), Ok(RoomOutbound::State(_))));
+
+    // The seat is theirs again, so the grace period no longer applies.
+    tokio::time::sleep(Duration::from_millis(80)).await;
+    assert_eq!(mgr.get_room_info(room).await.unwrap().player_count, 2);
+    mgr.collect_disposed();
+    assert_eq!(mgr.player_room(&pid(1)), Some(room));
+}
+
+#[tokio::test]
+async fn test_disconnected_player_removed_after_grace() {
+    let mut mgr = RoomManager::<SeatGame>::new();
+    let (room, _rx2) = seated_room(&mut mgr).await;
+
+    mgr.disconnect_player(pid(1)).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(80)).await;
+
+    let info = mgr.get_room_info(room).await.unwrap();
+    assert_eq!((info.player_count, info.bot_count), (1, 0));
+    mgr.collect_disposed();
+    assert_eq!(mgr.player_room(&pid(1)), None);
+    let result = mgr.reconnect_player(pid(1), dummy_sender()).await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_bot_takes_over_disconnected_seat() {
+    let mut mgr = seat_manager();
+    let (room, mut rx2) = seated_room(&mut mgr).await;
+
+    mgr.disconnect_player(pid(1)).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(80)).await;
+
+    let info = mgr.get_room_info(room).await.unwrap();
+    assert_eq!((info.player_count, info.bot_count), (2, 1));
+    // The stand-in plays under the player's own ID.
+    assert!(matches!(
+        rx2.try_recv(),
+        Ok(RoomOutbound::Message(CounterEvent::Counted(1)))
+    ));
+
+    // The player can still come back and take over from the bot.
+    let (tx1, mut rx1) = mpsc::unbounded_channel();
+    mgr.reconnect_player(pid(1), tx1).await.unwrap();
+    assert_eq!(mgr.get_room_info(room).await.unwrap().bot_count, 0);
+    assert!(matches!(
+        rx1.try_recv(),
+        Ok(RoomOutbound::State(SeatState { count: 1, .. }))
+    ));
+}
+
+#[tokio::test]
+async fn test_disconnect_outside_game_leaves_room() {
+    let mut mgr = RoomManager::<SeatGame>::new();
+    let room = mgr.create_room(());
+    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+
+    mgr.disconnect_player(pid(1)).await.unwrap();
+    assert_eq!(mgr.player_room(&pid(1)), None);
+    assert_eq!(mgr.get_room_info(room).await.unwrap().player_count, 0);
+}
+
+#[tokio::test]
+async fn test_list_rooms_empty() {
+    let mgr = RoomManager::<CounterGame>::new();
+    let rooms = mgr.list_rooms().await;
+    assert!(rooms.is_empty());
+}
+
+#[tokio::test]
+async fn test_list_rooms_returns_joinable_only() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let r1 = mgr.create_room(CounterConfig::default());
+    let r2 = mgr.create_room(CounterConfig::default());
+
+    // r2 gets filled → starts → no longer joinable
+    mgr.join_room(pid(10), r2, dummy_sender()).await.unwrap();
+    mgr.join_room(pid(11), r2, dummy_sender()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let rooms = mgr.list_rooms().await;
+    assert_eq!(rooms.len(), 1);
+    assert_eq!(rooms[0].room_id, r1);
+}
+
+#[tokio::test]
+async fn test_list_rooms_includes_metadata() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let room = mgr.create_room(CounterConfig {
+        mode: Some("ranked"),
+        ..Default::default()
+    });
+
+    let rooms = mgr.list_rooms().await;
+    assert_eq!(rooms.len(), 1);
+    assert_eq!(rooms[0].room_id, room);
+    assert_eq!(rooms[0].metadata.get("mode").map(String::as_str), Some("ranked"));
+}
+
+#[tokio::test]
+async fn test_list_rooms_matching_filters_by_metadata() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let ranked = mgr.create_room(CounterConfig {
+        mode: Some("ranked"),
+        ..Default::default()
+    });
+    let _casual = mgr.create_room(CounterConfig {
+        mode: Some("casual"),
+        ..Default::default()
+    });
+    let _untagged = mgr.create_room(CounterConfig::default());
+
+    let filter = RoomMetadata::from([("mode".into(), "ranked".into())]);
+    let rooms = mgr.list_rooms_matching(&filter).await;
+    assert_eq!(rooms.len(), 1);
+    assert_eq!(rooms[0].room_id, ranked);
+
+    // An empty filter matches everything joinable.
+    assert_eq!(mgr.list_rooms_matching(&RoomMetadata::new()).await.len(), 3);
+}
+
+#[tokio::test]
+async fn test_metadata_tracks_game_state() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let room = mgr.create_room(CounterConfig { finish_at: 10, ..Default::default() });
+    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+
+    // Not started yet: no state-derived metadata.
+    let info = mgr.get_room_info(room).await.unwrap();
+    assert_eq!(info.metadata.get("count"), None);
+
+    mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
+    mgr.route_message(pid(1), Increment).await.unwrap();
+    mgr.route_message(pid(2), Increment).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let info = mgr.get_room_info(room).await.unwrap();
+    assert_eq!(info.metadata.get("count").map(String::as_str), Some("2"));
+}
+
+#[tokio::test]
+async fn test_join_or_create_creates_when_empty() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let room_id = mgr
+        .join_or_create(pid(1), CounterConfig::default(), dummy_sender())
+        .await
+        .unwrap();
+
+    assert_eq!(mgr.room_count(), 1);
+    assert_eq!(mgr.player_room(&pid(1)), Some(room_id));
+}
+
+#[tokio::test]
+async fn test_join_or_create_joins_existing() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let _r1 = mgr.create_room(CounterConfig::default());
+
+    let room_id = mgr
+        .join_or_create(pid(1), CounterConfig::default(), dummy_sender())
+        .await
+        .unwrap();
+
+    // Should have joined the existing room, not created a new one.
+    assert_eq!(mgr.room_count(), 1);
+    assert_eq!(room_id, _r1);
+}
+
+#[tokio::test]
+async fn test_join_or_create_already_in_room() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    mgr.join_or_create(pid(1), CounterConfig::default(), dummy_sender())
+        .await
+        .unwrap();
+
+    let result = mgr
+        .join_or_create(pid(1), CounterConfig::default(), dummy_sender())
+        .await;
+    assert!(result.is_err());
+}
+
+// =========================================================================
+// Private rooms, invite codes, and passwords
+// =========================================================================
+
+#[tokio::test]
+async fn test_private_room_hidden_from_listing_and_join_or_create() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let private = mgr.create_room_with(
+        CounterConfig::default(),
+        RoomOptions {
+            private: true,
+            ..Default::default()
+        },
+    );
+
+    assert!(mgr.list_rooms().await.is_empty());
+
+    let joined = mgr
+        .join_or_create(pid(1), CounterConfig::default(), dummy_sender())
+        .await
+        .unwrap();
+    assert_ne!(joined, private);
+    assert_eq!(mgr.room_count(), 2);
+}
+
+#[tokio::test]
+async fn test_private_room_requires_invite_code() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let room = mgr.create_room_with(
+        CounterConfig::default(),
+        RoomOptions {
+            private: true,
+            ..Default::default()
+        },
+    );
+
+    let result = mgr.join_room(pid(1), room, dummy_sender()).await;
+    assert!(matches!(result, Err(RoomError::NotFound(_))));
+
+    let code = mgr.invite_code(room).unwrap().to_string();
+    let joined = mgr
+        .join_by_code(pid(1), &code, None, dummy_sender())
+        .await
+        .unwrap();
+    assert_eq!(joined, room);
+    assert_eq!(mgr.player_room(&pid(1)), Some(room));
+}
+
+#[tokio::test]
+async fn test_join_by_unknown_code() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let result = mgr
+        .join_by_code(pid(1), "NOPE42", None, dummy_sender())
+        .await;
+    assert!(matches!(result, Err(RoomError::InvalidInviteCode(_))));
+}
+
+#[tokio::test]
+async fn test_password_protected_room() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let room = mgr.create_room_with(
+        CounterConfig::default(),
+        RoomOptions {
+            password: Some("secret".into()),
+            ..Default::default()
+        },
+    );
+
+    let result = mgr.join_room(pid(1), room, dummy_sender()).await;
+    assert!(matches!(result, Err(RoomError::WrongPassword(_))));
+
+    let wrong = JoinCredentials {
+        password: Some("guess".into()),
+        ..Default::default()
+    };
+    let result = mgr.join_room_with(pid(1), room, dummy_sender(), wrong).await;
+    assert!(matches!(result, Err(RoomError::WrongPassword(_))));
+
+    let right = JoinCredentials {
+        password: Some("secret".into()),
+        ..Default::default()
+    };
+    mgr.join_room_with(pid(1), room, dummy_sender(), right)
+        .await
+        .unwrap();
+
+    let info = mgr.get_room_info(room).await.unwrap();
+    assert!(info.password_protected);
+    assert!(!info.private);
+}
+
+#[tokio::test]
+async fn test_create_and_join_seats_creator() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let room = mgr
+        .create_and_join(
+            pid(1),
+            CounterConfig::default(),
+            RoomOptions {
+                private: true,
+                password: Some("secret".into()),
+                ..Default::default()
+            },
+            dummy_sender(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(mgr.player_room(&pid(1)), Some(room));
+
+    // Already in a room: no second room gets created.
+    let result = mgr
+        .create_and_join(
+            pid(1),
+            CounterConfig::default(),
+            RoomOptions::default(),
+            dummy_sender(),
+        )
+        .await;
+    assert!(result.is_err());
+    assert_eq!(mgr.room_count(), 1);
+}
+
+#[tokio::test]
+async fn test_destroy_room_frees_invite_code() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let room = mgr.create_room(CounterConfig::default());
+    let code = mgr.invite_code(room).unwrap().to_string();
+
+    mgr.destroy_room(room).await.unwrap();
+
+    assert_eq!(mgr.invite_code(room), None);
+    let result = mgr.join_by_code(pid(1), &code, None, dummy_sender()).await;
+    assert!(matches!(result, Err(RoomError::InvalidInviteCode(_))));
+}
+
+// =========================================================================
+// Hosted rooms
+// =========================================================================
+
+/// Creates a hosted room with player 1 as host and player 2 as guest.
+async fn hosted_room(
+    mgr: &mut RoomManager<CounterGame>,
+) -> (RoomId, mpsc::UnboundedReceiver<RoomOutbound<CounterGame>>) {
+    let room = mgr
+        .create_and_join(
+            pid(1),
+            CounterConfig::default(),
+            RoomOptions::default(),
+            dummy_sender(),
+        )
+        .await
+        .unwrap();
+    let (tx, rx) = mpsc::unbounded_channel();
+    mgr.join_room(pid(2), room, tx).await.unwrap();
+    (room, rx)
+}
+
+#[tokio::test]
+async fn test_hosted_room_waits_for_host_to_start() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let (room, mut guest_rx) = hosted_room(&mut mgr).await;
+
+    // The guest learns who the host is.
+    match guest_rx.try_recv().unwrap() {
+        RoomOutbound::System(SystemMessage::HostChanged { host }) => {
+            assert_eq!(host, pid(1));
+        }
+        other => panic!("expected HostChanged, got {other:?}"),
+    }
+
+    // min_players reached, but no auto-start.
+    let info = mgr.get_room_info(room).await.unwrap();
+    assert_eq!(info.host, Some(pid(1)));
+    assert_eq!(info.state, RoomState::WaitingForPlayers);
+
+    let result = mgr.start_game(pid(2)).await;
+    assert!(matches!(result, Err(RoomError::NotHost(..))));
+
+    mgr.start_game(pid(1)).await.unwrap();
+    let info = mgr.get_room_info(room).await.unwrap();
+    assert_eq!(info.state, RoomState::InProgress);
+}
+
+#[tokio::test]
+async fn test_host_cannot_start_without_min_players() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    mgr.create_and_join(
+        pid(1),
+        CounterConfig::default(),
+        RoomOptions::default(),
+        dummy_sender(),
+    )
+    .await
+    .unwrap();
+
+    let result = mgr.start_game(pid(1)).await;
+    assert!(matches!(result, Err(RoomError::InvalidState(_))));
+}
+
+#[tokio::test]
+async fn test_unhosted_room_rejects_host_controls() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let room = mgr.create_room(CounterConfig::default());
+    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+
+    assert!(matches!(
+        mgr.start_game(pid(1)).await,
+        Err(RoomError::NotHost(..))
+    ));
+    assert!(matches!(
+        mgr.set_room_locked(pid(1), true).await,
+        Err(RoomError::NotHost(..))
+    ));
+}
+
+#[tokio::test]
+async fn test_host_kicks_player() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let (room, mut guest_rx) = hosted_room(&mut mgr).await;
+    let _ = guest_rx.try_recv();
+
+    let result = mgr.kick_player(pid(2), pid(1)).await;
+    assert!(matches!(result, Err(RoomError::NotHost(..))));
+
+    mgr.kick_player(pid(1), pid(2)).await.unwrap();
+
+    match guest_rx.recv().await.unwrap() {
+        RoomOutbound::System(SystemMessage::Kicked { room_id }) => {
+            assert_eq!(room_id, room);
+        }
+        other => panic!("expected Kicked, got {other:?}"),
+    }
+    assert!(guest_rx.recv().await.is_none());
+    assert_eq!(mgr.player_room(&pid(2)), None);
+    assert_eq!(mgr.get_room_info(room).await.unwrap().player_count, 1);
+}
+
+#[tokio::test]
+async fn test_locked_room_turns_players_away() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let (room, _guest_rx) = hosted_room(&mut mgr).await;
+
+    mgr.set_room_locked(pid(1), true).await.unwrap();
+    assert!(mgr.list_rooms().await.is_empty());
+    let result = mgr.join_room(pid(3), room, dummy_sender()).await;
+    assert!(matches!(result, Err(RoomError::Locked(_))));
+
+    mgr.set_room_locked(pid(1), false).await.unwrap();
+    mgr.join_room(pid(3), room, dummy_sender()).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_host_role_passes_to_oldest_player() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let (room, mut guest_rx) = hosted_room(&mut mgr).await;
+    mgr.join_room(pid(3), room, dummy_sender()).await.unwrap();
+    let _ = guest_rx.try_recv();
+
+    mgr.leave_room(pid(1)).await.unwrap();
+
+    match guest_rx.recv().await.unwrap() {
+        RoomOutbound::System(SystemMessage::HostChanged { host }) => {
+            assert_eq!(host, pid(2));
+        }
+        other => panic!("expected HostChanged, got {other:?}"),
+    }
+    let info = mgr.get_room_info(room).await.unwrap();
+    assert_eq!(info.host, Some(pid(2)));
+    mgr.start_game(pid(2)).await.unwrap();
+}
+
+// =========================================================================
+// Continuous state sync
+// =========================================================================
+
+/// Client-side reconstruction of synced snapshots.
+#[derive(Default)]
+struct SyncClient {
+    snapshots: std::collections::HashMap<u64, serde_json::Value>,
+    latest: u64,
+    keyframes: usize,
+    deltas: Vec<u64>,
+}
+
+impl SyncClient {
+    fn receive(
+        &mut self,
+        rx: &mut mpsc::UnboundedReceiver<RoomOutbound<SyncGame>>,
+    ) {
+        while let Ok(outbound) = rx.try_recv() {
+            match outbound {
+                RoomOutbound::Keyframe { seq, view } => {
+                    self.snapshots
+                        .insert(seq, serde_json::to_value(view).unwrap());
+                    self.keyframes += 1;
+                    self.latest = seq;
+                }
+                RoomOutbound::Delta {
+                    seq,
+                    base_seq,
+                    patch,
+                } => {
+                    let mut value = self.snapshots[&base_seq].clone();
+                    apply_patch(&mut value, &patch);
+                    self.snapshots.insert(seq, value);
+                    self.deltas.push(base_seq);
+                    self.latest = seq;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn latest_state(&self) -> CounterState {
+        serde_json::from_value(self.snapshots[&self.latest].clone()).unwrap()
+    }
+}
+
+async fn synced_room(
+    mgr: &mut RoomManager<SyncGame>,
+) -> mpsc::UnboundedReceiver<RoomOutbound<SyncGame>> {
+    let room = mgr.create_room(CounterConfig {
+        finish_at: 100,
+        ..Default::default()
+    });
+    let (tx1, rx1) = mpsc::unbounded_channel();
+    mgr.join_room(pid(1), room, tx1).await.unwrap();
+    mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
+    rx1
+}
+
+#[tokio::test]
+async fn test_sync_sends_keyframes_until_acked() {
+    let mut mgr = RoomManager::<SyncGame>::new();
+    let mut rx1 = synced_room(&mut mgr).await;
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let mut client = SyncClient::default();
+    client.receive(&mut rx1);
+
+    assert!(client.keyframes >= 2, "got {} keyframes", client.keyframes);
+    assert!(client.deltas.is_empty());
+}
+
+#[tokio::test]
+async fn test_sync_deltas_reconstruct_state() {
+    let mut mgr = RoomManager::<SyncGame>::new();
+    let mut rx1 = synced_room(&mut mgr).await;
+    let mut client = SyncClient::default();
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    client.receive(&mut rx1);
+    let acked = client.latest;
+    mgr.ack_state(pid(1), acked).await.unwrap();
+
+    for _ in 0..3 {
+        mgr.route_message(pid(2), Increment).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(30)).await;
+    }
+    client.receive(&mut rx1);
+
+    assert!(!client.deltas.is_empty(), "expected deltas after ack");
+    assert!(client.deltas.iter().all(|base| *base == acked));
+    assert_eq!(client.latest_state().count, 3);
+}
+
+#[tokio::test]
+async fn test_sync_skips_unchanged_state() {
+    let mut mgr = RoomManager::<SyncGame>::new();
+    let mut rx1 = synced_room(&mut mgr).await;
+    let mut client = SyncClient::default();
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    client.receive(&mut rx1);
+    mgr.ack_state(pid(1), client.latest).await.unwrap();
+
+    // Nothing changes, so nothing is sent.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(rx1.try_recv().is_err());
+}
+
+// =========================================================================
+// Tick-aligned input
+// =========================================================================
+
+async fn ticking_room<G: GameLogic<Config = (), ServerMessage = TickEvent>>(
+    mgr: &mut RoomManager<G>,
+) -> mpsc::UnboundedReceiver<RoomOutbound<G>> {
+    let room = mgr.create_room(());
+    let (tx1, rx1) = mpsc::unbounded_channel();
+    mgr.join_room(pid(1), room, tx1).await.unwrap();
+    mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
+    rx1
+}
+
+fn drain_events<G: GameLogic<ServerMessage = TickEvent>>(
+    rx: &mut mpsc::UnboundedReceiver<RoomOutbound<G>>,
+) -> Vec<TickEvent> {
+    let mut events = Vec::new();
+    while let Ok(outbound) = rx.try_recv() {
+        if let RoomOutbound::Message(event) = outbound {
+            events.push(event);
+        }
+    }
+    events
+}
+
+#[tokio::test]
+async fn test_inputs_are_delivered_as_one_batch_per_tick() {
+    let mut mgr = RoomManager::<TickGame>::new();
+    let mut rx1 = ticking_room(&mut mgr).await;
+
+    mgr.route_input(pid(2), 1, Some(5), 20).await.unwrap();
+    mgr.route_input(pid(1), 1, Some(5), 10).await.unwrap();
+    // Only the first input per player and tick counts.
+    mgr.route_input(pid(1), 2, Some(5), 99).await.unwrap();
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(
+        drain_events(&mut rx1),
+        vec![TickEvent::Batch {
+            tick: 5,
+            inputs: vec![(1, 10, false), (2, 20, false)],
+        }]
+    );
+}
+
+#[tokio::test]
+async fn test_untagged_messages_wait_for_next_tick() {
+    let mut mgr = RoomManager::<TickGame>::new();
+    let mut rx1 = ticking_room(&mut mgr).await;
+
+    mgr.route_message(pid(1), 7).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(150)).await;
+
+    let events = drain_events(&mut rx1);
+    assert!(!events.contains(&TickEvent::Immediate));
+    assert!(matches!(
+        events.as_slice(),
+        [TickEvent::Batch { inputs, .. }] if inputs == &[(1, 7, false)]
+    ));
+}
+
+#[tokio::test]
+async fn test_late_input_moves_to_next_tick() {
+    let mut mgr = RoomManager::<TickGame>::new();
+    let mut rx1 = ticking_room(&mut mgr).await;
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    mgr.route_input(pid(1), 1, Some(1), 3).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(150)).await;
+
+    match drain_events(&mut rx1).as_slice() {
+        [TickEvent::Batch { tick, inputs }] => {
+            assert!(*tick > 1, "late input ran on tick {tick}");
+            assert_eq!(inputs, &[(1, 3, false)]);
+        }
+        other => panic!("expected one batch, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_lockstep_drops_late_input_and_repeats_last() {
+    let mut mgr = RoomManager::<LockstepGame>::new();
+    let mut rx1 = ticking_room(&mut mgr).await;
+
+    mgr.route_input(pid(1), 1, Some(2), 7).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    // Tick 1 is long gone, so this is dropped rather than applied.
+    mgr.route_input(pid(1), 2, Some(1), 99).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(150)).await;
+
+    let events = drain_events(&mut rx1);
+    assert!(events.len() > 2, "got {events:?}");
+    for (i, event) in events.iter().enumerate() {
+        let TickEvent::Batch { tick, inputs } = event else {
+            panic!("unexpected event {event:?}");
+        };
+        // Player 2 never sent anything, so there's nothing to repeat.
+        assert_eq!(inputs, &[(1, 7, i > 0)], "tick {tick}");
+    }
+}
+
+// =========================================================================
+// Input acknowledgement
+// =========================================================================
+
+#[tokio::test]
+async fn test_processed_input_is_acked_before_its_results() {
+    let mut mgr = RoomManager::<CounterGame>::new();
+    let room = mgr.create_room(CounterConfig {
+        finish_at: 10,
+        ..Default::default()
+    });
+    let (tx1, mut rx1) = mpsc::unbounded_channel();
+    let (tx2, mut rx2) = mpsc::unbounded_channel();
+    mgr.join_room(pid(1), room, tx1).await.unwrap();
+    mgr.join_room(pid(2), room, tx2).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    while rx1.try_recv().is_ok() {}
+    while rx2.try_recv().is_ok() {}
+
+    mgr.route_input(pid(1), 7, None, Increment).await.unwrap();
+    // An older seq doesn't move the ack backwards.
+    mgr.route_input(pid(1), 5, None, Increment).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    assert!(matches!(
+        rx1.try_recv(),
+        Ok(RoomOutbound::InputAck { seq: 7 })
+    ));
+    assert!(matches!(
+        rx1.try_recv(),
+        Ok(RoomOutbound::Message(CounterEvent::Counted(1)))
+    ));
+    assert!(matches!(
+        rx1.try_recv(),
+        Ok(RoomOutbound::Message(CounterEvent::Counted(2)))
+    ));
+    assert!(rx1.try_recv().is_err());
+
+    // Other players' acks are their own business.
+    assert!(matches!(
+        rx2.try_recv(),
+        Ok(RoomOutbound::Message(CounterEvent::Counted(1)))
+    ));
+}
+
+#[tokio::test]
+async fn test_buffered_input_is_acked_on_its_tick() {
+    let mut mgr = RoomManager::<TickGame>::new();
+    let mut rx1 = ticking_room(&mut mgr).await;
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    while rx1.try_recv().is_ok() {}
+
+    mgr.route_input(pid(1), 3, Some(4), 1).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(rx1.try_recv().is_err(), "acked before its tick ran");
+
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    assert!(matches!(
+        rx1.try_recv(),
+        Ok(RoomOutbound::InputAck { seq: 3 })
+    ));
+    assert!(matches!(
+        rx1.try_recv(),
+        Ok(RoomOutbound::Message(TickEvent::Batch { tick: 4, .. }))
+    ));
+}
+
+#[tokio::test]
+async fn test_rejected_input_does_not_ack_a_queued_one() {
+    let mut mgr = RoomManager::<TickGame>::new();
+    let mut rx1 = ticking_room(&mut mgr).await;
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    while rx1.try_recv().is_ok() {}
+
+    mgr.route_input(pid(1), 5, Some(4), 1).await.unwrap();
+    mgr.route_input(pid(1), 6, None, u32::MAX).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    // Seq 6 is done with, but acking it would claim seq 5 ran too.
+    assert!(matches!(
+        rx1.try_recv(),
+        Ok(RoomOutbound::InputAck { seq: 4 })
+    ));
+    assert!(matches!(
+        rx1.try_recv(),
+        Ok(RoomOutbound::System(SystemMessage::Error { code: 400, .. }))
+    ));
+    assert!(rx1.try_recv().is_err());
+
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    assert!(matches!(
+        rx1.try_recv(),
+        Ok(RoomOutbound::InputAck { seq: 6 })
+    ));
+    assert!(matches!(
+        rx1.try_recv(),
+        Ok(RoomOutbound::Message(TickEvent::Batch { tick: 4, .. }))
+    ));
+}
+
+// =========================================================================
+// Lag compensation
+// =========================================================================
+
+async fn next_shot(
+    rx: &mut mpsc::UnboundedReceiver<RoomOutbound<HitscanGame>>,
+) -> Shot {
+    loop {
+        match tokio::time::timeout(Duration::from_secs(1), rx.recv()).await {
+            Ok(Some(RoomOutbound::Message(shot))) => return shot,
+            Ok(Some(_)) => continue,
+            other => panic!("expected a shot, got {other:?}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_shot_sees_state_from_one_rtt_ago() {
+    let mut mgr = RoomManager::<HitscanGame>::new();
+    let room = mgr.create_room(());
+    let (tx1, mut rx1) = mpsc::unbounded_channel();
+    let (tx2, mut rx2) = mpsc::unbounded_channel();
+    mgr.join_room(pid(1), room, tx1).await.unwrap();
+    mgr.join_room(pid(2), room, tx2).await.unwrap();
+
+    // 100ms is two ticks at 20 Hz. Player 2 never reports an RTT.
+    mgr.report_rtt(pid(1), Duration::from_millis(100)).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    mgr.route_message(pid(1), ()).await.unwrap();
+    let shot = next_shot(&mut rx1).await;
+    assert_eq!(shot.seen, Some(shot.current - 2));
+
+    mgr.route_message(pid(2), ()).await.unwrap();
+    let shot = next_shot(&mut rx2).await;
+    assert_eq!(shot.seen, Some(shot.current));
+}
+
+#[tokio::test]
+async fn test_rewind_is_capped_at_max_rewind() {
+    let mut mgr = RoomManager::<HitscanGame>::new();
+    let room = mgr.create_room(());
+    let (tx1, mut rx1) = mpsc::unbounded_channel();
+    mgr.join_room(pid(1), room, tx1).await.unwrap();
+    mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
+
+    mgr.report_rtt(pid(1), Duration::from_secs(2)).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    // 250ms at 20 Hz is five ticks, however bad the connection.
+    mgr.route_message(pid(1), ()).await.unwrap();
+    let shot = next_shot(&mut rx1).await;
+    assert_eq!(shot.seen, Some(shot.current - 5));
+}
+
+// =========================================================================
+// Game context
+// =========================================================================
+
+fn drain_dice(
+    rx: &mut mpsc::UnboundedReceiver<RoomOutbound<DiceGame>>,
+) -> Vec<DiceEvent> {
+    let mut events = Vec::new();
+    while let Ok(outbound) = rx.try_recv() {
+        if let RoomOutbound::Message(event) = outbound {
+            events.push(event);
+        }
+    }
+    events
+}
+
+#[tokio::test]
+async fn test_init_sends_through_context() {
+    let mut mgr = RoomManager::<DiceGame>::new();
+    let room = mgr.create_room(());
+    let (tx1, mut rx1) = mpsc::unbounded_channel();
+    let (tx2, mut rx2) = mpsc::unbounded_channel();
+    mgr.join_room(pid(2), room, tx2).await.unwrap();
+    mgr.join_room(pid(1), room, tx1).await.unwrap();
+
+    // The welcome follows the initial state snapshot.
+    assert!(matches!(rx1.try_recv(), Ok(RoomOutbound::State(_))));
+    let welcome = DiceEvent::Welcome {
+        room,
+        players: vec![pid(1), pid(2)],
+    };
+    assert_eq!(drain_dice(&mut rx1), vec![welcome.clone()]);
+    assert_eq!(drain_dice(&mut rx2), vec![welcome]);
+}
+
+#[tokio::test]
+async fn test_context_messages_go_out_before_returned_ones() {
+    let mut mgr = RoomManager::<DiceGame>::new();
+    let room = mgr.create_room(());
+    let (tx1, mut rx1) = mpsc::unbounded_channel();
+    let (tx2, mut rx2) = mpsc::unbounded_channel();
+    mgr.join_room(pid(1), room, tx1).await.unwrap();
+    mgr.join_room(pid(2), room, tx2).await.unwrap();
+    drain_dice(&mut rx1);
+    drain_dice(&mut rx2);
+
+    mgr.route_message(pid(1), ()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let events = drain_dice(&mut rx1);
+    assert_eq!(events.len(), 2, "got {events:?}");
+    assert!(matches!(events[0], DiceEvent::Rolled(1..=6)));
+    assert_eq!(events[1], DiceEvent::Returned);
+    assert_eq!(drain_dice(&mut rx2), events);
+}
+
+#[tokio::test]
+async fn test_send_after_waits_for_delay() {
+    let mut mgr = RoomManager::<DiceGame>::new();
+    let room = mgr.create_room(());
+    let (tx1, mut rx1) = mpsc::unbounded_channel();
+    let (tx2, mut rx2) = mpsc::unbounded_channel();
+    mgr.join_room(pid(1), room, tx1).await.unwrap();
+    mgr.join_room(pid(2), room, tx2).await.unwrap();
+
+    mgr.route_message(pid(1), ()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    drain_dice(&mut rx1);
+    drain_dice(&mut rx2);
+
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert!(drain_dice(&mut rx1).is_empty());
+
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    assert_eq!(drain_dice(&mut rx1), vec![DiceEvent::Reminder]);
+    assert!(drain_dice(&mut rx2).is_empty());
+}
+
+/// Plays `rolls` dice rolls in a fresh room and returns the results.
+async fn roll_dice(
+    mgr: &mut RoomManager<DiceGame>,
+    seed: Option<u64>,
+    first_player: u64,
+    rolls: usize,
+) -> (u64, Vec<u32>) {
+    let options = RoomOptions {
+        seed,
+        ..Default::default()
+    };
+    let room = mgr.create_room_with((), options);
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    mgr.join_room(pid(first_player), room, tx).await.unwrap();
+    mgr.join_room(pid(first_player + 1), room, dummy_sender())
+        .await
+        .unwrap();
+
+    for _ in 0..rolls {
+        mgr.route_message(pid(first_player), ()).await.unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    let results = drain_dice(&mut rx)
+        .into_iter()
+        .filter_map(|event| match event {
+            DiceEvent::Rolled(n) => Some(n),
+            _ => None,
+        })
+        .collect();
+    (mgr.get_room_info(room).await.unwrap().seed, results)
+}
+
+#[tokio::test]
+async fn test_same_seed_replays_same_rolls() {
+    let mut mgr = RoomManager::<DiceGame>::new();
+    let (seed, first) = roll_dice(&mut mgr, Some(42), 1, 10).await;
+    let (_, second) = roll_dice(&mut mgr, Some(42), 3, 10).await;
+    let (_, other) = roll_dice(&mut mgr, Some(43), 5, 10).await;
+
+    assert_eq!(seed, 42);
+    assert_eq!(first.len(), 10);
+    assert_eq!(first, second);
+    assert_ne!(first, other);
+}
+
+#[tokio::test]
+async fn test_create_seeded_room_pins_the_seed() {
+    let mut mgr = RoomManager::<DiceGame>::new();
+    let (_, expected) = roll_dice(&mut mgr, Some(42), 1, 5).await;
+
+    let room = mgr.create_seeded_room((), 42);
+    assert_eq!(mgr.get_room_info(room).await.unwrap().seed, 42);
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    mgr.join_room(pid(3), room, tx).await.unwrap();
+    mgr.join_room(pid(4), room, dummy_sender()).await.unwrap();
+    for _ in 0..5 {
+        mgr.route_message(pid(3), ()).await.unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let rolls: Vec<u32> = drain_dice(&mut rx)
+        .into_iter()
+        .filter_map(|event| match event {
+            DiceEvent::Rolled(n) => Some(n),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(rolls, expected);
+}
+
+#[tokio::test]
+async fn test_unseeded_rooms_record_their_seed() {
+    let mut mgr = RoomManager::<DiceGame>::new();
+    let (seed, rolls) = roll_dice(&mut mgr, None, 1, 10).await;
+    let (_, replayed) = roll_dice(&mut mgr, Some(seed), 3, 10).await;
+    assert_eq!(rolls, replayed);
+}
+
+// =========================================================================
+// Game timers
+// =========================================================================
+
+/// Sets up a room hosted by player 1. Player 2's channel is returned.
+async fn timer_room(
+    mgr: &mut RoomManager<TimerGame>,
+) -> mpsc::UnboundedReceiver<RoomOutbound<TimerGame>> {
+    let room = mgr
+        .create_and_join(pid(1), (), RoomOptions::default(), dummy_sender())
+        .await
+        .unwrap();
+    let (tx, rx) = mpsc::unbounded_channel();
+    mgr.join_room(pid(2), room, tx).await.unwrap();
+    rx
+}
+
+fn fired(
+    rx: &mut mpsc::UnboundedReceiver<RoomOutbound<TimerGame>>,
+) -> Vec<String> {
+    let mut ids = Vec::new();
+    while let Ok(outbound) = rx.try_recv() {
+        if let RoomOutbound::Message(id) = outbound {
+            ids.push(id);
+        }
+    }
+    ids
+}
+
+fn set(id: &str, ms: u64) -> TimerCmd {
+    TimerCmd::Set(id.into(), ms)
+}
+
+#[tokio::test]
+async fn test_timers_fire_in_deadline_order() {
+    let mut mgr = RoomManager::<TimerGame>::new();
+    let mut rx = timer_room(&mut mgr).await;
+    mgr.start_game(pid(1)).await.unwrap();
+
+    mgr.route_message(pid(1), set("slow", 80)).await.unwrap();
+    mgr.route_message(pid(1), set("fast", 40)).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(fired(&mut rx).is_empty());
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(fired(&mut rx), vec!["fast", "slow"]);
+
+    // Timers fire once.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(fired(&mut rx).is_empty());
+}
+
+#[tokio::test]
+async fn test_cancelled_timer_does_not_fire() {
+    let mut mgr = RoomManager::<TimerGame>::new();
+    let mut rx = timer_room(&mut mgr).await;
+    mgr.start_game(pid(1)).await.unwrap();
+
+    mgr.route_message(pid(1), set("turn", 50)).await.unwrap();
+    mgr.route_message(pid(1), TimerCmd::Cancel("turn".into()))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(fired(&mut rx).is_empty());
+}
+
+#[tokio::test]
+async fn test_setting_running_timer_restarts_it() {
+    let mut mgr = RoomManager::<TimerGame>::new();
+    let mut rx = timer_room(&mut mgr).await;
+    mgr.start_game(pid(1)).await.unwrap();
+
+    mgr.route_message(pid(1), set("turn", 60)).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(40)).await;
+    mgr.route_message(pid(1), set("turn", 60)).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(40)).await;
+    assert!(fired(&mut rx).is_empty());
+
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    assert_eq!(fired(&mut rx), vec!["turn"]);
+}
+
+#[tokio::test]
+async fn test_paused_timers_keep_their_remaining_time() {
+    let mut mgr = RoomManager::<TimerGame>::new();
+    let mut rx = timer_room(&mut mgr).await;
+    mgr.start_game(pid(1)).await.unwrap();
+
+    mgr.route_message(pid(1), set("turn", 100)).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    mgr.route_message(pid(1), TimerCmd::Pause).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert!(fired(&mut rx).is_empty());
+
+    // About 50ms were left when the room paused.
+    mgr.route_message(pid(1), TimerCmd::Resume).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(fired(&mut rx).is_empty());
+    tokio::time::sleep(Duration::from_millis(80)).await;
+    assert_eq!(fired(&mut rx), vec!["turn"]);
+}
+
+#[tokio::test]
+async fn test_elapsed_leaves_out_time_spent_paused() {
+    let mut mgr = RoomManager::<TimerGame>::new();
+    let mut rx = timer_room(&mut mgr).await;
+    mgr.start_game(pid(1)).await.unwrap();
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    mgr.route_message(pid(1), TimerCmd::Pause).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    mgr.route_message(pid(1), TimerCmd::Elapsed).await.unwrap();
+    mgr.route_message(pid(1), TimerCmd::Resume).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    mgr.route_message(pid(1), TimerCmd::Elapsed).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let elapsed: Vec<u64> = fired(&mut rx)
+        .iter()
+        .map(|ms| ms.parse().unwrap())
+        .collect();
+    let [paused, resumed] = elapsed[..] else {
+        panic!("expected two replies, got {elapsed:?}");
+    };
+    assert!((50..150).contains(&paused), "paused at {paused}ms");
+    assert!(resumed >= paused + 20, "resumed at {resumed}ms");
+    assert!(resumed < 200, "resumed at {resumed}ms");
+}
+
+#[tokio::test]
+async fn test_host_pauses_game() {
+    let mut mgr = RoomManager::<TimerGame>::new();
+    let mut rx = timer_room(&mut mgr).await;
+    let room = mgr.player_room(&pid(1)).unwrap();
+
+    // Nothing to pause before the game starts.
+    let result = mgr.set_room_paused(pid(1), true).await;
+    assert!(matches!(result, Err(RoomError::InvalidState(_))));
+    mgr.start_game(pid(1)).await.unwrap();
+
+    let result = mgr.set_room_paused(pid(2), true).await;
+    assert!(matches!(result, Err(RoomError::NotHost(..))));
+
+    mgr.route_message(pid(2), set("turn", 50)).await.unwrap();
+    mgr.set_room_paused(pid(1), true).await.unwrap();
+    assert!(mgr.get_room_info(room).await.unwrap().paused);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(fired(&mut rx).is_empty());
+
+    mgr.set_room_paused(pid(1), false).await.unwrap();
+    assert!(!mgr.get_room_info(room).await.unwrap().paused);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(fired(&mut rx), vec!["turn"]);
+}
+
+// =========================================================================
+// Per-player views
+// =========================================================================
+
+/// Returns the view in the next state snapshot on `rx`.
+fn next_view(
+    rx: &mut mpsc::UnboundedReceiver<RoomOutbound<SecretCardGame>>,
+) -> SecretView {
+    loop {
+        match rx.try_recv().expect("expected a state snapshot") {
+            RoomOutbound::State(view) => return view,
+            _ => continue,
+        }
+    }
+}
+
+/// Asserts that a serialized view contains none of `others`' cards.
+fn assert_no_foreign_cards(view: &SecretView, others: &[PlayerId]) {
+    let json = serde_json::to_string(view).unwrap();
+    for other in others {
+        assert!(
+            !json.contains(&card_for(*other).to_string()),
+            "{json} leaks the card of {other}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_each_player_sees_only_own_card_at_start() {
+    let mut mgr = RoomManager::<SecretCardGame>::new();
+    let room = mgr.create_room(CounterConfig::default());
+    let (tx1, mut rx1) = mpsc::unbounded_channel();
+    let (tx2, mut rx2) = mpsc::unbounded_channel();
+    mgr.join_room(pid(1), room, tx1).await.unwrap();
+    mgr.join_room(pid(2), room, tx2).await.unwrap();
+
+    let view1 = next_view(&mut rx1);
+    let view2 = next_view(&mut rx2);
+
+    assert_eq!(
+        view1,
+        SecretView {
+            my_card: Some(card_for(pid(1))),
+            opponents: 1,
+        }
+    );
+    assert_eq!(view2.my_card, Some(card_for(pid(2))));
+    assert_no_foreign_cards(&view1, &[pid(2)]);
+    assert_no_foreign_cards(&view2, &[pid(1)]);
+}
+
+#[tokio::test]
+async fn test_late_joiner_sees_only_own_card() {
+    let mut mgr = RoomManager::<SecretCardGame>::new();
+    let room = mgr.create_room(CounterConfig::default());
+    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+    mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
+
+    let (tx3, mut rx3) = mpsc::unbounded_channel();
+    mgr.join_room(pid(3), room, tx3).await.unwrap();
+
+    let view3 = next_view(&mut rx3);
+    assert_eq!(view3.my_card, Some(card_for(pid(3))));
+    assert_eq!(view3.opponents, 2);
+    assert_no_foreign_cards(&view3, &[pid(1), pid(2)]);
+}
+
+// =========================================================================
+// Late join
+// =========================================================================
+
+/// Starts an arena with players 1 and 2, returning player 1's channel.
+async fn running_arena(
+    mgr: &mut RoomManager<ArenaGame>,
+) -> (RoomId, mpsc::UnboundedReceiver<RoomOutbound<ArenaGame>>) {
+    let room = mgr.create_room(CounterConfig::default());
+    let (tx1, mut rx1) = mpsc::unbounded_channel();
+    mgr.join_room(pid(1), room, tx1).await.unwrap();
+    mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
+    while rx1.try_recv().is_ok() {}
+    (room, rx1)
+}
+
+#[tokio::test]
+async fn test_late_joiner_gets_snapshot() {
+    let mut mgr = RoomManager::<ArenaGame>::new();
+    let (room, mut rx1) = running_arena(&mut mgr).await;
+
+    let (tx3, mut rx3) = mpsc::unbounded_channel();
+    mgr.join_room(pid(3), room, tx3).await.unwrap();
+
+    match rx3.try_recv().unwrap() {
+        RoomOutbound::State(state) => {
+            assert_eq!(state.players, vec![pid(1), pid(2), pid(3)]);
+        }
+        other => panic!("expected State, got {other:?}"),
+    }
+    assert!(rx3.try_recv().is_err(), "joiner shouldn't see own spawn");
+
+    match rx1.try_recv().unwrap() {
+        RoomOutbound::Message(event) => {
+            assert_eq!(event, ArenaEvent::Spawned(pid(3)));
+        }
+        other => panic!("expected Spawned, got {other:?}"),
+    }
+
+    let info = mgr.get_room_info(room).await.unwrap();
+    assert_eq!(info.state, RoomState::InProgress);
+    assert_eq!(info.player_count, 3);
+}
+
+#[tokio::test]
+async fn test_leave_calls_on_player_leave() {
+    let mut mgr = RoomManager::<ArenaGame>::new();
+    let (_room, mut rx1) = running_arena(&mut mgr).await;
+
+    mgr.leave_room(pid(2)).await.unwrap();
+
+    match rx1.try_recv().unwrap() {
+        RoomOutbound::Message(event) => {
+            assert_eq!(event, ArenaEvent::Left(pid(2)));
+        }
+        other => panic!("expected Left, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_in_progress_drop_in_room_is_listed() {
+    let mut mgr = RoomManager::<ArenaGame>::new();
+    let (room, _rx1) = running_arena(&mut mgr).await;
+
+    let rooms = mgr.list_rooms().await;
+    assert_eq!(rooms.len(), 1);
+    assert_eq!(rooms[0].room_id, room);
+    assert_eq!(rooms[0].state, RoomState::InProgress);
+    assert!(rooms[0].accepts_joins);
+
+    // join_or_create drops new players into the running game.
+    let joined = mgr
+        .join_or_create(pid(3), CounterConfig::default(), dummy_sender())
+        .await
+        .unwrap();
+    assert_eq!(joined, room);
+}
+
+#[tokio::test]
+async fn test_late_join_still_respects_max_players() {
+    let mut mgr = RoomManager::<ArenaGame>::new();
+    let (room, _rx1) = running_arena(&mut mgr).await;
+    mgr.join_room(pid(3), room, dummy_sender()).await.unwrap();
+    mgr.join_room(pid(4), room, dummy_sender()).await.unwrap();
+
+    let result = mgr.join_room(pid(5), room, dummy_sender()).await;
+    assert!(matches!(result, Err(RoomError::RoomFull(_))));
+}
+
+// =========================================================================
+// Pre-game ready check
+// =========================================================================
+
+#[tokio::test]
+async fn test_countdown_starts_when_room_fills() {
+    let mut mgr = RoomManager::<PregameGame>::new();
+    let room = mgr.create_room(CounterConfig::default());
+    let (tx1, mut rx1) = mpsc::unbounded_channel();
+    mgr.join_room(pid(1), room, tx1).await.unwrap();
+    mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
 
     let info = mgr.get_room_info(room).await.unwrap();
-
-    assert_eq!(info.room_id, room);
-    assert_eq!(info.player_count, 1);
-    assert_eq!(info.max_players, 4);
-    assert_eq!(info.state, RoomState::WaitingForPlayers);
+    assert_eq!(info.state, RoomState::Starting);
+    assert!(matches!(
+        drain_system(&mut rx1).as_slice(),
+        [SystemMessage::Countdown { remaining_ms: 200 }]
+    ));
 }
 
 #[tokio::test]
-async fn test_auto_start_when_min_players_reached() {
-    let mut mgr = RoomManager::<CounterGame>::new();
+async fn test_game_starts_when_everyone_is_ready() {
+    let mut mgr = RoomManager::<PregameGame>::new();
     let room = mgr.create_room(CounterConfig::default());
+    let (tx1, mut rx1) = mpsc::unbounded_channel();
+    mgr.join_room(pid(1), room, tx1).await.unwrap();
+    mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
+    drain_system(&mut rx1);
 
-    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
-    let info = mgr.get_room_info(room).await.unwrap();
-    assert_eq!(info.state, RoomState::WaitingForPlayers);
+    mgr.set_ready(pid(1), true).await.unwrap();
+    assert_eq!(
+        drain_system(&mut rx1),
+        vec![SystemMessage::ReadyChanged {
+            player_id: pid(1),
+            ready: true,
+        }]
+    );
+    assert_eq!(
+        mgr.get_room_info(room).await.unwrap().state,
+        RoomState::Starting
+    );
 
-    // min_players is 2 — joining second player should auto-start
-    mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
-    let info = mgr.get_room_info(room).await.unwrap();
-    assert_eq!(info.state, RoomState::InProgress);
+    mgr.set_ready(pid(2), true).await.unwrap();
+    assert_eq!(
+        mgr.get_room_info(room).await.unwrap().state,
+        RoomState::InProgress
+    );
 }
 
 #[tokio::test]
-async fn test_cannot_join_after_game_started() {
-    let mut mgr = RoomManager::<CounterGame>::new();
+async fn test_game_starts_when_countdown_expires() {
+    let mut mgr = RoomManager::<PregameGame>::new();
     let room = mgr.create_room(CounterConfig::default());
-    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+    let (tx1, mut rx1) = mpsc::unbounded_channel();
+    mgr.join_room(pid(1), room, tx1).await.unwrap();
     mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
-    // Game is now InProgress
 
-    let result = mgr.join_room(pid(3), room, dummy_sender()).await;
-    assert!(result.is_err(), "should not join a running game");
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    assert_eq!(
+        mgr.get_room_info(room).await.unwrap().state,
+        RoomState::InProgress
+    );
+    let countdowns: Vec<u64> = drain_system(&mut rx1)
+        .into_iter()
+        .filter_map(|msg| match msg {
+            SystemMessage::Countdown { remaining_ms } => Some(remaining_ms),
+            _ => None,
+        })
+        .collect();
+    assert!(countdowns.len() > 1, "got {countdowns:?}");
+    assert!(countdowns.windows(2).all(|w| w[0] > w[1]));
 }
 
 #[tokio::test]
-async fn test_route_message() {
-    let mut mgr = RoomManager::<CounterGame>::new();
-    let room = mgr.create_room(CounterConfig { finish_at: 100 });
-    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+async fn test_leaving_cancels_countdown() {
+    let mut mgr = RoomManager::<PregameGame>::new();
+    let room = mgr.create_room(CounterConfig::default());
+    let (tx1, mut rx1) = mpsc::unbounded_channel();
+    mgr.join_room(pid(1), room, tx1).await.unwrap();
     mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
+    mgr.set_ready(pid(1), true).await.unwrap();
+    drain_system(&mut rx1);
 
-    // Game is InProgress, send a message
-    mgr.route_message(pid(1), Increment).await.unwrap();
+    mgr.leave_room(pid(2)).await.unwrap();
 
-    // Give the actor a moment to process
-    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert_eq!(
+        drain_system(&mut rx1),
+        vec![SystemMessage::CountdownCancelled]
+    );
+    assert_eq!(
+        mgr.get_room_info(room).await.unwrap().state,
+        RoomState::WaitingForPlayers
+    );
 
-    let info = mgr.get_room_info(room).await.unwrap();
-    assert_eq!(info.state, RoomState::InProgress);
+    // The countdown doesn't resume on its own.
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(
+        mgr.get_room_info(room).await.unwrap().state,
+        RoomState::WaitingForPlayers
+    );
 }
 
 #[tokio::test]
-async fn test_route_message_not_in_room() {
-    let mgr = RoomManager::<CounterGame>::new();
-    let result = mgr.route_message(pid(1), Increment).await;
-    assert!(result.is_err());
+async fn test_cancelled_countdown_waits_for_the_next_ready() {
+    let mut mgr = RoomManager::<PregameGame>::new();
+    let room = mgr.create_room(CounterConfig::default());
+    let (tx1, mut rx1) = mpsc::unbounded_channel();
+    let group = vec![
+        (pid(1), tx1),
+        (pid(2), dummy_sender()),
+        (pid(3), dummy_sender()),
+    ];
+    mgr.join_room_together(group, room, JoinCredentials::default())
+        .await
+        .unwrap();
+    mgr.set_ready(pid(1), true).await.unwrap();
+    mgr.set_ready(pid(2), true).await.unwrap();
+    drain_system(&mut rx1);
+
+    // Two players are still enough to start, but the countdown stops.
+    mgr.leave_room(pid(3)).await.unwrap();
+    assert_eq!(
+        drain_system(&mut rx1),
+        vec![SystemMessage::CountdownCancelled]
+    );
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(
+        mgr.get_room_info(room).await.unwrap().state,
+        RoomState::WaitingForPlayers
+    );
+
+    // Readiness was reset, so one ready restarts the countdown but
+    // doesn't start the game.
+    mgr.set_ready(pid(1), true).await.unwrap();
+    assert_eq!(
+        mgr.get_room_info(room).await.unwrap().state,
+        RoomState::Starting
+    );
+    assert!(drain_system(&mut rx1)
+        .iter()
+        .any(|msg| matches!(msg, SystemMessage::Countdown { .. })));
+    mgr.set_ready(pid(2), true).await.unwrap();
+    assert_eq!(
+        mgr.get_room_info(room).await.unwrap().state,
+        RoomState::InProgress
+    );
 }
 
 #[tokio::test]
-async fn test_destroy_room() {
+async fn test_ready_requires_pregame_phase() {
     let mut mgr = RoomManager::<CounterGame>::new();
     let room = mgr.create_room(CounterConfig::default());
     mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
 
-    mgr.destroy_room(room).await.unwrap();
-
-    assert_eq!(mgr.room_count(), 0);
-    assert_eq!(mgr.player_room(&pid(1)), None);
+    let result = mgr.set_ready(pid(1), true).await;
+    assert!(matches!(result, Err(RoomError::InvalidState(_))));
 }
 
+// =========================================================================
+// Automatic disposal
+// =========================================================================
+
 #[tokio::test]
-async fn test_destroy_room_not_found() {
-    let mut mgr = RoomManager::<CounterGame>::new();
-    let result = mgr.destroy_room(arcforge_protocol::RoomId(999)).await;
-    assert!(result.is_err());
+async fn test_empty_room_is_disposed() {
+    let mut mgr = RoomManager::<ShortLivedGame>::new();
+    let room = mgr.create_room(CounterConfig::default());
+    let code = mgr.invite_code(room).unwrap().to_string();
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    assert_eq!(mgr.collect_disposed(), vec![room]);
+    assert_eq!(mgr.room_count(), 0);
+    assert_eq!(mgr.invite_code(room), None);
+    let result = mgr.join_by_code(pid(1), &code, None, dummy_sender()).await;
+    assert!(matches!(result, Err(RoomError::InvalidInviteCode(_))));
 }
 
 #[tokio::test]
-async fn test_room_ids() {
-    let mut mgr = RoomManager::<CounterGame>::new();
-    let r1 = mgr.create_room(CounterConfig::default());
-    let r2 = mgr.create_room(CounterConfig::default());
+async fn test_room_disposed_after_last_player_leaves() {
+    let mut mgr = RoomManager::<ShortLivedGame>::new();
+    let room = mgr.create_room(CounterConfig::default());
+    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
 
-    let mut ids = mgr.room_ids();
-    ids.sort_by_key(|r| r.0);
-    assert_eq!(ids.len(), 2);
-    assert!(ids.contains(&r1));
-    assert!(ids.contains(&r2));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(mgr.collect_disposed().is_empty());
+
+    mgr.leave_room(pid(1)).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(mgr.collect_disposed(), vec![room]);
 }
 
 #[tokio::test]
-async fn test_game_finishes_on_target() {
-    let mut mgr = RoomManager::<CounterGame>::new();
-    let room = mgr.create_room(CounterConfig { finish_at: 2 });
-    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+async fn test_finished_room_is_disposed() {
+    let mut mgr = RoomManager::<ShortLivedGame>::new();
+    let config = CounterConfig {
+        finish_at: 1,
+        ..Default::default()
+    };
+    let room = mgr.create_room(config);
+    let (tx1, mut rx1) = mpsc::unbounded_channel();
+    mgr.join_room(pid(1), room, tx1).await.unwrap();
     mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
 
-    // Send 2 increments to reach the target
-    mgr.route_message(pid(1), Increment).await.unwrap();
     mgr.route_message(pid(1), Increment).await.unwrap();
-
-    tokio::time::sleep(Duration::from_millis(10)).await;
-
     let info = mgr.get_room_info(room).await.unwrap();
     assert_eq!(info.state, RoomState::Finished);
-}
 
-#[tokio::test]
-async fn test_list_rooms_empty() {
-    let mgr = RoomManager::<CounterGame>::new();
-    let rooms = mgr.list_rooms().await;
-    assert!(rooms.is_empty());
-}
-
-#[tokio::test]
-async fn test_list_rooms_returns_joinable_only() {
-    let mut mgr = RoomManager::<CounterGame>::new();
-    let r1 = mgr.create_room(CounterConfig::default());
-    let r2 = mgr.create_room(CounterConfig::default());
+    tokio::time::sleep(Duration::from_millis(100)).await;
 
-    // r2 gets filled → starts → no longer joinable
-    mgr.join_room(pid(10), r2, dummy_sender()).await.unwrap();
-    mgr.join_room(pid(11), r2, dummy_sender()).await.unwrap();
-    tokio::time::sleep(Duration::from_millis(10)).await;
+    // Players are told, and their outbound channels close with the room.
+    let closed = SystemMessage::RoomClosed {
+        room_id: room,
+        reason: "finished".into(),
+    };
+    assert_eq!(drain_system(&mut rx1).last(), Some(&closed));
+    assert!(rx1.recv().await.is_none());
 
-    let rooms = mgr.list_rooms().await;
-    assert_eq!(rooms.len(), 1);
-    assert_eq!(rooms[0].room_id, r1);
+    // The next mutating call cleans up the player index.
+    mgr.join_room(pid(3), room, dummy_sender())
+        .await
+        .expect_err("room is gone");
+    assert_eq!(mgr.room_count(), 0);
+    assert_eq!(mgr.player_room(&pid(1)), None);
+    assert_eq!(mgr.player_room(&pid(2)), None);
 }
 
 #[tokio::test]
-async fn test_join_or_create_creates_when_empty() {
-    let mut mgr = RoomManager::<CounterGame>::new();
-    let room_id = mgr
-        .join_or_create(pid(1), CounterConfig::default(), dummy_sender())
-        .await
-        .unwrap();
+async fn test_pending_rematch_vote_holds_off_disposal() {
+    let mut mgr = RoomManager::<ShortLivedGame>::new();
+    let config = CounterConfig {
+        finish_at: 1,
+        ..Default::default()
+    };
+    let room = mgr.create_room(config);
+    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+    mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
+    mgr.route_message(pid(1), Increment).await.unwrap();
+    mgr.set_rematch(pid(1), true).await.unwrap();
 
-    assert_eq!(mgr.room_count(), 1);
-    assert_eq!(mgr.player_room(&pid(1)), Some(room_id));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let info = mgr.get_room_info(room).await.unwrap();
+    assert_eq!(info.state, RoomState::Finished);
+
+    // Once nobody's voting, the room goes as usual.
+    mgr.set_rematch(pid(1), false).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(mgr.get_room_info(room).await.is_err());
 }
 
 #[tokio::test]
-async fn test_join_or_create_joins_existing() {
-    let mut mgr = RoomManager::<CounterGame>::new();
-    let _r1 = mgr.create_room(CounterConfig::default());
+async fn test_idle_room_is_disposed() {
+    let mut mgr = RoomManager::<ShortLivedGame>::new();
+    let config = CounterConfig {
+        finish_at: 100,
+        ..Default::default()
+    };
+    let room = mgr.create_room(config);
+    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+    mgr.join_room(pid(2), room, dummy_sender()).await.unwrap();
 
-    let room_id = mgr
-        .join_or_create(pid(1), CounterConfig::default(), dummy_sender())
-        .await
-        .unwrap();
+    // Activity keeps the room alive past the idle limit...
+    for _ in 0..4 {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        mgr.route_message(pid(1), Increment).await.unwrap();
+    }
+    assert!(mgr.collect_disposed().is_empty());
 
-    // Should have joined the existing room, not created a new one.
-    assert_eq!(mgr.room_count(), 1);
-    assert_eq!(room_id, _r1);
+    // ...and silence ends it.
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    assert_eq!(mgr.collect_disposed(), vec![room]);
+    assert_eq!(mgr.player_room(&pid(1)), None);
 }
 
 #[tokio::test]
-async fn test_join_or_create_already_in_room() {
-    let mut mgr = RoomManager::<CounterGame>::new();
-    mgr.join_or_create(pid(1), CounterConfig::default(), dummy_sender())
-        .await
-        .unwrap();
+async fn test_destroyed_room_not_reported_as_disposed() {
+    let mut mgr = RoomManager::<ShortLivedGame>::new();
+    let room = mgr.create_room(CounterConfig::default());
+    mgr.destroy_room(room).await.unwrap();
 
-    let result = mgr
-        .join_or_create(pid(1), CounterConfig::default(), dummy_sender())
-        .await;
-    assert!(result.is_err());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(mgr.collect_disposed().is_empty());
 }
 
 // =========================================================================
@@ -406,7 +3731,7 @@ async fn test_state_broadcast_on_game_start() {
     use arcforge_room::RoomOutbound;
 
     let mut mgr = RoomManager::<CounterGame>::new();
-    let room = mgr.create_room(CounterConfig { finish_at: 10 });
+    let room = mgr.create_room(CounterConfig { finish_at: 10, ..Default::default() });
 
     let (tx1, mut rx1) = mpsc::unbounded_channel();
     let (tx2, mut rx2) = mpsc::unbounded_channel();
@@ -429,7 +3754,7 @@ async fn test_game_message_broadcast() {
     use arcforge_room::RoomOutbound;
 
     let mut mgr = RoomManager::<CounterGame>::new();
-    let room = mgr.create_room(CounterConfig { finish_at: 10 });
+    let room = mgr.create_room(CounterConfig { finish_at: 10, ..Default::default() });
 
     let (tx1, mut rx1) = mpsc::unbounded_channel();
     let (tx2, mut rx2) = mpsc::unbounded_channel();
@@ -462,7 +3787,7 @@ async fn test_game_message_broadcast() {
 #[tokio::test]
 async fn test_leave_stops_receiving() {
     let mut mgr = RoomManager::<CounterGame>::new();
-    let room = mgr.create_room(CounterConfig { finish_at: 10 });
+    let room = mgr.create_room(CounterConfig { finish_at: 10, ..Default::default() });
 
     let (tx1, mut rx1) = mpsc::unbounded_channel();
     let (tx2, _rx2) = mpsc::unbounded_channel();
@@ -483,3 +3808,291 @@ async fn test_leave_stops_receiving() {
 
     assert!(rx1.try_recv().is_err());
 }
+
+// =========================================================================
+// Teams
+// =========================================================================
+
+#[tokio::test]
+async fn test_teams_are_filled_evenly_and_passed_to_init() {
+    let mut mgr = RoomManager::<TeamGame>::new();
+    let room = mgr.create_room(());
+    let mut receivers = Vec::new();
+    for i in 1..=4 {
+        let (tx, rx) = mpsc::unbounded_channel();
+        mgr.join_room(pid(i), room, tx).await.unwrap();
+        receivers.push(rx);
+    }
+
+    assert_eq!(teams_seen(&mut receivers[0]), vec![vec![1, 3], vec![2, 4]]);
+
+    // Only the sender's team sees the message; the other team hears of it.
+    mgr.route_message(pid(1), Increment).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    let events = |rx: &mut mpsc::UnboundedReceiver<RoomOutbound<TeamGame>>| {
+        let mut events = Vec::new();
+        while let Ok(outbound) = rx.try_recv() {
+            if let RoomOutbound::Message(event) = outbound {
+                events.push(event);
+            }
+        }
+        events
+    };
+    assert_eq!(events(&mut receivers[2]), vec![TeamEvent::Teammate(pid(1))]);
+    assert_eq!(events(&mut receivers[1]), vec![TeamEvent::Opponent]);
+    assert_eq!(events(&mut receivers[3]), vec![TeamEvent::Opponent]);
+}
+
+#[tokio::test]
+async fn test_players_joining_together_share_a_team() {
+    let mut mgr = RoomManager::<TeamGame>::new();
+    let room = mgr.create_room(());
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    mgr.join_room(pid(1), room, tx).await.unwrap();
+
+    // Team 1 is empty, so the pair goes there together.
+    let pair = vec![(pid(2), dummy_sender()), (pid(3), dummy_sender())];
+    mgr.join_room_together(pair, room, JoinCredentials::default())
+        .await
+        .unwrap();
+    mgr.join_room(pid(4), room, dummy_sender()).await.unwrap();
+
+    assert_eq!(teams_seen(&mut rx), vec![vec![1, 4], vec![2, 3]]);
+}
+
+#[tokio::test]
+async fn test_group_too_big_for_one_team_is_split() {
+    let mut mgr = RoomManager::<TeamGame>::new();
+    let room = mgr.create_room(());
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let group = std::iter::once((pid(1), tx))
+        .chain((2..=4).map(|i| (pid(i), dummy_sender())))
+        .collect();
+    mgr.join_room_together(group, room, JoinCredentials::default())
+        .await
+        .unwrap();
+
+    assert_eq!(teams_seen(&mut rx), vec![vec![1, 3], vec![2, 4]]);
+}
+
+#[tokio::test]
+async fn test_teams_are_balanced_by_rating() {
+    // Higher IDs are better players.
+    let mut mgr = RoomManager::<TeamGame>::new()
+        .with_ratings(|player| player.0 as f64 * 100.0);
+    let room = mgr.create_room(());
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    mgr.join_room(pid(4), room, tx).await.unwrap();
+    for i in [3, 2, 1] {
+        mgr.join_room(pid(i), room, dummy_sender()).await.unwrap();
+    }
+
+    // 4 + 1 against 3 + 2, rather than 4 + 2 against 3 + 1.
+    assert_eq!(teams_seen(&mut rx), vec![vec![4, 1], vec![3, 2]]);
+}
+
+#[tokio::test]
+async fn test_results_carry_each_players_team() {
+    let sink = Arc::new(CollectSink::default());
+    let mut mgr =
+        RoomManager::<TeamGame>::new().with_result_sink(sink.clone());
+    let room = mgr.create_room(());
+    for i in 1..=4 {
+        mgr.join_room(pid(i), room, dummy_sender()).await.unwrap();
+    }
+    for _ in 0..3 {
+        mgr.route_message(pid(1), Increment).await.unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let records = sink.0.lock().unwrap().clone();
+    let result = &records[0].result;
+    for (player, team) in [(1, 0), (2, 1), (3, 0), (4, 1)] {
+        let entry = result.players.iter().find(|p| p.player_id == pid(player));
+        assert_eq!(entry.unwrap().team, Some(TeamId(team)));
+    }
+}
+
+// =========================================================================
+// Chat
+// =========================================================================
+
+/// The chat messages a player has been sent, as (from, text).
+fn chat_seen<G: GameLogic>(
+    rx: &mut mpsc::UnboundedReceiver<RoomOutbound<G>>,
+) -> Vec<(u64, String)> {
+    drain_system(rx)
+        .into_iter()
+        .filter_map(|msg| match msg {
+            SystemMessage::ChatMessage { from, text, .. } => {
+                Some((from.0, text))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Joins players `1..=count` to a new team room, which waits for a
+/// fourth player before it starts.
+async fn chat_room(
+    mgr: &mut RoomManager<TeamGame>,
+    count: u64,
+) -> Vec<mpsc::UnboundedReceiver<RoomOutbound<TeamGame>>> {
+    let room = mgr.create_room(());
+    let mut receivers = Vec::new();
+    for i in 1..=count {
+        let (tx, rx) = mpsc::unbounded_channel();
+        mgr.join_room(pid(i), room, tx).await.unwrap();
+        receivers.push(rx);
+    }
+    receivers
+}
+
+#[tokio::test]
+async fn test_chat_to_room_team_and_whisper_while_waiting() {
+    let mut mgr = RoomManager::<TeamGame>::new();
+    // Teams: 1 and 3 against 2.
+    let mut rx = chat_room(&mut mgr, 3).await;
+
+    mgr.send_chat(pid(1), "hello".into(), ChatScope::Room)
+        .await
+        .unwrap();
+    mgr.send_chat(pid(1), "flank left".into(), ChatScope::Team)
+        .await
+        .unwrap();
+    mgr.send_chat(pid(2), "psst".into(), ChatScope::Whisper(pid(3)))
+        .await
+        .unwrap();
+
+    let hello = (1, "hello".to_string());
+    let flank = (1, "flank left".to_string());
+    let psst = (2, "psst".to_string());
+    assert_eq!(chat_seen(&mut rx[0]), vec![hello.clone(), flank.clone()]);
+    assert_eq!(chat_seen(&mut rx[1]), vec![hello.clone(), psst.clone()]);
+    assert_eq!(chat_seen(&mut rx[2]), vec![hello, flank, psst]);
+
+    let info = mgr.get_room_info(mgr.player_room(&pid(1)).unwrap()).await;
+    assert_eq!(info.unwrap().state, RoomState::WaitingForPlayers);
+}
+
+#[tokio::test]
+async fn test_late_joiners_get_the_chat_they_may_see() {
+    let mut mgr = RoomManager::<TeamGame>::new();
+    let mut rx = chat_room(&mut mgr, 2).await;
+    let room = mgr.player_room(&pid(1)).unwrap();
+    mgr.send_chat(pid(1), "hi all".into(), ChatScope::Room)
+        .await
+        .unwrap();
+    mgr.send_chat(pid(1), "team one".into(), ChatScope::Team)
+        .await
+        .unwrap();
+    mgr.send_chat(pid(2), "team two".into(), ChatScope::Team)
+        .await
+        .unwrap();
+    mgr.send_chat(pid(1), "secret".into(), ChatScope::Whisper(pid(2)))
+        .await
+        .unwrap();
+
+    // Player 3 joins player 1's team, player 4 player 2's.
+    let (tx, mut rx3) = mpsc::unbounded_channel();
+    mgr.join_room(pid(3), room, tx).await.unwrap();
+    let (tx, mut rx4) = mpsc::unbounded_channel();
+    mgr.join_room(pid(4), room, tx).await.unwrap();
+    assert_eq!(
+        chat_seen(&mut rx3),
+        vec![(1, "hi all".into()), (1, "team one".into())]
+    );
+    assert_eq!(
+        chat_seen(&mut rx4),
+        vec![(1, "hi all".into()), (2, "team two".into())]
+    );
+
+    // Chat carries on once the game is running.
+    let info = mgr.get_room_info(room).await.unwrap();
+    assert_eq!(info.state, RoomState::InProgress);
+    chat_seen(&mut rx[1]);
+    mgr.send_chat(pid(3), "gl".into(), ChatScope::Room)
+        .await
+        .unwrap();
+    assert_eq!(chat_seen(&mut rx[1]), vec![(3, "gl".into())]);
+}
+
+#[tokio::test]
+async fn test_muted_players_chat_is_hidden() {
+    let mut mgr = RoomManager::<TeamGame>::new();
+    let mut rx = chat_room(&mut mgr, 3).await;
+
+    mgr.mute_chat(pid(1), pid(2), true).await.unwrap();
+    mgr.send_chat(pid(2), "spam".into(), ChatScope::Room)
+        .await
+        .unwrap();
+    assert!(chat_seen(&mut rx[0]).is_empty());
+    assert_eq!(chat_seen(&mut rx[1]), vec![(2, "spam".into())]);
+    assert_eq!(chat_seen(&mut rx[2]), vec![(2, "spam".into())]);
+
+    mgr.mute_chat(pid(1), pid(2), false).await.unwrap();
+    mgr.send_chat(pid(2), "sorry".into(), ChatScope::Room)
+        .await
+        .unwrap();
+    assert_eq!(chat_seen(&mut rx[0]), vec![(2, "sorry".into())]);
+
+    assert!(matches!(
+        mgr.mute_chat(pid(1), pid(9), true).await,
+        Err(RoomError::NotInRoom(..))
+    ));
+}
+
+/// Turns away messages in all caps.
+struct NoShouting;
+
+impl ChatFilter for NoShouting {
+    fn filter(&self, _from: PlayerId, text: &str) -> Option<String> {
+        if text.chars().any(char::is_lowercase) {
+            Some(text.to_string())
+        } else {
+            None
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_chat_is_checked_and_filtered() {
+    let mut mgr = RoomManager::<TeamGame>::new()
+        .with_chat_filter(Arc::new(WordFilter::new(["darn"])));
+    let mut rx = chat_room(&mut mgr, 2).await;
+
+    mgr.send_chat(pid(1), "darn it".into(), ChatScope::Room)
+        .await
+        .unwrap();
+    assert_eq!(chat_seen(&mut rx[1]), vec![(1, "**** it".into())]);
+
+    for text in [String::new(), "  ".into(), "x".repeat(501)] {
+        assert!(matches!(
+            mgr.send_chat(pid(1), text, ChatScope::Room).await,
+            Err(RoomError::ChatRejected(_))
+        ));
+    }
+    assert!(matches!(
+        mgr.send_chat(pid(1), "hi".into(), ChatScope::Whisper(pid(9)))
+            .await,
+        Err(RoomError::NotInRoom(..))
+    ));
+
+    let mut mgr = RoomManager::<CounterGame>::new()
+        .with_chat_filter(Arc::new(NoShouting));
+    let room = mgr.create_room(CounterConfig::default());
+    mgr.join_room(pid(1), room, dummy_sender()).await.unwrap();
+    assert!(matches!(
+        mgr.send_chat(pid(1), "HEY".into(), ChatScope::Room).await,
+        Err(RoomError::ChatRejected(_))
+    ));
+    // No teams to talk to.
+    assert!(matches!(
+        mgr.send_chat(pid(1), "hey".into(), ChatScope::Team).await,
+        Err(RoomError::InvalidState(_))
+    ));
+    assert!(mgr
+        .send_chat(pid(1), "hey".into(), ChatScope::Room)
+        .await
+        .is_ok());
+}
@@ -18,7 +18,7 @@
 //!     tokio::select! {
 //!         Some(cmd) = cmd_rx.recv() => { /* handle commands */ }
 //!         tick_info = scheduler.wait_for_tick() => {
-//!             let msgs = G::tick(&mut state, tick_info.dt);
+//!             let msgs = G::tick(&mut state, tick_info.dt, inputs, &mut ctx);
 //!             scheduler.record_tick_end();
 //!         }
 //!     }
@@ -36,10 +36,11 @@ use tracing::{debug, trace, warn};
 // ---------------------------------------------------------------------------
 
 /// What to do when a tick takes longer than its budget.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum TickPolicy {
     /// Skip the missed tick(s) and resume from now.
     /// Safest default — prevents death spirals.
+    #[default]
     Skip,
     /// Run up to `max_catchup` extra ticks immediately.
     /// Use only when deterministic simulation replay is required.
@@ -53,12 +54,6 @@ pub enum TickPolicy {
     Drop,
 }
 
-impl Default for TickPolicy {
-    fn default() -> Self {
-        Self::Skip
-    }
-}
-
 /// Full configuration for the tick scheduler.
 #[derive(Debug, Clone)]
 pub struct TickConfig {
@@ -7,14 +7,19 @@
 //!   3. Send HandshakeAck → player is connected
 //!   4. Loop: receive envelopes → dispatch system or game messages
 
+use std::collections::VecDeque;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use arcforge_matchmaking::{PartyError, DEFAULT_RATING};
 use arcforge_protocol::{
-    Codec, Channel, Envelope, Payload, PlayerId, RoomListEntry,
-    SystemMessage,
+    Codec, Channel, Envelope, PartyId, PartyInfo, Payload, PlayerId,
+    RoomListEntry, SystemMessage,
+};
+use arcforge_room::{
+    GameLogic, JoinCredentials, PlayerSender, RoomError, RoomOptions,
+    RoomOutbound, RoomState,
 };
-use arcforge_room::{GameLogic, RoomOutbound};
 use arcforge_session::Authenticator;
 use arcforge_transport::{Connection, WebSocketConnection};
 use tokio::sync::mpsc;
@@ -22,10 +27,47 @@ use tokio::sync::mpsc;
 use crate::server::{ServerState, PROTOCOL_VERSION};
 use crate::ArcforgeError;
 
-/// Drop guard that disconnects a player's session when the handler exits.
+/// How often the server sends its own heartbeat to a player in a room,
+/// to measure their RTT.
+const SERVER_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How many unanswered server heartbeats a connection remembers. Acks
+/// for older ones are ignored.
+const MAX_PENDING_HEARTBEATS: usize = 4;
+
+/// What one connection's handler passes to another's.
+pub(crate) enum Notice<G: GameLogic> {
+    /// A system message to forward to the client (party traffic).
+    System(SystemMessage),
+    /// The player's party leader got them a seat or a place in the
+    /// queue. The room's (or queue's) traffic arrives on `rx`, after
+    /// `msg` if there is one.
+    Seated {
+        rx: mpsc::UnboundedReceiver<RoomOutbound<G>>,
+        msg: Option<SystemMessage>,
+    },
+}
+
+/// Where other connections send a connected player's [`Notice`]s.
+pub(crate) type Inbox<G> = mpsc::UnboundedSender<Notice<G>>;
+
+/// A room channel for each player in a group, and the other ends.
+type Channels<G> = (
+    Vec<(PlayerId, PlayerSender<G>)>,
+    Vec<(PlayerId, mpsc::UnboundedReceiver<RoomOutbound<G>>)>,
+);
+
+/// Drop guard that tells the player's room they're gone and disconnects
+/// their session when the handler exits.
 ///
 /// This ensures cleanup happens even if the handler panics. Since `Drop`
-/// is synchronous, we spawn a fire-and-forget task for the async lock.
+/// is synchronous, we spawn a fire-and-forget task for the async locks.
+/// A running game holds the player's seat so they can reconnect;
+/// otherwise they leave, which lets the room notice it's empty and
+/// dispose of itself. A player waiting for a match loses their place in
+/// the queue (taking their party with them), and leaves their party.
+/// The room is told before the session is released, so a reconnect
+/// never races ahead of it.
 struct SessionGuard<G: GameLogic, A: Authenticator, C: Codec> {
     player_id: PlayerId,
     state: Arc<ServerState<G, A, C>>,
@@ -38,6 +80,26 @@ impl<G: GameLogic, A: Authenticator, C: Codec> Drop
         let player_id = self.player_id;
         let state = Arc::clone(&self.state);
         tokio::spawn(async move {
+            if let Some(matchmaker) = &state.matchmaker {
+                let _ = matchmaker.lock().await.leave(player_id);
+            }
+            {
+                let mut rooms = state.rooms.lock().await;
+                if rooms.player_room(&player_id).is_some() {
+                    let _ = rooms.disconnect_player(player_id).await;
+                }
+            }
+            let left = {
+                let mut parties = state.parties.lock().await;
+                parties.clear_invites(player_id);
+                parties.leave(player_id)
+            };
+            state.inboxes.lock().await.remove(&player_id);
+            if let Ok((_, Some(party))) = left {
+                let members = party.members.clone();
+                let msg = SystemMessage::PartyUpdated { party };
+                notify(&state, &members, msg).await;
+            }
             let mut sessions = state.sessions.lock().await;
             let _ = sessions.disconnect(player_id);
         });
@@ -72,6 +134,8 @@ where
         player_id,
         state: Arc::clone(&state),
     };
+    let (inbox_tx, mut inbox) = mpsc::unbounded_channel();
+    state.inboxes.lock().await.insert(player_id, inbox_tx);
 
     // --- Step 2: Message loop ---
     let mut seq: u64 = 1;
@@ -79,8 +143,47 @@ where
     // Room outbound receiver — set when the player joins a room.
     let mut room_rx: Option<mpsc::UnboundedReceiver<RoomOutbound<G>>> =
         None;
+    // Last input ack from the room, stamped on state and game envelopes.
+    let mut input_ack: Option<u64> = None;
     let idle_deadline = tokio::time::sleep(Duration::from_secs(15));
     tokio::pin!(idle_deadline);
+    let mut server_heartbeat = tokio::time::interval_at(
+        tokio::time::Instant::now() + SERVER_HEARTBEAT_INTERVAL,
+        SERVER_HEARTBEAT_INTERVAL,
+    );
+    // When each unanswered server heartbeat went out, oldest first.
+    let mut pending_heartbeats: VecDeque<u64> = VecDeque::new();
+
+    // A player coming back to a seat their room held gets it back.
+    let (tx, rx) = mpsc::unbounded_channel();
+    let reconnected = {
+        let mut rooms = state.rooms.lock().await;
+        match rooms.player_room(&player_id) {
+            Some(_) => Some(rooms.reconnect_player(player_id, tx).await),
+            None => None,
+        }
+    };
+    match reconnected {
+        Some(Ok(room_id)) => {
+            room_rx = Some(rx);
+            send_system(
+                &conn,
+                &state.codec,
+                SystemMessage::RoomJoined {
+                    room_id,
+                    // TODO: populate with reconnection token
+                    session_id: String::new(),
+                },
+                next_seq(&mut seq),
+                &start,
+            )
+            .await?;
+        }
+        Some(Err(e)) => {
+            tracing::debug!(%player_id, error = %e, "reconnect failed");
+        }
+        None => {}
+    }
 
     loop {
         tokio::select! {
@@ -114,6 +217,36 @@ where
                 };
 
                 match envelope.payload {
+                    // Rooms need the player's RTT for lag compensation.
+                    // It's measured here, and only against heartbeats
+                    // this connection actually sent, so a client can't
+                    // make itself look laggier than it is.
+                    Payload::System(SystemMessage::ServerHeartbeatAck {
+                        server_time,
+                    }) if room_rx.is_some() => {
+                        let Some(index) = pending_heartbeats
+                            .iter()
+                            .position(|sent| *sent == server_time)
+                        else {
+                            tracing::debug!(
+                                %player_id, server_time,
+                                "ignoring ack for an unknown heartbeat"
+                            );
+                            continue;
+                        };
+                        // Older heartbeats go too; their acks would
+                        // only overstate the RTT.
+                        pending_heartbeats.drain(..=index);
+                        let now = start.elapsed().as_millis() as u64;
+                        let rtt = now.saturating_sub(server_time);
+                        let rtt = Duration::from_millis(rtt);
+                        let rooms = state.rooms.lock().await;
+                        if let Err(e) = rooms.report_rtt(player_id, rtt).await {
+                            tracing::debug!(
+                                %player_id, error = %e, "rtt report failed"
+                            );
+                        }
+                    }
                     Payload::System(sys_msg) => {
                         let should_close = handle_system_message(
                             &conn, &state, player_id, sys_msg,
@@ -124,9 +257,9 @@ where
                             break;
                         }
                     }
-                    Payload::Game(game_data) => {
+                    payload @ (Payload::Game(_) | Payload::Input { .. }) => {
                         handle_game_message::<G, A, C>(
-                            &conn, &state, player_id, game_data,
+                            &conn, &state, player_id, envelope.seq, payload,
                             &mut seq, &start,
                         )
                         .await?;
@@ -135,19 +268,27 @@ where
             }
 
             // Outbound: messages from the room actor to this player.
-            Some(outbound) = async {
+            outbound = async {
                 match room_rx.as_mut() {
                     Some(rx) => rx.recv().await,
                     None => std::future::pending().await,
                 }
             } => {
+                // The room was destroyed or disposed of itself. Its
+                // `RoomClosed` came through just before.
+                let Some(outbound) = outbound else {
+                    room_rx = None;
+                    input_ack = None;
+                    continue;
+                };
                 let envelope = match outbound {
-                    RoomOutbound::State(game_state) => {
-                        let data = state.codec.encode(&game_state)?;
+                    RoomOutbound::State(view) => {
+                        let data = state.codec.encode(&view)?;
                         Envelope {
                             seq: next_seq(&mut seq),
                             timestamp: start.elapsed().as_millis() as u64,
                             channel: Channel::ReliableOrdered,
+                            ack: input_ack,
                             payload: Payload::System(
                                 SystemMessage::RoomState { data },
                             ),
@@ -159,9 +300,54 @@ where
                             seq: next_seq(&mut seq),
                             timestamp: start.elapsed().as_millis() as u64,
                             channel: Channel::ReliableOrdered,
+                            ack: input_ack,
                             payload: Payload::Game(data),
                         }
                     }
+                    RoomOutbound::System(msg) => Envelope {
+                        seq: next_seq(&mut seq),
+                        timestamp: start.elapsed().as_millis() as u64,
+                        channel: Channel::ReliableOrdered,
+                        ack: None,
+                        payload: Payload::System(msg),
+                    },
+                    RoomOutbound::Keyframe { seq: snapshot, view } => {
+                        let data = state.codec.encode(&view)?;
+                        Envelope {
+                            seq: next_seq(&mut seq),
+                            timestamp: start.elapsed().as_millis() as u64,
+                            channel: Channel::ReliableOrdered,
+                            ack: input_ack,
+                            payload: Payload::System(
+                                SystemMessage::StateKeyframe {
+                                    seq: snapshot,
+                                    data,
+                                },
+                            ),
+                        }
+                    }
+                    // Deltas are superseded by the next one, so they
+                    // don't need reliable delivery.
+                    RoomOutbound::Delta { seq: snapshot, base_seq, patch } => {
+                        let data = state.codec.encode(&patch)?;
+                        Envelope {
+                            seq: next_seq(&mut seq),
+                            timestamp: start.elapsed().as_millis() as u64,
+                            channel: Channel::Unreliable,
+                            ack: input_ack,
+                            payload: Payload::System(
+                                SystemMessage::StateDelta {
+                                    seq: snapshot,
+                                    base_seq,
+                                    data,
+                                },
+                            ),
+                        }
+                    }
+                    RoomOutbound::InputAck { seq: acked } => {
+                        input_ack = Some(acked);
+                        continue;
+                    }
                 };
                 let bytes = state.codec.encode(&envelope)?;
                 conn.send(&bytes)
@@ -169,6 +355,41 @@ where
                     .map_err(ArcforgeError::Transport)?;
             }
 
+            _ = server_heartbeat.tick(), if room_rx.is_some() => {
+                let server_time = start.elapsed().as_millis() as u64;
+                if pending_heartbeats.len() == MAX_PENDING_HEARTBEATS {
+                    pending_heartbeats.pop_front();
+                }
+                pending_heartbeats.push_back(server_time);
+                let heartbeat = SystemMessage::ServerHeartbeat { server_time };
+                send_system(
+                    &conn, &state.codec, heartbeat, next_seq(&mut seq),
+                    &start,
+                )
+                .await?;
+            }
+
+            // Party traffic and seats from other players' connections.
+            Some(notice) = inbox.recv() => match notice {
+                Notice::System(msg) => {
+                    send_system(
+                        &conn, &state.codec, msg, next_seq(&mut seq), &start,
+                    )
+                    .await?;
+                }
+                Notice::Seated { rx, msg } => {
+                    if let Some(msg) = msg {
+                        send_system(
+                            &conn, &state.codec, msg, next_seq(&mut seq),
+                            &start,
+                        )
+                        .await?;
+                    }
+                    room_rx = Some(rx);
+                    input_ack = None;
+                }
+            },
+
             // Idle timeout: fires if no inbound data for 15 seconds.
             () = &mut idle_deadline => {
                 tracing::info!(%player_id, "connection timed out");
@@ -267,6 +488,7 @@ where
         seq: 0,
         timestamp: start.elapsed().as_millis() as u64,
         channel: Channel::ReliableOrdered,
+        ack: None,
         payload: Payload::System(SystemMessage::HandshakeAck {
             player_id,
             server_time: start.elapsed().as_millis() as u64,
@@ -293,12 +515,40 @@ where
     A: Authenticator,
     C: Codec,
 {
+    // Joining and queueing act for the player's whole party, which only
+    // its leader may do.
+    let group = match &msg {
+        SystemMessage::JoinRoom { .. }
+        | SystemMessage::JoinByCode { .. }
+        | SystemMessage::CreateRoom { .. }
+        | SystemMessage::JoinOrCreate { .. }
+        | SystemMessage::EnterQueue { .. } => {
+            match acting_for(state, player_id).await {
+                Ok(group) => group,
+                Err(message) => {
+                    send_error(
+                        conn,
+                        &state.codec,
+                        403,
+                        &message,
+                        next_seq(seq),
+                        start,
+                    )
+                    .await?;
+                    return Ok(false);
+                }
+            }
+        }
+        _ => Vec::new(),
+    };
+
     match msg {
         SystemMessage::Heartbeat { client_time } => {
             let ack = Envelope {
                 seq: next_seq(seq),
                 timestamp: start.elapsed().as_millis() as u64,
                 channel: Channel::ReliableOrdered,
+                ack: None,
                 payload: Payload::System(SystemMessage::HeartbeatAck {
                     client_time,
                     server_time: start.elapsed().as_millis() as u64,
@@ -308,38 +558,142 @@ where
             conn.send(&bytes).await.map_err(ArcforgeError::Transport)?;
         }
 
-        SystemMessage::JoinRoom { room_id } => {
-            let (tx, rx) = mpsc::unbounded_channel();
+        SystemMessage::JoinRoom { room_id, password } => {
+            let (senders, receivers) = open_channels(&group);
             let join_result = {
                 let mut rooms = state.rooms.lock().await;
-                rooms.join_room(player_id, room_id, tx).await
+                let credentials = JoinCredentials {
+                    invite_code: None,
+                    password,
+                };
+                rooms
+                    .join_room_together(senders, room_id, credentials)
+                    .await
             };
 
             match join_result {
                 Ok(()) => {
-                    *room_rx = Some(rx);
-                    let resp = Envelope {
-                        seq: next_seq(seq),
-                        timestamp: start.elapsed().as_millis() as u64,
-                        channel: Channel::ReliableOrdered,
-                        payload: Payload::System(
-                            SystemMessage::RoomJoined {
-                                room_id,
-                                // TODO: populate with reconnection token
-                                session_id: String::new(),
-                            },
-                        ),
+                    let joined = SystemMessage::RoomJoined {
+                        room_id,
+                        // TODO: populate with reconnection token
+                        session_id: String::new(),
                     };
-                    let bytes = state.codec.encode(&resp)?;
-                    conn.send(&bytes)
-                        .await
-                        .map_err(ArcforgeError::Transport)?;
+                    *room_rx =
+                        hand_over(state, player_id, receivers, Some(&joined))
+                            .await;
+                    send_system(
+                        conn,
+                        &state.codec,
+                        joined,
+                        next_seq(seq),
+                        start,
+                    )
+                    .await?;
                 }
                 Err(e) => {
                     send_error(
                         conn,
                         &state.codec,
-                        404,
+                        room_error_code(&e, 404),
+                        &e.to_string(),
+                        next_seq(seq),
+                        start,
+                    )
+                    .await?;
+                }
+            }
+        }
+
+        SystemMessage::JoinByCode { code, password } => {
+            let (senders, receivers) = open_channels(&group);
+            let result = {
+                let mut rooms = state.rooms.lock().await;
+                rooms.join_by_code_together(senders, &code, password).await
+            };
+
+            match result {
+                Ok(room_id) => {
+                    let joined = SystemMessage::RoomJoined {
+                        room_id,
+                        // TODO: populate with reconnection token
+                        session_id: String::new(),
+                    };
+                    *room_rx =
+                        hand_over(state, player_id, receivers, Some(&joined))
+                            .await;
+                    send_system(
+                        conn,
+                        &state.codec,
+                        joined,
+                        next_seq(seq),
+                        start,
+                    )
+                    .await?;
+                }
+                Err(e) => {
+                    send_error(
+                        conn,
+                        &state.codec,
+                        room_error_code(&e, 404),
+                        &e.to_string(),
+                        next_seq(seq),
+                        start,
+                    )
+                    .await?;
+                }
+            }
+        }
+
+        SystemMessage::CreateRoom { private, password } => {
+            let (senders, receivers) = open_channels(&group);
+            let options = RoomOptions {
+                private,
+                password,
+                ..Default::default()
+            };
+            let result = {
+                let mut rooms = state.rooms.lock().await;
+                rooms
+                    .create_and_join_together(
+                        senders,
+                        G::Config::default(),
+                        options,
+                    )
+                    .await
+                    .map(|room_id| {
+                        let code = rooms.invite_code(room_id).unwrap_or_default();
+                        (room_id, code.to_string())
+                    })
+            };
+
+            match result {
+                Ok((room_id, invite_code)) => {
+                    // The rest of the party joined the leader's room.
+                    let joined = SystemMessage::RoomJoined {
+                        room_id,
+                        // TODO: populate with reconnection token
+                        session_id: String::new(),
+                    };
+                    *room_rx =
+                        hand_over(state, player_id, receivers, Some(&joined))
+                            .await;
+                    send_system(
+                        conn,
+                        &state.codec,
+                        SystemMessage::RoomCreated {
+                            room_id,
+                            invite_code,
+                        },
+                        next_seq(seq),
+                        start,
+                    )
+                    .await?;
+                }
+                Err(e) => {
+                    send_error(
+                        conn,
+                        &state.codec,
+                        room_error_code(&e, 409),
                         &e.to_string(),
                         next_seq(seq),
                         start,
@@ -352,33 +706,32 @@ where
         SystemMessage::JoinOrCreate { .. } => {
             // MVP: `name` and `options` are ignored — single game type,
             // default config. Phase 2 will use these for multi-game servers.
-            let (tx, rx) = mpsc::unbounded_channel();
+            let (senders, receivers) = open_channels(&group);
             let result = {
                 let mut rooms = state.rooms.lock().await;
                 rooms
-                    .join_or_create(player_id, G::Config::default(), tx)
+                    .join_or_create_together(senders, G::Config::default())
                     .await
             };
 
             match result {
                 Ok(room_id) => {
-                    *room_rx = Some(rx);
-                    let resp = Envelope {
-                        seq: next_seq(seq),
-                        timestamp: start.elapsed().as_millis() as u64,
-                        channel: Channel::ReliableOrdered,
-                        payload: Payload::System(
-                            SystemMessage::RoomJoined {
-                                room_id,
-                                // TODO: populate with reconnection token
-                                session_id: String::new(),
-                            },
-                        ),
+                    let joined = SystemMessage::RoomJoined {
+                        room_id,
+                        // TODO: populate with reconnection token
+                        session_id: String::new(),
                     };
-                    let bytes = state.codec.encode(&resp)?;
-                    conn.send(&bytes)
-                        .await
-                        .map_err(ArcforgeError::Transport)?;
+                    *room_rx =
+                        hand_over(state, player_id, receivers, Some(&joined))
+                            .await;
+                    send_system(
+                        conn,
+                        &state.codec,
+                        joined,
+                        next_seq(seq),
+                        start,
+                    )
+                    .await?;
                 }
                 Err(e) => {
                     send_error(
@@ -394,26 +747,26 @@ where
             }
         }
 
-        SystemMessage::ListRooms => {
-            let handles = state.rooms.lock().await.room_handles();
-
-            let mut entries = Vec::with_capacity(handles.len());
-            for handle in &handles {
-                if let Ok(info) = handle.get_info().await {
-                    if info.state.is_joinable() {
-                        entries.push(RoomListEntry {
-                            room_id: info.room_id,
-                            player_count: info.player_count,
-                            max_players: info.max_players,
-                        });
-                    }
-                }
-            }
+        SystemMessage::ListRooms { filter } => {
+            let infos =
+                state.rooms.lock().await.list_rooms_matching(&filter).await;
+            let entries = infos
+                .into_iter()
+                .map(|info| RoomListEntry {
+                    room_id: info.room_id,
+                    player_count: info.player_count,
+                    max_players: info.max_players,
+                    metadata: info.metadata,
+                    password_protected: info.password_protected,
+                    in_progress: info.state == RoomState::InProgress,
+                })
+                .collect();
 
             let resp = Envelope {
                 seq: next_seq(seq),
                 timestamp: start.elapsed().as_millis() as u64,
                 channel: Channel::ReliableOrdered,
+                ack: None,
                 payload: Payload::System(SystemMessage::RoomList {
                     rooms: entries,
                 }),
@@ -424,6 +777,130 @@ where
                 .map_err(ArcforgeError::Transport)?;
         }
 
+        SystemMessage::EnterQueue { params } => {
+            let Some(matchmaker) = &state.matchmaker else {
+                send_error(
+                    conn,
+                    &state.codec,
+                    501,
+                    "matchmaking is not enabled",
+                    next_seq(seq),
+                    start,
+                )
+                .await?;
+                return Ok(false);
+            };
+
+            let in_room = {
+                let rooms = state.rooms.lock().await;
+                group
+                    .iter()
+                    .find_map(|p| rooms.player_room(p).map(|room| (p, room)))
+            };
+            let result = match in_room {
+                Some((member, room_id)) => Err(format!(
+                    "player {} is already in room {}",
+                    member, room_id
+                )),
+                None => {
+                    // A party queues at its members' mean rating.
+                    let total: f64 = group
+                        .iter()
+                        .map(|p| {
+                            state
+                                .ratings
+                                .as_ref()
+                                .and_then(|store| store.get(*p))
+                                .map_or(DEFAULT_RATING, |r| r.rating)
+                        })
+                        .sum();
+                    let rating = total / group.len() as f64;
+                    let (senders, receivers) = open_channels(&group);
+                    matchmaker
+                        .lock()
+                        .await
+                        .enter_party(senders, rating, params)
+                        .map(|()| receivers)
+                        .map_err(|e| e.to_string())
+                }
+            };
+
+            match result {
+                // Queue positions, the match, and then the room's
+                // traffic all arrive on these channels.
+                Ok(receivers) => {
+                    *room_rx =
+                        hand_over(state, player_id, receivers, None).await;
+                }
+                Err(message) => {
+                    send_error(
+                        conn,
+                        &state.codec,
+                        409,
+                        &message,
+                        next_seq(seq),
+                        start,
+                    )
+                    .await?;
+                }
+            }
+        }
+
+        SystemMessage::LeaveQueue => {
+            let result = match &state.matchmaker {
+                Some(matchmaker) => matchmaker
+                    .lock()
+                    .await
+                    .leave(player_id)
+                    .map_err(|e| (404, e.to_string())),
+                None => Err((501, "matchmaking is not enabled".to_string())),
+            };
+
+            match result {
+                Ok(()) => *room_rx = None,
+                Err((code, message)) => {
+                    send_error(
+                        conn,
+                        &state.codec,
+                        code,
+                        &message,
+                        next_seq(seq),
+                        start,
+                    )
+                    .await?;
+                }
+            }
+        }
+
+        msg @ (SystemMessage::CreateParty
+        | SystemMessage::InviteToParty { .. }
+        | SystemMessage::AcceptPartyInvite { .. }
+        | SystemMessage::LeaveParty
+        | SystemMessage::PromotePartyLeader { .. }
+        | SystemMessage::KickFromParty { .. }
+        | SystemMessage::PartyChat { .. }) => {
+            if let Err((code, message)) =
+                handle_party_message(state, player_id, msg).await
+            {
+                send_error(
+                    conn,
+                    &state.codec,
+                    code,
+                    &message,
+                    next_seq(seq),
+                    start,
+                )
+                .await?;
+            }
+        }
+
+        SystemMessage::StateAck { seq: snapshot } => {
+            let rooms = state.rooms.lock().await;
+            if let Err(e) = rooms.ack_state(player_id, snapshot).await {
+                tracing::debug!(%player_id, error = %e, "state ack failed");
+            }
+        }
+
         SystemMessage::LeaveRoom => {
             let mut rooms = state.rooms.lock().await;
             if let Err(e) = rooms.leave_room(player_id).await {
@@ -434,6 +911,65 @@ where
             *room_rx = None;
         }
 
+        // Room controls that only report back on failure.
+        msg @ (SystemMessage::KickPlayer { .. }
+        | SystemMessage::LockRoom
+        | SystemMessage::UnlockRoom
+        | SystemMessage::PauseGame
+        | SystemMessage::ResumeGame
+        | SystemMessage::StartGame
+        | SystemMessage::Ready { .. }
+        | SystemMessage::Rematch { .. }
+        | SystemMessage::ChatSend { .. }
+        | SystemMessage::MuteChat { .. }) => {
+            let result = {
+                let mut rooms = state.rooms.lock().await;
+                match msg {
+                    SystemMessage::ChatSend { text, scope } => {
+                        rooms.send_chat(player_id, text, scope).await
+                    }
+                    SystemMessage::MuteChat {
+                        player_id: target,
+                        muted,
+                    } => rooms.mute_chat(player_id, target, muted).await,
+                    SystemMessage::Ready { ready } => {
+                        rooms.set_ready(player_id, ready).await
+                    }
+                    SystemMessage::Rematch { rematch } => {
+                        rooms.set_rematch(player_id, rematch).await
+                    }
+                    SystemMessage::KickPlayer { player_id: target } => {
+                        rooms.kick_player(player_id, target).await
+                    }
+                    SystemMessage::LockRoom => {
+                        rooms.set_room_locked(player_id, true).await
+                    }
+                    SystemMessage::UnlockRoom => {
+                        rooms.set_room_locked(player_id, false).await
+                    }
+                    SystemMessage::PauseGame => {
+                        rooms.set_room_paused(player_id, true).await
+                    }
+                    SystemMessage::ResumeGame => {
+                        rooms.set_room_paused(player_id, false).await
+                    }
+                    _ => rooms.start_game(player_id).await,
+                }
+            };
+
+            if let Err(e) = result {
+                send_error(
+                    conn,
+                    &state.codec,
+                    room_error_code(&e, 400),
+                    &e.to_string(),
+                    next_seq(seq),
+                    start,
+                )
+                .await?;
+            }
+        }
+
         SystemMessage::Disconnect { reason } => {
             tracing::info!(%player_id, %reason, "client disconnected");
             return Ok(true);
@@ -449,12 +985,252 @@ where
     Ok(false)
 }
 
+/// Handles a party command. Everyone concerned hears about the change
+/// through their inbox, the sender included; failures come back as an
+/// error code and message for the sender.
+async fn handle_party_message<G, A, C>(
+    state: &Arc<ServerState<G, A, C>>,
+    player_id: PlayerId,
+    msg: SystemMessage,
+) -> Result<(), (u16, String)>
+where
+    G: GameLogic,
+    A: Authenticator,
+    C: Codec,
+{
+    match msg {
+        SystemMessage::CreateParty => {
+            if is_queued(state, player_id).await {
+                return Err((409, "leave the queue first".into()));
+            }
+            let party = state
+                .parties
+                .lock()
+                .await
+                .create(player_id)
+                .map_err(party_error)?;
+            let members = party.members.clone();
+            notify(state, &members, SystemMessage::PartyUpdated { party })
+                .await;
+        }
+
+        SystemMessage::InviteToParty { player_id: target } => {
+            if !state.inboxes.lock().await.contains_key(&target) {
+                return Err((404, format!("player {target} is not online")));
+            }
+            let party_id = state
+                .parties
+                .lock()
+                .await
+                .invite(player_id, target)
+                .map_err(party_error)?;
+            let invite = SystemMessage::PartyInvite {
+                party_id,
+                from: player_id,
+            };
+            notify(state, &[target], invite).await;
+        }
+
+        SystemMessage::AcceptPartyInvite { party_id } => {
+            // A queued ticket can't grow, so neither side may be queued.
+            // The matchmaker stays locked until the party has grown, so
+            // neither can enter the queue in between.
+            let matchmaker = match &state.matchmaker {
+                Some(matchmaker) => Some(matchmaker.lock().await),
+                None => None,
+            };
+            let queued = |p: PlayerId| {
+                matchmaker.as_ref().is_some_and(|m| m.is_queued(p))
+            };
+            let accepted = {
+                let mut parties = state.parties.lock().await;
+                let leader = parties.info(party_id).map(|party| party.leader);
+                if leader.is_some_and(queued) || queued(player_id) {
+                    Err((409, "can't join a party from the queue".into()))
+                } else {
+                    parties.accept(player_id, party_id).map_err(party_error)
+                }
+            };
+            drop(matchmaker);
+            let party = accepted?;
+            let members = party.members.clone();
+            notify(state, &members, SystemMessage::PartyUpdated { party })
+                .await;
+        }
+
+        SystemMessage::LeaveParty => {
+            let left = state.parties.lock().await.leave(player_id);
+            let (party_id, rest) = left.map_err(party_error)?;
+            party_left(state, player_id, party_id, rest).await;
+        }
+
+        SystemMessage::KickFromParty { player_id: target } => {
+            let kicked = state.parties.lock().await.kick(player_id, target);
+            let (party_id, rest) = kicked.map_err(party_error)?;
+            party_left(state, target, party_id, rest).await;
+        }
+
+        SystemMessage::PromotePartyLeader { player_id: target } => {
+            let party = state
+                .parties
+                .lock()
+                .await
+                .promote(player_id, target)
+                .map_err(party_error)?;
+            let members = party.members.clone();
+            notify(state, &members, SystemMessage::PartyUpdated { party })
+                .await;
+        }
+
+        SystemMessage::PartyChat { text } => {
+            let party = state
+                .parties
+                .lock()
+                .await
+                .party_of(player_id)
+                .ok_or_else(|| (404, "you are not in a party".to_string()))?;
+            let chat = SystemMessage::PartyChatMessage {
+                from: player_id,
+                text,
+            };
+            notify(state, &party.members, chat).await;
+        }
+
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Tells `player_id` they're out of their party and the rest who's
+/// left. A party waiting for a match leaves the queue, since its ticket
+/// no longer matches its members.
+async fn party_left<G, A, C>(
+    state: &Arc<ServerState<G, A, C>>,
+    player_id: PlayerId,
+    party_id: PartyId,
+    rest: Option<PartyInfo>,
+) where
+    G: GameLogic,
+    A: Authenticator,
+    C: Codec,
+{
+    if let Some(matchmaker) = &state.matchmaker {
+        let _ = matchmaker.lock().await.leave(player_id);
+    }
+    notify(state, &[player_id], SystemMessage::PartyLeft { party_id }).await;
+    if let Some(party) = rest {
+        let members = party.members.clone();
+        notify(state, &members, SystemMessage::PartyUpdated { party }).await;
+    }
+}
+
+/// Returns who a join or queue command from `player_id` acts for: their
+/// whole party, leader first, if they lead one, or else just them.
+/// Other party members get an error message instead.
+async fn acting_for<G, A, C>(
+    state: &Arc<ServerState<G, A, C>>,
+    player_id: PlayerId,
+) -> Result<Vec<PlayerId>, String>
+where
+    G: GameLogic,
+    A: Authenticator,
+    C: Codec,
+{
+    match state.parties.lock().await.party_of(player_id) {
+        None => Ok(vec![player_id]),
+        Some(party) if party.leader == player_id => {
+            let others = party.members.into_iter().filter(|p| *p != player_id);
+            Ok(std::iter::once(player_id).chain(others).collect())
+        }
+        Some(party) => Err(format!(
+            "only the party leader ({}) can do this",
+            party.leader
+        )),
+    }
+}
+
+/// Opens a room channel for each player in `group`.
+fn open_channels<G: GameLogic>(group: &[PlayerId]) -> Channels<G> {
+    group
+        .iter()
+        .map(|&player_id| {
+            let (tx, rx) = mpsc::unbounded_channel();
+            ((player_id, tx), (player_id, rx))
+        })
+        .unzip()
+}
+
+/// Hands the rest of a group the channels opened for them, each after
+/// `msg`, and returns `player_id`'s own.
+async fn hand_over<G, A, C>(
+    state: &Arc<ServerState<G, A, C>>,
+    player_id: PlayerId,
+    receivers: Vec<(PlayerId, mpsc::UnboundedReceiver<RoomOutbound<G>>)>,
+    msg: Option<&SystemMessage>,
+) -> Option<mpsc::UnboundedReceiver<RoomOutbound<G>>>
+where
+    G: GameLogic,
+    A: Authenticator,
+    C: Codec,
+{
+    let inboxes = state.inboxes.lock().await;
+    let mut own = None;
+    for (member, rx) in receivers {
+        if member == player_id {
+            own = Some(rx);
+        } else if let Some(inbox) = inboxes.get(&member) {
+            let msg = msg.cloned();
+            let _ = inbox.send(Notice::Seated { rx, msg });
+        }
+    }
+    own
+}
+
+/// Sends `msg` to each of `players` who is connected.
+async fn notify<G, A, C>(
+    state: &Arc<ServerState<G, A, C>>,
+    players: &[PlayerId],
+    msg: SystemMessage,
+) where
+    G: GameLogic,
+    A: Authenticator,
+    C: Codec,
+{
+    let inboxes = state.inboxes.lock().await;
+    for player_id in players {
+        if let Some(inbox) = inboxes.get(player_id) {
+            let _ = inbox.send(Notice::System(msg.clone()));
+        }
+    }
+}
+
+/// Returns `true` if the player is waiting for a match.
+async fn is_queued<G, A, C>(
+    state: &Arc<ServerState<G, A, C>>,
+    player_id: PlayerId,
+) -> bool
+where
+    G: GameLogic,
+    A: Authenticator,
+    C: Codec,
+{
+    match &state.matchmaker {
+        Some(matchmaker) => matchmaker.lock().await.is_queued(player_id),
+        None => false,
+    }
+}
+
 /// Handles a game message: decode, route to the player's room.
+///
+/// `payload` is a `Game` or `Input` payload; `Input` payloads target a
+/// specific server tick. `client_seq` is the seq of the envelope it came
+/// in, which the room acknowledges once it has processed the input.
 async fn handle_game_message<G, A, C>(
     conn: &WebSocketConnection,
     state: &Arc<ServerState<G, A, C>>,
     player_id: PlayerId,
-    game_data: Vec<u8>,
+    client_seq: u64,
+    payload: Payload,
     seq: &mut u64,
     start: &Instant,
 ) -> Result<(), ArcforgeError>
@@ -463,6 +1239,13 @@ where
     A: Authenticator,
     C: Codec,
 {
+    let (tick, game_data) = match payload {
+        Payload::Game(data) => (None, data),
+        Payload::Input { tick, data } => (Some(tick), data),
+        // System messages never get here.
+        Payload::System(_) => return Ok(()),
+    };
+
     let client_msg: G::ClientMessage = match state.codec.decode(&game_data)
     {
         Ok(msg) => msg,
@@ -486,7 +1269,7 @@ where
         .rooms
         .lock()
         .await
-        .route_message(player_id, client_msg)
+        .route_input(player_id, client_seq, tick, client_msg)
         .await;
 
     if let Err(e) = result {
@@ -504,6 +1287,52 @@ where
     Ok(())
 }
 
+/// Sends a system message envelope to the client.
+async fn send_system(
+    conn: &WebSocketConnection,
+    codec: &impl Codec,
+    msg: SystemMessage,
+    seq: u64,
+    start: &Instant,
+) -> Result<(), ArcforgeError> {
+    let envelope = Envelope {
+        seq,
+        timestamp: start.elapsed().as_millis() as u64,
+        channel: Channel::ReliableOrdered,
+        ack: None,
+        payload: Payload::System(msg),
+    };
+    let bytes = codec.encode(&envelope)?;
+    conn.send(&bytes).await.map_err(ArcforgeError::Transport)?;
+    Ok(())
+}
+
+/// Picks the error code sent to the client for a failed room operation.
+///
+/// Errors that clients need to tell apart get their own code; everything
+/// else uses the operation's `default` code.
+fn room_error_code(err: &RoomError, default: u16) -> u16 {
+    match err {
+        RoomError::WrongPassword(_) | RoomError::NotHost(..) => 403,
+        RoomError::Locked(_) => 423,
+        RoomError::NotFound(_) | RoomError::InvalidInviteCode(_) => 404,
+        RoomError::GameCrashed(_) => 500,
+        _ => default,
+    }
+}
+
+/// Picks the error code sent to the client for a failed party command.
+fn party_error(err: PartyError) -> (u16, String) {
+    let code = match err {
+        PartyError::NotLeader(_) => 403,
+        PartyError::NotInParty(_)
+        | PartyError::NotInvited(..)
+        | PartyError::NotFound(_) => 404,
+        _ => 409,
+    };
+    (code, err.to_string())
+}
+
 /// Sends a SystemMessage::Error envelope to the client.
 async fn send_error(
     conn: &WebSocketConnection,
@@ -517,6 +1346,7 @@ async fn send_error(
         seq,
         timestamp: start.elapsed().as_millis() as u64,
         channel: Channel::ReliableOrdered,
+        ack: None,
         payload: Payload::System(SystemMessage::Error {
             code,
             message: message.to_string(),
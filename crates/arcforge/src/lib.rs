@@ -45,8 +45,10 @@ pub mod prelude {
 
     // Protocol types
     pub use arcforge_protocol::{
-        Channel, Codec, Envelope, JsonCodec, Payload, PlayerId,
-        ProtocolError, Recipient, RoomId, RoomListEntry, SystemMessage,
+        Channel, ChatScope, Codec, EndReason, Envelope, JsonCodec,
+        MatchResult, PartyId, PartyInfo, Payload, PlayerId, PlayerResult,
+        ProtocolError, Recipient, RoomId, RoomListEntry, RoomMetadata,
+        SystemMessage, TeamId,
     };
 
     // Session types
@@ -57,8 +59,20 @@ pub mod prelude {
 
     // Room types
     pub use arcforge_room::{
-        GameLogic, PlayerSender, RoomConfig, RoomError, RoomHandle,
-        RoomInfo, RoomManager, RoomOutbound, RoomState,
+        AsyncGameLogic, AsyncHookConfig, Bot, BotConfig, ChatConfig,
+        ChatFilter, DisposalPolicy, GameContext, GameLogic, GameRng,
+        InputBufferConfig, JsonLinesSink, LagCompensation,
+        LagCompensationConfig, LateInputPolicy, MatchRecord, PanicPolicy,
+        PlayerInput, PlayerSender, PregameConfig, ResultSink, RoomConfig,
+        RoomError, RoomHandle, RoomInfo, RoomManager, RoomOutbound,
+        RoomState, StateSyncConfig, Team, TeamConfig, WordFilter,
+    };
+
+    // Matchmaking, rating, and party types
+    pub use arcforge_matchmaking::{
+        FileStore, Match, MatchQueue, Matchmaker, MatchmakingError,
+        MemoryStore, Parties, PartyError, QueueConfig, Rating, RatingSink,
+        RatingStore, RatingSystem, Ticket, DEFAULT_RATING,
     };
 
     // Transport types
@@ -3,23 +3,35 @@
 //! This is the entry point for running an Arcforge game server. It ties
 //! together all the layers: transport → protocol → session → room.
 
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
+use arcforge_matchmaking::{
+    Match, Matchmaker, Parties, QueueConfig, RatingSink, RatingStore,
+    RatingSystem, DEFAULT_RATING,
+};
 use arcforge_protocol::{
-    Codec, JsonCodec,
+    Codec, JsonCodec, PlayerId,
+};
+use arcforge_room::{
+    AsyncGameLogic, Bot, ChatFilter, GameLogic, MatchRecord, ResultSink,
+    RoomManager,
 };
-use arcforge_room::{GameLogic, RoomManager};
 use arcforge_session::{Authenticator, SessionConfig, SessionManager};
 use arcforge_transport::{Transport, WebSocketTransport};
 use tokio::sync::Mutex;
 
-use crate::handler::handle_connection;
+use crate::handler::{handle_connection, Inbox};
 use crate::ArcforgeError;
 
 /// The current protocol version. Clients must send this in their
 /// handshake or be rejected.
 pub const PROTOCOL_VERSION: u32 = 1;
 
+/// How often the server forgets rooms that disposed of themselves.
+const DISPOSAL_SWEEP_INTERVAL: Duration = Duration::from_secs(5);
+
 /// Shared server state passed to each connection handler task.
 ///
 /// Wrapped in `Arc` so it can be cheaply cloned across tasks.
@@ -27,6 +39,18 @@ pub const PROTOCOL_VERSION: u32 = 1;
 pub(crate) struct ServerState<G: GameLogic, A: Authenticator, C: Codec> {
     pub(crate) sessions: Mutex<SessionManager>,
     pub(crate) rooms: Mutex<RoomManager<G>>,
+    /// Set when the server runs a matchmaking queue. Lock it before
+    /// `rooms` when holding both.
+    pub(crate) matchmaker: Option<Mutex<Matchmaker<G>>>,
+    /// Set when the server rates finished games. The matchmaker queues
+    /// players at their stored rating.
+    pub(crate) ratings: Option<Arc<dyn RatingStore>>,
+    /// Who's in which party. Lock it after `matchmaker` and `rooms`
+    /// when holding several.
+    pub(crate) parties: Mutex<Parties>,
+    /// Each connected player's handler, for traffic from other
+    /// connections (party messages, and seats their leader got them).
+    pub(crate) inboxes: Mutex<HashMap<PlayerId, Inbox<G>>>,
     pub(crate) auth: A,
     pub(crate) codec: C,
 }
@@ -47,6 +71,9 @@ pub(crate) struct ServerState<G: GameLogic, A: Authenticator, C: Codec> {
 pub struct ArcforgeServerBuilder {
     bind_addr: String,
     session_config: SessionConfig,
+    result_sink: Option<Arc<dyn ResultSink>>,
+    ratings: Option<(RatingSystem, Arc<dyn RatingStore>)>,
+    chat_filter: Option<Arc<dyn ChatFilter>>,
 }
 
 impl ArcforgeServerBuilder {
@@ -55,6 +82,9 @@ impl ArcforgeServerBuilder {
         Self {
             bind_addr: "127.0.0.1:8080".to_string(),
             session_config: SessionConfig::default(),
+            result_sink: None,
+            ratings: None,
+            chat_filter: None,
         }
     }
 
@@ -70,6 +100,32 @@ impl ArcforgeServerBuilder {
         self
     }
 
+    /// Records the result of every finished game to `sink` (e.g. a
+    /// [`JsonLinesSink`](arcforge_room::JsonLinesSink)).
+    pub fn result_sink(mut self, sink: impl ResultSink) -> Self {
+        self.result_sink = Some(Arc::new(sink));
+        self
+    }
+
+    /// Rates every finished game with `system` and keeps the ratings in
+    /// `store`. Works alongside [`result_sink`](Self::result_sink).
+    /// Rooms with teams balance them by these ratings too.
+    pub fn ratings(
+        mut self,
+        system: RatingSystem,
+        store: impl RatingStore,
+    ) -> Self {
+        self.ratings = Some((system, Arc::new(store)));
+        self
+    }
+
+    /// Screens every chat message with `filter` (e.g. a
+    /// [`WordFilter`](arcforge_room::WordFilter)) before rooms send it.
+    pub fn chat_filter(mut self, filter: impl ChatFilter) -> Self {
+        self.chat_filter = Some(Arc::new(filter));
+        self
+    }
+
     /// Builds and starts the server with the given authenticator.
     ///
     /// Uses `JsonCodec` and `WebSocketTransport` as defaults (MVP).
@@ -77,13 +133,57 @@ impl ArcforgeServerBuilder {
         self,
         auth: impl Authenticator,
     ) -> Result<ArcforgeServer<G, impl Authenticator, JsonCodec>, ArcforgeError>
+    {
+        self.build_with(RoomManager::new(), auth).await
+    }
+
+    /// Like [`build`](Self::build), for games that also implement
+    /// [`AsyncGameLogic`]: every room runs the game's async hooks.
+    pub async fn build_async<G: AsyncGameLogic>(
+        self,
+        auth: impl Authenticator,
+    ) -> Result<ArcforgeServer<G, impl Authenticator, JsonCodec>, ArcforgeError>
+    {
+        self.build_with(RoomManager::new().with_async_hooks(), auth).await
+    }
+
+    async fn build_with<G: GameLogic>(
+        self,
+        mut rooms: RoomManager<G>,
+        auth: impl Authenticator,
+    ) -> Result<ArcforgeServer<G, impl Authenticator, JsonCodec>, ArcforgeError>
     {
         let transport =
             WebSocketTransport::bind(&self.bind_addr).await?;
 
+        let mut sinks: Vec<Arc<dyn ResultSink>> =
+            self.result_sink.into_iter().collect();
+        let ratings = self.ratings.map(|(system, store)| {
+            sinks.push(Arc::new(RatingSink::new(system, store.clone())));
+            store
+        });
+        if let Some(store) = ratings.clone() {
+            // Rooms balance their teams by the same ratings.
+            rooms = rooms.with_ratings(move |player| {
+                store.get(player).map_or(DEFAULT_RATING, |r| r.rating)
+            });
+        }
+        if let Some(filter) = self.chat_filter {
+            rooms = rooms.with_chat_filter(filter);
+        }
+        match sinks.len() {
+            0 => {}
+            1 => rooms = rooms.with_result_sink(sinks.remove(0)),
+            _ => rooms = rooms.with_result_sink(Arc::new(ResultSinks(sinks))),
+        }
+
         let state = Arc::new(ServerState {
             sessions: Mutex::new(SessionManager::new(self.session_config)),
-            rooms: Mutex::new(RoomManager::new()),
+            rooms: Mutex::new(rooms),
+            matchmaker: None,
+            ratings,
+            parties: Mutex::new(Parties::new(G::room_config().max_players)),
+            inboxes: Mutex::new(HashMap::new()),
             auth,
             codec: JsonCodec,
         });
@@ -117,6 +217,43 @@ where
         ArcforgeServerBuilder::new()
     }
 
+    /// Creates bots with `factory` for the seats the game's
+    /// [`BotConfig`](arcforge_room::BotConfig) hands to bots.
+    pub fn with_bots<F>(mut self, factory: F) -> Self
+    where
+        F: Fn(PlayerId) -> Box<dyn Bot<G>> + Send + Sync + 'static,
+    {
+        // Nothing else holds the state until `run` hands it out.
+        let state = Arc::get_mut(&mut self.state)
+            .expect("server state is shared before run");
+        let rooms = std::mem::take(state.rooms.get_mut());
+        *state.rooms.get_mut() = rooms.with_bots(factory);
+        self
+    }
+
+    /// Runs a matchmaking queue: players send `EnterQueue` and are put
+    /// into fresh rooms as matches form. Each room's game config comes
+    /// from `config_for`.
+    ///
+    /// # Panics
+    ///
+    /// If `config.match_size` is zero or more than the game's
+    /// `max_players`.
+    pub fn with_matchmaking<F>(
+        mut self,
+        config: QueueConfig,
+        config_for: F,
+    ) -> Self
+    where
+        F: Fn(&Match) -> G::Config + Send + Sync + 'static,
+    {
+        let state = Arc::get_mut(&mut self.state)
+            .expect("server state is shared before run");
+        state.matchmaker =
+            Some(Mutex::new(Matchmaker::new(config, config_for)));
+        self
+    }
+
     /// Returns the local address the server is bound to.
     pub fn local_addr(&self) -> std::io::Result<std::net::SocketAddr> {
         self.transport.local_addr()
@@ -130,6 +267,15 @@ where
     pub async fn run(mut self) -> Result<(), ArcforgeError> {
         tracing::info!("Arcforge server running");
 
+        tokio::spawn(sweep_disposed_rooms(Arc::downgrade(&self.state)));
+        if let Some(matchmaker) = &self.state.matchmaker {
+            let interval = matchmaker.lock().await.config().interval;
+            tokio::spawn(run_matchmaking(
+                Arc::downgrade(&self.state),
+                interval,
+            ));
+        }
+
         loop {
             match self.transport.accept().await {
                 Ok(conn) => {
@@ -152,3 +298,60 @@ where
         }
     }
 }
+
+/// Hands each result to several sinks, in order.
+struct ResultSinks(Vec<Arc<dyn ResultSink>>);
+
+impl ResultSink for ResultSinks {
+    fn record(&self, record: &MatchRecord) {
+        for sink in &self.0 {
+            sink.record(record);
+        }
+    }
+}
+
+/// Periodically removes self-disposed rooms from the room manager.
+///
+/// Holds only a weak reference so the task ends with the server.
+async fn sweep_disposed_rooms<G, A, C>(
+    state: std::sync::Weak<ServerState<G, A, C>>,
+) where
+    G: GameLogic,
+    A: Authenticator,
+    C: Codec,
+{
+    let mut interval = tokio::time::interval(DISPOSAL_SWEEP_INTERVAL);
+    loop {
+        interval.tick().await;
+        let Some(state) = state.upgrade() else { break };
+        let disposed = state.rooms.lock().await.collect_disposed();
+        if !disposed.is_empty() {
+            tracing::debug!(count = disposed.len(), "swept disposed rooms");
+        }
+    }
+}
+
+/// Periodically seats the matches the queue has formed.
+///
+/// Holds only a weak reference so the task ends with the server.
+async fn run_matchmaking<G, A, C>(
+    state: std::sync::Weak<ServerState<G, A, C>>,
+    period: Duration,
+) where
+    G: GameLogic,
+    A: Authenticator,
+    C: Codec,
+{
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        let Some(state) = state.upgrade() else { break };
+        let Some(matchmaker) = &state.matchmaker else { break };
+        let mut matchmaker = matchmaker.lock().await;
+        let mut rooms = state.rooms.lock().await;
+        let placed = matchmaker.place(&mut rooms).await;
+        if !placed.is_empty() {
+            tracing::debug!(count = placed.len(), "placed matches");
+        }
+    }
+}
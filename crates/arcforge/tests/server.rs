@@ -32,10 +32,14 @@ struct EchoReply {
 impl GameLogic for EchoGame {
     type Config = ();
     type State = EchoState;
+    type View = EchoState;
     type ClientMessage = EchoMsg;
     type ServerMessage = EchoReply;
 
-    fn init(_config: &(), _players: &[PlayerId]) -> EchoState {
+    fn init(
+        _config: &(),
+        _ctx: &mut GameContext<'_, EchoReply>,
+    ) -> EchoState {
         EchoState::default()
     }
 
@@ -43,6 +47,7 @@ impl GameLogic for EchoGame {
         state: &mut EchoState,
         sender: PlayerId,
         msg: EchoMsg,
+        _ctx: &mut GameContext<'_, EchoReply>,
     ) -> Vec<(Recipient, EchoReply)> {
         state.messages.push(msg.text.clone());
         vec![(
@@ -54,6 +59,10 @@ impl GameLogic for EchoGame {
         )]
     }
 
+    fn view_for(state: &EchoState, _player: PlayerId) -> EchoState {
+        state.clone()
+    }
+
     fn is_finished(state: &EchoState) -> bool {
         state.messages.len() >= 100
     }
@@ -97,7 +106,28 @@ async fn start_server() -> String {
         .build::<EchoGame>(TestAuth)
         .await
         .expect("server should build");
+    serve(server).await
+}
+
+/// Starts a server that matches queued players every 50ms.
+async fn start_matchmaking_server() -> String {
+    let config = QueueConfig {
+        interval: Duration::from_millis(50),
+        ..QueueConfig::default()
+    };
+    let server = ArcforgeServerBuilder::new()
+        .bind("127.0.0.1:0")
+        .build::<EchoGame>(TestAuth)
+        .await
+        .expect("server should build")
+        .with_matchmaking(config, |_: &Match| ());
+    serve(server).await
+}
 
+/// Runs `server` in the background and returns its address.
+async fn serve(
+    server: ArcforgeServer<EchoGame, impl Authenticator, JsonCodec>,
+) -> String {
     let addr = server
         .local_addr()
         .expect("should have local addr")
@@ -134,6 +164,7 @@ async fn handshake(ws: &mut ClientWs, player_id: u64) -> Envelope {
         seq: 0,
         timestamp: 0,
         channel: Channel::ReliableOrdered,
+        ack: None,
         payload: Payload::System(SystemMessage::Handshake {
             version: PROTOCOL_VERSION,
             token: Some(player_id.to_string()),
@@ -144,6 +175,27 @@ async fn handshake(ws: &mut ClientWs, player_id: u64) -> Envelope {
     decode_envelope(msg)
 }
 
+/// Wraps a system message in an envelope.
+fn system_envelope(msg: SystemMessage) -> Envelope {
+    Envelope {
+        seq: 1,
+        timestamp: 0,
+        channel: Channel::ReliableOrdered,
+        ack: None,
+        payload: Payload::System(msg),
+    }
+}
+
+/// Receives the next envelope, failing the test after 2 seconds.
+async fn recv_envelope(ws: &mut ClientWs) -> Envelope {
+    let msg = tokio::time::timeout(Duration::from_secs(2), ws.next())
+        .await
+        .expect("timeout")
+        .unwrap()
+        .expect("recv");
+    decode_envelope(msg)
+}
+
 // =========================================================================
 // Tests
 // =========================================================================
@@ -174,6 +226,7 @@ async fn test_handshake_version_mismatch() {
         seq: 0,
         timestamp: 0,
         channel: Channel::ReliableOrdered,
+        ack: None,
         payload: Payload::System(SystemMessage::Handshake {
             version: 999,
             token: Some("1".into()),
@@ -200,6 +253,7 @@ async fn test_handshake_auth_failure() {
         seq: 0,
         timestamp: 0,
         channel: Channel::ReliableOrdered,
+        ack: None,
         payload: Payload::System(SystemMessage::Handshake {
             version: PROTOCOL_VERSION,
             token: Some("not-a-number".into()),
@@ -227,6 +281,7 @@ async fn test_heartbeat_response() {
         seq: 1,
         timestamp: 0,
         channel: Channel::ReliableOrdered,
+        ack: None,
         payload: Payload::System(SystemMessage::Heartbeat {
             client_time: 12345,
         }),
@@ -247,6 +302,50 @@ async fn test_heartbeat_response() {
     }
 }
 
+#[tokio::test]
+async fn test_server_heartbeat_while_in_a_room() {
+    let addr = start_server().await;
+    let mut ws = connect(&addr).await;
+    handshake(&mut ws, 1).await;
+
+    let joc = system_envelope(SystemMessage::JoinOrCreate {
+        name: "test".into(),
+        options: vec![],
+    });
+    ws.send(encode_envelope(&joc)).await.expect("send");
+
+    // The server measures the RTT itself, starting a couple of seconds
+    // in.
+    let server_time = loop {
+        let msg = tokio::time::timeout(Duration::from_secs(4), ws.next())
+            .await
+            .expect("timeout")
+            .unwrap()
+            .expect("recv");
+        if let Payload::System(SystemMessage::ServerHeartbeat {
+            server_time,
+        }) = decode_envelope(msg).payload
+        {
+            break server_time;
+        }
+    };
+    // An ack for a heartbeat that was never sent is ignored.
+    let forged = system_envelope(SystemMessage::ServerHeartbeatAck {
+        server_time: 0,
+    });
+    ws.send(encode_envelope(&forged)).await.expect("send");
+    let echo =
+        system_envelope(SystemMessage::ServerHeartbeatAck { server_time });
+    ws.send(encode_envelope(&echo)).await.expect("send");
+
+    let hb = system_envelope(SystemMessage::Heartbeat { client_time: 1 });
+    ws.send(encode_envelope(&hb)).await.expect("send");
+    assert!(matches!(
+        recv_envelope(&mut ws).await.payload,
+        Payload::System(SystemMessage::HeartbeatAck { client_time: 1, .. })
+    ));
+}
+
 #[tokio::test]
 async fn test_disconnect_closes_connection() {
     let addr = start_server().await;
@@ -257,6 +356,7 @@ async fn test_disconnect_closes_connection() {
         seq: 1,
         timestamp: 0,
         channel: Channel::ReliableOrdered,
+        ack: None,
         payload: Payload::System(SystemMessage::Disconnect {
             reason: "bye".into(),
         }),
@@ -287,8 +387,10 @@ async fn test_join_room_not_found() {
         seq: 1,
         timestamp: 0,
         channel: Channel::ReliableOrdered,
+        ack: None,
         payload: Payload::System(SystemMessage::JoinRoom {
             room_id: RoomId(999),
+            password: None,
         }),
     };
     ws.send(encode_envelope(&join)).await.expect("send");
@@ -318,6 +420,7 @@ async fn test_game_message_not_in_room() {
         seq: 1,
         timestamp: 0,
         channel: Channel::ReliableOrdered,
+        ack: None,
         payload: Payload::Game(game_data),
     };
     ws.send(encode_envelope(&env)).await.expect("send");
@@ -333,6 +436,35 @@ async fn test_game_message_not_in_room() {
     }
 }
 
+#[tokio::test]
+async fn test_input_payload_routed_like_game_message() {
+    let addr = start_server().await;
+    let mut ws = connect(&addr).await;
+    handshake(&mut ws, 1).await;
+
+    // Tick-tagged input goes through the same routing as game messages.
+    let data = serde_json::to_vec(&EchoMsg {
+        text: "hello".into(),
+    })
+    .unwrap();
+    let env = Envelope {
+        seq: 1,
+        timestamp: 0,
+        channel: Channel::Unreliable,
+        ack: None,
+        payload: Payload::Input { tick: 3, data },
+    };
+    ws.send(encode_envelope(&env)).await.expect("send");
+
+    match recv_envelope(&mut ws).await.payload {
+        Payload::System(SystemMessage::Error { code, message }) => {
+            assert_eq!(code, 400);
+            assert!(message.contains("not in any room"));
+        }
+        other => panic!("expected Error 400, got {other:?}"),
+    }
+}
+
 #[tokio::test]
 async fn test_invalid_envelope_ignored() {
     let addr = start_server().await;
@@ -349,6 +481,7 @@ async fn test_invalid_envelope_ignored() {
         seq: 1,
         timestamp: 0,
         channel: Channel::ReliableOrdered,
+        ack: None,
         payload: Payload::System(SystemMessage::Heartbeat {
             client_time: 999,
         }),
@@ -373,6 +506,7 @@ async fn test_handshake_non_handshake_first_message() {
         seq: 0,
         timestamp: 0,
         channel: Channel::ReliableOrdered,
+        ack: None,
         payload: Payload::System(SystemMessage::Heartbeat {
             client_time: 0,
         }),
@@ -425,7 +559,10 @@ async fn test_list_rooms_empty_server() {
         seq: 1,
         timestamp: 0,
         channel: Channel::ReliableOrdered,
-        payload: Payload::System(SystemMessage::ListRooms),
+        ack: None,
+        payload: Payload::System(SystemMessage::ListRooms {
+            filter: RoomMetadata::new(),
+        }),
     };
     ws.send(encode_envelope(&list_req)).await.expect("send");
 
@@ -449,6 +586,7 @@ async fn test_join_or_create_creates_room() {
         seq: 1,
         timestamp: 0,
         channel: Channel::ReliableOrdered,
+        ack: None,
         payload: Payload::System(SystemMessage::JoinOrCreate {
             name: "test".into(),
             options: vec![],
@@ -478,6 +616,7 @@ async fn test_join_or_create_second_player_joins_existing() {
         seq: 1,
         timestamp: 0,
         channel: Channel::ReliableOrdered,
+        ack: None,
         payload: Payload::System(SystemMessage::JoinOrCreate {
             name: "test".into(),
             options: vec![],
@@ -518,6 +657,7 @@ async fn test_list_rooms_after_join_or_create() {
         seq: 1,
         timestamp: 0,
         channel: Channel::ReliableOrdered,
+        ack: None,
         payload: Payload::System(SystemMessage::JoinOrCreate {
             name: "test".into(),
             options: vec![],
@@ -534,7 +674,10 @@ async fn test_list_rooms_after_join_or_create() {
         seq: 1,
         timestamp: 0,
         channel: Channel::ReliableOrdered,
-        payload: Payload::System(SystemMessage::ListRooms),
+        ack: None,
+        payload: Payload::System(SystemMessage::ListRooms {
+            filter: RoomMetadata::new(),
+        }),
     };
     ws2.send(encode_envelope(&list_req)).await.expect("send");
 
@@ -560,6 +703,7 @@ async fn test_join_or_create_receives_state_on_game_start() {
         seq: 1,
         timestamp: 0,
         channel: Channel::ReliableOrdered,
+        ack: None,
         payload: Payload::System(SystemMessage::JoinOrCreate {
             name: "test".into(),
             options: vec![],
@@ -613,6 +757,7 @@ async fn test_game_message_broadcast_end_to_end() {
         seq: 1,
         timestamp: 0,
         channel: Channel::ReliableOrdered,
+        ack: None,
         payload: Payload::System(SystemMessage::JoinOrCreate {
             name: "test".into(),
             options: vec![],
@@ -638,6 +783,7 @@ async fn test_game_message_broadcast_end_to_end() {
         seq: 2,
         timestamp: 0,
         channel: Channel::ReliableOrdered,
+        ack: None,
         payload: Payload::Game(game_data),
     };
     ws1.send(encode_envelope(&game_env)).await.expect("send");
@@ -654,6 +800,8 @@ async fn test_game_message_broadcast_end_to_end() {
         "expected Game payload, got {:?}",
         env1.payload
     );
+    // The sender learns which of its inputs the room has processed.
+    assert_eq!(env1.ack, Some(2));
 
     let msg2 = tokio::time::timeout(Duration::from_secs(2), ws2.next())
         .await
@@ -665,5 +813,517 @@ async fn test_game_message_broadcast_end_to_end() {
         matches!(env2.payload, Payload::Game(_)),
         "expected Game payload, got {:?}",
         env2.payload
+    );    assert_eq!(env2.ack, None);
+}
+
+#[tokio::test]
+async fn test_create_private_room_hidden_and_joinable_by_code() {
+    let addr = start_server().await;
+
+    let mut host = connect(&addr).await;
+    handshake(&mut host, 1).await;
+    let create = system_envelope(SystemMessage::CreateRoom {
+        private: true,
+        password: None,
+    });
+    host.send(encode_envelope(&create)).await.expect("send");
+    let (room_id, invite_code) = match recv_envelope(&mut host).await.payload {
+        Payload::System(SystemMessage::RoomCreated {
+            room_id,
+            invite_code,
+        }) => (room_id, invite_code),
+        other => panic!("expected RoomCreated, got {other:?}"),
+    };
+    assert_eq!(invite_code.len(), 6);
+
+    // A private room doesn't show up in listings...
+    let mut guest = connect(&addr).await;
+    handshake(&mut guest, 2).await;
+    let list = system_envelope(SystemMessage::ListRooms {
+        filter: RoomMetadata::new(),
+    });
+    guest.send(encode_envelope(&list)).await.expect("send");
+    match recv_envelope(&mut guest).await.payload {
+        Payload::System(SystemMessage::RoomList { rooms }) => {
+            assert!(rooms.is_empty());
+        }
+        other => panic!("expected RoomList, got {other:?}"),
+    }
+
+    // ...and can't be joined by ID...
+    let join = system_envelope(SystemMessage::JoinRoom {
+        room_id,
+        password: None,
+    });
+    guest.send(encode_envelope(&join)).await.expect("send");
+    match recv_envelope(&mut guest).await.payload {
+        Payload::System(SystemMessage::Error { code, .. }) => {
+            assert_eq!(code, 404);
+        }
+        other => panic!("expected Error 404, got {other:?}"),
+    }
+
+    // ...but the invite code works (case-insensitively).
+    let join = system_envelope(SystemMessage::JoinByCode {
+        code: invite_code.to_lowercase(),
+        password: None,
+    });
+    guest.send(encode_envelope(&join)).await.expect("send");
+    match recv_envelope(&mut guest).await.payload {
+        Payload::System(SystemMessage::RoomJoined { room_id: joined, .. }) => {
+            assert_eq!(joined, room_id);
+        }
+        other => panic!("expected RoomJoined, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_wrong_room_password_returns_403() {
+    let addr = start_server().await;
+
+    let mut host = connect(&addr).await;
+    handshake(&mut host, 1).await;
+    let create = system_envelope(SystemMessage::CreateRoom {
+        private: false,
+        password: Some("secret".into()),
+    });
+    host.send(encode_envelope(&create)).await.expect("send");
+    let room_id = match recv_envelope(&mut host).await.payload {
+        Payload::System(SystemMessage::RoomCreated { room_id, .. }) => room_id,
+        other => panic!("expected RoomCreated, got {other:?}"),
+    };
+
+    let mut guest = connect(&addr).await;
+    handshake(&mut guest, 2).await;
+
+    // Listed, but flagged as needing a password.
+    let list = system_envelope(SystemMessage::ListRooms {
+        filter: RoomMetadata::new(),
+    });
+    guest.send(encode_envelope(&list)).await.expect("send");
+    match recv_envelope(&mut guest).await.payload {
+        Payload::System(SystemMessage::RoomList { rooms }) => {
+            assert_eq!(rooms.len(), 1);
+            assert!(rooms[0].password_protected);
+        }
+        other => panic!("expected RoomList, got {other:?}"),
+    }
+
+    let join = system_envelope(SystemMessage::JoinRoom {
+        room_id,
+        password: Some("guess".into()),
+    });
+    guest.send(encode_envelope(&join)).await.expect("send");
+    match recv_envelope(&mut guest).await.payload {
+        Payload::System(SystemMessage::Error { code, .. }) => {
+            assert_eq!(code, 403);
+        }
+        other => panic!("expected Error 403, got {other:?}"),
+    }
+
+    let join = system_envelope(SystemMessage::JoinRoom {
+        room_id,
+        password: Some("secret".into()),
+    });
+    guest.send(encode_envelope(&join)).await.expect("send");
+    assert!(matches!(
+        recv_envelope(&mut guest).await.payload,
+        Payload::System(SystemMessage::RoomJoined { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_disconnect_leaves_room() {
+    let addr = start_server().await;
+
+    let mut ws = connect(&addr).await;
+    handshake(&mut ws, 1).await;
+    let joc = system_envelope(SystemMessage::JoinOrCreate {
+        name: "test".into(),
+        options: vec![],
+    });
+    ws.send(encode_envelope(&joc)).await.expect("send");
+    let _ = recv_envelope(&mut ws).await;
+
+    ws.close(None).await.expect("close");
+    drop(ws);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mut ws2 = connect(&addr).await;
+    handshake(&mut ws2, 2).await;
+    let list = system_envelope(SystemMessage::ListRooms {
+        filter: RoomMetadata::new(),
+    });
+    ws2.send(encode_envelope(&list)).await.expect("send");
+    match recv_envelope(&mut ws2).await.payload {
+        Payload::System(SystemMessage::RoomList { rooms }) => {
+            assert_eq!(rooms.len(), 1);
+            assert_eq!(rooms[0].player_count, 0);
+        }
+        other => panic!("expected RoomList, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_host_controls_end_to_end() {
+    let addr = start_server().await;
+
+    let mut host = connect(&addr).await;
+    handshake(&mut host, 1).await;
+    let create = system_envelope(SystemMessage::CreateRoom {
+        private: false,
+        password: None,
+    });
+    host.send(encode_envelope(&create)).await.expect("send");
+    let room_id = match recv_envelope(&mut host).await.payload {
+        Payload::System(SystemMessage::RoomCreated { room_id, .. }) => room_id,
+        other => panic!("expected RoomCreated, got {other:?}"),
+    };
+    assert_eq!(
+        recv_envelope(&mut host).await.payload,
+        Payload::System(SystemMessage::HostChanged { host: PlayerId(1) })
     );
+
+    let mut guest = connect(&addr).await;
+    handshake(&mut guest, 2).await;
+    let join = system_envelope(SystemMessage::JoinRoom {
+        room_id,
+        password: None,
+    });
+    guest.send(encode_envelope(&join)).await.expect("send");
+    assert!(matches!(
+        recv_envelope(&mut guest).await.payload,
+        Payload::System(SystemMessage::RoomJoined { .. })
+    ));
+    assert_eq!(
+        recv_envelope(&mut guest).await.payload,
+        Payload::System(SystemMessage::HostChanged { host: PlayerId(1) })
+    );
+
+    // Only the host may start the game.
+    guest
+        .send(encode_envelope(&system_envelope(SystemMessage::StartGame)))
+        .await
+        .expect("send");
+    match recv_envelope(&mut guest).await.payload {
+        Payload::System(SystemMessage::Error { code, .. }) => {
+            assert_eq!(code, 403);
+        }
+        other => panic!("expected Error 403, got {other:?}"),
+    }
+
+    let kick = system_envelope(SystemMessage::KickPlayer {
+        player_id: PlayerId(2),
+    });
+    host.send(encode_envelope(&kick)).await.expect("send");
+    assert_eq!(
+        recv_envelope(&mut guest).await.payload,
+        Payload::System(SystemMessage::Kicked { room_id })
+    );
+}
+
+#[tokio::test]
+async fn test_enter_queue_without_matchmaking_returns_501() {
+    let addr = start_server().await;
+
+    let mut ws = connect(&addr).await;
+    handshake(&mut ws, 1).await;
+    let enter = system_envelope(SystemMessage::EnterQueue {
+        params: RoomMetadata::new(),
+    });
+    ws.send(encode_envelope(&enter)).await.expect("send");
+    assert!(matches!(
+        recv_envelope(&mut ws).await.payload,
+        Payload::System(SystemMessage::Error { code: 501, .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_queued_players_are_matched_into_a_room() {
+    let addr = start_matchmaking_server().await;
+
+    let mut ws1 = connect(&addr).await;
+    let mut ws2 = connect(&addr).await;
+    handshake(&mut ws1, 1).await;
+    handshake(&mut ws2, 2).await;
+    let enter = system_envelope(SystemMessage::EnterQueue {
+        params: RoomMetadata::new(),
+    });
+
+    ws1.send(encode_envelope(&enter)).await.expect("send");
+    assert_eq!(
+        recv_envelope(&mut ws1).await.payload,
+        Payload::System(SystemMessage::QueuePosition { position: 1 })
+    );
+    ws2.send(encode_envelope(&enter)).await.expect("send");
+    assert_eq!(
+        recv_envelope(&mut ws2).await.payload,
+        Payload::System(SystemMessage::QueuePosition { position: 2 })
+    );
+
+    let room1 = match recv_envelope(&mut ws1).await.payload {
+        Payload::System(SystemMessage::MatchFound { room_id }) => room_id,
+        other => panic!("expected MatchFound, got {other:?}"),
+    };
+    let room2 = match recv_envelope(&mut ws2).await.payload {
+        Payload::System(SystemMessage::MatchFound { room_id }) => room_id,
+        other => panic!("expected MatchFound, got {other:?}"),
+    };
+    assert_eq!(room1, room2);
+
+    // The game starts with both players seated.
+    assert!(matches!(
+        recv_envelope(&mut ws1).await.payload,
+        Payload::System(SystemMessage::RoomState { .. })
+    ));
+
+    // Matched players are in a room, so they can't queue again.
+    ws1.send(encode_envelope(&enter)).await.expect("send");
+    assert!(matches!(
+        recv_envelope(&mut ws1).await.payload,
+        Payload::System(SystemMessage::Error { code: 409, .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_leave_queue() {
+    let addr = start_matchmaking_server().await;
+
+    let mut ws = connect(&addr).await;
+    handshake(&mut ws, 1).await;
+    let enter = system_envelope(SystemMessage::EnterQueue {
+        params: RoomMetadata::new(),
+    });
+    ws.send(encode_envelope(&enter)).await.expect("send");
+    let _ = recv_envelope(&mut ws).await;
+
+    let leave = system_envelope(SystemMessage::LeaveQueue);
+    ws.send(encode_envelope(&leave)).await.expect("send");
+    // Leaving again: no longer queued.
+    ws.send(encode_envelope(&leave)).await.expect("send");
+    assert!(matches!(
+        recv_envelope(&mut ws).await.payload,
+        Payload::System(SystemMessage::Error { code: 404, .. })
+    ));
+}
+
+/// Puts players 1 and 2 in a party led by player 1.
+async fn form_party(leader: &mut ClientWs, member: &mut ClientWs) -> PartyId {
+    let create = system_envelope(SystemMessage::CreateParty);
+    leader.send(encode_envelope(&create)).await.expect("send");
+    let party_id = match recv_envelope(leader).await.payload {
+        Payload::System(SystemMessage::PartyUpdated { party }) => {
+            party.party_id
+        }
+        other => panic!("expected PartyUpdated, got {other:?}"),
+    };
+
+    let invite = system_envelope(SystemMessage::InviteToParty {
+        player_id: PlayerId(2),
+    });
+    leader.send(encode_envelope(&invite)).await.expect("send");
+    assert_eq!(
+        recv_envelope(member).await.payload,
+        Payload::System(SystemMessage::PartyInvite {
+            party_id,
+            from: PlayerId(1),
+        })
+    );
+
+    let accept = system_envelope(SystemMessage::AcceptPartyInvite { party_id });
+    member.send(encode_envelope(&accept)).await.expect("send");
+    let party = PartyInfo {
+        party_id,
+        leader: PlayerId(1),
+        members: vec![PlayerId(1), PlayerId(2)],
+    };
+    for ws in [leader, member] {
+        assert_eq!(
+            recv_envelope(ws).await.payload,
+            Payload::System(SystemMessage::PartyUpdated {
+                party: party.clone(),
+            })
+        );
+    }
+    party_id
+}
+
+#[tokio::test]
+async fn test_party_joins_a_room_together() {
+    let addr = start_server().await;
+
+    let mut ws1 = connect(&addr).await;
+    let mut ws2 = connect(&addr).await;
+    handshake(&mut ws1, 1).await;
+    handshake(&mut ws2, 2).await;
+    form_party(&mut ws1, &mut ws2).await;
+
+    let join = system_envelope(SystemMessage::JoinOrCreate {
+        name: "test".into(),
+        options: vec![],
+    });
+    // Only the leader joins for the party.
+    ws2.send(encode_envelope(&join)).await.expect("send");
+    assert!(matches!(
+        recv_envelope(&mut ws2).await.payload,
+        Payload::System(SystemMessage::Error { code: 403, .. })
+    ));
+
+    ws1.send(encode_envelope(&join)).await.expect("send");
+    let mut rooms = Vec::new();
+    for ws in [&mut ws1, &mut ws2] {
+        match recv_envelope(ws).await.payload {
+            Payload::System(SystemMessage::RoomJoined { room_id, .. }) => {
+                rooms.push(room_id)
+            }
+            other => panic!("expected RoomJoined, got {other:?}"),
+        }
+        // Both were seated at once, so the game has started.
+        assert!(matches!(
+            recv_envelope(ws).await.payload,
+            Payload::System(SystemMessage::RoomState { .. })
+        ));
+    }
+    assert_eq!(rooms[0], rooms[1]);
+}
+
+#[tokio::test]
+async fn test_party_chat_and_leader_handover() {
+    let addr = start_server().await;
+
+    let mut ws1 = connect(&addr).await;
+    let mut ws2 = connect(&addr).await;
+    handshake(&mut ws1, 1).await;
+    handshake(&mut ws2, 2).await;
+    let party_id = form_party(&mut ws1, &mut ws2).await;
+
+    let chat = system_envelope(SystemMessage::PartyChat {
+        text: "ready?".into(),
+    });
+    ws2.send(encode_envelope(&chat)).await.expect("send");
+    for ws in [&mut ws1, &mut ws2] {
+        assert_eq!(
+            recv_envelope(ws).await.payload,
+            Payload::System(SystemMessage::PartyChatMessage {
+                from: PlayerId(2),
+                text: "ready?".into(),
+            })
+        );
+    }
+
+    // The leader drops out and the party passes to player 2.
+    drop(ws1);
+    assert_eq!(
+        recv_envelope(&mut ws2).await.payload,
+        Payload::System(SystemMessage::PartyUpdated {
+            party: PartyInfo {
+                party_id,
+                leader: PlayerId(2),
+                members: vec![PlayerId(2)],
+            },
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_party_is_matched_together() {
+    let addr = start_matchmaking_server().await;
+
+    let mut ws1 = connect(&addr).await;
+    let mut ws2 = connect(&addr).await;
+    handshake(&mut ws1, 1).await;
+    handshake(&mut ws2, 2).await;
+    form_party(&mut ws1, &mut ws2).await;
+
+    let enter = system_envelope(SystemMessage::EnterQueue {
+        params: RoomMetadata::new(),
+    });
+    ws1.send(encode_envelope(&enter)).await.expect("send");
+
+    // A party of two fills a 1v1 match on its own.
+    let mut rooms = Vec::new();
+    for ws in [&mut ws1, &mut ws2] {
+        assert_eq!(
+            recv_envelope(ws).await.payload,
+            Payload::System(SystemMessage::QueuePosition { position: 1 })
+        );
+        match recv_envelope(ws).await.payload {
+            Payload::System(SystemMessage::MatchFound { room_id }) => {
+                rooms.push(room_id)
+            }
+            other => panic!("expected MatchFound, got {other:?}"),
+        }
+    }
+    assert_eq!(rooms[0], rooms[1]);
+}
+
+// =========================================================================
+// Chat
+// =========================================================================
+
+#[tokio::test]
+async fn test_room_chat_end_to_end() {
+    let server = ArcforgeServerBuilder::new()
+        .bind("127.0.0.1:0")
+        .chat_filter(WordFilter::new(["darn"]))
+        .build::<EchoGame>(TestAuth)
+        .await
+        .expect("server should build");
+    let addr = serve(server).await;
+
+    let mut ws1 = connect(&addr).await;
+    handshake(&mut ws1, 1).await;
+    let say = |text: &str| {
+        system_envelope(SystemMessage::ChatSend {
+            text: text.into(),
+            scope: ChatScope::Room,
+        })
+    };
+
+    // Not in a room yet.
+    ws1.send(encode_envelope(&say("hello?"))).await.expect("send");
+    match recv_envelope(&mut ws1).await.payload {
+        Payload::System(SystemMessage::Error { code, .. }) => {
+            assert_eq!(code, 400);
+        }
+        other => panic!("expected Error 400, got {other:?}"),
+    }
+
+    let create = system_envelope(SystemMessage::CreateRoom {
+        private: false,
+        password: None,
+    });
+    ws1.send(encode_envelope(&create)).await.expect("send");
+    let room_id = match recv_envelope(&mut ws1).await.payload {
+        Payload::System(SystemMessage::RoomCreated { room_id, .. }) => room_id,
+        other => panic!("expected RoomCreated, got {other:?}"),
+    };
+    let host = Payload::System(SystemMessage::HostChanged {
+        host: PlayerId(1),
+    });
+    assert_eq!(recv_envelope(&mut ws1).await.payload, host);
+
+    // The room is still waiting for a second player.
+    ws1.send(encode_envelope(&say("darn, anyone?"))).await.expect("send");
+    let filtered = Payload::System(SystemMessage::ChatMessage {
+        from: PlayerId(1),
+        text: "****, anyone?".into(),
+        scope: ChatScope::Room,
+    });
+    assert_eq!(recv_envelope(&mut ws1).await.payload, filtered);
+
+    // Player 2 joins and catches up on what was said.
+    let mut ws2 = connect(&addr).await;
+    handshake(&mut ws2, 2).await;
+    let join = system_envelope(SystemMessage::JoinRoom {
+        room_id,
+        password: None,
+    });
+    ws2.send(encode_envelope(&join)).await.expect("send");
+    assert!(matches!(
+        recv_envelope(&mut ws2).await.payload,
+        Payload::System(SystemMessage::RoomJoined { .. })
+    ));
+    assert_eq!(recv_envelope(&mut ws2).await.payload, host);
+    assert_eq!(recv_envelope(&mut ws2).await.payload, filtered);
 }
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use arcforge::prelude::*;
 use serde::{Deserialize, Serialize};
 
@@ -34,10 +36,12 @@ struct TicTacToe;
 impl GameLogic for TicTacToe {
     type Config = ();
     type State = State;
+    type View = State;
     type ClientMessage = Move;
     type ServerMessage = Event;
 
-    fn init(_: &(), players: &[PlayerId]) -> State {
+    fn init(_: &(), ctx: &mut GameContext<'_, Event>) -> State {
+        let players = ctx.players();
         State {
             board: [[Cell::Empty; 3]; 3],
             players: [players[0], players[1]],
@@ -62,7 +66,12 @@ impl GameLogic for TicTacToe {
         Ok(())
     }
 
-    fn handle_message(state: &mut State, sender: PlayerId, msg: Move) -> Vec<(Recipient, Event)> {
+    fn handle_message(
+        state: &mut State,
+        sender: PlayerId,
+        msg: Move,
+        _ctx: &mut GameContext<'_, Event>,
+    ) -> Vec<(Recipient, Event)> {
         let mark = if state.turn == 0 { Cell::X } else { Cell::O };
         state.board[msg.row][msg.col] = mark;
 
@@ -91,10 +100,21 @@ impl GameLogic for TicTacToe {
         out
     }
 
+    fn view_for(state: &State, _player: PlayerId) -> State {
+        state.clone()
+    }
+
     fn is_finished(state: &State) -> bool {
         state.winner.is_some()
     }
 
+    fn results(state: &State) -> MatchResult {
+        // A win is worth 1; a draw leaves both players tied at 0.
+        MatchResult::from_scores(
+            state.players.map(|p| (p, i64::from(state.winner == Some(p)))),
+        )
+    }
+
     fn room_config() -> RoomConfig {
         RoomConfig { min_players: 2, max_players: 2, ..RoomConfig::default() }
     }
@@ -125,14 +145,35 @@ impl Authenticator for TokenAuth {
     }
 }
 
+/// Prints the ten best-rated players.
+fn print_ladder(ratings: &impl RatingStore) {
+    let ladder = ratings.top(10);
+    if ladder.is_empty() {
+        eprintln!("ranked ladder is empty: play a game to get rated");
+        return;
+    }
+    eprintln!("ranked ladder:");
+    for (rank, (player, rating)) in ladder.iter().enumerate() {
+        eprintln!("  {:>2}. player {:<8} {:>6.0} ({} games)", rank + 1, player, rating.rating, rating.games);
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     eprintln!("starting tic-tac-toe server on 0.0.0.0:8080");
 
+    // Every finished game moves the players' Elo ratings, kept in
+    // ratings.json between runs. Ranked players queue with EnterQueue
+    // and are paired by rating.
+    let ratings = Arc::new(FileStore::open("ratings.json")?);
+    print_ladder(&ratings);
+
     let server = ArcforgeServerBuilder::new()
         .bind("0.0.0.0:8080")
+        .ratings(RatingSystem::elo(), ratings)
         .build::<TicTacToe>(TokenAuth)
-        .await?;
+        .await?
+        .with_matchmaking(QueueConfig::default(), |_: &Match| ());
 
     server.run().await?;
     Ok(())
@@ -142,6 +183,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 mod tests {
     use super::*;
     use futures_util::{SinkExt, StreamExt};
+    use rand::SeedableRng;
     use std::time::Duration;
     use tokio_tungstenite::tungstenite::Message;
 
@@ -150,8 +192,13 @@ mod tests {
     >;
 
     async fn start() -> String {
+        start_ranked(Arc::new(MemoryStore::new())).await
+    }
+
+    async fn start_ranked(ratings: Arc<MemoryStore>) -> String {
         let server = ArcforgeServerBuilder::new()
             .bind("127.0.0.1:0")
+            .ratings(RatingSystem::elo(), ratings)
             .build::<TicTacToe>(TokenAuth)
             .await
             .unwrap();
@@ -180,7 +227,7 @@ mod tests {
 
     async fn do_handshake(ws: &mut Ws, id: u64) {
         let env = Envelope {
-            seq: 0, timestamp: 0, channel: Channel::ReliableOrdered,
+            seq: 0, timestamp: 0, channel: Channel::ReliableOrdered, ack: None,
             payload: Payload::System(SystemMessage::Handshake {
                 version: PROTOCOL_VERSION, token: Some(id.to_string()),
             }),
@@ -191,7 +238,7 @@ mod tests {
 
     async fn join(ws: &mut Ws) {
         let env = Envelope {
-            seq: 1, timestamp: 0, channel: Channel::ReliableOrdered,
+            seq: 1, timestamp: 0, channel: Channel::ReliableOrdered, ack: None,
             payload: Payload::System(SystemMessage::JoinOrCreate {
                 name: "ttt".into(), options: vec![],
             }),
@@ -203,7 +250,7 @@ mod tests {
     async fn send_move(ws: &mut Ws, row: usize, col: usize) {
         let data = serde_json::to_vec(&Move { row, col }).unwrap();
         let env = Envelope {
-            seq: 0, timestamp: 0, channel: Channel::ReliableOrdered,
+            seq: 0, timestamp: 0, channel: Channel::ReliableOrdered, ack: None,
             payload: Payload::Game(data),
         };
         ws.send(enc(&env)).await.unwrap();
@@ -313,6 +360,30 @@ mod tests {
         let _ = recv(&mut p2).await;
     }
 
+    // A finished game moves both players on the ranked ladder.
+    #[tokio::test]
+    async fn test_win_updates_ladder() {
+        let ratings = Arc::new(MemoryStore::new());
+        let addr = start_ranked(ratings.clone()).await;
+        let (mut p1, mut p2) = setup_game(&addr).await;
+
+        play(&mut p1, &mut p2, 1, 0, 0).await; // X
+        play(&mut p1, &mut p2, 2, 0, 1).await; // O
+        play(&mut p1, &mut p2, 1, 1, 1).await; // X
+        play(&mut p1, &mut p2, 2, 1, 0).await; // O
+        send_move(&mut p1, 2, 2).await; // X wins
+        let _ = recv(&mut p1).await; // MoveMade
+        let _ = recv(&mut p1).await; // GameOver
+
+        // The room rates the game as it ends, just after GameOver.
+        for _ in 0..50 {
+            if ratings.top(2).len() == 2 { break; }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let ladder: Vec<_> = ratings.top(10).iter().map(|(p, r)| (p.0, r.rating)).collect();
+        assert_eq!(ladder, vec![(1, 1516.0), (2, 1484.0)]);
+    }
+
     // ---------------------------------------------------------------
     // Draw game
     //  X | O | X
@@ -363,13 +434,24 @@ mod tests {
         let _ = recv(&mut p2).await;
     }
 
+    /// Runs `f` with a context for a two-player room, as a room would.
+    fn with_ctx<R>(f: impl FnOnce(&mut GameContext<'_, Event>) -> R) -> R {
+        let players = [PlayerId(1), PlayerId(2)];
+        let mut rng = GameRng::seed_from_u64(0);
+        f(&mut GameContext::new(RoomId(1), 0, Duration::ZERO, &players, &mut rng))
+    }
+
+    fn new_game() -> State {
+        with_ctx(|ctx| TicTacToe::init(&(), ctx))
+    }
+
     // ---------------------------------------------------------------
     // Unit tests for validate_message — deterministic, no network.
     // Tests occupied cell, out of bounds, game over, and wrong turn.
     // ---------------------------------------------------------------
     #[test]
     fn test_validate_rejects_out_of_bounds() {
-        let state = TicTacToe::init(&(), &[PlayerId(1), PlayerId(2)]);
+        let state = new_game();
         let r = TicTacToe::validate_message(&state, PlayerId(1), &Move { row: 3, col: 0 });
         assert!(r.is_err());
         assert!(r.unwrap_err().contains("0-2"));
@@ -377,8 +459,10 @@ mod tests {
 
     #[test]
     fn test_validate_rejects_occupied_cell() {
-        let mut state = TicTacToe::init(&(), &[PlayerId(1), PlayerId(2)]);
-        TicTacToe::handle_message(&mut state, PlayerId(1), Move { row: 0, col: 0 });
+        let mut state = new_game();
+        with_ctx(|ctx| {
+            TicTacToe::handle_message(&mut state, PlayerId(1), Move { row: 0, col: 0 }, ctx)
+        });
         // Now it's O's turn, cell (0,0) is taken
         let r = TicTacToe::validate_message(&state, PlayerId(2), &Move { row: 0, col: 0 });
         assert!(r.is_err());
@@ -387,7 +471,7 @@ mod tests {
 
     #[test]
     fn test_validate_rejects_wrong_turn() {
-        let state = TicTacToe::init(&(), &[PlayerId(1), PlayerId(2)]);
+        let state = new_game();
         let r = TicTacToe::validate_message(&state, PlayerId(2), &Move { row: 0, col: 0 });
         assert!(r.is_err());
         assert!(r.unwrap_err().contains("not your turn"));
@@ -395,30 +479,43 @@ mod tests {
 
     #[test]
     fn test_validate_rejects_after_game_over() {
-        let mut state = TicTacToe::init(&(), &[PlayerId(1), PlayerId(2)]);
+        let mut state = new_game();
         state.winner = Some(PlayerId(1));
         let r = TicTacToe::validate_message(&state, PlayerId(2), &Move { row: 1, col: 1 });
         assert!(r.is_err());
         assert!(r.unwrap_err().contains("game is over"));
     }
 
+    #[test]
+    fn test_results_rank_the_winner_first() {
+        let mut state = new_game();
+        state.winner = Some(PlayerId(2));
+        let result = TicTacToe::results(&state);
+        assert_eq!(result.player(PlayerId(2)).unwrap().placement, 1);
+        assert_eq!(result.player(PlayerId(1)).unwrap().placement, 2);
+
+        state.winner = Some(PlayerId(0)); // draw
+        let result = TicTacToe::results(&state);
+        assert!(result.players.iter().all(|r| r.placement == 1));
+    }
+
     #[test]
     fn test_win_detection_all_lines() {
         // Rows
         for row in 0..3 {
             let mut b = [[Cell::Empty; 3]; 3];
-            for col in 0..3 { b[row][col] = Cell::X; }
+            b[row] = [Cell::X; 3];
             assert!(check_winner(&b, Cell::X), "row {row}");
         }
         // Columns
         for col in 0..3 {
             let mut b = [[Cell::Empty; 3]; 3];
-            for row in 0..3 { b[row][col] = Cell::O; }
+            for r in b.iter_mut() { r[col] = Cell::O; }
             assert!(check_winner(&b, Cell::O), "col {col}");
         }
         // Diagonals
         let mut b = [[Cell::Empty; 3]; 3];
-        for i in 0..3 { b[i][i] = Cell::X; }
+        for (i, r) in b.iter_mut().enumerate() { r[i] = Cell::X; }
         assert!(check_winner(&b, Cell::X), "main diagonal");
 
         let mut b = [[Cell::Empty; 3]; 3];